@@ -0,0 +1,145 @@
+use gtk::prelude::*;
+
+use crate::gedcom::{Address, ContactInfo};
+use crate::prelude::*;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+type ContactCallback = Box<dyn Fn(ContactInfo)>;
+
+/// Separator of multiple values in a single entry
+const LIST_SEPARATOR: char = ';';
+
+pub struct AddressEditorView {
+    gbuilder: gtk::Builder,
+    contact: ContactInfo,
+    on_save: Rc<RefCell<Vec<ContactCallback>>>,
+}
+
+impl AddressEditorView {
+    pub fn new(contact: Option<ContactInfo>) -> Self {
+        Self {
+            gbuilder: gtk::Builder::from_resource("/org/altereigo/npaf/AddressEditor.glade"),
+            contact: contact.unwrap_or_default(),
+            on_save: Default::default(),
+        }
+    }
+
+    fn entry(gbuilder: &gtk::Builder, name: &str) -> gtk::Entry {
+        gbuilder.object(name).unwrap()
+    }
+
+    fn lines(gbuilder: &gtk::Builder) -> gtk::TextBuffer {
+        let tv: gtk::TextView = gbuilder.object("tv_lines").unwrap();
+        tv.buffer().unwrap()
+    }
+
+    fn fill(&self) {
+        let set = |name, value: Option<&String>| {
+            Self::entry(&self.gbuilder, name).set_text(value.map_or("", |v| v.as_str()));
+        };
+        let join = |list: &Vec<String>| list.join(&format!("{} ", LIST_SEPARATOR));
+        let address = self.contact.address.clone().unwrap_or_default();
+        Self::lines(&self.gbuilder).set_text(address.lines.as_deref().unwrap_or(""));
+        set("e_adr1", address.line1.as_ref());
+        set("e_adr2", address.line2.as_ref());
+        set("e_adr3", address.line3.as_ref());
+        set("e_city", address.city.as_ref());
+        set("e_state", address.state.as_ref());
+        set("e_postal_code", address.postal_code.as_ref());
+        set("e_country", address.country.as_ref());
+        set("e_phones", Some(&join(&self.contact.phones)));
+        set("e_emails", Some(&join(&self.contact.emails)));
+        set("e_faxes", Some(&join(&self.contact.faxes)));
+        set("e_websites", Some(&join(&self.contact.websites)));
+    }
+
+    /// Reads the entries back into a contact structure. The
+    /// free form address is kept as written unless changed,
+    /// and gets rebuilt from the parts when left empty.
+    fn collect(gbuilder: &gtk::Builder, original: Option<&Address>) -> ContactInfo {
+        let value = |name| -> Option<String> {
+            let text = Self::entry(gbuilder, name).text().trim().to_owned();
+            if text.is_empty() { None } else { Some(text) }
+        };
+        let list = |name| -> Vec<String> {
+            value(name).map_or(vec![], |v| {
+                v.split(LIST_SEPARATOR)
+                    .map(|s| s.trim().to_owned())
+                    .filter(|s| !s.is_empty())
+                    .collect()
+            })
+        };
+        let buffer = Self::lines(gbuilder);
+        let text = buffer.text(&buffer.start_iter(), &buffer.end_iter(), false)
+            .map(|t| t.to_string())
+            .unwrap_or_default();
+        let original = original.and_then(|a| a.lines.clone());
+        let lines = match original {
+            Some(lines) if lines == text => Some(lines),
+            _ => Some(text.trim().to_owned()).filter(|t| !t.is_empty()),
+        };
+        let address = Address {
+            lines,
+            line1: value("e_adr1"),
+            line2: value("e_adr2"),
+            line3: value("e_adr3"),
+            city: value("e_city"),
+            state: value("e_state"),
+            postal_code: value("e_postal_code"),
+            country: value("e_country"),
+        };
+        ContactInfo {
+            address: if address.is_empty() { None } else { Some(address) },
+            phones: list("e_phones"),
+            emails: list("e_emails"),
+            faxes: list("e_faxes"),
+            websites: list("e_websites"),
+        }
+    }
+}
+
+impl EventEmitter<ContactInfo> for AddressEditorView {
+    fn subscribe<TF: Fn(ContactInfo) + 'static>(&mut self, f: TF) {
+        self.on_save.borrow_mut().push(Box::new(f));
+    }
+}
+
+impl View for AddressEditorView {
+    fn assemble(&self) -> gtk::Widget {
+        let root: gtk::Grid = self.gbuilder.object("root").unwrap();
+        self.fill();
+        root.show();
+        root.dynamic_cast::<gtk::Widget>().unwrap()
+    }
+}
+
+impl Windowed for AddressEditorView {
+    fn assemble_window(&self) -> gtk::Window {
+        let wdg = self.assemble();
+        let wdw: gtk::Window = self.gbuilder.object("top").unwrap();
+        wdw.set_child(Some(&wdg));
+
+        let b_cancel: gtk::Button = self.gbuilder.object("b_cancel").unwrap();
+        let wdw_cpy = wdw.clone();
+        b_cancel.connect_clicked(move |_| wdw_cpy.close());
+
+        let b_save: gtk::Button = self.gbuilder.object("b_save").unwrap();
+        let (gbuilder, on_save, wdw_cpy) = (self.gbuilder.clone(), self.on_save.clone(), wdw.clone());
+        let address = self.contact.address.clone();
+        b_save.connect_clicked(move |_| {
+            let contact = Self::collect(&gbuilder, address.as_ref());
+            if let Err(e) = contact.validate() {
+                let l_error: gtk::Label = gbuilder.object("l_error").unwrap();
+                l_error.set_text(&e.what());
+                l_error.style_context().add_class("color-urgent");
+                return;
+            }
+            for callback in on_save.borrow().iter() {
+                callback(contact.clone());
+            }
+            wdw_cpy.close();
+        });
+        wdw
+    }
+}
@@ -0,0 +1,19 @@
+//!
+//! Commands of the application as actions, shared by the
+//! menus, the toolbar and the keyboard shortcuts
+//!
+
+use gio::prelude::*;
+use gtk::prelude::*;
+
+/// Registers the action `app.<name>` running `f`, along with
+/// its keyboard shortcuts, in the syntax of `gtk::accelerator_parse`
+pub fn add<F: Fn() + 'static>(app: &gtk::Application, name: &str, accels: &[&str], f: F) -> gio::SimpleAction {
+    let action = gio::SimpleAction::new(name, None);
+    action.connect_activate(move |_, _| f());
+    app.add_action(&action);
+    if !accels.is_empty() {
+        app.set_accels_for_action(&format!("app.{}", name), accels);
+    }
+    action
+}
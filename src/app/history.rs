@@ -0,0 +1,113 @@
+//!
+//! Navigation history of the workspace
+//!
+
+use std::collections::VecDeque;
+
+/// Persons kept in the list of the recently viewed ones
+const RECENT: usize = 20;
+/// Steps kept behind the current person
+const BACK: usize = 100;
+
+/// Persons the workspace was focused on, most recent last, along
+/// with the ones left by going back, and the bookmarks of the user.
+/// Persons are known by their key in the registry.
+#[derive(Debug, Clone, Default)]
+pub struct History {
+    back: Vec<u64>,
+    current: Option<u64>,
+    forward: Vec<u64>,
+    recent: VecDeque<u64>,
+    bookmarks: Vec<u64>,
+}
+
+impl History {
+    /// Records a visit, which drops the steps left by going back
+    pub fn visit(&mut self, key: u64) {
+        if self.current == Some(key) {
+            return;
+        }
+        if let Some(current) = self.current.replace(key) {
+            self.back.push(current);
+            if self.back.len() > BACK {
+                self.back.remove(0);
+            }
+        }
+        self.forward.clear();
+        self.recent.retain(|k| *k != key);
+        self.recent.push_front(key);
+        self.recent.truncate(RECENT);
+    }
+
+    /// Steps back and tells the person to show again
+    pub fn back(&mut self) -> Option<u64> {
+        let key = self.back.pop()?;
+        if let Some(current) = self.current.replace(key) {
+            self.forward.push(current);
+        }
+        Some(key)
+    }
+
+    /// Steps forward again after going back
+    pub fn forward(&mut self) -> Option<u64> {
+        let key = self.forward.pop()?;
+        if let Some(current) = self.current.replace(key) {
+            self.back.push(current);
+        }
+        Some(key)
+    }
+
+    pub fn can_go_back(&self) -> bool {
+        !self.back.is_empty()
+    }
+
+    pub fn can_go_forward(&self) -> bool {
+        !self.forward.is_empty()
+    }
+
+    /// Persons recently viewed, the latest first
+    pub fn recent(&self) -> Vec<u64> {
+        self.recent.iter().copied().collect()
+    }
+
+    /// Bookmarked persons, in the order they were added
+    pub fn bookmarks(&self) -> &[u64] {
+        &self.bookmarks
+    }
+
+    pub fn is_bookmarked(&self, key: u64) -> bool {
+        self.bookmarks.contains(&key)
+    }
+
+    /// Bookmarks the person, or removes the bookmark.
+    /// Tells whether the person is now bookmarked.
+    pub fn toggle_bookmark(&mut self, key: u64) -> bool {
+        match self.bookmarks.iter().position(|k| *k == key) {
+            Some(index) => {
+                self.bookmarks.remove(index);
+                false
+            },
+            None => {
+                self.bookmarks.push(key);
+                true
+            },
+        }
+    }
+
+    /// Forgets the persons no longer in the tree, the steps
+    /// around them being joined
+    pub fn retain<F: Fn(u64) -> bool>(&mut self, exists: F) {
+        self.back.retain(|k| exists(*k));
+        self.forward.retain(|k| exists(*k));
+        self.recent.retain(|k| exists(*k));
+        self.bookmarks.retain(|k| exists(*k));
+        if self.current.map_or(false, |k| !exists(k)) {
+            self.current = self.back.pop();
+        }
+        self.back.dedup();
+        self.forward.dedup();
+        while self.current.is_some() && self.back.last() == self.current.as_ref() {
+            self.back.pop();
+        }
+    }
+}
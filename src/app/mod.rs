@@ -5,23 +5,50 @@
 use gio::prelude::*;
 use gtk::prelude::*;
 
+use crate::gedcom::RecordRegistry;
+use crate::import_view::{ImportMode, ImportView};
 use crate::prelude::*;
 use crate::root::RootView;
+use std::path::PathBuf;
 use std::rc::Rc;
 
+pub mod actions;
+pub mod history;
+pub mod models;
+pub mod preferences;
+pub mod window;
+use models::AppModel;
+
 pub struct Application {
     gtk_app: gtk::Application,
+    model: AppModel,
+    import: Option<PathBuf>,
 }
 
 #[derive(Default, Clone)]
-pub struct ApplicationBuilder {}
+pub struct ApplicationBuilder {
+    registry: RecordRegistry,
+    import: Option<PathBuf>,
+}
 
 impl ApplicationBuilder {
+    /// Record tree opened at startup
+    pub fn registry(self, registry: RecordRegistry) -> Self {
+        Self { registry, ..self }
+    }
+
+    /// GED file imported in the background once the window shows
+    pub fn import(self, path: PathBuf) -> Self {
+        Self { import: Some(path), ..self }
+    }
+
     pub fn build(self) -> Result<Application> {
         Ok(Application {
             gtk_app: gtk::Application::builder()
                 .application_id("org.altereigo.ae-task-manager")
                 .build(),
+            model: AppModel::new(self.registry),
+            import: self.import,
         })
     }
 }
@@ -32,7 +59,7 @@ impl Application {
     }
 
     fn assemble_root(&self) -> gtk::Widget {
-        let mut view = RootView::new();
+        let mut view = RootView::new(self.model.clone());
         view.assemble()
     }
 
@@ -43,12 +70,25 @@ impl Application {
 
     pub fn run(&self) -> i32 {
         Application::load_resources();
-        let root = RootView::new();
+        let root = Rc::new(RootView::new(self.model.clone()));
         let window = self.init_window();
         window.set_child(Some(&root.assemble()));
+        window::decorate(&window);
+        root.register_actions(&self.gtk_app);
         let window_cpy = window.clone();
-        root.on_window_close(move |_| {
-            window_cpy.close();
+        actions::add(&self.gtk_app, "quit", &["<Primary>q"], move || window_cpy.close());
+        let window_cpy = window.clone();
+        actions::add(&self.gtk_app, "minimize", &[], move || window_cpy.iconify());
+        let window_cpy = window.clone();
+        actions::add(&self.gtk_app, "maximize", &[], move || window::toggle_maximized(&window_cpy));
+        let window_cpy = window.clone();
+        actions::add(&self.gtk_app, "about", &[], move || Application::show_about(&window_cpy));
+        let window_cpy = window.clone();
+        root.on_title_bar_pressed(move |_, event| window::drag(&window_cpy, event));
+        let root_cpy = root.clone();
+        window.connect_window_state_event(move |_, event| {
+            root_cpy.show_maximized(event.new_window_state().contains(gtk::gdk::WindowState::MAXIMIZED));
+            gtk::Inhibit(false)
         });
         let css_provider = gtk::CssProvider::new();
         css_provider.load_from_resource("/org/altereigo/npaf/style.css");
@@ -57,11 +97,31 @@ impl Application {
             &css_provider,
             gtk::STYLE_PROVIDER_PRIORITY_APPLICATION,
         );
+        let (model, import) = (self.model.clone(), self.import.clone());
         self.gtk_app.connect_activate(move |app| {
             window.set_application(Some(app));
             window.present();
+            if let Some(path) = &import {
+                let import = ImportView::new(model.clone(), path.clone(), ImportMode::Replace).assemble_window();
+                import.set_transient_for(Some(&window));
+                import.present();
+            }
         });
-        self.gtk_app.run()
+        // The file to import is handled above, GTK gets
+        // the program name only
+        let program: Vec<String> = std::env::args().take(1).collect();
+        self.gtk_app.run_with_args(&program)
+    }
+
+    fn show_about(window: &gtk::ApplicationWindow) {
+        let about = gtk::AboutDialog::new();
+        about.set_program_name("nPAF");
+        about.set_version(Some(env!("CARGO_PKG_VERSION")));
+        about.set_comments(Some("Genealogy records in the spirit of Personal Ancestral File"));
+        about.set_authors(&[env!("CARGO_PKG_AUTHORS")]);
+        about.set_transient_for(Some(window));
+        about.run();
+        about.close();
     }
 
     fn load_resources() {
@@ -0,0 +1,193 @@
+//!
+//! Application state shared between the views
+//!
+
+use super::history::History;
+use crate::gedcom::{FamilyGraph, RecordRc, RecordRegistry};
+use crate::prelude::*;
+use std::cell::{Ref, RefCell, RefMut};
+use std::collections::BTreeSet;
+use std::rc::Rc;
+use std::sync::Arc;
+
+type NavigationCallback = Rc<dyn Fn(RecordRc)>;
+type ChangeCallback = Rc<dyn Fn()>;
+
+/// Opened record tree along with the person the workspace
+/// is focused on. Clones share the same state, so every view
+/// can keep its own handle on the model.
+#[derive(Default, Clone)]
+pub struct AppModel {
+    registry: Rc<RefCell<RecordRegistry>>,
+    graph: Rc<RefCell<Arc<FamilyGraph>>>,
+    current: Rc<RefCell<Option<u64>>>,
+    on_navigate: Rc<RefCell<Vec<NavigationCallback>>>,
+    on_change: Rc<RefCell<Vec<ChangeCallback>>>,
+    /// Individuals found by the latest search
+    search_result: Rc<RefCell<BTreeSet<u64>>>,
+    /// Individuals marked by the user for exports
+    marked: Rc<RefCell<BTreeSet<u64>>>,
+    history: Rc<RefCell<History>>,
+}
+
+impl AppModel {
+    pub fn new(registry: RecordRegistry) -> Self {
+        Self {
+            graph: Rc::new(RefCell::new(Arc::new(FamilyGraph::new(&registry)))),
+            registry: Rc::new(RefCell::new(registry)),
+            ..Default::default()
+        }
+    }
+
+    pub fn registry(&self) -> Ref<RecordRegistry> {
+        self.registry.borrow()
+    }
+
+    pub fn registry_mut(&self) -> RefMut<RecordRegistry> {
+        self.registry.borrow_mut()
+    }
+
+    /// Replaces the opened tree, the workspace being focused
+    /// on the first individual of the new one
+    pub fn load(&self, registry: RecordRegistry) {
+        self.registry.replace(registry);
+        self.current.replace(None);
+        self.search_result.replace(BTreeSet::new());
+        self.marked.replace(BTreeSet::new());
+        self.history.replace(History::default());
+        self.relink();
+        self.focus_first();
+    }
+
+    /// Focuses the workspace on the individual with the lowest RIN
+    fn focus_first(&self) {
+        let _ = self.go_home();
+    }
+
+    /// Goes to the home person, the individual with the lowest RIN
+    pub fn go_home(&self) -> Result<()> {
+        let first = self.registry.borrow().iter()
+            .filter(|(_, r)| r.borrow().rtype == "INDI")
+            .min_by_key(|(_, r)| r.borrow().rin)
+            .map(|(key, _)| *key)
+            .ok_or(Error::NotFound)?;
+        self.navigate(first)
+    }
+
+    /// Family links of the opened tree. The graph holds no
+    /// record, so it can be handed to worker threads.
+    pub fn graph(&self) -> Arc<FamilyGraph> {
+        self.graph.borrow().clone()
+    }
+
+    /// Builds the family graph again, once the records were
+    /// added, removed or changed, and notifies the views showing
+    /// them. The workspace moves to the first individual if its
+    /// own was removed.
+    pub fn relink(&self) {
+        let graph = FamilyGraph::new(&self.registry.borrow());
+        self.graph.replace(Arc::new(graph));
+        let registry = self.registry.borrow();
+        self.history.borrow_mut().retain(|key| registry.contains_key(&key));
+        drop(registry);
+        let callbacks: Vec<ChangeCallback> = self.on_change.borrow().clone();
+        for callback in callbacks {
+            callback();
+        }
+        if self.current_key().map_or(false, |key| self.record(key).is_none()) {
+            self.current.replace(None);
+            self.focus_first();
+        }
+    }
+
+    /// Calls `f` every time the records of the tree are changed
+    pub fn on_change<F: Fn() + 'static>(&self, f: F) {
+        self.on_change.borrow_mut().push(Rc::new(f));
+    }
+
+    pub fn record(&self, key: u64) -> Option<RecordRc> {
+        self.registry.borrow().get(&key).cloned()
+    }
+
+    /// Person the workspace is focused on
+    pub fn current(&self) -> Option<RecordRc> {
+        let key = (*self.current.borrow())?;
+        self.record(key)
+    }
+
+    pub fn current_key(&self) -> Option<u64> {
+        *self.current.borrow()
+    }
+
+    /// Individuals found by the latest search
+    pub fn search_result(&self) -> BTreeSet<u64> {
+        self.search_result.borrow().clone()
+    }
+
+    pub fn set_search_result<I: IntoIterator<Item = u64>>(&self, keys: I) {
+        self.search_result.replace(keys.into_iter().collect());
+    }
+
+    /// Individuals marked by the user
+    pub fn marked(&self) -> BTreeSet<u64> {
+        self.marked.borrow().clone()
+    }
+
+    pub fn mark<I: IntoIterator<Item = u64>>(&self, keys: I) {
+        self.marked.borrow_mut().extend(keys);
+    }
+
+    pub fn clear_marks(&self) {
+        self.marked.borrow_mut().clear();
+    }
+
+    /// Focuses the workspace on the record with the given
+    /// key, records it in the history and notifies all the
+    /// subscribers.
+    pub fn navigate(&self, key: u64) -> Result<()> {
+        self.record(key).ok_or(Error::NotFound)?;
+        self.history.borrow_mut().visit(key);
+        self.focus(key)
+    }
+
+    /// Navigation history, recently viewed persons and bookmarks
+    pub fn history(&self) -> Ref<History> {
+        self.history.borrow()
+    }
+
+    /// Goes back to the person viewed before
+    pub fn go_back(&self) -> Result<()> {
+        let key = self.history.borrow_mut().back().ok_or(Error::NotFound)?;
+        self.focus(key)
+    }
+
+    /// Goes forward again to the person left by going back
+    pub fn go_forward(&self) -> Result<()> {
+        let key = self.history.borrow_mut().forward().ok_or(Error::NotFound)?;
+        self.focus(key)
+    }
+
+    /// Bookmarks the current person, or removes the bookmark.
+    /// Tells whether the person is now bookmarked.
+    pub fn toggle_bookmark(&self) -> Option<bool> {
+        let key = self.current_key()?;
+        Some(self.history.borrow_mut().toggle_bookmark(key))
+    }
+
+    /// Focuses the workspace without touching the history
+    fn focus(&self, key: u64) -> Result<()> {
+        let record = self.record(key).ok_or(Error::NotFound)?;
+        self.current.replace(Some(key));
+        let callbacks: Vec<NavigationCallback> = self.on_navigate.borrow().clone();
+        for callback in callbacks {
+            callback(record.clone());
+        }
+        Ok(())
+    }
+}
+
+impl EventEmitter<RecordRc> for AppModel {
+    fn subscribe<TF: Fn(RecordRc) + 'static>(&mut self, f: TF) {
+        self.on_navigate.borrow_mut().push(Rc::new(f));
+    }
+}
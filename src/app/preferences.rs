@@ -0,0 +1,74 @@
+//!
+//! Preferences of the user, kept between sessions: the rules
+//! telling who is living and the score duplicates are looked
+//! for from
+//!
+
+use crate::gedcom::LivingRules;
+use std::path::PathBuf;
+
+const GROUP: &str = "preferences";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Preferences {
+    /// Persons born at least this many years ago are dead
+    pub max_age: i32,
+    /// Youngest age at which someone has a child or marries
+    pub min_parent_age: i32,
+    /// Minimal score of the duplicates offered for merging
+    pub duplicate_score: i32,
+}
+
+impl Default for Preferences {
+    fn default() -> Self {
+        let rules = LivingRules::default();
+        Self { max_age: rules.max_age, min_parent_age: rules.min_parent_age, duplicate_score: 60 }
+    }
+}
+
+impl Preferences {
+    fn path() -> PathBuf {
+        glib::user_config_dir().join("npaf").join("preferences.ini")
+    }
+
+    /// Preferences saved by the user, the default
+    /// ones standing for those never saved
+    pub fn load() -> Self {
+        let mut preferences = Self::default();
+        let file = glib::KeyFile::new();
+        if file.load_from_file(Self::path(), glib::KeyFileFlags::NONE).is_err() {
+            return preferences;
+        }
+        if let Ok(max_age) = file.integer(GROUP, "max_age") {
+            preferences.max_age = max_age.max(1);
+        }
+        if let Ok(min_parent_age) = file.integer(GROUP, "min_parent_age") {
+            preferences.min_parent_age = min_parent_age.max(1);
+        }
+        if let Ok(score) = file.integer(GROUP, "duplicate_score") {
+            preferences.duplicate_score = score.max(0).min(100);
+        }
+        preferences
+    }
+
+    pub fn save(&self) -> Result<(), glib::Error> {
+        let path = Self::path();
+        if let Some(dir) = path.parent() {
+            let _ = std::fs::create_dir_all(dir);
+        }
+        let file = glib::KeyFile::new();
+        file.set_integer(GROUP, "max_age", self.max_age);
+        file.set_integer(GROUP, "min_parent_age", self.min_parent_age);
+        file.set_integer(GROUP, "duplicate_score", self.duplicate_score);
+        file.save_to_file(path)
+    }
+
+    /// Living persons inference following the preferences
+    pub fn living_rules(&self) -> LivingRules {
+        LivingRules {
+            max_age: self.max_age,
+            min_parent_age: self.min_parent_age,
+            ..Default::default()
+        }
+    }
+}
@@ -0,0 +1,204 @@
+//!
+//! Chrome of the main window, which draws its own title bar:
+//! moving, resizing, maximizing and the geometry kept between
+//! sessions
+//!
+
+use gtk::gdk;
+use gtk::prelude::*;
+use std::cell::RefCell;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+/// Width of the borders grabbed to resize the window
+const BORDER: i32 = 4;
+const GROUP: &str = "window";
+
+/// Labels of the maximize button, the window being restored or maximized
+pub const MAXIMIZE_GLYPH: &str = "\u{f0c8}";
+pub const RESTORE_GLYPH: &str = "\u{f2d2}";
+
+/// Size and position of the window when it is not maximized,
+/// and whether it is
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WindowGeometry {
+    pub width: i32,
+    pub height: i32,
+    pub position: Option<(i32, i32)>,
+    pub maximized: bool,
+}
+
+impl Default for WindowGeometry {
+    fn default() -> Self {
+        Self { width: 1366, height: 768, position: None, maximized: false }
+    }
+}
+
+impl WindowGeometry {
+    fn path() -> PathBuf {
+        glib::user_config_dir().join("npaf").join("window.ini")
+    }
+
+    /// Geometry the window had at the end of the latest
+    /// session, or the default one
+    pub fn load() -> Self {
+        let mut geometry = Self::default();
+        let file = glib::KeyFile::new();
+        if file.load_from_file(Self::path(), glib::KeyFileFlags::NONE).is_err() {
+            return geometry;
+        }
+        if let (Ok(width), Ok(height)) = (file.integer(GROUP, "width"), file.integer(GROUP, "height")) {
+            if width > 0 && height > 0 {
+                geometry.width = width;
+                geometry.height = height;
+            }
+        }
+        if let (Ok(x), Ok(y)) = (file.integer(GROUP, "x"), file.integer(GROUP, "y")) {
+            geometry.position = Some((x, y));
+        }
+        geometry.maximized = file.boolean(GROUP, "maximized").unwrap_or(false);
+        geometry
+    }
+
+    pub fn save(&self) -> Result<(), glib::Error> {
+        let path = Self::path();
+        if let Some(dir) = path.parent() {
+            let _ = std::fs::create_dir_all(dir);
+        }
+        let file = glib::KeyFile::new();
+        file.set_integer(GROUP, "width", self.width);
+        file.set_integer(GROUP, "height", self.height);
+        if let Some((x, y)) = self.position {
+            file.set_integer(GROUP, "x", x);
+            file.set_integer(GROUP, "y", y);
+        }
+        file.set_boolean(GROUP, "maximized", self.maximized);
+        file.save_to_file(path)
+    }
+
+    pub fn apply<W: IsA<gtk::Window>>(&self, window: &W) {
+        window.set_default_size(self.width, self.height);
+        if let Some((x, y)) = self.position {
+            window.move_(x, y);
+        }
+        if self.maximized {
+            window.maximize();
+        }
+    }
+}
+
+/// Border of the window at the position, if any
+fn edge_at(window: &gtk::Window, (x, y): (f64, f64)) -> Option<gdk::WindowEdge> {
+    let (width, height) = (window.allocated_width() as f64, window.allocated_height() as f64);
+    let border = BORDER as f64;
+    let (left, right) = (x < border, x >= width - border);
+    let (top, bottom) = (y < border, y >= height - border);
+    match (left, right, top, bottom) {
+        (true, _, true, _) => Some(gdk::WindowEdge::NorthWest),
+        (_, true, true, _) => Some(gdk::WindowEdge::NorthEast),
+        (true, _, _, true) => Some(gdk::WindowEdge::SouthWest),
+        (_, true, _, true) => Some(gdk::WindowEdge::SouthEast),
+        (true, _, _, _) => Some(gdk::WindowEdge::West),
+        (_, true, _, _) => Some(gdk::WindowEdge::East),
+        (_, _, true, _) => Some(gdk::WindowEdge::North),
+        (_, _, _, true) => Some(gdk::WindowEdge::South),
+        _ => None,
+    }
+}
+
+fn cursor_name(edge: gdk::WindowEdge) -> &'static str {
+    match edge {
+        gdk::WindowEdge::NorthWest => "nw-resize",
+        gdk::WindowEdge::NorthEast => "ne-resize",
+        gdk::WindowEdge::SouthWest => "sw-resize",
+        gdk::WindowEdge::SouthEast => "se-resize",
+        gdk::WindowEdge::West => "w-resize",
+        gdk::WindowEdge::East => "e-resize",
+        gdk::WindowEdge::North => "n-resize",
+        _ => "s-resize",
+    }
+}
+
+/// Border the pointer is on, when the event happened on the
+/// window itself rather than on one of its widgets
+fn border_of(window: &gtk::Window, event: &gdk::Event, position: (f64, f64)) -> Option<gdk::WindowEdge> {
+    if window.is_maximized() || event.window() != window.window() {
+        return None;
+    }
+    edge_at(window, position)
+}
+
+/// Gives an undecorated window the geometry of the latest session,
+/// borders resizing it and saves its geometry when it is closed
+pub fn decorate<W: IsA<gtk::Window>>(window: &W) {
+    let window: gtk::Window = window.clone().upcast();
+    let geometry = Rc::new(RefCell::new(WindowGeometry::load()));
+    geometry.borrow().apply(&window);
+    window.set_border_width(BORDER as u32);
+    window.add_events(gdk::EventMask::POINTER_MOTION_MASK | gdk::EventMask::BUTTON_PRESS_MASK);
+
+    window.connect_motion_notify_event(|window, event| {
+        if let Some(gdk_window) = window.window() {
+            let cursor = border_of(window, event, event.position())
+                .and_then(|edge| gdk::Cursor::from_name(&gdk_window.display(), cursor_name(edge)));
+            gdk_window.set_cursor(cursor.as_ref());
+        }
+        gtk::Inhibit(false)
+    });
+    window.connect_button_press_event(|window, event| {
+        match border_of(window, event, event.position()) {
+            Some(edge) if event.button() == 1 => {
+                let (x, y) = event.root();
+                window.begin_resize_drag(edge, 1, x as i32, y as i32, event.time());
+                gtk::Inhibit(true)
+            },
+            _ => gtk::Inhibit(false),
+        }
+    });
+
+    let geometry_cpy = geometry.clone();
+    window.connect_configure_event(move |window, _| {
+        if !window.is_maximized() {
+            let mut geometry = geometry_cpy.borrow_mut();
+            let (width, height) = window.size();
+            geometry.width = width;
+            geometry.height = height;
+            geometry.position = Some(window.position());
+        }
+        false
+    });
+    let geometry_cpy = geometry.clone();
+    window.connect_window_state_event(move |_, event| {
+        geometry_cpy.borrow_mut().maximized = event.new_window_state().contains(gdk::WindowState::MAXIMIZED);
+        gtk::Inhibit(false)
+    });
+    window.connect_delete_event(move |_, _| {
+        let _ = geometry.borrow().save();
+        gtk::Inhibit(false)
+    });
+}
+
+pub fn toggle_maximized<W: IsA<gtk::Window>>(window: &W) {
+    if window.is_maximized() {
+        window.unmaximize();
+    } else {
+        window.maximize();
+    }
+}
+
+/// Moves the window along with the pointer pressed on its title
+/// bar, a double click maximizing or restoring it
+pub fn drag<W: IsA<gtk::Window>>(window: &W, event: &gdk::EventButton) -> gtk::Inhibit {
+    if event.button() != 1 {
+        return gtk::Inhibit(false);
+    }
+    match event.event_type() {
+        gdk::EventType::DoubleButtonPress => toggle_maximized(window),
+        gdk::EventType::ButtonPress => {
+            let (x, y) = event.root();
+            window.begin_move_drag(1, x as i32, y as i32, event.time());
+        },
+        _ => return gtk::Inhibit(false),
+    }
+    gtk::Inhibit(true)
+}
@@ -0,0 +1,200 @@
+use gtk::prelude::*;
+
+use crate::app::models::AppModel;
+use crate::app::preferences::Preferences;
+use crate::gedcom::{batch, merge, xref_key, Record};
+use crate::prelude::*;
+
+/// Review of the batches imported into the opened tree: their
+/// persons can be linked to existing ones, or the whole batch
+/// rolled back.
+pub struct BatchView {
+    gbuilder: gtk::Builder,
+    model: AppModel,
+    batch: Option<String>,
+}
+
+impl BatchView {
+    pub fn new(model: AppModel) -> Self {
+        Self {
+            gbuilder: gtk::Builder::from_resource("/org/altereigo/npaf/Batch.glade"),
+            model,
+            batch: None,
+        }
+    }
+
+    /// Opens the view on the given batch
+    pub fn with_batch(model: AppModel, batch: &str) -> Self {
+        Self {
+            batch: Some(batch.to_owned()),
+            ..Self::new(model)
+        }
+    }
+
+    fn status(gbuilder: &gtk::Builder, text: &str) {
+        let status: gtk::Label = gbuilder.object("l_status").unwrap();
+        status.set_text(text);
+    }
+
+    fn selected_batch(gbuilder: &gtk::Builder) -> Option<String> {
+        let batches: gtk::ComboBoxText = gbuilder.object("cb_batch").unwrap();
+        batches.active_id().map(|b| b.to_string())
+    }
+
+    /// Lists the batches still in the tree, selecting the given
+    /// one or else the latest
+    fn fill_batches(gbuilder: &gtk::Builder, model: &AppModel, selected: Option<&str>) {
+        let combo: gtk::ComboBoxText = gbuilder.object("cb_batch").unwrap();
+        combo.remove_all();
+        let batches = batch::batches(&model.registry());
+        for (batch, count) in &batches {
+            combo.append(Some(batch), &format!("Batch {} ({} records)", batch, count));
+        }
+        let latest = batches.last().map(|(batch, _)| batch.as_str());
+        if !selected.map_or(false, |b| combo.set_active_id(Some(b))) {
+            combo.set_active_id(latest);
+        }
+        if batches.is_empty() {
+            Self::status(gbuilder, "No imported batch left in the tree.");
+        }
+    }
+
+    fn find_matches(gbuilder: &gtk::Builder, model: &AppModel) {
+        let store: gtk::ListStore = gbuilder.object("candidates_store").unwrap();
+        let threshold: gtk::SpinButton = gbuilder.object("sb_threshold").unwrap();
+        store.clear();
+        let batch = match Self::selected_batch(gbuilder) {
+            Some(batch) => batch,
+            None => return,
+        };
+        let describe = |r: &Record| match r.lifespan() {
+            lifespan if lifespan.is_empty() => format!("{} [{}]", r.display_name(), r.rin),
+            lifespan => format!("{} ({}) [{}]", r.display_name(), lifespan, r.rin),
+        };
+        let registry = model.registry();
+        let keys = batch::batch_keys(&registry, &batch);
+        let candidates = merge::find_duplicates_in(&registry, &keys, threshold.value_as_int().max(0) as u32);
+        for candidate in &candidates {
+            let (imported, existing) = (candidate.left.borrow(), candidate.right.borrow());
+            store.insert_with_values(None, &[
+                (0, &xref_key(&imported.xref)),
+                (1, &xref_key(&existing.xref)),
+                (2, &candidate.score),
+                (3, &describe(&imported)),
+                (4, &describe(&existing)),
+            ]);
+        }
+        Self::status(gbuilder, &format!("{} possible match(es) found in batch {}.", candidates.len(), batch));
+    }
+
+    fn link(gbuilder: &gtk::Builder, model: &AppModel, imported: u64, existing: u64) {
+        let result = batch::link(&mut model.registry_mut(), imported, existing);
+        match result {
+            Ok(()) => {
+                model.relink();
+                let selected = Self::selected_batch(gbuilder);
+                // Filling the batches again lists the matches left
+                Self::fill_batches(gbuilder, model, selected.as_deref());
+                Self::status(gbuilder, "Imported person linked to the existing one.");
+                let _ = model.navigate(existing);
+            },
+            Err(err) => Self::status(gbuilder, &err.what()),
+        }
+    }
+
+    /// Registry key of the individual with the RIN typed in the entry
+    fn key_of_rin(gbuilder: &gtk::Builder, model: &AppModel, entry: &str) -> Option<u64> {
+        let entry: gtk::Entry = gbuilder.object(entry).unwrap();
+        let rin = entry.text().trim().parse::<u64>().ok()?;
+        model.registry().iter()
+            .find(|(_, r)| r.borrow().rtype == "INDI" && r.borrow().rin == rin)
+            .map(|(key, _)| *key)
+    }
+
+    fn link_rins(gbuilder: &gtk::Builder, model: &AppModel) {
+        let imported = Self::key_of_rin(gbuilder, model, "e_imported");
+        let existing = Self::key_of_rin(gbuilder, model, "e_existing");
+        match (imported, existing) {
+            (Some(imported), Some(existing)) => Self::link(gbuilder, model, imported, existing),
+            _ => Self::status(gbuilder, "Type the RINs of two individuals of the tree."),
+        }
+    }
+
+    /// Removes the selected batch once the user agreed to
+    fn rollback(gbuilder: &gtk::Builder, model: &AppModel) {
+        let batch = match Self::selected_batch(gbuilder) {
+            Some(batch) => batch,
+            None => return,
+        };
+        let window: gtk::Window = gbuilder.object("top").unwrap();
+        let dialog = gtk::MessageDialog::new(
+            Some(&window),
+            gtk::DialogFlags::MODAL,
+            gtk::MessageType::Question,
+            gtk::ButtonsType::YesNo,
+            &format!("Remove all the records of batch {}? Persons already linked to existing ones are kept.", batch),
+        );
+        let answer = dialog.run();
+        dialog.close();
+        if answer != gtk::ResponseType::Yes {
+            return;
+        }
+        let removed = batch::rollback(&mut model.registry_mut(), &batch);
+        model.relink();
+        Self::fill_batches(gbuilder, model, None);
+        Self::status(gbuilder, &format!("Batch {} rolled back, {} record(s) removed.", batch, removed));
+    }
+}
+
+impl View for BatchView {
+    fn assemble(&self) -> gtk::Widget {
+        let root: gtk::Grid = self.gbuilder.object("root").unwrap();
+        let threshold: gtk::SpinButton = self.gbuilder.object("sb_threshold").unwrap();
+        threshold.set_value(Preferences::load().duplicate_score as f64);
+        Self::fill_batches(&self.gbuilder, &self.model, self.batch.as_deref());
+        Self::find_matches(&self.gbuilder, &self.model);
+
+        let batches: gtk::ComboBoxText = self.gbuilder.object("cb_batch").unwrap();
+        let (gbuilder, model) = (self.gbuilder.clone(), self.model.clone());
+        batches.connect_changed(move |_| Self::find_matches(&gbuilder, &model));
+
+        let b_find: gtk::Button = self.gbuilder.object("b_find").unwrap();
+        let (gbuilder, model) = (self.gbuilder.clone(), self.model.clone());
+        b_find.connect_clicked(move |_| Self::find_matches(&gbuilder, &model));
+
+        let b_link: gtk::Button = self.gbuilder.object("b_link").unwrap();
+        let candidates: gtk::TreeView = self.gbuilder.object("tv_candidates").unwrap();
+        let (gbuilder, model) = (self.gbuilder.clone(), self.model.clone());
+        b_link.connect_clicked(move |_| match candidates.selection().selected() {
+            Some((store, iter)) => {
+                let imported = store.value(&iter, 0).get::<u64>().unwrap();
+                let existing = store.value(&iter, 1).get::<u64>().unwrap();
+                Self::link(&gbuilder, &model, imported, existing);
+            },
+            None => Self::status(&gbuilder, "Select a match first."),
+        });
+
+        let b_link_rin: gtk::Button = self.gbuilder.object("b_link_rin").unwrap();
+        let (gbuilder, model) = (self.gbuilder.clone(), self.model.clone());
+        b_link_rin.connect_clicked(move |_| Self::link_rins(&gbuilder, &model));
+
+        let b_rollback: gtk::Button = self.gbuilder.object("b_rollback").unwrap();
+        let (gbuilder, model) = (self.gbuilder.clone(), self.model.clone());
+        b_rollback.connect_clicked(move |_| Self::rollback(&gbuilder, &model));
+
+        root.show();
+        root.dynamic_cast::<gtk::Widget>().unwrap()
+    }
+}
+
+impl Windowed for BatchView {
+    fn assemble_window(&self) -> gtk::Window {
+        let wdg = self.assemble();
+        let wdw: gtk::Window = self.gbuilder.object("top").unwrap();
+        wdw.set_child(Some(&wdg));
+        let b_close: gtk::Button = self.gbuilder.object("b_close").unwrap();
+        let wdw_cpy = wdw.clone();
+        b_close.connect_clicked(move |_| wdw_cpy.close());
+        wdw
+    }
+}
@@ -0,0 +1,582 @@
+//! Command line of nPAF: the GUI by default, or subcommands
+//! working on files without it, so that scripts use the same
+//! parsing engine. Every subcommand takes `--json` to print
+//! its result as JSON instead of text.
+
+use crate::diagram::{Coloring, Diagram, DiagramFormat, NodeKind};
+use crate::gedcom::csv;
+use crate::gedcom::export::{ExportContent, ExportFormat, ExportScope};
+use crate::gedcom::import::{self, Diagnostic, ImportSummary};
+use crate::gedcom::json::Json;
+use crate::gedcom::search::{self, DateCriterion, NameMatch, SearchCriteria};
+use crate::gedcom::stats::Statistics;
+use crate::gedcom::verify::{self, Severity, VerifyRules};
+use crate::gedcom::{xref_key, GedWriter, ParseError, PrivacyMode, RecordRegistry, SimpleDate};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+
+/// Exit code of a successful command
+pub const EXIT_OK: i32 = 0;
+/// Exit code of a file with errors, or of a query without result
+pub const EXIT_FAILED: i32 = 1;
+/// Exit code of a wrong command line
+pub const EXIT_USAGE: i32 = 2;
+/// Exit code of a file that couldn't be read or written
+pub const EXIT_IO: i32 = 3;
+
+/// Error of a command, along with the exit code it ends with
+struct Failure {
+    code: i32,
+    msg: String,
+}
+
+impl Failure {
+    fn new(code: i32, msg: String) -> Self {
+        Failure { code, msg }
+    }
+
+    fn io(msg: String) -> Self {
+        Self::new(EXIT_IO, msg)
+    }
+}
+
+/// Writes to the standard output, a reader that went away
+/// (`npaf query … | head`) not being an error
+fn to_stdout<F: FnOnce(&mut std::io::StdoutLock) -> std::io::Result<()>>(write: F) -> std::io::Result<()> {
+    let stdout = std::io::stdout();
+    let mut out = stdout.lock();
+    match write(&mut out).and_then(|()| out.flush()) {
+        Err(err) if err.kind() == std::io::ErrorKind::BrokenPipe => Ok(()),
+        result => result,
+    }
+}
+
+pub const USAGE: &str = "\
+Usage: npaf [COMMAND] [OPTIONS]
+
+Commands:
+  gui [FILE]                 Open the application, importing FILE if given (default)
+  validate FILE              Check the file, exits with 1 when it has errors
+  convert IN OUT             Convert IN to the format told by the extension of OUT
+  stats FILE                 Count the records, surnames, births and deaths by decade,
+                             ages, places and the completeness of the data
+  export FILE                Export part of the file (see the export options)
+  query FILE                 List the individuals matching the criteria, exits with 1 when none does
+  graph FILE                 Write the family graph as Graphviz DOT or SVG (see the graph options)
+  help                       Show this help
+
+Files are read as GEDCOM 5.5.1 or 7.0 (.ged), GEDZIP packages (.gdz, .zip),
+Gramps XML (.gramps) or JSON-LD (.json, .jsonld).
+
+Options:
+  --json                     Print the result as JSON
+
+Export options (convert takes --format and --columns only):
+  --format FORMAT            ged551, ged7, gedzip, gramps, json or csv
+  --columns LIST             Columns of the CSV files, comma separated: id, name,
+                             given, surname, sex, TAG.date, TAG.place, TAG.value,
+                             father, mother, spouse or note (default id,given,
+                             surname,sex,BIRT.date,BIRT.place,DEAT.date,DEAT.place,
+                             father,mother)
+  --output OUT               File written, the standard output by default
+  --scope SCOPE              all, ancestors, descendants or related (default all)
+  --root XREF                Person the ancestors, descendants or relatives are those of
+  --generations N            Generations of ancestors or descendants (default 4)
+  --living MODE              include, names, redact or omit (default include)
+  --no-notes, --no-sources, --no-media, --no-lds, --no-private
+                             Leave the notes, sources, media, LDS ordinances
+                             or private data out
+
+Graph options:
+  --format FORMAT            dot or svg, told by the extension of --output by default
+  --output OUT, --scope SCOPE, --root XREF, --generations N, --living MODE
+                             As for export, the related scope being everyone
+                             connected to the person
+  --color COLORING           plain, sex or surname (default sex)
+
+Query options:
+  --given NAME, --surname NAME
+  --match MODE               exact, contains, soundex or dm (default contains)
+  --sex M|F|U
+  --place TEXT               Part of the place of any event
+  --born FROM..TO, --died FROM..TO
+                             Dates or years, either end being optional
+  --id ID                    Custom identifier (REFN)
+";
+
+/// Options followed by a value
+const VALUE_OPTIONS: [&str; 16] = [
+    "format", "columns", "output", "scope", "root", "generations", "living", "color",
+    "given", "surname", "match", "sex", "place", "born", "died", "id",
+];
+
+/// Options of the export settings
+const EXPORT_OPTIONS: [&str; 12] = [
+    "format", "columns", "output", "scope", "root", "generations", "living",
+    "no-notes", "no-sources", "no-media", "no-lds", "no-private",
+];
+
+/// Options of the family graphs
+const GRAPH_OPTIONS: [&str; 7] = ["format", "output", "scope", "root", "generations", "living", "color"];
+
+const QUERY_OPTIONS: [&str; 8] = ["given", "surname", "match", "sex", "place", "born", "died", "id"];
+
+/// Generations exported when not given
+const DEFAULT_GENERATIONS: usize = 4;
+
+#[derive(Clone)]
+pub enum Command {
+    Gui { import: Option<PathBuf> },
+    Help,
+    Validate { file: PathBuf },
+    Stats { file: PathBuf },
+    /// Export or conversion, to the standard
+    /// output when there's no output file
+    Export { file: PathBuf, output: Option<PathBuf>, format: ExportFormat, writer: GedWriter },
+    Query { file: PathBuf, criteria: SearchCriteria },
+    /// Family graph of the persons in the scope of the writer
+    Graph { file: PathBuf, output: Option<PathBuf>, format: DiagramFormat, coloring: Coloring, writer: GedWriter },
+}
+
+/// Command given on the command line and the way it prints its result
+#[derive(Clone)]
+pub struct Invocation {
+    pub command: Command,
+    pub json: bool,
+}
+
+/// Arguments split into positional ones and options
+struct Arguments {
+    positional: Vec<String>,
+    options: Vec<(String, Option<String>)>,
+}
+
+impl Arguments {
+    /// Splits the arguments, taking `--name value` or
+    /// `--name=value` for the options expecting a value
+    fn split<I: Iterator<Item = String>>(mut args: I) -> Result<Self, String> {
+        let mut arguments = Arguments { positional: vec![], options: vec![] };
+        while let Some(arg) = args.next() {
+            let option = match arg.strip_prefix("--") {
+                Some(option) if !option.is_empty() => option,
+                _ => {
+                    arguments.positional.push(arg);
+                    continue;
+                },
+            };
+            let (name, value) = match option.split_once('=') {
+                Some((name, value)) => (name.to_owned(), Some(value.to_owned())),
+                None if VALUE_OPTIONS.contains(&option) => {
+                    let value = args.next().ok_or_else(|| format!("--{} expects a value.", option))?;
+                    (option.to_owned(), Some(value))
+                },
+                None => (option.to_owned(), None),
+            };
+            arguments.options.push((name, value));
+        }
+        Ok(arguments)
+    }
+
+    fn flag(&self, name: &str) -> bool {
+        self.options.iter().any(|(n, _)| n == name)
+    }
+
+    fn value(&self, name: &str) -> Option<&str> {
+        self.options.iter()
+            .rev()
+            .find(|(n, _)| n == name)
+            .and_then(|(_, v)| v.as_deref())
+    }
+
+    /// Fails on the options the command doesn't take and on
+    /// a wrong number of positional arguments
+    fn check(&self, command: &str, allowed: &[&str], positional: usize) -> Result<(), String> {
+        if let Some((name, _)) = self.options.iter().find(|(n, _)| n != "json" && !allowed.contains(&n.as_str())) {
+            return Err(format!("Unknown option for {}: --{}", command, name));
+        }
+        match self.positional.len() {
+            n if n == positional => Ok(()),
+            n if n < positional => Err(format!("Missing argument for {}.", command)),
+            _ => Err(format!("Too many arguments for {}.", command)),
+        }
+    }
+
+    fn path(&self, index: usize) -> PathBuf {
+        PathBuf::from(&self.positional[index])
+    }
+}
+
+/// Date range of the `FROM..TO` form, a single date standing
+/// for both ends
+fn date_range(text: &str, event: &str) -> Result<DateCriterion, String> {
+    let date = |text: &str| match text.trim() {
+        "" => Ok(None),
+        text => SimpleDate::parse(text).map(Some).ok_or_else(|| format!("Invalid date: '{}'", text)),
+    };
+    let (from, to) = match text.split_once("..") {
+        Some((from, to)) => (date(from)?, date(to)?),
+        None => (date(text)?, date(text)?),
+    };
+    Ok(DateCriterion { event: Some(event.to_owned()), from, to })
+}
+
+fn criteria(args: &Arguments) -> Result<SearchCriteria, String> {
+    let name_match = match args.value("match") {
+        None | Some("contains") => NameMatch::Contains,
+        Some("exact") => NameMatch::Exact,
+        Some("soundex") => NameMatch::Soundex,
+        Some("dm") => NameMatch::DaitchMokotoff,
+        Some(other) => return Err(format!("Unknown name match: {}", other)),
+    };
+    let sex = match args.value("sex").map(|s| s.to_uppercase()) {
+        None => None,
+        Some(s) if matches!(s.as_str(), "M" | "F" | "U") => s.chars().next(),
+        Some(other) => return Err(format!("Unknown sex: {}", other)),
+    };
+    let mut dates = vec![];
+    if let Some(born) = args.value("born") {
+        dates.push(date_range(born, "BIRT")?);
+    }
+    if let Some(died) = args.value("died") {
+        dates.push(date_range(died, "DEAT")?);
+    }
+    let text = |name: &str| args.value(name).map(str::to_owned);
+    Ok(SearchCriteria {
+        given: text("given"),
+        surname: text("surname"),
+        name_match,
+        sex,
+        dates,
+        place: text("place"),
+        custom_id: text("id"),
+    })
+}
+
+fn format_of(args: &Arguments, output: Option<&Path>) -> Result<ExportFormat, String> {
+    let format = match (args.value("format"), output) {
+        (Some(id), _) => ExportFormat::from_id(id).ok_or_else(|| format!("Unknown format: {}", id))?,
+        (None, Some(output)) => ExportFormat::of_path(output)
+            .ok_or_else(|| format!("Can't tell the format of '{}', give it with --format.", output.display()))?,
+        (None, None) => ExportFormat::default(),
+    };
+    match (format, args.value("columns")) {
+        (ExportFormat::Csv(_), Some(ids)) => csv::columns(ids).map(ExportFormat::Csv),
+        (_, Some(_)) => Err(String::from("--columns is only for the csv format.")),
+        (format, None) => Ok(format),
+    }
+}
+
+fn writer(args: &Arguments) -> Result<GedWriter, String> {
+    let generations = match args.value("generations") {
+        Some(n) => n.parse::<usize>().ok().filter(|n| *n > 0).ok_or_else(|| format!("Invalid generations: {}", n))?,
+        None => DEFAULT_GENERATIONS,
+    };
+    let root = || args.value("root")
+        .map(xref_key)
+        .ok_or_else(|| String::from("--root is needed by this scope."));
+    let scope = match args.value("scope") {
+        None | Some("all") => ExportScope::All,
+        Some("ancestors") => ExportScope::Ancestors { root: root()?, generations },
+        Some("descendants") => ExportScope::Descendants { root: root()?, generations },
+        Some("related") => ExportScope::Related { root: root()? },
+        Some(other) => return Err(format!("Unknown scope: {}", other)),
+    };
+    let privacy = match args.value("living") {
+        None => PrivacyMode::Include,
+        Some(id) => PrivacyMode::from_id(id).ok_or_else(|| format!("Unknown living mode: {}", id))?,
+    };
+    Ok(GedWriter {
+        privacy,
+        scope,
+        content: ExportContent {
+            notes: !args.flag("no-notes"),
+            sources: !args.flag("no-sources"),
+            media: !args.flag("no-media"),
+            lds: !args.flag("no-lds"),
+            private: !args.flag("no-private"),
+        },
+        ..Default::default()
+    })
+}
+
+impl Invocation {
+    /// Reads the arguments following the program name. Without
+    /// command, a single file argument is imported in the GUI.
+    pub fn parse<I: Iterator<Item = String>>(args: I) -> Result<Self, String> {
+        let mut args = Arguments::split(args)?;
+        let json = args.flag("json");
+        let name = match args.positional.first() {
+            Some(name) => name.clone(),
+            None if args.flag("help") => String::from("help"),
+            None => String::from("gui"),
+        };
+        let known = ["gui", "help", "validate", "convert", "stats", "export", "query", "graph"];
+        if known.contains(&name.as_str()) {
+            args.positional.remove(0);
+        }
+        let command = match name.as_str() {
+            "help" => Command::Help,
+            "validate" => {
+                args.check(&name, &[], 1)?;
+                Command::Validate { file: args.path(0) }
+            },
+            "stats" => {
+                args.check(&name, &[], 1)?;
+                Command::Stats { file: args.path(0) }
+            },
+            "convert" => {
+                args.check(&name, &["format", "columns"], 2)?;
+                let output = args.path(1);
+                Command::Export {
+                    file: args.path(0),
+                    format: format_of(&args, Some(&output))?,
+                    output: Some(output),
+                    writer: GedWriter::default(),
+                }
+            },
+            "export" => {
+                args.check(&name, &EXPORT_OPTIONS, 1)?;
+                let output = args.value("output").map(PathBuf::from);
+                Command::Export {
+                    file: args.path(0),
+                    format: format_of(&args, output.as_deref())?,
+                    output,
+                    writer: writer(&args)?,
+                }
+            },
+            "query" => {
+                args.check(&name, &QUERY_OPTIONS, 1)?;
+                Command::Query { file: args.path(0), criteria: criteria(&args)? }
+            },
+            "graph" => {
+                args.check(&name, &GRAPH_OPTIONS, 1)?;
+                let output = args.value("output").map(PathBuf::from);
+                let format = match (args.value("format"), output.as_deref()) {
+                    (Some(id), _) => DiagramFormat::from_id(id).ok_or_else(|| format!("Unknown format: {}", id))?,
+                    (None, Some(output)) => DiagramFormat::of_path(output).unwrap_or(DiagramFormat::Dot),
+                    (None, None) => DiagramFormat::Dot,
+                };
+                let coloring = match args.value("color") {
+                    Some(id) => Coloring::from_id(id).ok_or_else(|| format!("Unknown coloring: {}", id))?,
+                    None => Coloring::default(),
+                };
+                Command::Graph { file: args.path(0), output, format, coloring, writer: writer(&args)? }
+            },
+            _ if args.flag("help") => Command::Help,
+            _ => {
+                args.check("the application", &[], args.positional.len().min(1))?;
+                let import = args.positional.first().map(PathBuf::from);
+                if let Some(path) = import.as_ref().filter(|p| !p.exists()) {
+                    return Err(format!("Unknown command or file: {}", path.display()));
+                }
+                Command::Gui { import }
+            },
+        };
+        Ok(Invocation { command, json })
+    }
+
+    /// Runs a command other than the GUI, returning the exit code
+    pub fn execute(&self) -> i32 {
+        let result = match &self.command {
+            Command::Gui { .. } => Ok(EXIT_OK),
+            Command::Help => to_stdout(|out| write!(out, "{}", USAGE))
+                .map(|()| EXIT_OK)
+                .map_err(|err| Failure::io(format!("Could not print the help: {}", err))),
+            Command::Validate { file } => self.validate(file),
+            Command::Stats { file } => self.stats(file),
+            Command::Export { file, output, format, writer } => self.export(file, output.as_deref(), format, writer),
+            Command::Query { file, criteria } => self.query(file, criteria),
+            Command::Graph { file, output, format, coloring, writer } => self.graph(file, output.as_deref(), *format, *coloring, writer),
+        };
+        result.unwrap_or_else(|failure| {
+            self.fail(&failure.msg);
+            failure.code
+        })
+    }
+
+    /// Prints an error, to the standard output in JSON
+    /// so that scripts read it along with the results
+    pub fn fail(&self, msg: &str) {
+        match self.json {
+            true => {
+                let _ = to_stdout(|out| writeln!(out, "{}", Json::object().with("error", msg)));
+            },
+            false => eprintln!("npaf: {}", msg),
+        }
+    }
+
+    fn print(&self, json: Json, text: &str) -> Result<(), Failure> {
+        to_stdout(|out| match self.json {
+            true => writeln!(out, "{}", json.pretty()),
+            false => write!(out, "{}", text),
+        }).map_err(|err| Failure::io(format!("Could not print the result: {}", err)))
+    }
+
+    fn validate(&self, file: &Path) -> Result<i32, Failure> {
+        let (registry, summary) = load(file)?;
+        let issues = verify::verify(&registry, &VerifyRules::default());
+        let errors = issues.iter().filter(|i| i.severity() == Severity::Error).count();
+        let unparsed = summary.diagnostics.iter().filter(|d| !matches!(d, Diagnostic::Encoding { .. })).count();
+        let valid = errors == 0 && unparsed == 0;
+
+        let name = file.display();
+        let mut text = format!("{}: {}, {} records\n", name, summary.origin(), summary.records());
+        let mut diagnostics = vec![];
+        for diagnostic in &summary.diagnostics {
+            match diagnostic.line() {
+                Some(line) => text.push_str(&format!("{}:{}: {}\n", name, line, diagnostic.what())),
+                None => text.push_str(&format!("{}: {}\n", name, diagnostic.what())),
+            }
+            diagnostics.push(Json::object()
+                .with("line", diagnostic.line())
+                .with("message", diagnostic.what()));
+        }
+        let mut found = vec![];
+        for issue in &issues {
+            let xref = registry.get(&issue.record).map(|r| r.borrow().xref.clone());
+            let severity = match issue.severity() {
+                Severity::Error => "error",
+                Severity::Warning => "warning",
+            };
+            text.push_str(&format!("{}: {}: {} ({}): {}\n",
+                name, severity, issue.name, xref.as_deref().unwrap_or("?"), issue.what()));
+            found.push(Json::object()
+                .with("severity", severity)
+                .with("record", xref)
+                .with("name", issue.name.as_str())
+                .with("message", issue.what()));
+        }
+        text.push_str(&format!("{} error(s), {} warning(s), {} line(s) or record(s) left out.\n",
+            errors, issues.len() - errors, unparsed));
+
+        let json = Json::object()
+            .with("file", name.to_string())
+            .with("format", summary.format.label())
+            .with("lines", summary.lines)
+            .with("records", summary.records())
+            .with("valid", valid)
+            .with("errors", errors)
+            .with("warnings", issues.len() - errors)
+            .with("diagnostics", diagnostics)
+            .with("issues", found);
+        self.print(json, &text)?;
+        Ok(if valid { EXIT_OK } else { EXIT_FAILED })
+    }
+
+    fn stats(&self, file: &Path) -> Result<i32, Failure> {
+        let (registry, summary) = load(file)?;
+        let stats = Statistics::new(&registry);
+        let mut text = format!("{}: {}\n", file.display(), summary.origin());
+        let mut section = "";
+        for (name, item, value) in stats.rows(false) {
+            if name != section {
+                text.push_str(&format!("{}:\n", name));
+                section = name;
+            }
+            text.push_str(&format!("  {:<32}{}\n", item, value));
+        }
+        let json = Json::object()
+            .with("file", file.display().to_string())
+            .with("format", summary.format.label());
+        let json = match stats.to_json() {
+            Json::Object(members) => members.into_iter().fold(json, |json, (key, value)| json.with(&key, value)),
+            _ => json,
+        };
+        self.print(json, &text)?;
+        Ok(EXIT_OK)
+    }
+
+    fn export(&self, file: &Path, output: Option<&Path>, format: &ExportFormat, writer: &GedWriter) -> Result<i32, Failure> {
+        let registry = load_scoped(file, writer)?;
+        let written = match output {
+            Some(output) => std::fs::File::create(output)
+                .and_then(|out| format.write(writer, &registry, out)),
+            None => {
+                let mut out = std::io::Cursor::new(vec![]);
+                format.write(writer, &registry, &mut out)
+                    .and_then(|()| to_stdout(|stdout| stdout.write_all(out.get_ref())))
+            },
+        };
+        written.map_err(|err| Failure::io(format!("Could not export the file: {}", err)))?;
+        if let (Some(output), true) = (output, self.json) {
+            let json = Json::object()
+                .with("file", file.display().to_string())
+                .with("output", output.display().to_string())
+                .with("format", format.id());
+            self.print(json, "")?;
+        }
+        Ok(EXIT_OK)
+    }
+
+    fn query(&self, file: &Path, criteria: &SearchCriteria) -> Result<i32, Failure> {
+        let (registry, _) = load(file)?;
+        let found = search::search(&registry, criteria);
+        let mut text = String::new();
+        let mut persons = vec![];
+        for person in &found {
+            let person = person.borrow();
+            text.push_str(&format!("{}\t{}\t{}\n", person.xref, person.display_name(), person.lifespan()));
+            let year = |tag: &str| person.event_date(tag).and_then(|d| d.year());
+            persons.push(Json::object()
+                .with("xref", person.xref.as_str())
+                .with("name", person.display_name())
+                .with("given", person.given_names())
+                .with("surname", person.surname())
+                .with("sex", person.sex())
+                .with("birth", year("BIRT"))
+                .with("death", year("DEAT")));
+        }
+        self.print(Json::from(persons), &text)?;
+        Ok(if found.is_empty() { EXIT_FAILED } else { EXIT_OK })
+    }
+
+    fn graph(&self, file: &Path, output: Option<&Path>, format: DiagramFormat, coloring: Coloring, writer: &GedWriter) -> Result<i32, Failure> {
+        let registry = load_scoped(file, writer)?;
+        let diagram = Diagram::new(writer.exported(&registry), coloring);
+        let written = match output {
+            Some(output) => std::fs::File::create(output).and_then(|out| diagram.write(format, out)),
+            None => to_stdout(|out| diagram.write(format, out)),
+        };
+        written.map_err(|err| Failure::io(format!("Could not write the graph: {}", err)))?;
+        if let (Some(output), true) = (output, self.json) {
+            let count = |kind| diagram.nodes.iter().filter(|n| n.kind == kind).count();
+            let json = Json::object()
+                .with("file", file.display().to_string())
+                .with("output", output.display().to_string())
+                .with("format", format.extension())
+                .with("individuals", count(NodeKind::Person))
+                .with("families", count(NodeKind::Family));
+            self.print(json, "")?;
+        }
+        Ok(EXIT_OK)
+    }
+}
+
+/// Imports any file nPAF reads, as the GUI does. Files that
+/// can't be opened fail with [`EXIT_IO`], the ones that can't
+/// be parsed with [`EXIT_FAILED`].
+fn load(file: &Path) -> Result<(RecordRegistry, ImportSummary), Failure> {
+    import::import_path(file, &AtomicBool::new(false), |_| ())
+        .map(|imported| imported.into_registry())
+        .map_err(|err| {
+            let code = match err {
+                ParseError::IO(_) => EXIT_IO,
+                _ => EXIT_FAILED,
+            };
+            Failure::new(code, format!("Could not read '{}': {}", file.display(), err.what()))
+        })
+}
+
+/// Reads the file, checking that the person the scope
+/// of the writer starts from is in it: an unknown `--root`
+/// is a wrong command line
+fn load_scoped(file: &Path, writer: &GedWriter) -> Result<RecordRegistry, Failure> {
+    let (registry, _) = load(file)?;
+    if let ExportScope::Ancestors { root, .. } | ExportScope::Descendants { root, .. } | ExportScope::Related { root } = writer.scope {
+        if !registry.contains_key(&root) {
+            return Err(Failure::new(EXIT_USAGE, String::from("The --root person isn't in the file.")));
+        }
+    }
+    Ok(registry)
+}
@@ -0,0 +1,204 @@
+use gtk::prelude::*;
+
+use crate::app::models::AppModel;
+use crate::batch_view::BatchView;
+use crate::gedcom::batch;
+use crate::gedcom::csv::{self, CsvField, CsvImport};
+use crate::prelude::*;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+/// Wizard importing the rows of a CSV file as persons: every column
+/// is mapped to a field, the persons and problems found being shown
+/// before anything is added to the tree
+pub struct CsvImportView {
+    gbuilder: gtk::Builder,
+    model: AppModel,
+    path: PathBuf,
+}
+
+impl CsvImportView {
+    pub fn new(model: AppModel, path: PathBuf) -> Self {
+        Self {
+            gbuilder: gtk::Builder::from_resource("/org/altereigo/npaf/CsvImport.glade"),
+            model,
+            path,
+        }
+    }
+
+    fn status(gbuilder: &gtk::Builder, text: &str) {
+        let status: gtk::Label = gbuilder.object("l_status").unwrap();
+        status.set_text(text);
+    }
+
+    fn delimiter(gbuilder: &gtk::Builder) -> char {
+        let combo: gtk::ComboBoxText = gbuilder.object("cb_delimiter").unwrap();
+        match combo.active_id().as_deref() {
+            Some("semicolon") => ';',
+            Some("tab") => '\t',
+            _ => ',',
+        }
+    }
+
+    fn has_header(gbuilder: &gtk::Builder) -> bool {
+        let header: gtk::CheckButton = gbuilder.object("c_header").unwrap();
+        header.is_active()
+    }
+
+    /// Field chosen for every column
+    fn fields(gbuilder: &gtk::Builder) -> Vec<CsvField> {
+        let columns: gtk::Grid = gbuilder.object("p_columns").unwrap();
+        (0..)
+            .map_while(|row| columns.child_at(2, row))
+            .map(|combo| {
+                let combo = combo.downcast::<gtk::ComboBoxText>().unwrap();
+                combo.active_id()
+                    .and_then(|id| CsvField::from_id(&id))
+                    .unwrap_or(CsvField::Ignored)
+            })
+            .collect()
+    }
+
+    /// Lists the columns of the file with their first value and
+    /// the field guessed out of their name
+    fn fill_columns(gbuilder: &gtk::Builder, text: &Rc<String>) {
+        let columns: gtk::Grid = gbuilder.object("p_columns").unwrap();
+        for child in columns.children() {
+            columns.remove(&child);
+        }
+        let rows = csv::parse(text, Self::delimiter(gbuilder));
+        let header = Self::has_header(gbuilder);
+        let count = rows.iter().map(|r| r.len()).max().unwrap_or(0);
+        let sample = rows.get(if header { 1 } else { 0 });
+        for column in 0..count {
+            let name = match rows.first().and_then(|r| r.get(column)) {
+                Some(name) if header => name.clone(),
+                _ => format!("Column {}", column + 1),
+            };
+            let value = sample.and_then(|r| r.get(column)).cloned().unwrap_or_default();
+            let l_value = gtk::Label::new(Some(&value));
+            l_value.set_halign(gtk::Align::Start);
+            l_value.set_ellipsize(gtk::pango::EllipsizeMode::End);
+            l_value.set_max_width_chars(20);
+            l_value.style_context().add_class("color-secondary");
+            let l_name = gtk::Label::new(Some(&name));
+            l_name.set_halign(gtk::Align::Start);
+            let combo = gtk::ComboBoxText::new();
+            for field in CsvField::choices() {
+                combo.append(Some(&field.id()), &field.label());
+            }
+            let guessed = if header { CsvField::guess(&name) } else { CsvField::Ignored };
+            if !combo.set_active_id(Some(&guessed.id())) {
+                combo.set_active_id(Some(&CsvField::Ignored.id()));
+            }
+            let (gbuilder, text) = (gbuilder.clone(), text.clone());
+            combo.connect_changed(move |_| Self::refresh(&gbuilder, &text));
+            columns.attach(&l_name, 0, column as i32, 1, 1);
+            columns.attach(&l_value, 1, column as i32, 1, 1);
+            columns.attach(&combo, 2, column as i32, 1, 1);
+        }
+        columns.show_all();
+        Self::refresh(gbuilder, text);
+    }
+
+    fn read(gbuilder: &gtk::Builder, text: &str) -> CsvImport {
+        let rows = csv::parse(text, Self::delimiter(gbuilder));
+        csv::read(&rows, &Self::fields(gbuilder), Self::has_header(gbuilder))
+    }
+
+    /// Shows the persons the rows make and the problems met
+    fn refresh(gbuilder: &gtk::Builder, text: &str) {
+        let import = Self::read(gbuilder, text);
+        let preview: gtk::ListStore = gbuilder.object("preview_store").unwrap();
+        preview.clear();
+        for ((row, name), record) in import.persons.iter().zip(&import.records) {
+            let details = record.events.iter()
+                .map(|e| format!("{}: {}", e.label(), e.summary()))
+                .collect::<Vec<String>>()
+                .join("; ");
+            preview.insert_with_values(None, &[(0, &row.to_string()), (1, name), (2, &details)]);
+        }
+        let problems: gtk::ListStore = gbuilder.object("problems_store").unwrap();
+        problems.clear();
+        for problem in &import.problems {
+            let outcome = if problem.skipped { "Row left out" } else { "Imported" };
+            problems.insert_with_values(None, &[(0, &problem.row.to_string()), (1, &problem.message), (2, &outcome)]);
+        }
+        let b_import: gtk::Button = gbuilder.object("b_import").unwrap();
+        b_import.set_sensitive(!import.persons.is_empty());
+        Self::status(gbuilder, &format!(
+            "{} person(s), {} relative(s) named by the rows and {} family(ies) to add, {} row(s) left out.",
+            import.persons.len(), import.relatives, import.families, import.skipped()));
+    }
+
+    /// Adds the persons and families to the tree as a new batch
+    fn import(gbuilder: &gtk::Builder, model: &AppModel, text: &str) {
+        let import = Self::read(gbuilder, text);
+        let (persons, families) = (import.persons.len() + import.relatives, import.families);
+        let appended = batch::append(&mut model.registry_mut(), import.records);
+        model.relink();
+        Self::status(gbuilder, &format!(
+            "{} person(s) and {} family(ies) added to the tree as batch {}.", persons, families, appended.batch));
+        for name in &["b_import", "cb_delimiter", "c_header", "p_columns"] {
+            let widget: gtk::Widget = gbuilder.object(name).unwrap();
+            widget.set_sensitive(false);
+        }
+        let b_review: gtk::Button = gbuilder.object("b_review").unwrap();
+        let model = model.clone();
+        b_review.connect_clicked(move |_| {
+            BatchView::with_batch(model.clone(), &appended.batch).assemble_window().present();
+        });
+        b_review.show();
+    }
+}
+
+impl View for CsvImportView {
+    fn assemble(&self) -> gtk::Widget {
+        let root: gtk::Grid = self.gbuilder.object("root").unwrap();
+        let l_file: gtk::Label = self.gbuilder.object("l_file").unwrap();
+        l_file.set_text(&format!("Importing '{}'", self.path.display()));
+        let b_review: gtk::Button = self.gbuilder.object("b_review").unwrap();
+        b_review.hide();
+        let text = match std::fs::read(&self.path) {
+            Ok(bytes) => Rc::new(String::from_utf8_lossy(&bytes).into_owned()),
+            Err(err) => {
+                let b_import: gtk::Button = self.gbuilder.object("b_import").unwrap();
+                b_import.set_sensitive(false);
+                Self::status(&self.gbuilder, &format!("Could not read the file: {}", err));
+                root.show();
+                return root.dynamic_cast::<gtk::Widget>().unwrap();
+            },
+        };
+        let delimiter: gtk::ComboBoxText = self.gbuilder.object("cb_delimiter").unwrap();
+        delimiter.set_active_id(Some(match csv::guess_delimiter(&text) {
+            ';' => "semicolon",
+            '\t' => "tab",
+            _ => "comma",
+        }));
+        Self::fill_columns(&self.gbuilder, &text);
+
+        let (gbuilder, cpy) = (self.gbuilder.clone(), text.clone());
+        delimiter.connect_changed(move |_| Self::fill_columns(&gbuilder, &cpy));
+        let header: gtk::CheckButton = self.gbuilder.object("c_header").unwrap();
+        let (gbuilder, cpy) = (self.gbuilder.clone(), text.clone());
+        header.connect_toggled(move |_| Self::fill_columns(&gbuilder, &cpy));
+        let b_import: gtk::Button = self.gbuilder.object("b_import").unwrap();
+        let (gbuilder, model) = (self.gbuilder.clone(), self.model.clone());
+        b_import.connect_clicked(move |_| Self::import(&gbuilder, &model, &text));
+
+        root.show();
+        root.dynamic_cast::<gtk::Widget>().unwrap()
+    }
+}
+
+impl Windowed for CsvImportView {
+    fn assemble_window(&self) -> gtk::Window {
+        let wdg = self.assemble();
+        let wdw: gtk::Window = self.gbuilder.object("top").unwrap();
+        wdw.set_child(Some(&wdg));
+        let b_close: gtk::Button = self.gbuilder.object("b_close").unwrap();
+        let wdw_cpy = wdw.clone();
+        b_close.connect_clicked(move |_| wdw_cpy.close());
+        wdw
+    }
+}
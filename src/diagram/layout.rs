@@ -0,0 +1,176 @@
+//! Layered layout of the graphs: every node gets a rank from the
+//! edges, the nodes of a rank are ordered to cross as few edges as
+//! possible, then moved towards the nodes they are linked to.
+
+use super::{Diagram, NodeKind};
+use crate::report::FontFamily;
+use std::collections::VecDeque;
+
+pub(super) const FONT_SIZE: f64 = 12.0;
+pub(super) const LINE_HEIGHT: f64 = 15.0;
+const PADDING: f64 = 8.0;
+/// Space between two nodes of a rank
+const SPACING: f64 = 24.0;
+/// Space between two ranks
+const RANK_SPACING: f64 = 28.0;
+const MARGIN: f64 = 12.0;
+/// Diameter of the families without label
+pub(super) const FAMILY_DOT: f64 = 8.0;
+/// Rounds of the crossing reduction and of the placement
+const ORDER_ROUNDS: usize = 12;
+const PLACE_ROUNDS: usize = 20;
+
+/// Size and center of every node, and size of the drawing
+pub(super) struct Layout {
+    pub sizes: Vec<(f64, f64)>,
+    pub centers: Vec<(f64, f64)>,
+    pub width: f64,
+    pub height: f64,
+}
+
+/// Size of the node holding its lines of text
+fn size(diagram: &Diagram, node: usize) -> (f64, f64) {
+    let node = &diagram.nodes[node];
+    let text = |line: &String, bold: bool| FontFamily::Helvetica.text_width(line, FONT_SIZE, bold);
+    match (node.kind, node.lines.is_empty()) {
+        (NodeKind::Family, true) => (FAMILY_DOT, FAMILY_DOT),
+        (NodeKind::Family, false) => (node.lines.iter().map(|l| text(l, false)).fold(0.0, f64::max) * 0.8 + PADDING, LINE_HEIGHT),
+        (NodeKind::Person, _) => {
+            let width = node.lines.iter().enumerate().map(|(i, l)| text(l, i == 0)).fold(0.0, f64::max);
+            (width + PADDING * 2.0, LINE_HEIGHT * node.lines.len().max(1) as f64 + PADDING)
+        },
+    }
+}
+
+/// Rank of every node: the longest path from the nodes without
+/// parent, those being then moved down next to the first node
+/// they point to, so that a spouse stands by their partner.
+/// Nodes left over by a cycle of the edges go to the first rank.
+fn ranks(diagram: &Diagram, incoming: &[Vec<usize>], outgoing: &[Vec<usize>]) -> Vec<usize> {
+    let count = diagram.nodes.len();
+    let mut pending: Vec<usize> = incoming.iter().map(|i| i.len()).collect();
+    let mut queue: VecDeque<usize> = (0..count).filter(|n| pending[*n] == 0).collect();
+    let mut order = vec![];
+    let mut rank = vec![0usize; count];
+    while let Some(node) = queue.pop_front() {
+        order.push(node);
+        for next in &outgoing[node] {
+            rank[*next] = rank[*next].max(rank[node] + 1);
+            pending[*next] -= 1;
+            if pending[*next] == 0 {
+                queue.push_back(*next);
+            }
+        }
+    }
+    for node in order.iter().rev() {
+        if incoming[*node].is_empty() {
+            if let Some(lowest) = outgoing[*node].iter().map(|n| rank[*n]).min() {
+                rank[*node] = lowest.saturating_sub(1);
+            }
+        }
+    }
+    rank
+}
+
+/// Mean position of the neighbours, when there are some
+fn barycenter(neighbours: &[usize], position: &[f64]) -> Option<f64> {
+    match neighbours.len() {
+        0 => None,
+        n => Some(neighbours.iter().map(|m| position[*m]).sum::<f64>() / n as f64),
+    }
+}
+
+pub(super) fn layout(diagram: &Diagram) -> Layout {
+    let count = diagram.nodes.len();
+    let mut incoming = vec![vec![]; count];
+    let mut outgoing = vec![vec![]; count];
+    for (from, to) in &diagram.edges {
+        outgoing[*from].push(*to);
+        incoming[*to].push(*from);
+    }
+    let rank = ranks(diagram, &incoming, &outgoing);
+    let sizes: Vec<(f64, f64)> = (0..count).map(|n| size(diagram, n)).collect();
+    let mut layers: Vec<Vec<usize>> = vec![vec![]; rank.iter().max().map_or(0, |r| r + 1)];
+    for node in 0..count {
+        layers[rank[node]].push(node);
+    }
+
+    // Order of the nodes in their rank, by the barycenter of
+    // their parents then of their children, in turn
+    let mut position = vec![0.0; count];
+    for layer in &layers {
+        for (i, node) in layer.iter().enumerate() {
+            position[*node] = i as f64;
+        }
+    }
+    for round in 0..ORDER_ROUNDS {
+        let downwards = round % 2 == 0;
+        let neighbours = if downwards { &incoming } else { &outgoing };
+        let ranks: Vec<usize> = match downwards {
+            true => (1..layers.len()).collect(),
+            false => (0..layers.len().saturating_sub(1)).rev().collect(),
+        };
+        for r in ranks {
+            let mut sorted: Vec<(usize, f64)> = layers[r].iter()
+                .map(|n| (*n, barycenter(&neighbours[*n], &position).unwrap_or(position[*n])))
+                .collect();
+            sorted.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
+            layers[r] = sorted.into_iter().map(|(n, _)| n).collect();
+            for (i, node) in layers[r].iter().enumerate() {
+                position[*node] = i as f64;
+            }
+        }
+    }
+
+    // Horizontal centers: packed, then pulled towards the
+    // neighbours while keeping the order and the spacing
+    let mut x = vec![0.0; count];
+    for layer in &layers {
+        let mut left = 0.0;
+        for node in layer {
+            x[*node] = left + sizes[*node].0 / 2.0;
+            left += sizes[*node].0 + SPACING;
+        }
+    }
+    for _ in 0..PLACE_ROUNDS {
+        for layer in &layers {
+            let desired: Vec<f64> = layer.iter()
+                .map(|n| {
+                    let linked: Vec<usize> = incoming[*n].iter().chain(&outgoing[*n]).copied().collect();
+                    barycenter(&linked, &x).unwrap_or(x[*n])
+                })
+                .collect();
+            let gap = |i: usize| (sizes[layer[i - 1]].0 + sizes[layer[i]].0) / 2.0 + SPACING;
+            let mut pushed_right = desired.clone();
+            for i in 1..layer.len() {
+                pushed_right[i] = pushed_right[i].max(pushed_right[i - 1] + gap(i));
+            }
+            let mut pushed_left = desired;
+            for i in (1..layer.len()).rev() {
+                pushed_left[i - 1] = pushed_left[i - 1].min(pushed_left[i] - gap(i));
+            }
+            for (i, node) in layer.iter().enumerate() {
+                x[*node] = (pushed_right[i] + pushed_left[i]) / 2.0;
+            }
+        }
+    }
+
+    // Ranks stacked from the top, each as high as its highest node
+    let left = (0..count).map(|n| x[n] - sizes[n].0 / 2.0).fold(f64::INFINITY, f64::min);
+    let right = (0..count).map(|n| x[n] + sizes[n].0 / 2.0).fold(f64::NEG_INFINITY, f64::max);
+    let mut centers = vec![(0.0, 0.0); count];
+    let mut top = MARGIN;
+    for layer in &layers {
+        let height = layer.iter().map(|n| sizes[*n].1).fold(0.0, f64::max);
+        for node in layer {
+            centers[*node] = (x[*node] - left + MARGIN, top + height / 2.0);
+        }
+        top += height + RANK_SPACING;
+    }
+    Layout {
+        sizes,
+        centers,
+        width: (right - left).max(0.0) + MARGIN * 2.0,
+        height: (top - RANK_SPACING).max(MARGIN) + MARGIN,
+    }
+}
@@ -0,0 +1,206 @@
+//! Family graphs: persons and families as the nodes of a directed
+//! graph, parents pointing to their family and families to their
+//! children. Written as Graphviz DOT for the tools that lay it out
+//! themselves, or laid out here and drawn as SVG.
+
+mod layout;
+mod svg;
+
+use crate::gedcom::{FamilyGraph, GedParser, GedWriter, Record, RecordRegistry};
+use std::collections::{BTreeSet, HashMap};
+use std::io::Write;
+use std::path::Path;
+
+/// Fill of the person boxes by sex: male, female, unknown
+const SEX_COLORS: [&str; 3] = ["#cfe2f3", "#f4cccc", "#eeeeee"];
+
+/// Fill of the person boxes by surname, the surnames taking
+/// the colors in alphabetical order
+const SURNAME_COLORS: [&str; 12] = [
+    "#cfe2f3", "#f4cccc", "#d9ead3", "#fff2cc", "#d9d2e9", "#fce5cd",
+    "#d0e0e3", "#ead1dc", "#c9daf8", "#e6b8af", "#b6d7a8", "#ffe599",
+];
+
+const PLAIN_COLOR: &str = "#ffffff";
+
+/// Colors of the person boxes
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum Coloring {
+    Plain,
+    Sex,
+    Surname,
+}
+
+impl Default for Coloring {
+    fn default() -> Self {
+        Coloring::Sex
+    }
+}
+
+impl Coloring {
+    /// Coloring from the identifier used by the option lists
+    /// and the command line (`plain`, `sex`, `surname`)
+    pub fn from_id(id: &str) -> Option<Self> {
+        match id {
+            "plain" | "none" => Some(Coloring::Plain),
+            "sex" => Some(Coloring::Sex),
+            "surname" => Some(Coloring::Surname),
+            _ => None,
+        }
+    }
+}
+
+/// File formats the graphs are written to
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum DiagramFormat {
+    Dot,
+    Svg,
+}
+
+impl DiagramFormat {
+    pub fn from_id(id: &str) -> Option<Self> {
+        match id {
+            "dot" | "gv" => Some(DiagramFormat::Dot),
+            "svg" => Some(DiagramFormat::Svg),
+            _ => None,
+        }
+    }
+
+    /// Format told by the extension of the file
+    pub fn of_path(path: &Path) -> Option<Self> {
+        path.extension()
+            .and_then(|e| e.to_str())
+            .and_then(|e| Self::from_id(&e.to_lowercase()))
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            DiagramFormat::Dot => "dot",
+            DiagramFormat::Svg => "svg",
+        }
+    }
+}
+
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum NodeKind {
+    Person,
+    Family,
+}
+
+/// Person or family of the graph
+#[derive(Debug,Clone)]
+pub struct Node {
+    /// Cross-reference of the record
+    pub id: String,
+    pub kind: NodeKind,
+    /// Name and lifespan of a person, year of marriage of a family
+    pub lines: Vec<String>,
+    pub fill: &'static str,
+}
+
+/// Graph of the persons and families of a registry,
+/// edges going from the parents to the children
+#[derive(Debug,Clone,Default)]
+pub struct Diagram {
+    pub nodes: Vec<Node>,
+    pub edges: Vec<(usize, usize)>,
+}
+
+impl Diagram {
+    /// Graph of the records, usually the ones [exported](crate::gedcom::GedWriter::exported)
+    /// with the scope and the privacy chosen. Families are left out
+    /// unless they link two of the persons.
+    pub fn new(records: Vec<Record>, coloring: Coloring) -> Self {
+        let registry = GedParser::registry_of(records);
+        let graph = FamilyGraph::new(&registry);
+        let surnames: BTreeSet<String> = graph.persons()
+            .filter_map(|p| graph.record(&registry, p))
+            .map(|r| r.borrow().surname().to_uppercase())
+            .collect();
+        let fill = |record: &Record| match coloring {
+            Coloring::Plain => PLAIN_COLOR,
+            Coloring::Sex => match record.sex() {
+                'M' => SEX_COLORS[0],
+                'F' => SEX_COLORS[1],
+                _ => SEX_COLORS[2],
+            },
+            Coloring::Surname => {
+                let surname = record.surname().to_uppercase();
+                let index = surnames.iter().position(|s| *s == surname).unwrap_or(0);
+                SURNAME_COLORS[index % SURNAME_COLORS.len()]
+            },
+        };
+
+        let mut diagram = Diagram::default();
+        let mut persons = HashMap::new();
+        let mut sorted: Vec<_> = graph.persons().filter_map(|p| Some((p, graph.record(&registry, p)?))).collect();
+        sorted.sort_by_key(|(_, r)| r.borrow().id);
+        for (person, record) in sorted {
+            let record = record.borrow();
+            let lines = std::iter::once(record.display_name())
+                .chain(std::iter::once(record.lifespan()))
+                .filter(|l| !l.is_empty())
+                .collect();
+            persons.insert(person, diagram.nodes.len());
+            diagram.nodes.push(Node { id: record.xref.clone(), kind: NodeKind::Person, lines, fill: fill(&record) });
+        }
+        let mut families: Vec<_> = graph.families().filter_map(|f| Some((f, graph.family_record(&registry, f)?))).collect();
+        families.sort_by_key(|(_, r)| r.borrow().id);
+        for (family, record) in families {
+            let partners: Vec<usize> = graph.partners(family).iter().filter_map(|p| persons.get(p).copied()).collect();
+            let children: Vec<usize> = graph.family_children(family).iter().filter_map(|p| persons.get(p).copied()).collect();
+            if partners.len() + children.len() < 2 {
+                continue;
+            }
+            let record = record.borrow();
+            let node = diagram.nodes.len();
+            let lines = record.event_date("MARR")
+                .and_then(|d| d.year())
+                .map(|year| vec![format!("m. {}", year)])
+                .unwrap_or_default();
+            diagram.nodes.push(Node { id: record.xref.clone(), kind: NodeKind::Family, lines, fill: PLAIN_COLOR });
+            diagram.edges.extend(partners.into_iter().map(|p| (p, node)));
+            diagram.edges.extend(children.into_iter().map(|c| (node, c)));
+        }
+        diagram
+    }
+
+    /// Graphviz DOT source of the graph
+    pub fn to_dot(&self) -> String {
+        let quote = |text: &str| format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""));
+        let mut dot = String::from("digraph family {\n");
+        dot.push_str("  rankdir=TB;\n");
+        dot.push_str("  node [shape=box, style=\"rounded,filled\", fontname=\"sans-serif\"];\n");
+        dot.push_str("  edge [arrowhead=none, color=\"#888888\"];\n");
+        for node in &self.nodes {
+            let attributes = match (node.kind, node.lines.is_empty()) {
+                (NodeKind::Person, _) => format!("label={}, fillcolor={}", quote(&node.lines.join("\n")).replace('\n', "\\n"), quote(node.fill)),
+                (NodeKind::Family, true) => String::from("shape=point, width=0.1"),
+                (NodeKind::Family, false) => format!("shape=ellipse, style=solid, fontsize=9, label={}", quote(&node.lines.join(" "))),
+            };
+            dot.push_str(&format!("  {} [{}];\n", quote(&node.id), attributes));
+        }
+        for (from, to) in &self.edges {
+            dot.push_str(&format!("  {} -> {};\n", quote(&self.nodes[*from].id), quote(&self.nodes[*to].id)));
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// SVG drawing of the graph, laid out by generations
+    pub fn to_svg(&self) -> String {
+        svg::draw(self, &layout::layout(self))
+    }
+
+    pub fn write<W: Write>(&self, format: DiagramFormat, mut out: W) -> std::io::Result<()> {
+        match format {
+            DiagramFormat::Dot => out.write_all(self.to_dot().as_bytes()),
+            DiagramFormat::Svg => out.write_all(self.to_svg().as_bytes()),
+        }
+    }
+}
+
+/// Writes the graph of the records exported from the registry
+pub fn write<W: Write>(writer: &GedWriter, registry: &RecordRegistry, coloring: Coloring, format: DiagramFormat, out: W) -> std::io::Result<()> {
+    Diagram::new(writer.exported(registry), coloring).write(format, out)
+}
@@ -0,0 +1,54 @@
+//! Laid out graphs drawn as standalone SVG documents
+
+use super::layout::{Layout, FAMILY_DOT, FONT_SIZE, LINE_HEIGHT};
+use super::{Diagram, NodeKind};
+use crate::website::escape;
+
+const STROKE: &str = "#5b4636";
+const EDGE: &str = "#888888";
+
+pub(super) fn draw(diagram: &Diagram, layout: &Layout) -> String {
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{:.0}\" height=\"{:.0}\" viewBox=\"0 0 {:.0} {:.0}\" \
+         font-family=\"Helvetica, Arial, sans-serif\" font-size=\"{}\">\n",
+        layout.width, layout.height, layout.width, layout.height, FONT_SIZE);
+    // Edges first, under the nodes: from the bottom of the
+    // parent to the top of the child
+    for (from, to) in &diagram.edges {
+        let ((x1, y1), (x2, y2)) = (layout.centers[*from], layout.centers[*to]);
+        let (y1, y2) = (y1 + layout.sizes[*from].1 / 2.0, y2 - layout.sizes[*to].1 / 2.0);
+        let middle = (y1 + y2) / 2.0;
+        svg.push_str(&format!(
+            "<path d=\"M{:.1},{:.1} C{:.1},{:.1} {:.1},{:.1} {:.1},{:.1}\" fill=\"none\" stroke=\"{}\"/>\n",
+            x1, y1, x1, middle, x2, middle, x2, y2, EDGE));
+    }
+    for (i, node) in diagram.nodes.iter().enumerate() {
+        let ((x, y), (width, height)) = (layout.centers[i], layout.sizes[i]);
+        let id = escape(&node.id);
+        match node.kind {
+            NodeKind::Family if node.lines.is_empty() => svg.push_str(&format!(
+                "<circle id=\"{}\" cx=\"{:.1}\" cy=\"{:.1}\" r=\"{:.1}\" fill=\"{}\"/>\n",
+                id, x, y, FAMILY_DOT / 2.0, EDGE)),
+            NodeKind::Family => svg.push_str(&format!(
+                "<g id=\"{}\"><ellipse cx=\"{:.1}\" cy=\"{:.1}\" rx=\"{:.1}\" ry=\"{:.1}\" fill=\"#fff\" stroke=\"{}\"/>\
+                 <text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"middle\" font-size=\"{:.0}\">{}</text></g>\n",
+                id, x, y, width / 2.0, height / 2.0, EDGE,
+                x, y + FONT_SIZE * 0.3, FONT_SIZE * 0.8, escape(&node.lines.join(" ")))),
+            NodeKind::Person => {
+                svg.push_str(&format!(
+                    "<g id=\"{}\"><rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" rx=\"4\" fill=\"{}\" stroke=\"{}\"/>",
+                    id, x - width / 2.0, y - height / 2.0, width, height, node.fill, STROKE));
+                let first = y - height / 2.0 + (height - LINE_HEIGHT * node.lines.len() as f64) / 2.0 + LINE_HEIGHT * 0.75;
+                for (n, line) in node.lines.iter().enumerate() {
+                    let weight = if n == 0 { " font-weight=\"bold\"" } else { "" };
+                    svg.push_str(&format!(
+                        "<text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"middle\"{}>{}</text>",
+                        x, first + LINE_HEIGHT * n as f64, weight, escape(line)));
+                }
+                svg.push_str("</g>\n");
+            },
+        }
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
@@ -0,0 +1,187 @@
+use gtk::prelude::*;
+
+use crate::app::models::AppModel;
+use crate::app::preferences::Preferences;
+use crate::gedcom::csv;
+use crate::gedcom::export::{self, ExportContent, ExportFormat, ExportScope};
+use crate::gedcom::{GedWriter, PrivacyMode};
+use crate::prelude::*;
+
+/// Settings of a GED export: the format, the part of the tree
+/// exported, the data left out and the privacy of the living persons
+pub struct ExportView {
+    gbuilder: gtk::Builder,
+    model: AppModel,
+}
+
+impl ExportView {
+    pub fn new(model: AppModel) -> Self {
+        Self {
+            gbuilder: gtk::Builder::from_resource("/org/altereigo/npaf/Export.glade"),
+            model,
+        }
+    }
+
+    fn combo(gbuilder: &gtk::Builder, name: &str) -> Option<String> {
+        let combo: gtk::ComboBoxText = gbuilder.object(name).unwrap();
+        combo.active_id().map(|id| id.to_string())
+    }
+
+    fn check(gbuilder: &gtk::Builder, name: &str) -> bool {
+        let check: gtk::CheckButton = gbuilder.object(name).unwrap();
+        check.is_active()
+    }
+
+    fn label(gbuilder: &gtk::Builder, name: &str) -> gtk::Label {
+        gbuilder.object(name).unwrap()
+    }
+
+    /// Scope chosen in the form, the person based ones
+    /// starting from the current person
+    fn scope(gbuilder: &gtk::Builder, model: &AppModel) -> std::result::Result<ExportScope, String> {
+        let spin: gtk::SpinButton = gbuilder.object("sb_generations").unwrap();
+        let generations = spin.value_as_int().max(1) as usize;
+        let root = || model.current_key().ok_or_else(|| String::from("Select a person in the workspace first."));
+        Ok(match Self::combo(gbuilder, "cb_scope").as_deref() {
+            Some("ancestors") => ExportScope::Ancestors { root: root()?, generations },
+            Some("descendants") => ExportScope::Descendants { root: root()?, generations },
+            Some("related") => ExportScope::Related { root: root()? },
+            Some("search") => ExportScope::Persons(model.search_result()),
+            Some("marked") => ExportScope::Persons(model.marked()),
+            _ => ExportScope::All,
+        })
+    }
+
+    /// Format chosen in the form, with the columns
+    /// typed in for the CSV tables
+    fn format(gbuilder: &gtk::Builder) -> std::result::Result<ExportFormat, String> {
+        let columns: gtk::Entry = gbuilder.object("e_columns").unwrap();
+        match Self::combo(gbuilder, "cb_format").and_then(|id| ExportFormat::from_id(&id)) {
+            Some(ExportFormat::Csv(_)) => csv::columns(&columns.text()).map(ExportFormat::Csv),
+            format => Ok(format.unwrap_or_default()),
+        }
+    }
+
+    fn writer(gbuilder: &gtk::Builder, model: &AppModel) -> std::result::Result<GedWriter, String> {
+        Ok(GedWriter {
+            privacy: Self::combo(gbuilder, "cb_living")
+                .and_then(|id| PrivacyMode::from_id(&id))
+                .unwrap_or(PrivacyMode::Include),
+            scope: Self::scope(gbuilder, model)?,
+            content: ExportContent {
+                notes: Self::check(gbuilder, "c_notes"),
+                sources: Self::check(gbuilder, "c_sources"),
+                media: Self::check(gbuilder, "c_media"),
+                lds: Self::check(gbuilder, "c_lds"),
+                private: Self::check(gbuilder, "c_private"),
+            },
+            living_rules: Preferences::load().living_rules(),
+            ..Default::default()
+        })
+    }
+
+    /// Shows the current person, the marks and the number
+    /// of individuals the scope holds
+    fn refresh(gbuilder: &gtk::Builder, model: &AppModel) {
+        let root = model.current().map(|r| r.borrow().display_name());
+        Self::label(gbuilder, "l_root").set_text(root.as_deref().unwrap_or("None"));
+        Self::label(gbuilder, "l_marked").set_text(&format!("{} individual(s)", model.marked().len()));
+        let columns: gtk::Entry = gbuilder.object("e_columns").unwrap();
+        columns.set_sensitive(Self::combo(gbuilder, "cb_format").as_deref() == Some("csv"));
+        let generations: gtk::SpinButton = gbuilder.object("sb_generations").unwrap();
+        let scope = Self::combo(gbuilder, "cb_scope");
+        generations.set_sensitive(matches!(scope.as_deref(), Some("ancestors") | Some("descendants")));
+        Self::label(gbuilder, "l_status").set_text(&match Self::scope(gbuilder, model) {
+            Ok(scope) => {
+                let count = export::persons(&scope, &model.graph(), &model.registry()).len();
+                format!("{} individual(s) to export.", count)
+            },
+            Err(msg) => msg,
+        });
+    }
+
+    fn export(gbuilder: &gtk::Builder, model: &AppModel) {
+        let status = Self::label(gbuilder, "l_status");
+        let writer = match Self::writer(gbuilder, model) {
+            Ok(writer) => writer,
+            Err(msg) => return status.set_text(&msg),
+        };
+        let format = match Self::format(gbuilder) {
+            Ok(format) => format,
+            Err(msg) => return status.set_text(&msg),
+        };
+        let dialog = gtk::FileChooserDialog::with_buttons::<gtk::Window>(
+            Some("Export"),
+            None,
+            gtk::FileChooserAction::Save,
+            &[("_Cancel", gtk::ResponseType::Cancel), ("_Export", gtk::ResponseType::Accept)],
+        );
+        dialog.set_do_overwrite_confirmation(true);
+        dialog.set_current_name(&format!("export.{}", format.extension()));
+        if dialog.run() == gtk::ResponseType::Accept {
+            if let Some(path) = dialog.filename() {
+                let written = std::fs::File::create(&path)
+                    .and_then(|file| format.write(&writer, &model.registry(), file));
+                status.set_text(&match written {
+                    Ok(()) => format!("Exported to '{}'.", path.display()),
+                    Err(err) => format!("Could not export the file: {}", err),
+                });
+            }
+        }
+        dialog.close();
+    }
+}
+
+impl View for ExportView {
+    fn assemble(&self) -> gtk::Widget {
+        let root: gtk::Grid = self.gbuilder.object("root").unwrap();
+        Self::refresh(&self.gbuilder, &self.model);
+
+        let columns: gtk::Entry = self.gbuilder.object("e_columns").unwrap();
+        columns.set_text(csv::DEFAULT_COLUMNS);
+        let format: gtk::ComboBoxText = self.gbuilder.object("cb_format").unwrap();
+        let (gbuilder, model) = (self.gbuilder.clone(), self.model.clone());
+        format.connect_changed(move |_| Self::refresh(&gbuilder, &model));
+
+        let scope: gtk::ComboBoxText = self.gbuilder.object("cb_scope").unwrap();
+        let (gbuilder, model) = (self.gbuilder.clone(), self.model.clone());
+        scope.connect_changed(move |_| Self::refresh(&gbuilder, &model));
+
+        let generations: gtk::SpinButton = self.gbuilder.object("sb_generations").unwrap();
+        let (gbuilder, model) = (self.gbuilder.clone(), self.model.clone());
+        generations.connect_value_changed(move |_| Self::refresh(&gbuilder, &model));
+
+        let b_mark: gtk::Button = self.gbuilder.object("b_mark").unwrap();
+        let (gbuilder, model) = (self.gbuilder.clone(), self.model.clone());
+        b_mark.connect_clicked(move |_| {
+            model.mark(model.current_key());
+            Self::refresh(&gbuilder, &model);
+        });
+
+        let b_clear_marks: gtk::Button = self.gbuilder.object("b_clear_marks").unwrap();
+        let (gbuilder, model) = (self.gbuilder.clone(), self.model.clone());
+        b_clear_marks.connect_clicked(move |_| {
+            model.clear_marks();
+            Self::refresh(&gbuilder, &model);
+        });
+
+        let b_export: gtk::Button = self.gbuilder.object("b_export").unwrap();
+        let (gbuilder, model) = (self.gbuilder.clone(), self.model.clone());
+        b_export.connect_clicked(move |_| Self::export(&gbuilder, &model));
+
+        root.show();
+        root.dynamic_cast::<gtk::Widget>().unwrap()
+    }
+}
+
+impl Windowed for ExportView {
+    fn assemble_window(&self) -> gtk::Window {
+        let wdg = self.assemble();
+        let wdw: gtk::Window = self.gbuilder.object("top").unwrap();
+        wdw.set_child(Some(&wdg));
+        let b_close: gtk::Button = self.gbuilder.object("b_close").unwrap();
+        let wdw_cpy = wdw.clone();
+        b_close.connect_clicked(move |_| wdw_cpy.close());
+        wdw
+    }
+}
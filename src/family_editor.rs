@@ -0,0 +1,185 @@
+use gtk::prelude::*;
+
+use crate::app::models::AppModel;
+use crate::gedcom::family::{self, FamilyMembers};
+use crate::gedcom::{xref_key, Event};
+use crate::prelude::*;
+
+/// Editor creating a family out of persons of the tree,
+/// the current person standing as one of the partners
+#[derive(Clone)]
+pub struct FamilyEditorView {
+    gbuilder: gtk::Builder,
+    model: AppModel,
+}
+
+impl FamilyEditorView {
+    pub fn new(model: AppModel) -> Self {
+        Self {
+            gbuilder: gtk::Builder::from_resource("/org/altereigo/npaf/FamilyEditor.glade"),
+            model,
+        }
+    }
+
+    fn combo(&self, name: &str) -> gtk::ComboBoxText {
+        self.gbuilder.object(name).unwrap()
+    }
+
+    fn entry(&self, name: &str) -> gtk::Entry {
+        self.gbuilder.object(name).unwrap()
+    }
+
+    fn store(&self) -> gtk::ListStore {
+        self.gbuilder.object("children_store").unwrap()
+    }
+
+    fn status(&self, text: &str) {
+        let status: gtk::Label = self.gbuilder.object("l_status").unwrap();
+        status.set_text(text);
+    }
+
+    /// Individuals of the tree sorted by name, with the
+    /// registry key as id and the name and lifespan as label
+    fn persons(&self) -> Vec<(String, String, String)> {
+        let mut persons: Vec<(String, String, String)> = self.model.registry().iter()
+            .filter(|(_, record)| record.borrow().rtype == "INDI")
+            .map(|(key, record)| {
+                let record = record.borrow();
+                (key.to_string(), record.display_name(), record.lifespan())
+            })
+            .collect();
+        persons.sort_by(|a, b| a.1.cmp(&b.1).then_with(|| a.0.cmp(&b.0)));
+        persons
+    }
+
+    fn fill_combos(&self) {
+        let persons = self.persons();
+        for name in &["cb_husband", "cb_wife", "cb_child"] {
+            let combo = self.combo(name);
+            combo.append(Some(""), "");
+            for (key, name, lifespan) in &persons {
+                match lifespan.is_empty() {
+                    true => combo.append(Some(key), name),
+                    false => combo.append(Some(key), &format!("{} ({})", name, lifespan)),
+                }
+            }
+            combo.set_active_id(Some(""));
+        }
+        if let Some(person) = self.model.current() {
+            let person = person.borrow();
+            let partner = if person.sex() == 'F' { "cb_wife" } else { "cb_husband" };
+            self.combo(partner).set_active_id(Some(&xref_key(&person.xref).to_string()));
+        }
+    }
+
+    fn key(&self, name: &str) -> Option<u64> {
+        self.combo(name).active_id().and_then(|id| id.parse().ok())
+    }
+
+    fn children(&self) -> Vec<u64> {
+        let (store, mut children) = (self.store(), vec![]);
+        if let Some(iter) = store.iter_first() {
+            loop {
+                let key = store.value(&iter, 0).get::<String>().unwrap_or_default();
+                children.extend(key.parse::<u64>().ok());
+                if !store.iter_next(&iter) {
+                    break;
+                }
+            }
+        }
+        children
+    }
+
+    fn add_child(&self) {
+        let key = match self.key("cb_child") {
+            Some(key) => key,
+            None => return self.status("Select the child to add first."),
+        };
+        if self.children().contains(&key) {
+            return self.status("This child is already in the family.");
+        }
+        if let Some(child) = self.model.record(key) {
+            let child = child.borrow();
+            self.store().insert_with_values(None, &[
+                (0, &key.to_string()),
+                (1, &child.display_name()),
+                (2, &child.lifespan()),
+            ]);
+            self.status("");
+        }
+    }
+
+    fn remove_child(&self) {
+        let tv: gtk::TreeView = self.gbuilder.object("tv_children").unwrap();
+        if let Some((_, iter)) = tv.selection().selected() {
+            self.store().remove(&iter);
+        }
+    }
+
+    /// Marriage event, when its date or place is given
+    fn marriage(&self) -> Option<Event> {
+        let text = |name: &str| Some(self.entry(name).text().trim().to_owned()).filter(|t| !t.is_empty());
+        let (date, place) = (text("e_date"), text("e_place"));
+        if date.is_none() && place.is_none() {
+            return None;
+        }
+        let mut marriage = Event::new("MARR");
+        marriage.date = date;
+        marriage.place = place;
+        Some(marriage)
+    }
+
+    /// Adds the family to the tree, the workspace following
+    /// the new links of its members
+    fn save(&self) -> std::result::Result<(), String> {
+        let members = FamilyMembers {
+            husband: self.key("cb_husband"),
+            wife: self.key("cb_wife"),
+            children: self.children(),
+        };
+        let events = self.marriage().into_iter().collect();
+        let added = family::add_family(&mut self.model.registry_mut(), &members, events);
+        added.map_err(|e| e.what())?;
+        self.model.relink();
+        Ok(())
+    }
+}
+
+impl View for FamilyEditorView {
+    fn assemble(&self) -> gtk::Widget {
+        let root: gtk::Grid = self.gbuilder.object("root").unwrap();
+        self.fill_combos();
+
+        let b_add: gtk::Button = self.gbuilder.object("b_add_child").unwrap();
+        let view = self.clone();
+        b_add.connect_clicked(move |_| view.add_child());
+        let b_remove: gtk::Button = self.gbuilder.object("b_remove_child").unwrap();
+        let view = self.clone();
+        b_remove.connect_clicked(move |_| view.remove_child());
+
+        root.show();
+        root.dynamic_cast::<gtk::Widget>().unwrap()
+    }
+}
+
+impl Windowed for FamilyEditorView {
+    fn assemble_window(&self) -> gtk::Window {
+        let wdg = self.assemble();
+        let wdw: gtk::Window = self.gbuilder.object("top").unwrap();
+        wdw.set_child(Some(&wdg));
+
+        let b_cancel: gtk::Button = self.gbuilder.object("b_cancel").unwrap();
+        let wdw_cpy = wdw.clone();
+        b_cancel.connect_clicked(move |_| wdw_cpy.close());
+
+        let b_save: gtk::Button = self.gbuilder.object("b_save").unwrap();
+        let (view, wdw_cpy) = (self.clone(), wdw.clone());
+        b_save.connect_clicked(move |_| {
+            match view.save() {
+                Ok(()) => wdw_cpy.close(),
+                Err(msg) => view.status(&msg),
+            }
+        });
+        wdw
+    }
+}
@@ -0,0 +1,151 @@
+use gtk::prelude::*;
+
+use crate::app::models::AppModel;
+use crate::gedcom::search::{self, DateCriterion, NameMatch, SearchCriteria};
+use crate::gedcom::{xref_key, GedDate, SimpleDate};
+use crate::prelude::*;
+
+pub struct FindView {
+    gbuilder: gtk::Builder,
+    model: AppModel,
+}
+
+impl FindView {
+    pub fn new(model: AppModel) -> Self {
+        Self {
+            gbuilder: gtk::Builder::from_resource("/org/altereigo/npaf/Find.glade"),
+            model,
+        }
+    }
+
+    fn text(gbuilder: &gtk::Builder, name: &str) -> Option<String> {
+        let entry: gtk::Entry = gbuilder.object(name).unwrap();
+        let text = entry.text().trim().to_owned();
+        if text.is_empty() { None } else { Some(text) }
+    }
+
+    fn combo(gbuilder: &gtk::Builder, name: &str) -> Option<String> {
+        let combo: gtk::ComboBoxText = gbuilder.object(name).unwrap();
+        combo.active_id().map(|id| id.to_string()).filter(|id| !id.is_empty())
+    }
+
+    /// Reads the criteria from the form, fails on dates
+    /// that can't be understood.
+    fn criteria(gbuilder: &gtk::Builder) -> std::result::Result<SearchCriteria, String> {
+        let date = |name| -> std::result::Result<Option<SimpleDate>, String> {
+            match Self::text(gbuilder, name) {
+                Some(text) => SimpleDate::parse(&text)
+                    .map(Some)
+                    .ok_or(format!("'{}' is not a valid date.", text)),
+                None => Ok(None),
+            }
+        };
+        let (from, to) = (date("e_date_from")?, date("e_date_to")?);
+        let event = Self::combo(gbuilder, "cb_event");
+        let dates = if from.is_some() || to.is_some() {
+            vec![DateCriterion { event, from, to }]
+        } else {
+            vec![]
+        };
+        let name_match = match Self::combo(gbuilder, "cb_name_match").as_deref() {
+            Some("exact") => NameMatch::Exact,
+            Some("soundex") => NameMatch::Soundex,
+            Some("dm") => NameMatch::DaitchMokotoff,
+            _ => NameMatch::Contains,
+        };
+        Ok(SearchCriteria {
+            given: Self::text(gbuilder, "e_given"),
+            surname: Self::text(gbuilder, "e_surname"),
+            name_match,
+            sex: Self::combo(gbuilder, "cb_sex").and_then(|s| s.chars().next()),
+            dates,
+            place: Self::text(gbuilder, "e_place"),
+            custom_id: Self::text(gbuilder, "e_custom_id"),
+        })
+    }
+
+    fn run_search(gbuilder: &gtk::Builder, model: &AppModel) {
+        let status: gtk::Label = gbuilder.object("l_status").unwrap();
+        let store: gtk::ListStore = gbuilder.object("results_store").unwrap();
+        let criteria = match Self::criteria(gbuilder) {
+            Ok(criteria) => criteria,
+            Err(msg) => return status.set_text(&msg),
+        };
+        store.clear();
+        let found = search::search(&model.registry(), &criteria);
+        model.set_search_result(found.iter().map(|r| xref_key(&r.borrow().xref)));
+        let date = |d: &Option<GedDate>| d.as_ref().map(|d| d.to_string()).unwrap_or_default();
+        // Dates are sorted on yyyymmdd keys, the unknown ones coming first
+        let sort_key = |d: &Option<GedDate>| match d.as_ref().and_then(|d| d.sort_date()) {
+            Some(d) => {
+                let (year, month, day) = d.lower();
+                year as i64 * 10000 + month as i64 * 100 + day as i64
+            },
+            None => i64::MIN,
+        };
+        for record in &found {
+            let record = record.borrow();
+            let (birth, death) = (record.event_date("BIRT"), record.event_date("DEAT"));
+            store.insert_with_values(None, &[
+                (0, &xref_key(&record.xref)),
+                (1, &record.display_name()),
+                (2, &record.sex().to_string()),
+                (3, &date(&birth)),
+                (4, &date(&death)),
+                (5, &record.custom_id().unwrap_or_default()),
+                (6, &sort_key(&birth)),
+                (7, &sort_key(&death)),
+            ]);
+        }
+        status.set_text(&format!("{} individual(s) found.", found.len()));
+    }
+}
+
+impl View for FindView {
+    fn assemble(&self) -> gtk::Widget {
+        let root: gtk::Grid = self.gbuilder.object("root").unwrap();
+
+        let b_search: gtk::Button = self.gbuilder.object("b_search").unwrap();
+        let (gbuilder, model) = (self.gbuilder.clone(), self.model.clone());
+        b_search.connect_clicked(move |_| Self::run_search(&gbuilder, &model));
+        for name in &["e_given", "e_surname", "e_date_from", "e_date_to", "e_place", "e_custom_id"] {
+            let entry: gtk::Entry = self.gbuilder.object(name).unwrap();
+            let (gbuilder, model) = (self.gbuilder.clone(), self.model.clone());
+            entry.connect_activate(move |_| Self::run_search(&gbuilder, &model));
+        }
+
+        let b_mark: gtk::Button = self.gbuilder.object("b_mark").unwrap();
+        let (gbuilder, model) = (self.gbuilder.clone(), self.model.clone());
+        b_mark.connect_clicked(move |_| {
+            let found = model.search_result();
+            model.mark(found.iter().copied());
+            let status: gtk::Label = gbuilder.object("l_status").unwrap();
+            status.set_text(&format!("{} individual(s) marked, {} in all.", found.len(), model.marked().len()));
+        });
+
+        let results: gtk::TreeView = self.gbuilder.object("tv_results").unwrap();
+        let model = self.model.clone();
+        results.connect_row_activated(move |tv, path, _| {
+            let store = tv.model().unwrap();
+            if let Some(iter) = store.iter(path) {
+                let key = store.value(&iter, 0).get::<u64>().unwrap();
+                let _ = model.navigate(key);
+            }
+        });
+
+        root.show();
+        root.dynamic_cast::<gtk::Widget>().unwrap()
+    }
+}
+
+impl Windowed for FindView {
+    fn assemble_window(&self) -> gtk::Window {
+        let wdg = self.assemble();
+        let wdw: gtk::Window = self.gbuilder.object("top").unwrap();
+        wdw.set_child(Some(&wdg));
+        let b_close: gtk::Button = self.gbuilder.object("b_close").unwrap();
+        let wdw_cpy = wdw.clone();
+        b_close.connect_clicked(move |_| wdw_cpy.close());
+        wdw
+    }
+}
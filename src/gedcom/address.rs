@@ -0,0 +1,230 @@
+//! Address and contact information structures as
+//! described by the GEDCOM 5.5.1 `ADDRESS_STRUCTURE`.
+
+use crate::gedcom::gedex::Tag;
+use regex::Regex;
+use std::fmt;
+
+/// Maximum amount of phones, emails, faxes and web
+/// pages allowed by the standard for one structure
+pub const MAX_CONTACTS: usize = 3;
+
+/// Postal address of a person, a residence or a
+/// repository.
+#[derive(Default,Debug,Clone,PartialEq)]
+pub struct Address {
+    /// Free form address (`ADDR` line with its `CONT`s)
+    pub lines: Option<String>,
+    pub line1: Option<String>,
+    pub line2: Option<String>,
+    pub line3: Option<String>,
+    pub city: Option<String>,
+    pub state: Option<String>,
+    pub postal_code: Option<String>,
+    pub country: Option<String>,
+}
+
+/// Full contact information: an optional address
+/// along with phone numbers, emails, faxes and web pages.
+#[derive(Default,Debug,Clone,PartialEq)]
+pub struct ContactInfo {
+    pub address: Option<Address>,
+    pub phones: Vec<String>,
+    pub emails: Vec<String>,
+    pub faxes: Vec<String>,
+    pub websites: Vec<String>,
+}
+
+/// Problems that can be found while validating
+/// a contact structure
+#[derive(Debug,Clone,PartialEq)]
+pub enum ContactError {
+    InvalidEmail(String),
+    InvalidUrl(String),
+    TooMany(&'static str),
+}
+
+impl ContactError {
+    pub fn what(&self) -> String {
+        match &self {
+            ContactError::InvalidEmail(email) => format!("'{}' is not a valid email address.", email),
+            ContactError::InvalidUrl(url) => format!("'{}' is not a valid web address.", url),
+            ContactError::TooMany(tag) => format!("At most {} {} values are allowed.", MAX_CONTACTS, tag),
+        }
+    }
+}
+
+/// Tags of the address structure, in the order they
+/// have to be written
+const ADDRESS_TAGS: [&str; 7] = ["ADR1", "ADR2", "ADR3", "CITY", "STAE", "POST", "CTRY"];
+
+impl Address {
+    pub fn from_tag(tag: &Tag) -> Self {
+        Address {
+            lines: tag.text(),
+            line1: tag.value("ADR1"),
+            line2: tag.value("ADR2"),
+            line3: tag.value("ADR3"),
+            city: tag.value("CITY"),
+            state: tag.value("STAE"),
+            postal_code: tag.value("POST"),
+            country: tag.value("CTRY"),
+        }
+    }
+
+    pub fn to_tag(&self) -> Tag {
+        let addr = match &self.lines {
+            Some(lines) => Tag::with_text("ADDR", lines),
+            None => Tag::with_text("ADDR", &self.fallback_lines()),
+        };
+        ADDRESS_TAGS.iter()
+            .zip(self.parts().iter())
+            .filter_map(|(name, value)| value.map(|v| Tag::new(name, Some(v))))
+            .fold(addr, |addr, tag| addr.nest(tag))
+    }
+
+    /// Address parts in the same order as `ADDRESS_TAGS`
+    fn parts(&self) -> [Option<&str>; 7] {
+        [
+            self.line1.as_deref(),
+            self.line2.as_deref(),
+            self.line3.as_deref(),
+            self.city.as_deref(),
+            self.state.as_deref(),
+            self.postal_code.as_deref(),
+            self.country.as_deref(),
+        ]
+    }
+
+    /// Free form address rebuilt from the structured
+    /// parts, used when the `ADDR` line itself is missing
+    fn fallback_lines(&self) -> String {
+        let [l1, l2, l3, city, state, post, ctry] = self.parts();
+        let locality = [post, city, state].iter()
+            .filter_map(|p| *p)
+            .collect::<Vec<&str>>()
+            .join(" ");
+        [l1, l2, l3, Some(locality.as_str()), ctry].iter()
+            .filter_map(|p| *p)
+            .filter(|p| !p.is_empty())
+            .collect::<Vec<&str>>()
+            .join("\n")
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_none() && self.parts().iter().all(|p| p.is_none())
+    }
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.lines {
+            Some(lines) => write!(f, "{}", lines),
+            None => write!(f, "{}", self.fallback_lines()),
+        }
+    }
+}
+
+impl ContactInfo {
+    /// Checks whether the given tag is a part of
+    /// the contact structure
+    pub fn is_contact_tag(name: &str) -> bool {
+        matches!(name, "ADDR" | "PHON" | "EMAIL" | "FAX" | "WWW")
+    }
+
+    /// Extracts the contact information from the nested
+    /// tags of a structure, returns `None` if there is none
+    pub fn from_parent(tag: &Tag) -> Option<Self> {
+        let values = |name| tag.children(name).filter_map(|t| t.text()).collect();
+        let contact = ContactInfo {
+            address: tag.child("ADDR").map(Address::from_tag),
+            phones: values("PHON"),
+            emails: values("EMAIL"),
+            faxes: values("FAX"),
+            websites: values("WWW"),
+        };
+        if contact.is_empty() { None } else { Some(contact) }
+    }
+
+    /// Tags to be nested into the parent structure
+    pub fn to_tags(&self) -> Vec<Tag> {
+        let values = |name, list: &Vec<String>| -> Vec<Tag> {
+            list.iter().map(|v| Tag::new(name, Some(v))).collect()
+        };
+        self.address.iter().map(|a| a.to_tag())
+            .chain(values("PHON", &self.phones))
+            .chain(values("EMAIL", &self.emails))
+            .chain(values("FAX", &self.faxes))
+            .chain(values("WWW", &self.websites))
+            .collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.address.as_ref().map_or(true, |a| a.is_empty())
+            && self.phones.is_empty()
+            && self.emails.is_empty()
+            && self.faxes.is_empty()
+            && self.websites.is_empty()
+    }
+
+    /// Checks emails and web pages formats along with
+    /// the amount of values of each kind.
+    pub fn validate(&self) -> Result<(), ContactError> {
+        let counts = [
+            ("PHON", &self.phones),
+            ("EMAIL", &self.emails),
+            ("FAX", &self.faxes),
+            ("WWW", &self.websites),
+        ];
+        if let Some((tag, _)) = counts.iter().find(|(_, l)| l.len() > MAX_CONTACTS) {
+            return Err(ContactError::TooMany(tag));
+        }
+        let email = Self::regex_email();
+        if let Some(bad) = self.emails.iter().find(|e| !email.is_match(e)) {
+            return Err(ContactError::InvalidEmail(bad.clone()));
+        }
+        let url = Self::regex_url();
+        if let Some(bad) = self.websites.iter().find(|u| !url.is_match(u)) {
+            return Err(ContactError::InvalidUrl(bad.clone()));
+        }
+        Ok(())
+    }
+
+    /// Contact information as a list of human readable
+    /// lines, used in reports
+    pub fn lines(&self) -> Vec<String> {
+        let prefixed = |prefix: &str, list: &Vec<String>| -> Vec<String> {
+            list.iter().map(|v| format!("{}: {}", prefix, v)).collect()
+        };
+        self.address.iter()
+            .flat_map(|a| a.to_string().lines().map(String::from).collect::<Vec<String>>())
+            .chain(prefixed("Phone", &self.phones))
+            .chain(prefixed("Email", &self.emails))
+            .chain(prefixed("Fax", &self.faxes))
+            .chain(prefixed("Web", &self.websites))
+            .collect()
+    }
+
+    /// Email address regular expression getter
+    fn regex_email() -> Regex {
+        Regex::new(r"(?x)
+                ^
+                [A-Za-z0-9._%+\-]+              # Local part
+                @
+                [A-Za-z0-9\-]+(\.[A-Za-z0-9\-]+)*\.[A-Za-z]{2,}  # Domain
+                $
+            ").unwrap()
+    }
+
+    /// Web address regular expression getter
+    fn regex_url() -> Regex {
+        Regex::new(r"(?x)
+                ^
+                (https?|ftp)://                 # Scheme
+                [A-Za-z0-9\-]+(\.[A-Za-z0-9\-]+)*  # Host
+                (:[0-9]{1,5})?                  # Port
+                ([/?\#][^\s]*)?                 # Path, query and fragment
+                $
+            ").unwrap()
+    }
+}
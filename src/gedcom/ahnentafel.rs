@@ -0,0 +1,154 @@
+//! Ahnentafel (Sosa-Stradonitz) numbering of the ancestors of
+//! a person: the person is 1, the father of `n` is `2n` and the
+//! mother `2n+1`. An ancestor reached through several lines
+//! (pedigree collapse) gets several numbers, the first one
+//! being the reference for the others.
+
+use crate::gedcom::{FamilyGraph, PersonId, Record, RecordRc, RecordRegistry};
+use std::collections::{HashMap, VecDeque};
+
+/// Generations that can be numbered with 64 bits
+pub const MAX_GENERATIONS: usize = 64;
+
+/// Generation of an ahnentafel number, the person
+/// being generation 0, the parents generation 1...
+pub fn generation(number: u64) -> usize {
+    63 - number.max(1).leading_zeros() as usize
+}
+
+pub fn father(number: u64) -> u64 {
+    number * 2
+}
+
+pub fn mother(number: u64) -> u64 {
+    number * 2 + 1
+}
+
+/// Number of the child through whom the ancestor is reached
+pub fn child(number: u64) -> u64 {
+    number / 2
+}
+
+/// Ancestor reached by the walk
+#[derive(Clone)]
+pub struct Ancestor {
+    pub number: u64,
+    pub generation: usize,
+    pub person: RecordRc,
+    /// First number the same person got, when they were
+    /// already reached through another line
+    pub duplicate_of: Option<u64>,
+}
+
+impl Ancestor {
+    pub fn is_duplicate(&self) -> bool {
+        self.duplicate_of.is_some()
+    }
+}
+
+/// Iterator over the ancestors of a person in ahnentafel order,
+/// generation by generation. The ancestors of a duplicate are
+/// not walked again unless asked to, as they already appear
+/// above the first occurrence.
+pub struct Ancestors<'a> {
+    graph: &'a FamilyGraph,
+    registry: &'a RecordRegistry,
+    queue: VecDeque<(u64, PersonId)>,
+    seen: HashMap<PersonId, u64>,
+    generations: usize,
+    expand_duplicates: bool,
+}
+
+impl<'a> Ancestors<'a> {
+    /// Walks `generations` generations, the person included
+    pub fn new(graph: &'a FamilyGraph, registry: &'a RecordRegistry, root: &Record, generations: usize) -> Self {
+        Ancestors {
+            graph,
+            registry,
+            queue: graph.person_of(root).map(|p| (1, p)).into_iter().collect(),
+            seen: HashMap::new(),
+            generations: generations.min(MAX_GENERATIONS),
+            expand_duplicates: false,
+        }
+    }
+
+    /// Also walks the ancestors of the duplicates, so that every
+    /// number of the pedigree is filled (for charts)
+    pub fn expand_duplicates(mut self, expand: bool) -> Self {
+        self.expand_duplicates = expand;
+        self
+    }
+}
+
+impl<'a> Iterator for Ancestors<'a> {
+    type Item = Ancestor;
+
+    fn next(&mut self) -> Option<Ancestor> {
+        loop {
+            let (number, id) = self.queue.pop_front()?;
+            let generation = generation(number);
+            let duplicate_of = self.seen.get(&id).copied();
+            if duplicate_of.is_none() {
+                self.seen.insert(id, number);
+            }
+            if generation + 1 < self.generations && (duplicate_of.is_none() || self.expand_duplicates) {
+                self.queue.extend(self.graph.father(id).map(|f| (father(number), f)));
+                self.queue.extend(self.graph.mother(id).map(|m| (mother(number), m)));
+            }
+            if let Some(person) = self.graph.record(self.registry, id) {
+                return Some(Ancestor { number, generation, person, duplicate_of });
+            }
+        }
+    }
+}
+
+/// Numbered ancestors of a person, along with
+/// the pedigree collapses found
+pub struct Ahnentafel {
+    ancestors: Vec<Ancestor>,
+}
+
+impl Ahnentafel {
+    pub fn new(graph: &FamilyGraph, registry: &RecordRegistry, root: &Record, generations: usize) -> Self {
+        Ahnentafel { ancestors: Ancestors::new(graph, registry, root, generations).collect() }
+    }
+
+    /// Ancestors by increasing number
+    pub fn iter(&self) -> std::slice::Iter<'_, Ancestor> {
+        self.ancestors.iter()
+    }
+
+    pub fn get(&self, number: u64) -> Option<&Ancestor> {
+        self.ancestors.binary_search_by_key(&number, |a| a.number)
+            .ok()
+            .map(|i| &self.ancestors[i])
+    }
+
+    /// All the numbers of a person, more than one
+    /// meaning a pedigree collapse
+    pub fn numbers_of(&self, person: &Record) -> Vec<u64> {
+        let first = match self.ancestors.iter().find(|a| a.person.borrow().xref == person.xref) {
+            Some(first) => first.number,
+            None => return vec![],
+        };
+        std::iter::once(first)
+            .chain(self.ancestors.iter().filter(|a| a.duplicate_of == Some(first)).map(|a| a.number))
+            .collect()
+    }
+
+    /// Ancestors reached again through another line
+    pub fn duplicates(&self) -> impl Iterator<Item = &Ancestor> {
+        self.ancestors.iter().filter(|a| a.is_duplicate())
+    }
+}
+
+/// Ancestors by number over the few `generations` of a chart,
+/// unknown ancestors left empty, the lines above duplicates
+/// being walked too
+pub fn slots(graph: &FamilyGraph, registry: &RecordRegistry, root: &Record, generations: usize) -> Vec<Option<RecordRc>> {
+    let mut slots: Vec<Option<RecordRc>> = vec![None; 1 << generations];
+    for ancestor in Ancestors::new(graph, registry, root, generations).expand_duplicates(true) {
+        slots[ancestor.number as usize] = Some(ancestor.person);
+    }
+    slots
+}
@@ -0,0 +1,184 @@
+//! Import of a file into the opened registry. The incoming records
+//! get fresh cross-references and are tagged with the number of
+//! their import batch (`_BATCH`), so that they can be told apart
+//! from the existing ones and the whole import can be rolled back.
+
+use crate::gedcom::merge::{self, MergeChoices, MergeError};
+use crate::gedcom::gedex::pointer_xref;
+use crate::gedcom::import::Diagnostic;
+use crate::gedcom::{ident, xref_key, Record, RecordRegistry, Tag};
+use std::collections::{HashMap, HashSet};
+
+/// Tag holding the import batch of a record
+pub const BATCH_TAG: &str = "_BATCH";
+
+/// Import batch of a record, if it was imported into
+/// an existing registry
+pub fn batch_of(record: &Record) -> Option<String> {
+    record.extra_value(BATCH_TAG).map(|b| b.trim().to_owned())
+}
+
+/// All the import batches of the registry, oldest first,
+/// with the number of records still belonging to them
+pub fn batches(registry: &RecordRegistry) -> Vec<(String, usize)> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for record in registry.values() {
+        if let Some(batch) = batch_of(&record.borrow()) {
+            *counts.entry(batch).or_default() += 1;
+        }
+    }
+    let mut batches: Vec<(String, usize)> = counts.into_iter().collect();
+    batches.sort_by_key(|(batch, _)| (batch.parse::<u64>().unwrap_or(0), batch.clone()));
+    batches
+}
+
+/// Registry keys of the records of a batch
+pub fn batch_keys(registry: &RecordRegistry, batch: &str) -> HashSet<u64> {
+    registry.iter()
+        .filter(|(_, r)| batch_of(&r.borrow()).as_deref() == Some(batch))
+        .map(|(key, _)| *key)
+        .collect()
+}
+
+fn next_batch(registry: &RecordRegistry) -> String {
+    let last = registry.values()
+        .filter_map(|r| batch_of(&r.borrow()))
+        .filter_map(|b| b.parse::<u64>().ok())
+        .max()
+        .unwrap_or(0);
+    (last + 1).to_string()
+}
+
+/// Letters of a cross-reference before its number (`I` for `I12`)
+fn xref_prefix(xref: &str) -> &str {
+    xref.trim_end_matches(|c: char| c.is_ascii_digit())
+}
+
+/// Rewrites all the pointers of the tags at once, so that a
+/// new cross-reference is never mapped again. Pointers to records
+/// missing from the incoming ones would bind to unrelated existing
+/// records, they are dropped and pushed to `dropped` instead.
+fn remap_pointers(tags: &mut Vec<Tag>, xrefs: &HashMap<String, String>, dropped: &mut Vec<(String, String)>) {
+    tags.retain(|tag| match tag.pointer() {
+        Some(xref) if !xrefs.contains_key(xref) => {
+            dropped.push((tag.name.clone(), xref.to_owned()));
+            false
+        },
+        _ => true,
+    });
+    for tag in tags.iter_mut() {
+        let new = tag.pointer().and_then(|xref| xrefs.get(xref));
+        if let Some(new) = new {
+            tag.content = Some(format!("@{}@", new));
+        }
+        remap_pointers(&mut tag.nested, xrefs, dropped);
+    }
+}
+
+/// Records added to the registry by [`append`]
+#[derive(Debug,Clone,Default)]
+pub struct Appended {
+    pub batch: String,
+    /// New cross-reference of every incoming one
+    pub xrefs: HashMap<String, String>,
+    /// Registry keys of the added records
+    pub keys: HashSet<u64>,
+    /// Pointers dropped for lack of the record they point to
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// Adds the records of another file to the registry. Every record
+/// gets a cross-reference unused so far, the pointers between them
+/// following, new RINs and the `_UID`s that would be repeated are
+/// replaced. Pointers to records that aren't among the incoming
+/// ones are dropped, each one being reported. The [`FamilyGraph`](crate::gedcom::FamilyGraph) of the
+/// registry has to be built again.
+pub fn append(registry: &mut RecordRegistry, mut records: Vec<Record>) -> Appended {
+    let batch = next_batch(registry);
+    let mut last: HashMap<String, u64> = HashMap::new();
+    for record in registry.values() {
+        let record = record.borrow();
+        let number = record.xref[xref_prefix(&record.xref).len()..].parse::<u64>().unwrap_or(0);
+        let max = last.entry(xref_prefix(&record.xref).to_owned()).or_default();
+        *max = (*max).max(number);
+    }
+    let mut xrefs: HashMap<String, String> = HashMap::new();
+    for record in &records {
+        let prefix = match xref_prefix(&record.xref) {
+            "" => record.rtype.chars().next().unwrap_or('X').to_string(),
+            prefix => prefix.to_owned(),
+        };
+        let number = last.entry(prefix.clone()).or_default();
+        *number += 1;
+        let mut xref = format!("{}{}", prefix, number);
+        while registry.contains_key(&xref_key(&xref)) {
+            *number += 1;
+            xref = format!("{}{}", prefix, number);
+        }
+        xrefs.insert(record.xref.clone(), xref);
+    }
+    let uids: HashSet<String> = registry.values()
+        .filter_map(|r| r.borrow().uid.clone())
+        .collect();
+    let (mut keys, mut diagnostics) = (HashSet::new(), vec![]);
+    for record in records.iter_mut() {
+        let original = record.xref.clone();
+        record.xref = xrefs[&original].clone();
+        let mut dropped = vec![];
+        record.id = record.xref[xref_prefix(&record.xref).len()..].parse().unwrap_or(0);
+        record.rin = 0;
+        if record.uid.as_ref().map_or(false, |uid| uids.contains(uid)) {
+            record.uid = None;
+        }
+        remap_pointers(&mut record.extra, &xrefs, &mut dropped);
+        for event in record.events.iter_mut() {
+            remap_pointers(&mut event.extra, &xrefs, &mut dropped);
+        }
+        for ordinance in record.ordinances.iter_mut() {
+            remap_pointers(&mut ordinance.extra, &xrefs, &mut dropped);
+            let family = ordinance.family.as_deref().and_then(pointer_xref).map(String::from);
+            if let Some(family) = family {
+                ordinance.family = xrefs.get(&family).map(|xref| format!("@{}@", xref));
+                if ordinance.family.is_none() {
+                    dropped.push((ordinance.kind.tag().to_owned(), family));
+                }
+            }
+        }
+        diagnostics.extend(dropped.into_iter().map(|(tag, pointer)| Diagnostic::DanglingPointer {
+            xref: original.clone(),
+            tag,
+            pointer,
+        }));
+        record.extra.retain(|t| t.name != BATCH_TAG);
+        record.extra.push(Tag::new(BATCH_TAG, Some(&batch)));
+    }
+    for record in records {
+        let key = xref_key(&record.xref);
+        keys.insert(key);
+        registry.insert(key, record.into());
+    }
+    ident::assign_identifiers(registry);
+    Appended { batch, xrefs, keys, diagnostics }
+}
+
+/// Merges an imported person into an existing one, the existing
+/// record being kept. The result no longer belongs to the batch,
+/// so a rollback leaves it in place.
+pub fn link(registry: &mut RecordRegistry, imported: u64, existing: u64) -> Result<(), MergeError> {
+    if let Some(record) = registry.get(&imported) {
+        record.borrow_mut().extra.retain(|t| t.name != BATCH_TAG);
+    }
+    merge::merge(registry, existing, imported, &MergeChoices::new())
+}
+
+/// Removes all the records of a batch along with the pointers to
+/// them, returns the number of records removed. What was linked to
+/// existing persons stays with them.
+pub fn rollback(registry: &mut RecordRegistry, batch: &str) -> usize {
+    let removed = batch_keys(registry, batch);
+    registry.retain(|key, _| !removed.contains(key));
+    for record in registry.values() {
+        record.borrow_mut().prune_pointers(&removed);
+    }
+    removed.len()
+}
@@ -0,0 +1,498 @@
+//! CSV files of individuals: export with the columns chosen by the
+//! user, and import of spreadsheet transcriptions (census, parish
+//! registers...) where every row becomes a person and the parents'
+//! and spouses' columns the families.
+
+use crate::gedcom::{Event, FamilyGraph, GedDate, GedParser, GedWriter, Record, RecordRegistry, Tag};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::Path;
+
+/// Extension of the CSV files
+pub const EXTENSION: &str = "csv";
+
+/// Columns exported when none are chosen
+pub const DEFAULT_COLUMNS: &str = "id,given,surname,sex,BIRT.date,BIRT.place,DEAT.date,DEAT.place,father,mother";
+
+/// Delimiters told apart when reading a file
+const DELIMITERS: [char; 3] = [',', ';', '\t'];
+
+/// Events offered for the date and place columns
+const COLUMN_EVENTS: [&str; 6] = ["BIRT", "CHR", "DEAT", "BURI", "MARR", "RESI"];
+
+/// Attributes offered for the value columns
+const COLUMN_ATTRIBUTES: [&str; 4] = ["OCCU", "RELI", "NATI", "TITL"];
+
+/// What a column holds about the person of a row
+#[derive(Debug,Clone,PartialEq)]
+pub enum CsvField {
+    Ignored,
+    /// Identifier of the row, the cross-reference on export,
+    /// that the parents' and spouses' columns refer to
+    Id,
+    /// Full name, the surname between slashes or last
+    Name,
+    Given,
+    Surname,
+    Sex,
+    /// Date of the first event with the tag
+    Date(String),
+    /// Place of the first event with the tag
+    Place(String),
+    /// Value of the first attribute with the tag (`OCCU`...)
+    Value(String),
+    /// Identifier of the row of the father, or his name
+    Father,
+    Mother,
+    /// Identifier of the row of the first spouse, or their name
+    Spouse,
+    Note,
+}
+
+impl CsvField {
+    /// Field of the identifier used by the column lists
+    /// (`given`, `BIRT.date`, `OCCU.value`...)
+    pub fn from_id(id: &str) -> Option<Self> {
+        let id = id.trim();
+        if let Some((tag, part)) = id.split_once('.') {
+            let tag = tag.to_uppercase();
+            return match part.to_lowercase().as_str() {
+                "date" => Some(CsvField::Date(tag)),
+                "place" => Some(CsvField::Place(tag)),
+                "value" => Some(CsvField::Value(tag)),
+                _ => None,
+            };
+        }
+        match id.to_lowercase().as_str() {
+            "ignored" | "" => Some(CsvField::Ignored),
+            "id" => Some(CsvField::Id),
+            "name" => Some(CsvField::Name),
+            "given" => Some(CsvField::Given),
+            "surname" => Some(CsvField::Surname),
+            "sex" => Some(CsvField::Sex),
+            "father" => Some(CsvField::Father),
+            "mother" => Some(CsvField::Mother),
+            "spouse" => Some(CsvField::Spouse),
+            "note" => Some(CsvField::Note),
+            _ => None,
+        }
+    }
+
+    pub fn id(&self) -> String {
+        match self {
+            CsvField::Ignored => String::from("ignored"),
+            CsvField::Id => String::from("id"),
+            CsvField::Name => String::from("name"),
+            CsvField::Given => String::from("given"),
+            CsvField::Surname => String::from("surname"),
+            CsvField::Sex => String::from("sex"),
+            CsvField::Date(tag) => format!("{}.date", tag),
+            CsvField::Place(tag) => format!("{}.place", tag),
+            CsvField::Value(tag) => format!("{}.value", tag),
+            CsvField::Father => String::from("father"),
+            CsvField::Mother => String::from("mother"),
+            CsvField::Spouse => String::from("spouse"),
+            CsvField::Note => String::from("note"),
+        }
+    }
+
+    /// Column header (`Birth date`)
+    pub fn label(&self) -> String {
+        match self {
+            CsvField::Ignored => String::from("Ignored"),
+            CsvField::Id => String::from("ID"),
+            CsvField::Name => String::from("Name"),
+            CsvField::Given => String::from("Given names"),
+            CsvField::Surname => String::from("Surname"),
+            CsvField::Sex => String::from("Sex"),
+            CsvField::Date(tag) => format!("{} date", Event::new(tag).label()),
+            CsvField::Place(tag) => format!("{} place", Event::new(tag).label()),
+            CsvField::Value(tag) => Event::new(tag).label().to_owned(),
+            CsvField::Father => String::from("Father"),
+            CsvField::Mother => String::from("Mother"),
+            CsvField::Spouse => String::from("Spouse"),
+            CsvField::Note => String::from("Note"),
+        }
+    }
+
+    /// Fields offered by the forms
+    pub fn choices() -> Vec<CsvField> {
+        let mut fields = vec![
+            CsvField::Ignored, CsvField::Id, CsvField::Name, CsvField::Given,
+            CsvField::Surname, CsvField::Sex,
+        ];
+        for tag in COLUMN_EVENTS.iter() {
+            fields.push(CsvField::Date(tag.to_string()));
+            fields.push(CsvField::Place(tag.to_string()));
+        }
+        fields.extend(COLUMN_ATTRIBUTES.iter().map(|t| CsvField::Value(t.to_string())));
+        fields.extend(vec![CsvField::Father, CsvField::Mother, CsvField::Spouse, CsvField::Note]);
+        fields
+    }
+
+    /// Field a column header stands for, by its identifier or
+    /// its label, ignored when it isn't recognized
+    pub fn guess(header: &str) -> Self {
+        let header = header.trim();
+        Self::from_id(header)
+            .or_else(|| Self::choices().into_iter().find(|f| f.label().eq_ignore_ascii_case(header)))
+            .unwrap_or(CsvField::Ignored)
+    }
+}
+
+/// Fields of a comma separated list of identifiers
+pub fn columns(ids: &str) -> Result<Vec<CsvField>, String> {
+    ids.split(',')
+        .filter(|id| !id.trim().is_empty())
+        .map(|id| CsvField::from_id(id).ok_or_else(|| format!("Unknown column: {}", id.trim())))
+        .collect()
+}
+
+/// Whether the file is to be read as a CSV file
+pub fn is_csv(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map_or(false, |e| e.eq_ignore_ascii_case(EXTENSION))
+}
+
+/// Delimiter of the text, the one found the most on its first line
+pub fn guess_delimiter(text: &str) -> char {
+    let first = text.lines().next().unwrap_or("");
+    DELIMITERS.iter()
+        .copied()
+        .max_by_key(|d| first.matches(*d).count())
+        .filter(|d| first.contains(*d))
+        .unwrap_or(',')
+}
+
+/// Reads the rows of a CSV text. Quoted fields can hold
+/// delimiters, line breaks and doubled quotes.
+pub fn parse(text: &str, delimiter: char) -> Vec<Vec<String>> {
+    let mut rows = vec![];
+    let mut row = vec![];
+    let mut field = String::new();
+    let (mut quoted, mut chars) = (false, text.trim_start_matches('\u{feff}').chars().peekable());
+    while let Some(c) = chars.next() {
+        match (quoted, c) {
+            (true, '"') if chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            },
+            (true, '"') => quoted = false,
+            (true, c) => field.push(c),
+            (false, '"') if field.is_empty() => quoted = true,
+            (false, '\r') => (),
+            (false, '\n') => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            },
+            (false, c) if c == delimiter => row.push(std::mem::take(&mut field)),
+            (false, c) => field.push(c),
+        }
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+    rows
+}
+
+/// Quotes the field when it holds a delimiter,
+/// a quote or a line break
+fn quote(field: &str, delimiter: char) -> String {
+    match field.contains(|c| c == delimiter || c == '"' || c == '\n' || c == '\r') {
+        true => format!("\"{}\"", field.replace('"', "\"\"")),
+        false => field.to_owned(),
+    }
+}
+
+pub(crate) fn write_row<W: Write>(out: &mut W, fields: &[String], delimiter: char) -> std::io::Result<()> {
+    let fields: Vec<String> = fields.iter().map(|f| quote(f, delimiter)).collect();
+    write!(out, "{}\r\n", fields.join(&delimiter.to_string()))
+}
+
+/// Writes a row for every individual exported by the writer, with
+/// a header of the column labels. Parents and spouses are given by
+/// their cross-references.
+pub fn write<W: Write>(writer: &GedWriter, registry: &RecordRegistry, columns: &[CsvField], mut out: W) -> std::io::Result<()> {
+    let records = writer.exported(registry);
+    let exported = GedParser::registry_of(records.clone());
+    let graph = FamilyGraph::new(&exported);
+    let xref = |person| graph.record(&exported, person).map(|r| r.borrow().xref.clone()).unwrap_or_default();
+    let header: Vec<String> = columns.iter().map(|c| c.label()).collect();
+    write_row(&mut out, &header, ',')?;
+    for record in records.iter().filter(|r| r.rtype == "INDI") {
+        let person = graph.person_of(record);
+        // Family events (`MARR`, `DIV`...) are read from the
+        // spouse families when the individual has none
+        let family_event = |tag: &str| person.into_iter()
+            .flat_map(|p| graph.spouse_families(p).iter())
+            .filter_map(|family| graph.family_record(&exported, *family))
+            .find_map(|family| family.borrow().events_of(tag).next().cloned());
+        let event = |tag: &str| record.events_of(tag).next().cloned().or_else(|| family_event(tag));
+        let fields: Vec<String> = columns.iter()
+            .map(|column| match column {
+                CsvField::Ignored => String::new(),
+                CsvField::Id => record.xref.clone(),
+                CsvField::Name => record.display_name(),
+                CsvField::Given => record.given_names(),
+                CsvField::Surname => record.surname(),
+                CsvField::Sex => match record.sex() {
+                    'U' => String::new(),
+                    sex => sex.to_string(),
+                },
+                CsvField::Date(tag) => event(tag).and_then(|e| e.date.clone()).unwrap_or_default(),
+                CsvField::Place(tag) => event(tag).and_then(|e| e.place.clone()).unwrap_or_default(),
+                CsvField::Value(tag) => event(tag).and_then(|e| e.value.clone()).unwrap_or_default(),
+                CsvField::Father => person.and_then(|p| graph.father(p)).map(xref).unwrap_or_default(),
+                CsvField::Mother => person.and_then(|p| graph.mother(p)).map(xref).unwrap_or_default(),
+                CsvField::Spouse => person.and_then(|p| graph.spouses(p).first().copied()).map(xref).unwrap_or_default(),
+                CsvField::Note => record.notes(&exported).join("\n"),
+            })
+            .collect();
+        write_row(&mut out, &fields, ',')?;
+    }
+    Ok(())
+}
+
+/// Problem met on a row of a file being imported
+#[derive(Debug,Clone,PartialEq)]
+pub struct RowProblem {
+    /// Line of the row in the file, the header being the first one
+    pub row: usize,
+    pub message: String,
+    /// Whether the row was left out
+    pub skipped: bool,
+}
+
+/// Records read from the rows of a file, ready to be
+/// [appended](crate::gedcom::batch::append) to a registry
+#[derive(Debug,Clone,Default)]
+pub struct CsvImport {
+    pub records: Vec<Record>,
+    /// Rows imported, with their line and the person read
+    pub persons: Vec<(usize, String)>,
+    /// Parents and spouses added from their names
+    pub relatives: usize,
+    pub families: usize,
+    pub problems: Vec<RowProblem>,
+}
+
+impl CsvImport {
+    pub fn skipped(&self) -> usize {
+        self.problems.iter().filter(|p| p.skipped).count()
+    }
+
+    fn problem(&mut self, row: usize, message: String, skipped: bool) {
+        self.problems.push(RowProblem { row, message, skipped });
+    }
+}
+
+/// Person of a row, or a relative known by name only
+#[derive(Debug,Clone,PartialEq,Eq,Hash)]
+enum PersonRef {
+    Row(usize),
+    Named(String),
+}
+
+/// GED name of a name column: kept when it has slashes, the
+/// surname written first when followed by a comma (`Doe, John`),
+/// the last word being the surname otherwise
+fn ged_name(name: &str) -> String {
+    let name = name.trim();
+    if name.contains('/') {
+        return name.to_owned();
+    }
+    if let Some((surname, given)) = name.split_once(',') {
+        return format!("{} /{}/", given.trim(), surname.trim()).trim_start().to_owned();
+    }
+    match name.rsplit_once(' ') {
+        Some((given, surname)) => format!("{} /{}/", given.trim(), surname),
+        None => format!("/{}/", name),
+    }
+}
+
+/// Families made out of the parents' and spouses' columns
+#[derive(Default)]
+struct Families {
+    /// Partners and children of every family
+    families: Vec<(Option<PersonRef>, Option<PersonRef>, Vec<usize>)>,
+    /// Family of every couple
+    index: HashMap<(Option<PersonRef>, Option<PersonRef>), usize>,
+}
+
+impl Families {
+    fn family(&mut self, husband: Option<PersonRef>, wife: Option<PersonRef>) -> usize {
+        let key = (husband.clone(), wife.clone());
+        if let Some(index) = self.index.get(&key) {
+            return *index;
+        }
+        self.families.push((husband, wife, vec![]));
+        self.index.insert(key, self.families.len() - 1);
+        self.families.len() - 1
+    }
+}
+
+/// Reads the rows as persons, the first one being skipped when it
+/// holds the column names. Blank rows and rows without any name
+/// are left out, values that can't be understood are reported
+/// and kept as text.
+pub fn read(rows: &[Vec<String>], fields: &[CsvField], header: bool) -> CsvImport {
+    let mut import = CsvImport::default();
+    let first_line = if header { 2 } else { 1 };
+    let rows: Vec<(usize, &Vec<String>)> = rows.iter()
+        .skip(if header { 1 } else { 0 })
+        .enumerate()
+        .map(|(i, row)| (i + first_line, row))
+        .collect();
+    let value = |row: &Vec<String>, field: &CsvField| -> Option<String> {
+        fields.iter()
+            .position(|f| f == field)
+            .and_then(|i| row.get(i))
+            .map(|v| v.trim().to_owned())
+            .filter(|v| !v.is_empty())
+    };
+
+    // Persons of the rows, by line
+    let mut persons: Vec<(usize, Tag)> = vec![];
+    let mut ids: HashMap<String, usize> = HashMap::new();
+    for (line, row) in rows.iter().filter(|(_, r)| r.iter().any(|f| !f.trim().is_empty())) {
+        let (given, surname) = (value(row, &CsvField::Given), value(row, &CsvField::Surname));
+        let name = match (value(row, &CsvField::Name), &given, &surname) {
+            (_, Some(given), Some(surname)) => format!("{} /{}/", given, surname),
+            (_, Some(given), None) => given.clone(),
+            (_, None, Some(surname)) => format!("/{}/", surname),
+            (Some(name), None, None) => ged_name(&name),
+            (None, None, None) => {
+                import.problem(*line, String::from("No name, row left out."), true);
+                continue;
+            },
+        };
+        let name_tag = [("GIVN", &given), ("SURN", &surname)].iter()
+            .filter_map(|(tag, value)| value.as_deref().map(|v| Tag::new(tag, Some(v))))
+            .fold(Tag::new("NAME", Some(&name)), Tag::nest);
+        let mut person = Tag { xref: Some(format!("I{}", persons.len() + 1)), ..Tag::new("INDI", None) }
+            .nest(name_tag);
+        match value(row, &CsvField::Sex).map(|s| s.to_uppercase()) {
+            Some(sex) if sex.starts_with('M') || sex.starts_with('H') => person = person.nest(Tag::new("SEX", Some("M"))),
+            Some(sex) if sex.starts_with('F') || sex.starts_with('W') => person = person.nest(Tag::new("SEX", Some("F"))),
+            Some(sex) if sex == "U" => (),
+            Some(sex) => import.problem(*line, format!("Sex '{}' not understood, left unknown.", sex), false),
+            None => (),
+        }
+        // Events in the order of their first column
+        let mut events: Vec<Tag> = vec![];
+        for (i, field) in fields.iter().enumerate() {
+            let (tag, part) = match field {
+                CsvField::Date(tag) => (tag, "DATE"),
+                CsvField::Place(tag) => (tag, "PLAC"),
+                CsvField::Value(tag) => (tag, ""),
+                _ => continue,
+            };
+            let text = match row.get(i).map(|v| v.trim()).filter(|v| !v.is_empty()) {
+                Some(text) => text,
+                None => continue,
+            };
+            let index = match events.iter().position(|e| e.name == *tag) {
+                Some(index) => index,
+                None => {
+                    events.push(Tag::new(tag, None));
+                    events.len() - 1
+                },
+            };
+            let event = &mut events[index];
+            match part {
+                "DATE" if GedDate::parse(text).is_none() => {
+                    import.problem(*line, format!("Date '{}' not understood, kept as a phrase.", text), false);
+                    event.nested.push(Tag::new("DATE", Some(&format!("({})", text))));
+                },
+                "DATE" => event.nested.push(Tag::new("DATE", Some(&text.to_uppercase()))),
+                "PLAC" => event.nested.push(Tag::new("PLAC", Some(text))),
+                _ => event.content = Some(text.to_owned()),
+            }
+        }
+        person.nested.extend(events);
+        if let Some(note) = value(row, &CsvField::Note) {
+            person = person.nest(Tag::with_text("NOTE", &note));
+        }
+        if let Some(id) = value(row, &CsvField::Id) {
+            if ids.insert(id.clone(), persons.len()).is_some() {
+                import.problem(*line, format!("ID '{}' already used by another row.", id), false);
+            }
+        }
+        import.persons.push((*line, name.replace('/', "")));
+        persons.push((*line, person));
+    }
+
+    // Families of the parents and spouses, given by the
+    // identifier of their row or by their name
+    let names: HashMap<String, usize> = persons.iter()
+        .enumerate()
+        .rev()
+        .filter_map(|(i, (_, tag))| Some((tag.value("NAME")?.to_lowercase(), i)))
+        .collect();
+    let reference = |text: String| {
+        let name = ged_name(&text);
+        match ids.get(&text).or_else(|| names.get(&name.to_lowercase())) {
+            Some(index) => PersonRef::Row(*index),
+            None => PersonRef::Named(name),
+        }
+    };
+    let mut families = Families::default();
+    let row_of: HashMap<usize, &Vec<String>> = rows.iter().map(|(line, row)| (*line, *row)).collect();
+    for (index, (line, _)) in persons.iter().enumerate() {
+        let row = row_of[line];
+        let (father, mother) = (value(row, &CsvField::Father).map(reference), value(row, &CsvField::Mother).map(reference));
+        if father.is_some() || mother.is_some() {
+            let family = families.family(father, mother);
+            families.families[family].2.push(index);
+        }
+        if let Some(spouse) = value(row, &CsvField::Spouse).map(reference) {
+            let this = PersonRef::Row(index);
+            if spouse == this {
+                continue;
+            }
+            let is_wife = persons[index].1.value("SEX").as_deref() == Some("F");
+            let (husband, wife) = if is_wife { (spouse, this) } else { (this, spouse) };
+            // Both rows naming each other make a single family
+            if !families.index.contains_key(&(Some(wife.clone()), Some(husband.clone()))) {
+                families.family(Some(husband), Some(wife));
+            }
+        }
+    }
+
+    // Relatives known by name become persons of their own,
+    // one for every name
+    let mut tags: Vec<Tag> = persons.into_iter().map(|(_, tag)| tag).collect();
+    let mut relatives: HashMap<String, usize> = HashMap::new();
+    let mut family_tags = vec![];
+    for (i, (husband, wife, children)) in families.families.iter().enumerate() {
+        let xref = format!("F{}", i + 1);
+        let mut family = Tag { xref: Some(xref.clone()), ..Tag::new("FAM", None) };
+        for (role, partner, sex) in [("HUSB", husband, "M"), ("WIFE", wife, "F")].iter() {
+            let index = match partner {
+                Some(PersonRef::Row(index)) => *index,
+                Some(PersonRef::Named(name)) => *relatives.entry(name.to_lowercase()).or_insert_with(|| {
+                    tags.push(Tag { xref: Some(format!("I{}", tags.len() + 1)), ..Tag::new("INDI", None) }
+                        .nest(Tag::new("NAME", Some(name)))
+                        .nest(Tag::new("SEX", Some(sex))));
+                    tags.len() - 1
+                }),
+                None => continue,
+            };
+            let pointer = format!("@{}@", tags[index].xref.as_deref().unwrap_or_default());
+            family = family.nest(Tag::new(role, Some(&pointer)));
+            tags[index].nested.push(Tag::new("FAMS", Some(&format!("@{}@", xref))));
+        }
+        for child in children {
+            let pointer = format!("@{}@", tags[*child].xref.as_deref().unwrap_or_default());
+            family = family.nest(Tag::new("CHIL", Some(&pointer)));
+            tags[*child].nested.push(Tag::new("FAMC", Some(&format!("@{}@", xref))));
+        }
+        family_tags.push(family);
+    }
+    import.relatives = relatives.len();
+    import.families = family_tags.len();
+    import.records = tags.iter().chain(family_tags.iter()).map(Record::from_tag).collect();
+    import
+}
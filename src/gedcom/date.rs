@@ -0,0 +1,298 @@
+//! Interpretation of the GEDCOM date values (`DATE` lines):
+//! simple dates, approximations, ranges and periods.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::time::SystemTime;
+
+const MONTHS: [&str; 12] = [
+    "JAN", "FEB", "MAR", "APR", "MAY", "JUN",
+    "JUL", "AUG", "SEP", "OCT", "NOV", "DEC",
+];
+
+/// Calendar keywords of GEDCOM 7
+const CALENDARS: [&str; 4] = ["GREGORIAN", "JULIAN", "HEBREW", "FRENCH_R"];
+
+/// Calendar date with optional month and day, as
+/// genealogical dates are often incomplete.
+#[derive(Default,Debug,Clone,Copy,PartialEq,Eq,Hash)]
+pub struct SimpleDate {
+    pub year: i32,
+    pub month: Option<u8>,
+    pub day: Option<u8>,
+}
+
+/// Qualifier of a date value
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub enum DateModifier {
+    Exact,
+    About,
+    Calculated,
+    Estimated,
+    Before,
+    After,
+    Between,
+    From,
+    To,
+    FromTo,
+    Interpreted,
+}
+
+/// Parsed date value. `start` holds the only date of
+/// single dates, `end` is used by ranges and periods.
+#[derive(Debug,Clone,PartialEq)]
+pub struct GedDate {
+    pub modifier: DateModifier,
+    pub start: Option<SimpleDate>,
+    pub end: Option<SimpleDate>,
+    pub phrase: Option<String>,
+}
+
+impl SimpleDate {
+    pub fn new(year: i32, month: Option<u8>, day: Option<u8>) -> Self {
+        SimpleDate { year, month, day }
+    }
+
+    pub fn year(year: i32) -> Self {
+        Self::new(year, None, None)
+    }
+
+    /// Current date of the system clock (UTC)
+    pub fn today() -> Self {
+        let days = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|d| d.as_secs() / 86_400)
+            .unwrap_or(0) as i64;
+        // Civil date from the days since the epoch, the years
+        // being counted from March so leap days come last
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z - era * 146_097;
+        let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = (doy - (153 * mp + 2) / 5 + 1) as u8;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u8;
+        let year = (yoe + era * 400 + if month <= 2 { 1 } else { 0 }) as i32;
+        Self::new(year, Some(month), Some(day))
+    }
+
+    /// Parses `[[DAY] MON] YEAR[/YY] [B.C.]`, calendar escapes
+    /// like `@#DJULIAN@` and GEDCOM 7 calendars like `JULIAN`
+    /// are ignored, as is the `BCE` era of GEDCOM 7.
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+        let text = match text.strip_prefix("@#") {
+            Some(rest) => rest.splitn(2, '@').nth(1)?.trim(),
+            None => text,
+        };
+        let upper = text.to_uppercase();
+        let upper = CALENDARS.iter()
+            .find_map(|c| upper.strip_prefix(c).filter(|r| r.starts_with(' ')))
+            .map_or(upper.clone(), |r| r.trim().to_owned());
+        let era = upper.strip_suffix("BCE")
+            .or_else(|| upper.strip_suffix("B.C."))
+            .or_else(|| upper.strip_suffix("BC"));
+        let (upper, bc) = match era {
+            Some(rest) => (rest.trim().to_owned(), true),
+            None => (upper.clone(), false),
+        };
+        let words: Vec<&str> = upper.split_whitespace().collect();
+        let year = |w: &str| -> Option<i32> {
+            let y: i32 = w.splitn(2, '/').next()?.parse().ok()?;
+            Some(if bc { -y } else { y })
+        };
+        let month = |w: &str| MONTHS.iter().position(|m| *m == w).map(|m| m as u8 + 1);
+        let date = match words.as_slice() {
+            [y] => Self::year(year(y)?),
+            [m, y] => Self::new(year(y)?, Some(month(m)?), None),
+            [d, m, y] => Self::new(year(y)?, Some(month(m)?), Some(d.parse().ok()?)),
+            _ => return None,
+        };
+        match date.day {
+            Some(d) if d == 0 || d > 31 => None,
+            _ => Some(date),
+        }
+    }
+
+    /// Earliest complete date this one can stand for
+    pub fn lower(&self) -> (i32, u8, u8) {
+        (self.year, self.month.unwrap_or(1), self.day.unwrap_or(1))
+    }
+
+    /// Latest complete date this one can stand for
+    pub fn upper(&self) -> (i32, u8, u8) {
+        (self.year, self.month.unwrap_or(12), self.day.unwrap_or(31))
+    }
+
+    /// Difference in full years between two dates,
+    /// uncertain when one of them lacks month or day
+    pub fn years_until(&self, other: &SimpleDate) -> i32 {
+        let (from, to) = (self.lower(), other.lower());
+        let years = to.0 - from.0;
+        if (to.1, to.2) < (from.1, from.2) { years - 1 } else { years }
+    }
+}
+
+impl PartialOrd for SimpleDate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for SimpleDate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.lower().cmp(&other.lower())
+    }
+}
+
+impl fmt::Display for SimpleDate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(day) = self.day {
+            write!(f, "{} ", day)?;
+        }
+        if let Some(month) = self.month {
+            write!(f, "{} ", MONTHS[(month as usize - 1) % 12])?;
+        }
+        match self.year {
+            y if y < 0 => write!(f, "{} B.C.", -y),
+            y => write!(f, "{}", y),
+        }
+    }
+}
+
+impl GedDate {
+    fn single(modifier: DateModifier, date: &str) -> Option<Self> {
+        Some(GedDate {
+            modifier,
+            start: Some(SimpleDate::parse(date)?),
+            end: None,
+            phrase: None,
+        })
+    }
+
+    fn pair(modifier: DateModifier, start: &str, end: &str) -> Option<Self> {
+        Some(GedDate {
+            modifier,
+            start: Some(SimpleDate::parse(start)?),
+            end: Some(SimpleDate::parse(end)?),
+            phrase: None,
+        })
+    }
+
+    /// Parses a `DATE` line value. Returns `None` for values
+    /// that are neither a valid date nor a date phrase.
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+        let upper = text.to_uppercase();
+        let after = |prefix: &str| upper.strip_prefix(prefix).map(|r| r.trim().to_owned());
+        if text.starts_with('(') && text.ends_with(')') {
+            return Some(GedDate {
+                modifier: DateModifier::Interpreted,
+                start: None,
+                end: None,
+                phrase: Some(text[1..text.len() - 1].to_owned()),
+            });
+        }
+        if let Some(rest) = after("BET ") {
+            let mut parts = rest.splitn(2, " AND ");
+            return Self::pair(DateModifier::Between, parts.next()?, parts.next()?);
+        }
+        if let Some(rest) = after("FROM ") {
+            let mut parts = rest.splitn(2, " TO ");
+            return match (parts.next(), parts.next()) {
+                (Some(from), Some(to)) => Self::pair(DateModifier::FromTo, from, to),
+                (Some(from), None) => Self::single(DateModifier::From, from),
+                _ => None,
+            };
+        }
+        if upper.starts_with("INT ") {
+            let rest = &text[4..];
+            let (date, phrase) = match rest.find('(') {
+                Some(pos) => (&rest[..pos], Some(rest[pos..].trim_matches(&['(', ')', ' '][..]).to_owned())),
+                None => (rest, None),
+            };
+            return Self::single(DateModifier::Interpreted, date).map(|d| GedDate { phrase, ..d });
+        }
+        let prefixed = [
+            ("ABT ", DateModifier::About),
+            ("CAL ", DateModifier::Calculated),
+            ("EST ", DateModifier::Estimated),
+            ("BEF ", DateModifier::Before),
+            ("AFT ", DateModifier::After),
+            ("TO ", DateModifier::To),
+        ];
+        for (prefix, modifier) in prefixed.iter() {
+            if let Some(rest) = after(prefix) {
+                return Self::single(*modifier, &rest);
+            }
+        }
+        Self::single(DateModifier::Exact, &upper)
+    }
+
+    /// Date used for sorting and age computations
+    pub fn sort_date(&self) -> Option<SimpleDate> {
+        self.start.or(self.end)
+    }
+
+    /// Year of the sort date
+    pub fn year(&self) -> Option<i32> {
+        self.sort_date().map(|d| d.year)
+    }
+
+    /// Lower and upper bounds of the value as complete
+    /// dates, `None` standing for an open bound.
+    pub fn bounds(&self) -> (Option<(i32, u8, u8)>, Option<(i32, u8, u8)>) {
+        use DateModifier::*;
+        let (start, end) = (self.start, self.end);
+        match self.modifier {
+            Before | To => (None, start.map(|d| d.upper())),
+            After | From => (start.map(|d| d.lower()), None),
+            Between | FromTo => (start.map(|d| d.lower()), end.map(|d| d.upper())),
+            _ => (start.map(|d| d.lower()), start.map(|d| d.upper())),
+        }
+    }
+
+    /// Checks whether the value can fall into the given
+    /// range of years (both ends included)
+    pub fn overlaps(&self, from: Option<SimpleDate>, to: Option<SimpleDate>) -> bool {
+        if self.start.is_none() && self.end.is_none() {
+            return false;
+        }
+        let (lower, upper) = self.bounds();
+        let after_from = match (from, upper) {
+            (Some(from), Some(upper)) => upper >= from.lower(),
+            _ => true,
+        };
+        let before_to = match (to, lower) {
+            (Some(to), Some(lower)) => lower <= to.upper(),
+            _ => true,
+        };
+        after_from && before_to
+    }
+}
+
+impl fmt::Display for GedDate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use DateModifier::*;
+        let opt = |d: Option<SimpleDate>| d.map(|d| d.to_string()).unwrap_or_default();
+        let (start, end) = (opt(self.start), opt(self.end));
+        match self.modifier {
+            Exact => write!(f, "{}", start),
+            About => write!(f, "ABT {}", start),
+            Calculated => write!(f, "CAL {}", start),
+            Estimated => write!(f, "EST {}", start),
+            Before => write!(f, "BEF {}", start),
+            After => write!(f, "AFT {}", start),
+            Between => write!(f, "BET {} AND {}", start, end),
+            From => write!(f, "FROM {}", start),
+            To => write!(f, "TO {}", start),
+            FromTo => write!(f, "FROM {} TO {}", start, end),
+            Interpreted => match (&self.start, &self.phrase) {
+                (Some(_), Some(phrase)) => write!(f, "INT {} ({})", start, phrase),
+                (Some(_), None) => write!(f, "INT {}", start),
+                (None, phrase) => write!(f, "({})", phrase.as_deref().unwrap_or("")),
+            },
+        }
+    }
+}
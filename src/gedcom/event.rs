@@ -0,0 +1,144 @@
+//! Events and attributes of individuals and families
+//! (births, marriages, residences, occupations...).
+
+use crate::gedcom::address::ContactInfo;
+use crate::gedcom::gedex::Tag;
+
+/// Tags that are interpreted as events or attributes
+/// of an individual or a family
+pub const EVENT_TAGS: [&str; 43] = [
+    // Individual events
+    "BIRT", "CHR", "DEAT", "BURI", "CREM", "ADOP", "BAPM", "BARM", "BASM",
+    "BLES", "CHRA", "CONF", "FCOM", "ORDN", "NATU", "EMIG", "IMMI", "CENS",
+    "PROB", "WILL", "GRAD", "RETI", "EVEN",
+    // Individual attributes
+    "RESI", "OCCU", "EDUC", "RELI", "TITL", "PROP", "NATI", "DSCR",
+    // Family events
+    "ANUL", "DIV", "DIVF", "ENGA", "MARB", "MARC", "MARR", "MARL", "MARS",
+    // Attributes shared with the family
+    "FACT", "CAST", "NCHI",
+];
+
+/// Single event or attribute with its date, place and
+/// optional contact information (mainly for residences)
+#[derive(Default,Debug,Clone,PartialEq)]
+pub struct Event {
+    pub tag: String,
+    pub value: Option<String>,
+    pub date: Option<String>,
+    pub place: Option<String>,
+    /// Structures nested in the place: coordinates, form...
+    pub place_details: Vec<Tag>,
+    pub contact: Option<ContactInfo>,
+    /// Nested structures not interpreted by the model
+    pub extra: Vec<Tag>,
+}
+
+impl Event {
+    pub fn new(tag: &str) -> Self {
+        Event {
+            tag: String::from(tag),
+            ..Default::default()
+        }
+    }
+
+    pub fn is_event_tag(name: &str) -> bool {
+        EVENT_TAGS.contains(&name)
+    }
+
+    /// Human readable name of the event
+    pub fn label(&self) -> &str {
+        match self.tag.as_str() {
+            "BIRT" => "Birth",
+            "CHR" => "Christening",
+            "DEAT" => "Death",
+            "BURI" => "Burial",
+            "CREM" => "Cremation",
+            "ADOP" => "Adoption",
+            "BAPM" => "Baptism",
+            "BARM" => "Bar Mitzvah",
+            "BASM" => "Bas Mitzvah",
+            "BLES" => "Blessing",
+            "CHRA" => "Adult christening",
+            "CONF" => "Confirmation",
+            "FCOM" => "First communion",
+            "ORDN" => "Ordination",
+            "NATU" => "Naturalization",
+            "EMIG" => "Emigration",
+            "IMMI" => "Immigration",
+            "CENS" => "Census",
+            "PROB" => "Probate",
+            "WILL" => "Will",
+            "GRAD" => "Graduation",
+            "RETI" => "Retirement",
+            "EVEN" => "Event",
+            "RESI" => "Residence",
+            "OCCU" => "Occupation",
+            "EDUC" => "Education",
+            "RELI" => "Religion",
+            "TITL" => "Title",
+            "PROP" => "Property",
+            "NATI" => "Nationality",
+            "DSCR" => "Description",
+            "ANUL" => "Annulment",
+            "DIV" => "Divorce",
+            "DIVF" => "Divorce filed",
+            "ENGA" => "Engagement",
+            "MARB" => "Marriage bann",
+            "MARC" => "Marriage contract",
+            "MARR" => "Marriage",
+            "MARL" => "Marriage license",
+            "MARS" => "Marriage settlement",
+            "FACT" => "Fact",
+            "CAST" => "Caste",
+            "NCHI" => "Children count",
+            tag => tag,
+        }
+    }
+
+    /// One line description: value, date and place
+    pub fn summary(&self) -> String {
+        self.value.iter()
+            .chain(self.date.iter())
+            .chain(self.place.iter())
+            .map(|s| s.trim())
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<&str>>()
+            .join(", ")
+    }
+
+    pub fn from_tag(tag: &Tag) -> Self {
+        Event {
+            tag: tag.name.clone(),
+            value: tag.text(),
+            date: tag.value("DATE"),
+            place: tag.value("PLAC"),
+            place_details: tag.child("PLAC")
+                .map(|p| p.nested.iter().filter(|t| t.name != "CONT" && t.name != "CONC").cloned().collect())
+                .unwrap_or_default(),
+            contact: ContactInfo::from_parent(tag),
+            extra: tag.nested.iter()
+                .filter(|t| !matches!(t.name.as_str(), "DATE" | "PLAC" | "CONT" | "CONC"))
+                .filter(|t| !ContactInfo::is_contact_tag(&t.name))
+                .cloned()
+                .collect(),
+        }
+    }
+
+    pub fn to_tag(&self) -> Tag {
+        let tag = match &self.value {
+            Some(value) => Tag::with_text(&self.tag, value),
+            None => Tag::new(&self.tag, None),
+        };
+        let date = self.date.iter().map(|d| Tag::new("DATE", Some(d)));
+        let place = self.place.iter().map(|p| Tag {
+            nested: self.place_details.clone(),
+            ..Tag::new("PLAC", Some(p))
+        });
+        let contact = self.contact.iter().flat_map(|c| c.to_tags());
+        date.chain(place)
+            .chain(contact)
+            .chain(self.extra.iter().cloned())
+            .fold(tag, |tag, nested| tag.nest(nested))
+    }
+}
@@ -0,0 +1,316 @@
+//! Partial exports: the part of the tree going into a GED file
+//! and the kinds of data left out of it. Applied before the
+//! [privacy filter](crate::gedcom::PrivacyFilter). Also the
+//! file formats an export can be written in.
+
+use crate::gedcom::csv::{self, CsvField};
+use crate::gedcom::{gedzip, gramps, jsonld, xref_key, FamilyGraph, GedVersion, GedWriter, PersonId, Record, RecordRegistry, Tag};
+use std::collections::{BTreeSet, HashSet, VecDeque};
+use std::io::{Seek, Write};
+use std::path::Path;
+
+/// Values of `RESN` making a fact or a record private
+const PRIVATE_RESTRICTIONS: [&str; 2] = ["confidential", "privacy"];
+
+/// Part of the tree going into an export, persons
+/// being given by their registry keys
+#[derive(Debug,Clone,PartialEq)]
+pub enum ExportScope {
+    /// Every record of the tree
+    All,
+    /// The person and their ancestors
+    Ancestors { root: u64, generations: usize },
+    /// The person, their descendants and the partners
+    /// of all of them
+    Descendants { root: u64, generations: usize },
+    /// Everyone linked to the person through parents,
+    /// children and partners, however far
+    Related { root: u64 },
+    /// Given individuals: a search result, a list
+    /// marked by the user...
+    Persons(BTreeSet<u64>),
+}
+
+impl Default for ExportScope {
+    fn default() -> Self {
+        ExportScope::All
+    }
+}
+
+/// Kinds of data going into an export
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub struct ExportContent {
+    /// Notes, inline or records
+    pub notes: bool,
+    /// Source citations, sources and repositories
+    pub sources: bool,
+    /// Multimedia links and records
+    pub media: bool,
+    /// LDS ordinances
+    pub lds: bool,
+    /// Facts and records restricted with `RESN`
+    pub private: bool,
+}
+
+impl Default for ExportContent {
+    fn default() -> Self {
+        ExportContent { notes: true, sources: true, media: true, lds: true, private: true }
+    }
+}
+
+impl ExportContent {
+    /// Whether a record of the given type can be exported
+    fn has_type(&self, rtype: &str) -> bool {
+        match rtype {
+            "NOTE" => self.notes,
+            "SOUR" | "REPO" => self.sources,
+            "OBJE" => self.media,
+            _ => true,
+        }
+    }
+
+    /// Whether a tag can be exported
+    fn has_tag(&self, tag: &Tag) -> bool {
+        match tag.name.as_str() {
+            "NOTE" => self.notes,
+            "SOUR" => self.sources,
+            "OBJE" => self.media,
+            _ => self.private || !is_private(&tag.nested),
+        }
+    }
+}
+
+/// Whether the structures restrict their parent to private use
+fn is_private(nested: &[Tag]) -> bool {
+    nested.iter()
+        .filter(|t| t.name == "RESN")
+        .filter_map(|t| t.content.as_deref())
+        .any(|r| PRIVATE_RESTRICTIONS.contains(&r.trim().to_lowercase().as_str()))
+}
+
+/// Everyone linked to the person through parents,
+/// children and partners, the person included
+fn related(graph: &FamilyGraph, person: PersonId) -> Vec<PersonId> {
+    let mut seen: HashSet<PersonId> = std::iter::once(person).collect();
+    let mut queue: VecDeque<PersonId> = std::iter::once(person).collect();
+    let mut related = vec![];
+    while let Some(person) = queue.pop_front() {
+        related.push(person);
+        let relatives = graph.parents(person).into_iter()
+            .chain(graph.children(person))
+            .chain(graph.spouses(person));
+        for relative in relatives {
+            if seen.insert(relative) {
+                queue.push_back(relative);
+            }
+        }
+    }
+    related
+}
+
+/// Keys of the individuals in the scope
+pub fn persons(scope: &ExportScope, graph: &FamilyGraph, registry: &RecordRegistry) -> HashSet<u64> {
+    let root = match scope {
+        ExportScope::All => return registry.iter()
+            .filter(|(_, r)| r.borrow().rtype == "INDI")
+            .map(|(key, _)| *key)
+            .collect(),
+        ExportScope::Persons(keys) => return keys.iter()
+            .filter(|key| graph.person(**key).is_some())
+            .copied()
+            .collect(),
+        ExportScope::Ancestors { root, .. }
+        | ExportScope::Descendants { root, .. }
+        | ExportScope::Related { root } => match graph.person(*root) {
+            Some(root) => root,
+            None => return HashSet::new(),
+        },
+    };
+    let persons: Vec<PersonId> = match scope {
+        ExportScope::Ancestors { generations, .. } => std::iter::once(root)
+            .chain(graph.ancestors(root, *generations).map(|(p, _)| p))
+            .collect(),
+        ExportScope::Descendants { generations, .. } => {
+            let descendants: Vec<PersonId> = std::iter::once(root)
+                .chain(graph.descendants(root, *generations).map(|(p, _)| p))
+                .collect();
+            let partners: Vec<PersonId> = descendants.iter().flat_map(|p| graph.spouses(*p)).collect();
+            descendants.into_iter().chain(partners).collect()
+        },
+        _ => related(graph, root),
+    };
+    persons.into_iter().map(|p| graph.key(p)).collect()
+}
+
+/// Keys of the records pointed to from anywhere in the record
+pub(crate) fn pointed(record: &Record) -> Vec<u64> {
+    fn collect(tags: &[Tag], keys: &mut Vec<u64>) {
+        for tag in tags {
+            keys.extend(tag.pointer().map(xref_key));
+            collect(&tag.nested, keys);
+        }
+    }
+    let mut keys = vec![];
+    collect(&record.extra, &mut keys);
+    for event in &record.events {
+        collect(&event.extra, &mut keys);
+    }
+    for ordinance in &record.ordinances {
+        collect(&ordinance.extra, &mut keys);
+    }
+    keys
+}
+
+/// Copy of the part of the registry to export. The families of
+/// the persons in the scope go with them, as do the records
+/// (sources, notes...) they point to. Pointers to the records
+/// left out are removed.
+pub fn apply(registry: &RecordRegistry, scope: &ExportScope, content: &ExportContent) -> RecordRegistry {
+    let graph = FamilyGraph::new(registry);
+    let is_public = |key: &u64| content.private
+        || registry.get(key).map_or(false, |r| !is_private(&r.borrow().extra));
+    let persons: HashSet<u64> = persons(scope, &graph, registry).into_iter()
+        .filter(is_public)
+        .collect();
+    let families: HashSet<u64> = graph.families()
+        .filter(|f| *scope == ExportScope::All || graph.partners(*f).iter().any(|p| persons.contains(&graph.key(*p))))
+        .map(|f| graph.family_key(f))
+        .filter(is_public)
+        .collect();
+
+    let copy = |key: &u64| -> Option<Record> {
+        let mut record = registry.get(key)?.borrow().clone();
+        if !content.has_type(&record.rtype) || !is_public(key) {
+            return None;
+        }
+        record.retain_tags(|t| content.has_tag(t));
+        if !content.private {
+            record.events.retain(|e| !is_private(&e.extra));
+            record.ordinances.retain(|o| !is_private(&o.extra));
+        }
+        if !content.lds {
+            record.ordinances.clear();
+        }
+        Some(record)
+    };
+    let mut exported = RecordRegistry::new();
+    let mut pending: VecDeque<u64> = persons.iter().chain(families.iter()).copied().collect();
+    // Submitters are always kept, the other records once pointed to
+    pending.extend(registry.iter().filter(|(_, r)| match r.borrow().rtype.as_str() {
+        "INDI" | "FAM" => false,
+        "SUBM" => true,
+        _ => *scope == ExportScope::All,
+    }).map(|(key, _)| *key));
+    while let Some(key) = pending.pop_front() {
+        if exported.contains_key(&key) {
+            continue;
+        }
+        let record = match copy(&key) {
+            Some(record) => record,
+            None => continue,
+        };
+        pending.extend(pointed(&record).into_iter().filter(|key| match registry.get(key) {
+            Some(r) => !matches!(r.borrow().rtype.as_str(), "INDI" | "FAM"),
+            None => false,
+        }));
+        exported.insert(key, record.into());
+    }
+
+    let removed: HashSet<u64> = registry.keys()
+        .filter(|key| !exported.contains_key(key))
+        .copied()
+        .collect();
+    for record in exported.values() {
+        record.borrow_mut().prune_pointers(&removed);
+    }
+    exported
+}
+
+/// File formats the records are exported to
+#[derive(Debug,Clone,PartialEq)]
+pub enum ExportFormat {
+    Ged(GedVersion),
+    GedZip,
+    Gramps,
+    Json,
+    /// Table of the individuals with the given columns
+    Csv(Vec<CsvField>),
+}
+
+impl Default for ExportFormat {
+    fn default() -> Self {
+        ExportFormat::Ged(GedVersion::default())
+    }
+}
+
+impl ExportFormat {
+    /// Format from the identifier used by the option lists
+    /// and the command line (`ged551`, `ged7`, `gedzip`, `gramps`, `json`,
+    /// `csv` with the [default columns](csv::DEFAULT_COLUMNS))
+    pub fn from_id(id: &str) -> Option<Self> {
+        match id {
+            "ged551" | "ged" => Some(ExportFormat::Ged(GedVersion::V551)),
+            "ged7" => Some(ExportFormat::Ged(GedVersion::V7)),
+            "gedzip" | "gdz" => Some(ExportFormat::GedZip),
+            "gramps" => Some(ExportFormat::Gramps),
+            "json" => Some(ExportFormat::Json),
+            "csv" => csv::columns(csv::DEFAULT_COLUMNS).ok().map(ExportFormat::Csv),
+            _ => None,
+        }
+    }
+
+    pub fn id(&self) -> &'static str {
+        match self {
+            ExportFormat::Ged(GedVersion::V551) => "ged551",
+            ExportFormat::Ged(GedVersion::V7) => "ged7",
+            ExportFormat::GedZip => "gedzip",
+            ExportFormat::Gramps => "gramps",
+            ExportFormat::Json => "json",
+            ExportFormat::Csv(_) => "csv",
+        }
+    }
+
+    /// Format told by the extension of the file, the
+    /// GED files being written in 5.5.1
+    pub fn of_path(path: &Path) -> Option<Self> {
+        if gedzip::is_package(path) {
+            Some(ExportFormat::GedZip)
+        } else if gramps::is_gramps(path) {
+            Some(ExportFormat::Gramps)
+        } else if jsonld::is_json(path) {
+            Some(ExportFormat::Json)
+        } else if csv::is_csv(path) {
+            Self::from_id("csv")
+        } else {
+            path.extension()
+                .and_then(|e| e.to_str())
+                .filter(|e| e.eq_ignore_ascii_case("ged"))
+                .map(|_| ExportFormat::default())
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Ged(_) => "ged",
+            ExportFormat::GedZip => "gdz",
+            ExportFormat::Gramps => gramps::EXTENSION,
+            ExportFormat::Json => jsonld::EXTENSION,
+            ExportFormat::Csv(_) => csv::EXTENSION,
+        }
+    }
+
+    /// Writes the registry with the settings of the writer,
+    /// its version being the one of the format
+    pub fn write<W: Write + Seek>(&self, writer: &GedWriter, registry: &RecordRegistry, mut out: W) -> std::io::Result<()> {
+        match self {
+            ExportFormat::Ged(version) => {
+                let writer = GedWriter { version: *version, ..writer.clone() };
+                writer.write(registry, &mut out)
+            },
+            ExportFormat::GedZip => gedzip::write(writer, registry, out),
+            ExportFormat::Gramps => gramps::write(writer, registry, out),
+            ExportFormat::Json => jsonld::write(writer, registry, out),
+            ExportFormat::Csv(columns) => csv::write(writer, registry, columns, out),
+        }
+    }
+}
@@ -0,0 +1,72 @@
+//! Creation of families out of the individuals of the registry,
+//! the family record and the links of its members both written.
+
+use crate::gedcom::{ident, xref_key, Event, Record, RecordRegistry, Tag};
+use std::collections::HashSet;
+
+/// Individuals of a new family, by registry key
+#[derive(Debug,Clone,Default,PartialEq)]
+pub struct FamilyMembers {
+    pub husband: Option<u64>,
+    pub wife: Option<u64>,
+    pub children: Vec<u64>,
+}
+
+#[derive(Debug)]
+pub enum FamilyError {
+    NoPartner,
+    NotFound,
+    SamePerson,
+}
+
+impl FamilyError {
+    pub fn what(&self) -> String {
+        match &self {
+            FamilyError::NoPartner => "NoPartner: a family needs a husband or a wife.".to_string(),
+            FamilyError::NotFound => "NotFound: one of the members is not an individual of the tree.".to_string(),
+            FamilyError::SamePerson => "SamePerson: a person can't stand twice in the same family.".to_string(),
+        }
+    }
+}
+
+/// Adds a family with the given members and events, returns its
+/// registry key. The members get their `FAMS` or `FAMC` links to
+/// it, the [`FamilyGraph`](crate::gedcom::FamilyGraph) of the
+/// registry has to be built again.
+pub fn add_family(registry: &mut RecordRegistry, members: &FamilyMembers, events: Vec<Event>) -> Result<u64, FamilyError> {
+    if members.husband.is_none() && members.wife.is_none() {
+        return Err(FamilyError::NoPartner);
+    }
+    let links: Vec<(&str, &str, u64)> = members.husband.iter().map(|key| ("HUSB", "FAMS", *key))
+        .chain(members.wife.iter().map(|key| ("WIFE", "FAMS", *key)))
+        .chain(members.children.iter().map(|key| ("CHIL", "FAMC", *key)))
+        .collect();
+    let mut seen = HashSet::new();
+    for (_, _, key) in &links {
+        match registry.get(key) {
+            Some(record) if record.borrow().rtype == "INDI" => (),
+            _ => return Err(FamilyError::NotFound),
+        }
+        if !seen.insert(*key) {
+            return Err(FamilyError::SamePerson);
+        }
+    }
+
+    let xref = ident::next_xref(registry, "F");
+    let mut family = Record {
+        rtype: String::from("FAM"),
+        id: xref[1..].parse().unwrap_or(0),
+        xref: xref.clone(),
+        events,
+        ..Default::default()
+    };
+    for (tag, link, key) in links {
+        let mut member = registry[&key].borrow_mut();
+        family.extra.push(Tag::new(tag, Some(&format!("@{}@", member.xref))));
+        member.extra.push(Tag::new(link, Some(&format!("@{}@", xref))));
+    }
+    let key = xref_key(&xref);
+    registry.insert(key, family.into());
+    ident::assign_identifiers(registry);
+    Ok(key)
+}
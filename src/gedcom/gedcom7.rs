@@ -0,0 +1,458 @@
+//! GEDCOM 7.0 support. Files are converted at the level of the
+//! raw structures: a 7.0 file is turned into 5.5.1 ones when
+//! imported, which the rest of the crate understands, and the
+//! 5.5.1 structures of an export are turned into 7.0 ones.
+
+use crate::gedcom::date::DateModifier;
+use crate::gedcom::{ident, GedDate, Tag};
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+/// Pointer to nothing, standing for a record left out
+pub const VOID: &str = "@VOID@";
+
+/// Namespace of the 5.5.1 tags removed from GEDCOM 7
+const V551_TERMS: &str = "https://gedcom.io/terms/v5.5.1/";
+
+/// Namespace of the extension tags of nPAF
+pub(crate) const NPAF_TERMS: &str = "https://altereigo.org/npaf/terms/";
+
+/// 5.5.1 tags written as extensions in 7.0 files
+const REMOVED_TAGS: [&str; 3] = ["RIN", "AFN", "RFN"];
+
+/// Extension tags of both versions, `_UID` being one when
+/// it is not a UUID
+const EXTENSION_TAGS: [&str; 3] = ["_LIVING", "_BATCH", "_UID"];
+
+/// Tags renamed from 5.5.1 to 7.0. `_UID` becomes `UID`
+/// only when it can be written as a UUID.
+const RENAMED_TAGS: [(&str, &str); 1] = [("_EXID", "EXID")];
+
+/// Calendar escapes of 5.5.1 and keywords of 7.0
+const CALENDARS: [(&str, &str); 4] = [
+    ("@#DGREGORIAN@", "GREGORIAN"),
+    ("@#DJULIAN@", "JULIAN"),
+    ("@#DHEBREW@", "HEBREW"),
+    ("@#DFRENCH R@", "FRENCH_R"),
+];
+
+/// Age keywords of 5.5.1 and the ages standing for them in 7.0
+const AGE_KEYWORDS: [(&str, &str); 3] = [("CHILD", "< 8y"), ("INFANT", "< 1y"), ("STILLBORN", "0y")];
+
+/// LDS ordinance statuses spelled differently in 7.0
+const STATUSES: [(&str, &str); 2] = [("DNS/CAN", "DNS_CAN"), ("PRE-1970", "PRE_1970")];
+
+/// File extensions of 5.5.1 media formats and the media types of 7.0
+const MEDIA_TYPES: [(&str, &str); 14] = [
+    ("jpg", "image/jpeg"),
+    ("jpeg", "image/jpeg"),
+    ("gif", "image/gif"),
+    ("png", "image/png"),
+    ("bmp", "image/bmp"),
+    ("tif", "image/tiff"),
+    ("tiff", "image/tiff"),
+    ("pdf", "application/pdf"),
+    ("wav", "audio/wav"),
+    ("mp3", "audio/mpeg"),
+    ("mp4", "video/mp4"),
+    ("txt", "text/plain"),
+    ("htm", "text/html"),
+    ("html", "text/html"),
+];
+
+/// Version of the GEDCOM standard a file follows
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub enum GedVersion {
+    V551,
+    V7,
+}
+
+impl Default for GedVersion {
+    fn default() -> Self {
+        GedVersion::V551
+    }
+}
+
+impl GedVersion {
+    /// Version declared in the header (`HEAD.GEDC.VERS`) of
+    /// the level 0 structures, 5.5.1 when missing
+    pub fn of(tags: &[Tag]) -> Self {
+        let version = tags.iter()
+            .find(|t| t.name == "HEAD")
+            .and_then(|h| h.child("GEDC"))
+            .and_then(|g| g.value("VERS"));
+        match version {
+            Some(v) if v.trim().starts_with('7') => GedVersion::V7,
+            _ => GedVersion::V551,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            GedVersion::V551 => "5.5.1",
+            GedVersion::V7 => "7.0",
+        }
+    }
+}
+
+/// URI documenting an extension tag written by nPAF
+fn extension_uri(tag: &str) -> Option<String> {
+    let bare = tag.trim_start_matches('_');
+    if REMOVED_TAGS.contains(&bare) {
+        Some(format!("{}{}", V551_TERMS, bare))
+    } else if EXTENSION_TAGS.contains(&tag) {
+        Some(format!("{}{}", NPAF_TERMS, bare))
+    } else {
+        None
+    }
+}
+
+/// 5.5.1 tag of an extension documented by the URI
+fn known_extension(uri: &str) -> Option<String> {
+    let uri = uri.trim();
+    if let Some(tag) = uri.strip_prefix(V551_TERMS) {
+        Some(tag.to_owned())
+    } else {
+        uri.strip_prefix(NPAF_TERMS).map(|tag| format!("_{}", tag))
+    }
+}
+
+/// Extension schema (`HEAD.SCHMA`) of the 7.0 structures,
+/// documenting the extension tags they use
+pub fn schema(tags: &[Tag]) -> Option<Tag> {
+    fn collect(tags: &[Tag], names: &mut BTreeSet<String>) {
+        for tag in tags {
+            if tag.name.starts_with('_') {
+                names.insert(tag.name.clone());
+            }
+            collect(&tag.nested, names);
+        }
+    }
+    let mut names = BTreeSet::new();
+    collect(tags, &mut names);
+    let entries: Vec<Tag> = names.iter()
+        .filter_map(|name| extension_uri(name).map(|uri| Tag::new("TAG", Some(&format!("{} {}", name, uri)))))
+        .collect();
+    match entries.is_empty() {
+        true => None,
+        false => Some(Tag { nested: entries, ..Tag::new("SCHMA", None) }),
+    }
+}
+
+/// Replaces the calendar escapes and era of a 5.5.1 date by
+/// the 7.0 keywords, or the other way round. The default
+/// calendar is left implicit.
+fn convert_calendars(date: &str, to_v7: bool) -> String {
+    let mut date = date.to_owned();
+    if to_v7 {
+        for (escape, keyword) in CALENDARS.iter() {
+            date = date.replace(escape, keyword);
+        }
+    }
+    date.split_whitespace()
+        .filter(|w| *w != "GREGORIAN")
+        .map(|w| match (to_v7, w) {
+            (true, "B.C.") => "BCE",
+            (false, "BCE") => "B.C.",
+            (true, w) => w,
+            (false, w) => CALENDARS.iter().find(|(_, k)| *k == w).map_or(w, |(escape, _)| *escape),
+        })
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+/// 7.0 value of a 5.5.1 date, with the phrase (`PHRASE`) it
+/// comes with. Date phrases, dual years and the dates that
+/// can't be understood become phrases.
+pub fn date_v7(value: &str) -> (String, Option<String>) {
+    let value = value.trim();
+    match GedDate::parse(value) {
+        None => return (String::new(), Some(value.to_owned())),
+        Some(date) if date.start.is_none() => return (String::new(), date.phrase),
+        Some(_) => (),
+    }
+    let (date, phrase) = match value.get(..4).map(|p| p.eq_ignore_ascii_case("INT ")) {
+        Some(true) => {
+            let rest = &value[4..];
+            match rest.find('(') {
+                Some(pos) => (rest[..pos].trim(), Some(rest[pos..].trim_matches(&['(', ')', ' '][..]).to_owned())),
+                None => (rest.trim(), None),
+            }
+        },
+        _ => (value, None),
+    };
+    // Dual years (1699/00) keep their first year, the whole
+    // date going to the phrase
+    let dual = date.split(' ').any(|w| w.contains('/') && w.chars().all(|c| c.is_ascii_digit() || c == '/'));
+    let phrase = match dual {
+        true => phrase.or_else(|| Some(convert_calendars(&date.to_uppercase(), true))),
+        false => phrase,
+    };
+    let date: Vec<&str> = date.split(' ')
+        .map(|w| match w.contains('/') && !w.starts_with('@') {
+            true => w.splitn(2, '/').next().unwrap_or(w),
+            false => w,
+        })
+        .collect();
+    (convert_calendars(&date.join(" ").to_uppercase(), true), phrase)
+}
+
+/// 5.5.1 value of a 7.0 date and its phrase. A phrase alone
+/// becomes a date phrase, with a single date an interpreted one.
+pub fn date_v551(value: &str, phrase: Option<&str>) -> String {
+    let date = convert_calendars(value.trim(), false);
+    let single = GedDate::parse(&date).map_or(false, |d| d.modifier == DateModifier::Exact);
+    match (date.is_empty(), phrase) {
+        (true, Some(phrase)) => format!("({})", phrase),
+        (false, Some(phrase)) if single => format!("INT {} ({})", date, phrase),
+        _ => date,
+    }
+}
+
+/// 7.0 value of a 5.5.1 age, with its phrase for the keywords
+pub fn age_v7(value: &str) -> (String, Option<String>) {
+    let value = value.trim();
+    if let Some((keyword, age)) = AGE_KEYWORDS.iter().find(|(k, _)| value.eq_ignore_ascii_case(k)) {
+        let mut phrase = keyword.to_lowercase();
+        phrase[..1].make_ascii_uppercase();
+        return (age.to_string(), Some(phrase));
+    }
+    match value.chars().next() {
+        Some(c) if c == '<' || c == '>' => (format!("{} {}", c, value[1..].trim()), None),
+        _ => (value.to_owned(), None),
+    }
+}
+
+/// 5.5.1 value of a 7.0 age and its phrase, the weeks
+/// being counted in days
+pub fn age_v551(value: &str, phrase: Option<&str>) -> String {
+    let keyword = phrase.and_then(|p| AGE_KEYWORDS.iter().find(|(k, _)| p.trim().eq_ignore_ascii_case(k)));
+    if let Some((keyword, _)) = keyword {
+        return keyword.to_string();
+    }
+    let value = value.trim();
+    let (bound, value) = match value.chars().next() {
+        Some(c) if c == '<' || c == '>' => (value[..1].to_owned(), value[1..].trim()),
+        _ => (String::new(), value),
+    };
+    let mut parts: Vec<String> = vec![];
+    let mut days = 0;
+    for part in value.split_whitespace() {
+        match part.strip_suffix('w').and_then(|w| w.parse::<u32>().ok()) {
+            Some(weeks) => days += weeks * 7,
+            None => match part.strip_suffix('d').and_then(|d| d.parse::<u32>().ok()) {
+                Some(d) => days += d,
+                None => parts.push(part.to_owned()),
+            },
+        }
+    }
+    if days > 0 {
+        parts.push(format!("{}d", days));
+    }
+    format!("{}{}", bound, parts.join(" "))
+}
+
+/// Media type of a file extension (`image/jpeg` for `jpg`)
+pub(crate) fn media_type(extension: &str) -> Option<&'static str> {
+    let extension = extension.trim().to_lowercase();
+    MEDIA_TYPES.iter()
+        .find(|(ext, _)| *ext == extension)
+        .map(|(_, mime)| *mime)
+}
+
+/// Reads a `FILE` value as a local path when it's a `file:` URI
+pub(crate) fn file_path(value: &str) -> String {
+    match value.trim().strip_prefix("file://") {
+        Some(path) => {
+            let path = path.replace("%20", " ");
+            // file:///C:/dir/file.jpg
+            match path.len() > 3 && path.as_bytes()[2] == b':' {
+                true => path[1..].to_owned(),
+                false => path,
+            }
+        },
+        // Relative references are percent-encoded too
+        None if !value.contains(':') => value.trim().replace("%20", " "),
+        None => value.trim().to_owned(),
+    }
+}
+
+/// Writes an absolute local path as a `file:` URI, the
+/// relative ones being valid URI references as they are
+fn file_uri(value: &str) -> String {
+    let value = value.trim();
+    let windows = value.len() > 2 && value.as_bytes()[1] == b':';
+    if value.starts_with('/') {
+        format!("file://{}", value.replace(' ', "%20"))
+    } else if windows {
+        format!("file:///{}", value.replace('\\', "/").replace(' ', "%20"))
+    } else if !value.contains(':') {
+        value.replace(' ', "%20")
+    } else {
+        value.to_owned()
+    }
+}
+
+/// Takes the value of the `PHRASE` nested in the tag out of it
+fn take_phrase(tag: &mut Tag) -> Option<String> {
+    let position = tag.nested.iter().position(|t| t.name == "PHRASE")?;
+    tag.nested.remove(position).content
+}
+
+/// Folds the `CONC` lines into the line they continue, as
+/// 7.0 only knows of `CONT`
+fn fold_conc(tag: &mut Tag) {
+    if tag.nested.iter().all(|t| t.name != "CONC") {
+        return;
+    }
+    let mut nested: Vec<Tag> = vec![];
+    for child in tag.nested.drain(..) {
+        if child.name != "CONC" {
+            nested.push(child);
+            continue;
+        }
+        let text = child.content.unwrap_or_default();
+        let last = nested.iter_mut().rev().find(|t| t.name == "CONT");
+        let line = match last {
+            Some(cont) => &mut cont.content,
+            None => &mut tag.content,
+        };
+        line.get_or_insert_with(String::new).push_str(&text);
+    }
+    tag.nested = nested;
+}
+
+/// Converts a 7.0 structure nested in the `parent` tag
+fn down(mut tag: Tag, parent: &str, renames: &HashMap<String, String>) -> Option<Tag> {
+    if tag.content.as_deref().map(str::trim) == Some(VOID) {
+        return None;
+    }
+    if let Some(name) = renames.get(&tag.name) {
+        tag.name = name.clone();
+    } else if let Some((old, _)) = RENAMED_TAGS.iter().find(|(_, new)| *new == tag.name) {
+        tag.name = old.to_string();
+    }
+    match tag.name.as_str() {
+        "SNOTE" => tag.name = String::from("NOTE"),
+        "UID" => {
+            tag.name = String::from("_UID");
+            if let Some(uid) = tag.content.as_deref().and_then(ident::uuid_to_uid) {
+                tag.content = Some(uid);
+            }
+        },
+        "DATE" => {
+            let phrase = take_phrase(&mut tag);
+            let value = date_v551(tag.content.as_deref().unwrap_or(""), phrase.as_deref());
+            tag.content = Some(value).filter(|v| !v.is_empty());
+        },
+        "AGE" => {
+            let phrase = take_phrase(&mut tag);
+            tag.content = Some(age_v551(tag.content.as_deref().unwrap_or(""), phrase.as_deref()));
+        },
+        "SEX" if tag.content.as_deref() == Some("X") => tag.content = Some(String::from("U")),
+        "STAT" => if let Some((old, _)) = STATUSES.iter().find(|(_, new)| tag.content.as_deref() == Some(new)) {
+            tag.content = Some(old.to_string());
+        },
+        "PEDI" | "RESN" => tag.content = tag.content.map(|c| c.to_lowercase()),
+        "FORM" if parent == "FILE" || parent == "OBJE" => {
+            let media = MEDIA_TYPES.iter().find(|(_, mime)| tag.content.as_deref().map(str::trim) == Some(mime));
+            if let Some((extension, _)) = media {
+                tag.content = Some(extension.to_string());
+            }
+        },
+        "FILE" => tag.content = tag.content.map(|c| file_path(&c)),
+        "MIME" | "LANG" if parent == "NOTE" || parent == "SNOTE" => return None,
+        _ => (),
+    }
+    let name = tag.name.clone();
+    tag.nested = tag.nested.into_iter().filter_map(|t| down(t, &name, renames)).collect();
+    Some(tag)
+}
+
+/// Converts the level 0 structures of a 7.0 file into 5.5.1
+/// ones. Shared notes become note records, the extension tags
+/// documented by the schema get their 5.5.1 names and the
+/// pointers to nothing (`@VOID@`) are dropped.
+pub fn to_v551(tags: Vec<Tag>) -> Vec<Tag> {
+    let renames: HashMap<String, String> = tags.iter()
+        .filter(|t| t.name == "HEAD")
+        .filter_map(|h| h.child("SCHMA"))
+        .flat_map(|s| s.children("TAG"))
+        .filter_map(|t| {
+            let mut words = t.content.as_deref()?.split_whitespace();
+            let (tag, uri) = (words.next()?, words.next()?);
+            Some((tag.to_owned(), known_extension(uri)?))
+        })
+        .collect();
+    tags.into_iter()
+        .filter_map(|tag| match tag.name.as_str() {
+            "HEAD" | "TRLR" => Some(tag),
+            _ => down(tag, "", &renames),
+        })
+        .collect()
+}
+
+/// Converts a 5.5.1 structure nested in the `parent` tag
+fn up(mut tag: Tag, parent: &str, xrefs: &HashSet<&str>) -> Tag {
+    fold_conc(&mut tag);
+    if tag.pointer().map_or(false, |xref| !xrefs.contains(xref)) {
+        tag.content = Some(VOID.to_owned());
+    }
+    if let Some((_, new)) = RENAMED_TAGS.iter().find(|(old, _)| *old == tag.name) {
+        tag.name = new.to_string();
+    } else if REMOVED_TAGS.contains(&tag.name.as_str()) {
+        tag.name = format!("_{}", tag.name);
+    }
+    let mut phrase = None;
+    match tag.name.as_str() {
+        "NOTE" if tag.pointer().is_some() || tag.content.as_deref() == Some(VOID) => tag.name = String::from("SNOTE"),
+        "_UID" => if let Some(uuid) = tag.content.as_deref().and_then(ident::uid_to_uuid) {
+            tag.name = String::from("UID");
+            tag.content = Some(uuid);
+        },
+        "DATE" => {
+            let (value, date_phrase) = date_v7(tag.content.as_deref().unwrap_or(""));
+            tag.content = Some(value).filter(|v| !v.is_empty());
+            phrase = date_phrase;
+        },
+        "AGE" => {
+            let (value, age_phrase) = age_v7(tag.content.as_deref().unwrap_or(""));
+            tag.content = Some(value);
+            phrase = age_phrase;
+        },
+        "STAT" => if let Some((_, new)) = STATUSES.iter().find(|(old, _)| tag.content.as_deref() == Some(old)) {
+            tag.content = Some(new.to_string());
+        },
+        "PEDI" | "RESN" => tag.content = tag.content.map(|c| c.to_uppercase()),
+        "FORM" if parent == "FILE" || parent == "OBJE" => {
+            if let Some(mime) = tag.content.as_deref().and_then(media_type) {
+                tag.content = Some(mime.to_owned());
+            }
+        },
+        "FILE" => tag.content = tag.content.map(|c| file_uri(&c)),
+        _ => (),
+    }
+    let name = tag.name.clone();
+    tag.nested = tag.nested.into_iter().map(|t| up(t, &name, xrefs)).collect();
+    if let Some(phrase) = phrase {
+        tag.nested.insert(0, Tag::new("PHRASE", Some(&phrase)));
+    }
+    tag
+}
+
+/// Converts 5.5.1 records into 7.0 ones: note records become
+/// shared notes (`SNOTE`), the pointers to records missing from
+/// `tags` become `@VOID@` and the removed tags extensions. The
+/// header isn't part of the records, see [`schema`].
+pub fn to_v7(tags: Vec<Tag>) -> Vec<Tag> {
+    let owned: Vec<String> = tags.iter().filter_map(|t| t.xref.clone()).collect();
+    let xrefs: HashSet<&str> = owned.iter().map(|x| x.as_str()).collect();
+    tags.into_iter()
+        .map(|tag| {
+            let mut tag = up(tag, "", &xrefs);
+            if tag.name == "NOTE" {
+                tag.name = String::from("SNOTE");
+            }
+            tag
+        })
+        .collect()
+}
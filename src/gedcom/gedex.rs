@@ -1,4 +1,4 @@
-use crate::gedcom::{GedLine,Record,ParseError};
+use crate::gedcom::{GedLine,ParseError};
 use std::convert::{TryInto, TryFrom};
 use std::io::{BufReader, BufRead};
 
@@ -8,15 +8,38 @@ use regex::Regex;
 type Predicate = dyn Fn(&GedLine) -> bool;
 type TagStack = Vec<Tag>;
 
-#[derive(Default,Clone,Debug)]
-struct Tag {
-    name: String,
-    content: Option<String>,
-    nested: Vec<Tag>
+/// Raw GED structure: a tag with its optional cross-reference
+/// identifier (only for level 0 records), its line content
+/// and all the nested lines of the upper level.
+#[derive(Default,Clone,Debug,PartialEq)]
+pub struct Tag {
+    pub xref: Option<String>,
+    pub name: String,
+    pub content: Option<String>,
+    pub nested: Vec<Tag>
+}
+
+/// Cross-reference of a pointer (`I12` for `@I12@`), the
+/// `@#` escapes not being pointers
+pub fn pointer_xref(content: &str) -> Option<&str> {
+    let content = content.trim();
+    match content.len() > 2 && content.starts_with('@') && content.ends_with('@') && !content.starts_with("@#") {
+        true => Some(&content[1..content.len() - 1]),
+        false => None,
+    }
+}
+
+/// Keeps the tags for which `keep` holds, at every level:
+/// the tags nested in a dropped one go with it
+pub fn retain_tags<F: Fn(&Tag) -> bool>(tags: &mut Vec<Tag>, keep: &F) {
+    tags.retain(|t| keep(t));
+    for tag in tags.iter_mut() {
+        retain_tags(&mut tag.nested, keep);
+    }
 }
 
 impl Tag {
-    fn new(name: &str, content: Option<&str>) -> Self {
+    pub fn new(name: &str, content: Option<&str>) -> Self {
         Tag {
             name: String::from(name),
             content: content.map(|x| String::from(x)),
@@ -24,12 +47,90 @@ impl Tag {
         }
     }
 
-    fn nest(self, child: Tag) -> Self {
+    pub fn nest(self, child: Tag) -> Self {
         Self {
             nested: [&self.nested[..], &[child]].concat(),
             ..self
         }
     }
+
+    /// Returns the first nested tag with the given name
+    pub fn child(&self, name: &str) -> Option<&Tag> {
+        self.nested.iter().find(|t| t.name == name)
+    }
+
+    /// Returns all the nested tags with the given name
+    pub fn children<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Tag> + 'a {
+        self.nested.iter().filter(move |t| t.name == name)
+    }
+
+    /// Shortcut for the full text of the first nested
+    /// tag with the given name
+    pub fn value(&self, name: &str) -> Option<String> {
+        self.child(name).and_then(|t| t.text())
+    }
+
+    /// Cross-reference the tag points to (`I12` for `@I12@`)
+    pub fn pointer(&self) -> Option<&str> {
+        self.content.as_deref().and_then(pointer_xref)
+    }
+
+    /// Returns the tag content with all the `CONT` and
+    /// `CONC` continuation lines applied to it
+    pub fn text(&self) -> Option<String> {
+        let mut text = self.content.clone().unwrap_or_default();
+        let mut continued = false;
+        for tag in &self.nested {
+            match tag.name.as_str() {
+                "CONT" => text.push('\n'),
+                "CONC" => (),
+                _ => continue
+            }
+            continued = true;
+            text.push_str(tag.content.as_deref().unwrap_or(""));
+        }
+        match (&self.content, continued) {
+            (None, false) => None,
+            _ => Some(text)
+        }
+    }
+
+    /// Builds a tag from a possibly multi-line text,
+    /// splitting it into `CONT` lines.
+    pub fn with_text(name: &str, text: &str) -> Self {
+        let mut lines = text.split('\n');
+        let first = lines.next().filter(|l| !l.is_empty());
+        lines.fold(Tag::new(name, first), |tag, line| {
+            tag.nest(Tag::new("CONT", Some(line).filter(|l| !l.is_empty())))
+        })
+    }
+
+    /// Replaces every pointer to the `from` cross-reference
+    /// by a pointer to `to`, in this tag and all the nested ones
+    pub fn replace_pointer(&mut self, from: &str, to: &str) {
+        let from = format!("@{}@", from.trim_matches('@'));
+        if self.content.as_deref() == Some(from.as_str()) {
+            self.content = Some(format!("@{}@", to.trim_matches('@')));
+        }
+        for tag in self.nested.iter_mut() {
+            tag.replace_pointer(&from, to);
+        }
+    }
+
+    /// Serializes the tag and all its nested tags into
+    /// GED lines starting from the given level
+    pub fn to_lines(&self, level: u16) -> Vec<String> {
+        let mut line = level.to_string();
+        if let Some(xref) = &self.xref {
+            line = format!("{} @{}@", line, xref);
+        }
+        line = format!("{} {}", line, self.name);
+        if let Some(content) = &self.content {
+            line = format!("{} {}", line, content);
+        }
+        let nested = self.nested.iter().flat_map(|t| t.to_lines(level + 1));
+        std::iter::once(line).chain(nested).collect()
+    }
 }
 
 #[derive(Debug)]
@@ -75,21 +176,20 @@ impl State {
         }
     }
 
-    fn concat(s1: &str, s2: &str) -> String {
-        [&s1[..], &s2[..]].concat()
-    }
-
     fn advance_ref(self, data: GedLine) -> Self {
         let sequence: Vec<Tag> = match self {
             State::Reference {sequence: seq, ..} => seq,
             _ => panic!("Unexpected state.")
         };
         match data {
-            GedLine::Ref(0, rtype, rid, content) => {
+            GedLine::Ref(0, xref, content) => {
+                let content = content.unwrap_or_default();
+                let mut words = content.splitn(2, ' ');
                 let stack: TagStack = vec!(
                     Tag {
-                        name: Self::concat(&rtype, &rid.to_string()),
-                        content: content,
+                        xref: Some(xref),
+                        name: words.next().unwrap_or_default().to_owned(),
+                        content: words.next().map(|s| s.to_owned()),
                         ..Default::default()
                     }
                 );
@@ -108,6 +208,13 @@ impl State {
             GedLine::Ref(..) => Self::advance_ref(State::Reference {
                 sequence: [&sequence[..], &[fold_stack(stack).unwrap()]].concat()
             }, data),
+            GedLine::Data(0, tag, content) => {
+                let ntag = Tag::new(&tag, content.as_deref());
+                Self::RecordTag {
+                    sequence: [&sequence[..], &[fold_stack(stack).unwrap()]].concat(),
+                    stack: vec!(ntag)
+                }
+            },
             GedLine::Data(level, tag, content) => {
                 let ntag = Tag {
                     name: tag,
@@ -158,21 +265,20 @@ impl State {
         }
     }
 
-    pub fn fold(self) -> Result<Vec<Record>, ParseError> {
+    pub fn fold(self) -> Result<Vec<Tag>, ParseError> {
         match self {
-            Self::RecordTag {sequence: mut seq, stack: stack} => {
+            Self::RecordTag {sequence: mut seq, stack} => {
                 seq.push(
                     fold_stack(stack).unwrap()
                 );
-                println!("SEQUENCE: {:#?}", seq);
+                Ok(seq)
             },
-            _ => ()
-        };
-        Err(
-            ParseError::Runtime(
-                String::from("Not implemented.")
+            _ => Err(
+                ParseError::Runtime(
+                    String::from("Malformed GED data.")
+                )
             )
-        )
+        }
     }
 }
 
@@ -195,14 +301,34 @@ impl GedEx {
         }
     }
 
-    pub fn parse(self) -> Result<Vec<Record>, ParseError> {
-        let records = self.contents.into_iter()
-            .filter_map(|line| Self::parse_line(&line))
-            .fold(State::Initial, |state, line| {
-                state.next(line)
-            })
-            .fold()?;
-        Ok(records)
+    pub fn parse(self) -> Result<Vec<Tag>, ParseError> {
+        self.parse_with(|_, _, _| true)
+    }
+
+    /// Same as [`parse`](GedEx::parse), `on_line` being called
+    /// with the number of every line, the line itself and whether
+    /// it could be interpreted. Parsing stops with
+    /// [`ParseError::Cancelled`] as soon as it returns `false`,
+    /// and at the first line that breaks the structure of the
+    /// file, the error telling its number and text.
+    pub fn parse_with<F: FnMut(usize, &str, bool) -> bool>(self, mut on_line: F) -> Result<Vec<Tag>, ParseError> {
+        let (r_data, r_ref) = (Self::regex_line(), Self::regex_ref());
+        let mut state = State::Initial;
+        for (number, line) in self.contents.iter().enumerate() {
+            let parsed = Self::match_line(line, &r_data, &r_ref);
+            if !on_line(number + 1, line, parsed.is_some()) {
+                return Err(ParseError::Cancelled);
+            }
+            if let Some(parsed) = parsed {
+                state = state.next(parsed);
+                if !state.can_advance() {
+                    return Err(ParseError::Runtime(
+                        format!("Malformed GED data at line {}: '{}'.", number + 1, line.trim_end())
+                    ));
+                }
+            }
+        }
+        state.fold()
     }
 
     /// Method allowing to count all the lines that can't
@@ -221,9 +347,11 @@ impl GedEx {
     /// Private subroutine that takes a raw string and
     /// parses it into an interpreted line with data
     fn parse_line(line: &str) -> Option<GedLine> {
-        let r_data = Self::regex_line();
-        let r_ref = Self::regex_ref();
+        Self::match_line(line, &Self::regex_line(), &Self::regex_ref())
+    }
 
+    /// Same as `parse_line` with already compiled expressions
+    fn match_line(line: &str, r_data: &Regex, r_ref: &Regex) -> Option<GedLine> {
         if let Some(caps) = r_data.captures(&line) {
             Some(GedLine::Data(
                 caps.name("Level").unwrap().as_str().parse().unwrap(),
@@ -233,10 +361,8 @@ impl GedEx {
         } else if let Some(caps) = r_ref.captures(&line) {
             Some(GedLine::Ref(
                 caps.name("Level").unwrap().as_str().parse().unwrap(),
-                caps.name("Type").unwrap().as_str().to_owned(),
-                caps.name("Number").unwrap().as_str().parse().unwrap(),
+                caps.name("Xref").unwrap().as_str().to_owned(),
                 caps.name("Content").map(|s| s.as_str().to_owned())
-                // if let Some(content) = caps.name("Content") { Some(content.as_str().to_owned()) } else { None }
             ))
         } else {
             None
@@ -247,9 +373,9 @@ impl GedEx {
     fn regex_line() -> Regex {
         Regex::new(r"(?x) # Insignificant whitespace mode
                 ^
-                (?P<Level>[0-9]{1,2})\ *       # Line level
-                (?P<Tag>_?[A-Z]{3,5})\ *       # Record tag
-                (?P<Content>[^\r\n]+)*         # Either end of line or content
+                (?P<Level>[0-9]{1,2})\ *          # Line level
+                (?P<Tag>_?[A-Z][A-Z0-9_]*)          # Record tag, of any length in GEDCOM 7
+                (?:\ (?P<Content>[^\r\n]+))?\ ?   # Either end of line or content
                 $
             ").unwrap()
     }
@@ -259,7 +385,7 @@ impl GedEx {
         Regex::new(r"(?x)
                 ^
                 (?P<Level>[0-9]{1,2})\ *               # Line level
-                @(?P<Type>[A-Z]+)(?P<Number>\d+)@\ *   # Record tag
+                @(?P<Xref>[A-Za-z0-9_][^@]*)@\ *       # Cross-reference, kept as written
                 (?P<Content>[^\r\n]+)?                 # Either end of line or content
                 $
             ").unwrap()
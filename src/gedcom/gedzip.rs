@@ -0,0 +1,150 @@
+//! GEDZIP packages: a GEDCOM 7 file, `gedcom.ged`, zipped along
+//! with the media files it points to by relative paths.
+
+use crate::gedcom::gedcom7::{self, GedVersion};
+use crate::gedcom::{GedWriter, ParseError, Record, RecordRegistry, Tag};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Seek, Write};
+use std::path::{Path, PathBuf};
+
+/// Entry of the package holding the GEDCOM file
+pub const GEDCOM_ENTRY: &str = "gedcom.ged";
+
+/// Folder of the package the media files are written to
+const MEDIA_FOLDER: &str = "media";
+
+/// Extensions of the files read as packages
+const PACKAGE_EXTENSIONS: [&str; 2] = ["gdz", "zip"];
+
+/// Whether the file is to be read as a package
+pub fn is_package(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map_or(false, |e| PACKAGE_EXTENSIONS.contains(&e.to_lowercase().as_str()))
+}
+
+/// Folder the media files of a package are extracted to,
+/// next to it (`family_media` for `family.gdz`)
+pub fn media_dir(path: &Path) -> PathBuf {
+    let stem = path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+    path.with_file_name(format!("{}_media", stem))
+}
+
+/// Calls `f` on the tags and all the nested ones
+fn for_each_tag<F: FnMut(&mut Tag)>(tags: &mut [Tag], f: &mut F) {
+    for tag in tags.iter_mut() {
+        f(tag);
+        for_each_tag(&mut tag.nested, f);
+    }
+}
+
+/// Calls `f` on every `FILE` tag of the record
+fn for_each_file<F: FnMut(&mut Tag)>(record: &mut Record, f: &mut F) {
+    let mut on_file = |tag: &mut Tag| if tag.name == "FILE" {
+        f(tag);
+    };
+    for_each_tag(&mut record.extra, &mut on_file);
+    for event in record.events.iter_mut() {
+        for_each_tag(&mut event.extra, &mut on_file);
+    }
+}
+
+/// Reads the lines of the GEDCOM file of a package, extracting
+/// its other files to the [media folder](media_dir)
+pub fn read(path: &Path) -> Result<Vec<String>, ParseError> {
+    let mut archive = zip::ZipArchive::new(File::open(path)?)?;
+    let dir = media_dir(path);
+    for index in 0..archive.len() {
+        let mut entry = archive.by_index(index)?;
+        // Entries escaping the folder are never written
+        let name = match entry.enclosed_name() {
+            Some(name) if name != Path::new(GEDCOM_ENTRY) && !entry.is_dir() => name.to_owned(),
+            _ => continue,
+        };
+        let target = dir.join(name);
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::io::copy(&mut entry, &mut File::create(target)?)?;
+    }
+    let gedcom = archive.by_name(GEDCOM_ENTRY)?;
+    let mut lines = vec![];
+    for line in BufReader::new(gedcom).lines() {
+        lines.push(line?);
+    }
+    // A leading byte order mark isn't part of the header
+    if let Some(first) = lines.first_mut() {
+        *first = first.trim_start_matches('\u{feff}').to_owned();
+    }
+    Ok(lines)
+}
+
+/// Points the relative `FILE` paths of the records to the
+/// media extracted to `dir`
+pub fn relocate_media(records: &mut [Record], dir: &Path) {
+    for record in records.iter_mut() {
+        for_each_file(record, &mut |tag| {
+            let relative = tag.content.as_deref()
+                .map(|c| c.trim().to_owned())
+                .filter(|c| !c.contains(':') && !Path::new(c).is_absolute());
+            if let Some(relative) = relative {
+                tag.content = Some(dir.join(relative).to_string_lossy().into_owned());
+            }
+        });
+    }
+}
+
+/// Writes the exported records as a package. The local media
+/// files they point to go into it, the `FILE` paths becoming
+/// relative to the package; the other ones are left as they are.
+pub fn write<W: Write + Seek>(writer: &GedWriter, registry: &RecordRegistry, out: W) -> std::io::Result<()> {
+    let writer = GedWriter { version: GedVersion::V7, ..writer.clone() };
+    let mut tags = writer.tags(registry);
+    let mut media: HashMap<String, PathBuf> = HashMap::new();
+    let mut entries: HashMap<PathBuf, String> = HashMap::new();
+    for_each_tag(&mut tags, &mut |tag| {
+        if tag.name != "FILE" {
+            return;
+        }
+        let path = match tag.content.as_deref().map(|c| c.trim()) {
+            Some(c) => PathBuf::from(gedcom7::file_path(c)),
+            None => return,
+        };
+        if !path.is_file() {
+            return;
+        }
+        let entry = entries.entry(path.clone()).or_insert_with(|| {
+            let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+            let mut entry = format!("{}/{}", MEDIA_FOLDER, name);
+            let mut copy = 1;
+            while media.contains_key(&entry) {
+                copy += 1;
+                entry = format!("{}/{}_{}", MEDIA_FOLDER, copy, name);
+            }
+            media.insert(entry.clone(), path.clone());
+            entry
+        });
+        tag.content = Some(entry.replace(' ', "%20"));
+    });
+
+    let mut zip = zip::ZipWriter::new(out);
+    let options = zip::write::FileOptions::default();
+    zip.start_file(GEDCOM_ENTRY, options)?;
+    for tag in &tags {
+        for line in tag.to_lines(0) {
+            writeln!(zip, "{}", line)?;
+        }
+    }
+    writeln!(zip, "0 TRLR")?;
+    let mut media: Vec<(String, PathBuf)> = media.into_iter().collect();
+    media.sort();
+    for (entry, path) in media {
+        let mut content = vec![];
+        File::open(&path)?.read_to_end(&mut content)?;
+        zip.start_file(entry, options)?;
+        zip.write_all(&content)?;
+    }
+    zip.finish()?;
+    Ok(())
+}
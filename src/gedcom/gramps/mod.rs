@@ -0,0 +1,116 @@
+//! Gramps XML files (`.gramps`, gzipped XML). People, families,
+//! events, places, citations, sources, repositories, media and
+//! notes are turned into GED structures when read, so that they
+//! end up in the usual [records](crate::gedcom::Record), and
+//! written back from the records of a registry.
+
+mod read;
+mod write;
+pub mod xml;
+
+use crate::gedcom::{GedParser, ParseError, ParseResult, Parser, Tag};
+use std::io::Read;
+use std::path::Path;
+pub use write::write;
+use xml::Element;
+
+/// Extension of the Gramps files
+pub const EXTENSION: &str = "gramps";
+
+/// Version of the Gramps XML grammar written
+const XML_VERSION: &str = "1.7.1";
+
+/// Starting bytes of a gzipped file
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// GED tags of the events and the Gramps event types
+/// they stand for, the others being custom types of `EVEN`
+const EVENT_TYPES: [(&str, &str); 39] = [
+    ("BIRT", "Birth"),
+    ("CHR", "Christening"),
+    ("DEAT", "Death"),
+    ("BURI", "Burial"),
+    ("CREM", "Cremation"),
+    ("ADOP", "Adopted"),
+    ("BAPM", "Baptism"),
+    ("BARM", "Bar Mitzvah"),
+    ("BASM", "Bas Mitzvah"),
+    ("BLES", "Blessing"),
+    ("CHRA", "Adult Christening"),
+    ("CONF", "Confirmation"),
+    ("FCOM", "First Communion"),
+    ("ORDN", "Ordination"),
+    ("NATU", "Naturalization"),
+    ("EMIG", "Emigration"),
+    ("IMMI", "Immigration"),
+    ("CENS", "Census"),
+    ("PROB", "Probate"),
+    ("WILL", "Will"),
+    ("GRAD", "Graduation"),
+    ("RETI", "Retirement"),
+    ("RESI", "Residence"),
+    ("OCCU", "Occupation"),
+    ("EDUC", "Education"),
+    ("RELI", "Religion"),
+    ("TITL", "Nobility Title"),
+    ("PROP", "Property"),
+    ("ANUL", "Annulment"),
+    ("DIV", "Divorce"),
+    ("DIVF", "Divorce Filing"),
+    ("ENGA", "Engagement"),
+    ("MARB", "Marriage Banns"),
+    ("MARC", "Marriage Contract"),
+    ("MARR", "Marriage"),
+    ("MARL", "Marriage License"),
+    ("MARS", "Marriage Settlement"),
+    ("NATI", "Nationality"),
+    ("DSCR", "Description"),
+];
+
+/// GED tags of the attributes and the Gramps attribute types
+/// they stand for, the others being custom types of `FACT`
+const ATTRIBUTE_TYPES: [(&str, &str); 5] = [
+    ("CAST", "Caste"),
+    ("NCHI", "Number of Children"),
+    ("IDNO", "Identification Number"),
+    ("SSN", "Social Security Number"),
+    ("NICK", "Nickname"),
+];
+
+/// Whether the file is to be read as a Gramps file
+pub fn is_gramps(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map_or(false, |e| e.eq_ignore_ascii_case(EXTENSION))
+}
+
+/// Reads a Gramps file, gzipped or not, into level 0 GED
+/// structures, as [`GedEx`](crate::gedcom::gedex::GedEx) does
+pub fn read_tags<R: Read>(mut input: R) -> Result<Vec<Tag>, ParseError> {
+    let mut bytes = vec![];
+    input.read_to_end(&mut bytes)?;
+    if bytes.starts_with(&GZIP_MAGIC) {
+        let mut unzipped = vec![];
+        flate2::read::GzDecoder::new(&bytes[..]).read_to_end(&mut unzipped)?;
+        bytes = unzipped;
+    }
+    let root = Element::parse(&String::from_utf8_lossy(&bytes))?;
+    if root.name != "database" {
+        return Err(ParseError::Runtime(format!("Not a Gramps database: <{}> root element.", root.name)));
+    }
+    Ok(read::tags(&root))
+}
+
+/// Parser of Gramps files, the counterpart of [`GedParser`]
+#[derive(Default)]
+pub struct GrampsParser {
+
+}
+
+impl Parser for GrampsParser {
+    type FileType = std::fs::File;
+
+    fn parse(&mut self, file: &Self::FileType) -> ParseResult {
+        Ok(GedParser::build_registry(&read_tags(file)?))
+    }
+}
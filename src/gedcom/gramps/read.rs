@@ -0,0 +1,493 @@
+//! Gramps elements turned into level 0 GED structures. Records get
+//! their Gramps ID as cross-reference when it's a valid one; events,
+//! places and citations, which have no record of their own in GED,
+//! are copied into the facts and records referring to them.
+
+use super::xml::Element;
+use super::{ATTRIBUTE_TYPES, EVENT_TYPES};
+use crate::gedcom::place::coordinate;
+use crate::gedcom::{Address, ContactInfo, SimpleDate, Tag};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+/// Sections of the database holding records, with the name of
+/// their elements, the GED record type and the prefix of the
+/// cross-references made up for them
+const RECORD_SECTIONS: [(&str, &str, &str, &str); 6] = [
+    ("people", "person", "INDI", "I"),
+    ("families", "family", "FAM", "F"),
+    ("sources", "source", "SOUR", "S"),
+    ("repositories", "repository", "REPO", "R"),
+    ("objects", "object", "OBJE", "O"),
+    ("notes", "note", "NOTE", "N"),
+];
+
+/// Sections of the objects copied into the records
+const OBJECT_SECTIONS: [(&str, &str); 3] = [("events", "event"), ("places", "placeobj"), ("citations", "citation")];
+
+/// Events whose description is the value of the GED fact
+const VALUED_EVENTS: [&str; 7] = ["OCCU", "EDUC", "RELI", "TITL", "PROP", "NATI", "DSCR"];
+
+/// Deepest place hierarchy followed
+const MAX_PLACE_DEPTH: usize = 16;
+
+/// Elements of a section of the database
+fn section<'a>(root: &'a Element, section: &'a str, element: &'a str) -> impl Iterator<Item = &'a Element> + 'a {
+    root.first(section).into_iter().flat_map(move |s| s.elements(element))
+}
+
+/// Whether a Gramps ID can be used as cross-reference (`I0012`)
+fn is_xref(id: &str) -> bool {
+    let letters = id.trim_end_matches(|c: char| c.is_ascii_digit());
+    !letters.is_empty() && letters.chars().all(|c| c.is_ascii_uppercase() || c == '_')
+}
+
+/// GED date of a Gramps ISO date (`1850-03-12`), the
+/// unknown month and day being written as zeros
+fn iso_date(value: &str) -> Option<String> {
+    let negative = value.starts_with('-');
+    let mut parts = value.trim_start_matches('-').split('-');
+    let year: i32 = parts.next()?.trim().parse().ok()?;
+    let month = parts.next().and_then(|m| m.parse::<u8>().ok()).filter(|m| (1..=12).contains(m));
+    let day = parts.next().and_then(|d| d.parse::<u8>().ok()).filter(|d| *d > 0 && month.is_some());
+    if year == 0 && month.is_none() {
+        return None;
+    }
+    Some(SimpleDate::new(if negative { -year } else { year }, month, day).to_string())
+}
+
+/// GED value of the date of an element (`dateval`,
+/// `daterange`, `datespan` or `datestr`)
+fn date(element: &Element) -> Option<String> {
+    let dated = |e: &Element, attribute: &str| -> Option<String> {
+        let calendar = match e.attribute("cformat") {
+            Some("Julian") => "@#DJULIAN@ ",
+            Some("Hebrew") => "@#DHEBREW@ ",
+            Some("French Republican") => "@#DFRENCH R@ ",
+            _ => "",
+        };
+        Some(format!("{}{}", calendar, iso_date(e.attribute(attribute)?)?))
+    };
+    if let Some(value) = element.first("dateval") {
+        let prefix = match (value.attribute("type"), value.attribute("quality")) {
+            (Some("about"), _) => "ABT ",
+            (Some("before"), _) => "BEF ",
+            (Some("after"), _) => "AFT ",
+            (Some("from"), _) => "FROM ",
+            (Some("to"), _) => "TO ",
+            (_, Some("estimated")) => "EST ",
+            (_, Some("calculated")) => "CAL ",
+            _ => "",
+        };
+        return Some(format!("{}{}", prefix, dated(value, "val")?));
+    }
+    if let Some(range) = element.first("daterange") {
+        return Some(format!("BET {} AND {}", dated(range, "start")?, dated(range, "stop")?));
+    }
+    if let Some(span) = element.first("datespan") {
+        return Some(format!("FROM {} TO {}", dated(span, "start")?, dated(span, "stop")?));
+    }
+    element.first("datestr")
+        .and_then(|d| d.attribute("val"))
+        .filter(|t| !t.trim().is_empty())
+        .map(|t| format!("({})", t.trim()))
+}
+
+/// GED coordinate of a Gramps one, given in degrees
+fn gramps_coordinate(value: &str, positive: char, negative: char) -> String {
+    match value.trim().parse::<f64>() {
+        Ok(degrees) => coordinate(degrees, positive, negative),
+        Err(_) => value.trim().to_owned(),
+    }
+}
+
+/// Adds a nested tag when there's a value for it
+fn push_value(tag: &mut Tag, name: &str, value: Option<String>) {
+    if let Some(value) = value {
+        tag.nested.push(Tag::new(name, Some(&value)));
+    }
+}
+
+/// Contact of a residence read from a Gramps address, the
+/// phones being split again
+fn address(address: &Element) -> ContactInfo {
+    let postal = Address {
+        line1: address.text_of("street"),
+        line2: address.text_of("locality"),
+        city: address.text_of("city"),
+        state: address.text_of("state"),
+        postal_code: address.text_of("postal"),
+        country: address.text_of("country"),
+        ..Default::default()
+    };
+    let phones = address.text_of("phone").map_or(vec![], |p| {
+        p.split(';').map(|p| p.trim().to_owned()).filter(|p| !p.is_empty()).collect()
+    });
+    ContactInfo {
+        address: Some(postal).filter(|a| !a.is_empty()),
+        phones,
+        ..Default::default()
+    }
+}
+
+/// Emails and web pages of the web links of a person
+fn links(person: &Element) -> ContactInfo {
+    let mut links = ContactInfo::default();
+    for url in person.elements("url") {
+        let href = url.attribute("href").unwrap_or_default().trim();
+        match href.strip_prefix("mailto:") {
+            _ if href.is_empty() => (),
+            Some(email) => links.emails.push(email.to_owned()),
+            None if url.attribute("type") == Some("E-mail") => links.emails.push(href.to_owned()),
+            None => links.websites.push(href.to_owned()),
+        }
+    }
+    links
+}
+
+struct Reader<'a> {
+    /// Cross-reference of every record, by handle
+    xrefs: HashMap<&'a str, String>,
+    /// Events, places and citations, by handle
+    objects: HashMap<&'a str, &'a Element>,
+    /// `PEDI` of the children who aren't birth
+    /// ones, by child and family handles
+    pedigrees: HashMap<(&'a str, &'a str), &'static str>,
+    /// Base folder of the relative media paths
+    media_path: Option<String>,
+}
+
+impl<'a> Reader<'a> {
+    fn new(root: &'a Element) -> Self {
+        let mut used: HashSet<String> = HashSet::new();
+        let mut xrefs: HashMap<&str, String> = HashMap::new();
+        // The valid IDs are kept first, the others are made up
+        for (name, element, _, _) in RECORD_SECTIONS.iter() {
+            for record in section(root, name, element) {
+                let (handle, id) = match (record.attribute("handle"), record.attribute("id")) {
+                    (Some(handle), Some(id)) if is_xref(id) && !used.contains(id) => (handle, id),
+                    _ => continue,
+                };
+                used.insert(id.to_owned());
+                xrefs.insert(handle, id.to_owned());
+            }
+        }
+        for (name, element, _, prefix) in RECORD_SECTIONS.iter() {
+            let mut number = 0;
+            for record in section(root, name, element) {
+                let handle = match record.attribute("handle") {
+                    Some(handle) if !xrefs.contains_key(handle) => handle,
+                    _ => continue,
+                };
+                let xref = loop {
+                    number += 1;
+                    let xref = format!("{}{}", prefix, number);
+                    if !used.contains(&xref) {
+                        break xref;
+                    }
+                };
+                used.insert(xref.clone());
+                xrefs.insert(handle, xref);
+            }
+        }
+        let objects = OBJECT_SECTIONS.iter()
+            .flat_map(|(name, element)| section(root, name, element))
+            .filter_map(|e| Some((e.attribute("handle")?, e)))
+            .collect();
+        let mut pedigrees = HashMap::new();
+        for family in section(root, "families", "family") {
+            for child in family.elements("childref") {
+                let relations = [child.attribute("frel"), child.attribute("mrel")];
+                let pedigree = relations.iter().find_map(|r| match r {
+                    Some("Adopted") => Some("adopted"),
+                    Some("Foster") => Some("foster"),
+                    _ => None,
+                });
+                if let (Some(pedigree), Some(child), Some(family)) = (pedigree, child.attribute("hlink"), family.attribute("handle")) {
+                    pedigrees.insert((child, family), pedigree);
+                }
+            }
+        }
+        let media_path = root.first("header").and_then(|h| h.text_of("mediapath"));
+        Reader { xrefs, objects, pedigrees, media_path }
+    }
+
+    /// Pointer to the record with the given handle
+    fn pointer(&self, name: &str, handle: Option<&str>) -> Option<Tag> {
+        let xref = self.xrefs.get(handle?)?;
+        Some(Tag::new(name, Some(&format!("@{}@", xref))))
+    }
+
+    /// Pointers of the references (`noteref`...) of an element
+    fn references(&self, element: &Element, reference: &str, name: &str) -> Vec<Tag> {
+        element.elements(reference)
+            .filter_map(|r| self.pointer(name, r.attribute("hlink")))
+            .collect()
+    }
+
+    /// Source citations (`SOUR` pointers) of an element
+    fn citations(&self, element: &Element) -> Vec<Tag> {
+        element.elements("citationref")
+            .filter_map(|r| self.objects.get(r.attribute("hlink")?))
+            .filter_map(|citation| {
+                let source = citation.first("sourceref")?.attribute("hlink");
+                let mut tag = self.pointer("SOUR", source)?;
+                push_value(&mut tag, "PAGE", citation.text_of("page"));
+                if let Some(date) = date(citation) {
+                    tag.nested.push(Tag::new("DATA", None).nest(Tag::new("DATE", Some(&date))));
+                }
+                // Gramps goes up to 4 (very high), GED to 3 (primary evidence)
+                let quality = citation.text_of("confidence").and_then(|q| q.parse::<u8>().ok());
+                push_value(&mut tag, "QUAY", quality.map(|q| q.min(3).to_string()));
+                tag.nested.extend(self.references(citation, "noteref", "NOTE"));
+                Some(tag)
+            })
+            .collect()
+    }
+
+    /// Notes, citations and media of an element
+    fn annotations(&self, element: &Element) -> Vec<Tag> {
+        let mut tags = self.references(element, "noteref", "NOTE");
+        tags.extend(self.citations(element));
+        tags.extend(self.references(element, "objref", "OBJE"));
+        tags
+    }
+
+    /// Full name of a place, from the place itself up
+    /// through the ones enclosing it
+    fn place_name(&self, handle: &str) -> Option<String> {
+        let mut names = vec![];
+        let mut seen = HashSet::new();
+        let mut current = Some(handle);
+        while let Some(handle) = current.filter(|h| seen.insert(*h) && names.len() < MAX_PLACE_DEPTH) {
+            let place = self.objects.get(handle)?;
+            let parent = place.first("placeref").and_then(|r| r.attribute("hlink"));
+            // Older files only have the full title
+            match (place.text_of("ptitle"), names.is_empty() && parent.is_none()) {
+                (Some(title), true) => return Some(title),
+                _ => names.extend(place.first("pname").and_then(|n| n.attribute("value")).map(str::to_owned)),
+            }
+            current = parent;
+        }
+        Some(names.join(", ")).filter(|n| !n.is_empty())
+    }
+
+    fn place(&self, handle: &str) -> Option<Tag> {
+        let mut tag = Tag::new("PLAC", Some(&self.place_name(handle)?));
+        let coordinates = self.objects.get(handle).and_then(|p| p.first("coord"));
+        if let Some(coordinates) = coordinates {
+            let (latitude, longitude) = (coordinates.attribute("lat"), coordinates.attribute("long"));
+            if let (Some(latitude), Some(longitude)) = (latitude, longitude) {
+                tag.nested.push(Tag::new("MAP", None)
+                    .nest(Tag::new("LATI", Some(&gramps_coordinate(latitude, 'N', 'S'))))
+                    .nest(Tag::new("LONG", Some(&gramps_coordinate(longitude, 'E', 'W')))));
+            }
+        }
+        Some(tag)
+    }
+
+    fn event(&self, handle: &str) -> Option<Tag> {
+        let event = self.objects.get(handle)?;
+        let kind = event.text_of("type").unwrap_or_default();
+        let description = event.text_of("description");
+        let known = EVENT_TYPES.iter()
+            .find(|(_, t)| t.eq_ignore_ascii_case(&kind))
+            .map(|(tag, _)| *tag);
+        let mut tag = match known {
+            Some(name) if VALUED_EVENTS.contains(&name) => Tag::new(name, description.as_deref()),
+            Some(name) => Tag::new(name, None),
+            None => {
+                let mut tag = Tag::new("EVEN", description.as_deref());
+                push_value(&mut tag, "TYPE", Some(kind).filter(|k| !k.is_empty()));
+                tag
+            },
+        };
+        push_value(&mut tag, "DATE", date(event));
+        tag.nested.extend(event.first("place").and_then(|p| self.place(p.attribute("hlink")?)));
+        push_value(&mut tag, "CAUS", event.text_of("cause"));
+        tag.nested.extend(self.annotations(event));
+        Some(tag)
+    }
+
+    fn attribute(&self, attribute: &Element) -> Option<Tag> {
+        let kind = attribute.attribute("type")?;
+        let value = attribute.attribute("value");
+        let known = ATTRIBUTE_TYPES.iter().find(|(_, t)| t.eq_ignore_ascii_case(kind));
+        let mut tag = match known {
+            Some((name, _)) => Tag::new(name, value),
+            None => Tag::new("FACT", value).nest(Tag::new("TYPE", Some(kind))),
+        };
+        tag.nested.extend(self.annotations(attribute));
+        Some(tag)
+    }
+
+    fn name(&self, name: &Element) -> Tag {
+        let given = name.text_of("first");
+        let surnames: Vec<&Element> = name.elements("surname").collect();
+        let prefix = surnames.iter()
+            .find_map(|s| s.attribute("prefix"))
+            .map(str::to_owned)
+            .filter(|p| !p.is_empty());
+        let surname = surnames.iter()
+            .map(|s| s.text.trim())
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<&str>>()
+            .join(" ");
+        let suffix = name.text_of("suffix");
+        let full_surname = [prefix.as_deref().unwrap_or(""), &surname].join(" ");
+        let full = format!("{} /{}/ {}",
+            given.as_deref().unwrap_or(""), full_surname.trim(), suffix.as_deref().unwrap_or(""));
+        let mut tag = Tag::new("NAME", Some(full.trim()));
+        push_value(&mut tag, "NPFX", name.text_of("title"));
+        push_value(&mut tag, "GIVN", given);
+        push_value(&mut tag, "NICK", name.text_of("nick"));
+        push_value(&mut tag, "SPFX", prefix);
+        push_value(&mut tag, "SURN", Some(surname).filter(|s| !s.is_empty()));
+        push_value(&mut tag, "NSFX", suffix);
+        let kind = match name.attribute("type") {
+            Some("Married Name") => Some("married"),
+            Some("Also Known As") => Some("aka"),
+            _ => None,
+        };
+        push_value(&mut tag, "TYPE", kind.map(str::to_owned));
+        tag.nested.extend(self.references(name, "noteref", "NOTE"));
+        tag.nested.extend(self.citations(name));
+        tag
+    }
+
+    fn person(&self, person: &Element) -> Vec<Tag> {
+        let mut tags: Vec<Tag> = person.elements("name").map(|n| self.name(n)).collect();
+        let sex = match person.text_of("gender").as_deref() {
+            Some("M") => "M",
+            Some("F") => "F",
+            _ => "U",
+        };
+        tags.push(Tag::new("SEX", Some(sex)));
+        for event in person.elements("eventref") {
+            if matches!(event.attribute("role"), None | Some("Primary")) {
+                tags.extend(event.attribute("hlink").and_then(|h| self.event(h)));
+            }
+        }
+        // Addresses go to the residences in turn, the web links
+        // along with the last address
+        let mut contacts: Vec<ContactInfo> = person.elements("address").map(address).filter(|c| !c.is_empty()).collect();
+        let links = links(person);
+        match contacts.last_mut() {
+            _ if links.is_empty() => (),
+            Some(last) => {
+                last.emails.extend(links.emails);
+                last.websites.extend(links.websites);
+            },
+            None => contacts.push(links),
+        }
+        let mut contacts = contacts.into_iter();
+        for residence in tags.iter_mut().filter(|t| t.name == "RESI" && ContactInfo::from_parent(t).is_none()) {
+            match contacts.next() {
+                Some(contact) => residence.nested.extend(contact.to_tags()),
+                None => break,
+            }
+        }
+        tags.extend(contacts.map(|c| Tag { nested: c.to_tags(), ..Tag::new("RESI", None) }));
+        tags.extend(person.elements("attribute").filter_map(|a| self.attribute(a)));
+        for family in person.elements("childof") {
+            let pedigree = person.attribute("handle")
+                .zip(family.attribute("hlink"))
+                .and_then(|key| self.pedigrees.get(&key));
+            if let Some(mut tag) = self.pointer("FAMC", family.attribute("hlink")) {
+                push_value(&mut tag, "PEDI", pedigree.map(|p| p.to_string()));
+                tags.push(tag);
+            }
+        }
+        tags.extend(self.references(person, "parentin", "FAMS"));
+        tags.extend(self.annotations(person));
+        tags
+    }
+
+    fn family(&self, family: &Element) -> Vec<Tag> {
+        let mut tags = self.references(family, "father", "HUSB");
+        tags.extend(self.references(family, "mother", "WIFE"));
+        tags.extend(self.references(family, "childref", "CHIL"));
+        tags.extend(family.elements("eventref").filter_map(|e| self.event(e.attribute("hlink")?)));
+        tags.extend(family.elements("attribute").filter_map(|a| self.attribute(a)));
+        tags.extend(self.annotations(family));
+        tags
+    }
+
+    fn source(&self, source: &Element) -> Vec<Tag> {
+        let mut tag = Tag::default();
+        push_value(&mut tag, "TITL", source.text_of("stitle"));
+        push_value(&mut tag, "AUTH", source.text_of("sauthor"));
+        push_value(&mut tag, "PUBL", source.text_of("spubinfo"));
+        push_value(&mut tag, "ABBR", source.text_of("sabbrev"));
+        for repository in source.elements("reporef") {
+            if let Some(mut pointer) = self.pointer("REPO", repository.attribute("hlink")) {
+                if let Some(number) = repository.attribute("callno").filter(|c| !c.is_empty()) {
+                    let mut call = Tag::new("CALN", Some(number));
+                    push_value(&mut call, "MEDI", repository.attribute("medium").map(str::to_owned));
+                    pointer.nested.push(call);
+                }
+                tag.nested.push(pointer);
+            }
+        }
+        tag.nested.extend(self.annotations(source));
+        tag.nested
+    }
+
+    fn object(&self, object: &Element) -> Vec<Tag> {
+        let mut tags = vec![];
+        if let Some(file) = object.first("file") {
+            let src = file.attribute("src").unwrap_or_default();
+            let path = match &self.media_path {
+                Some(base) if !src.is_empty() && Path::new(src).is_relative() => Path::new(base).join(src).to_string_lossy().into_owned(),
+                _ => src.to_owned(),
+            };
+            let mut tag = Tag::new("FILE", Some(&path));
+            let extension = Path::new(src).extension().map(|e| e.to_string_lossy().to_lowercase());
+            push_value(&mut tag, "FORM", extension);
+            push_value(&mut tag, "TITL", file.attribute("description").map(str::to_owned).filter(|d| !d.is_empty()));
+            tags.push(tag);
+        }
+        tags.extend(self.annotations(object));
+        tags
+    }
+}
+
+/// Level 0 GED structures of a Gramps database
+pub(super) fn tags(root: &Element) -> Vec<Tag> {
+    let reader = Reader::new(root);
+    let mut tags = vec![];
+    let researcher = root.first("header")
+        .and_then(|h| h.first("researcher"))
+        .and_then(|r| r.text_of("resname"));
+    if let Some(researcher) = researcher {
+        tags.push(Tag { xref: Some(String::from("SUBM1")), ..Tag::new("SUBM", None) }.nest(Tag::new("NAME", Some(&researcher))));
+    }
+    for (name, element, rtype, _) in RECORD_SECTIONS.iter() {
+        for record in section(root, name, element) {
+            let xref = match record.attribute("handle").and_then(|h| reader.xrefs.get(h)) {
+                Some(xref) => xref.clone(),
+                None => continue,
+            };
+            let tag = match *rtype {
+                "NOTE" => Tag::with_text("NOTE", record.first("text").map_or("", |t| t.text.as_str())),
+                _ => Tag::new(rtype, None),
+            };
+            let nested = match *rtype {
+                "INDI" => reader.person(record),
+                "FAM" => reader.family(record),
+                "SOUR" => reader.source(record),
+                "REPO" => record.text_of("rname").map(|n| Tag::new("NAME", Some(&n))).into_iter()
+                    .chain(reader.annotations(record))
+                    .collect(),
+                "OBJE" => reader.object(record),
+                _ => reader.annotations(record),
+            };
+            tags.push(Tag {
+                xref: Some(xref),
+                nested: [tag.nested, nested].concat(),
+                ..tag
+            });
+        }
+    }
+    tags
+}
@@ -0,0 +1,562 @@
+//! Records of a registry written as a Gramps database. The GED
+//! facts become events, the place names a hierarchy of places and
+//! the source pointers citations; records keep their
+//! cross-reference as Gramps ID.
+
+use super::xml::Element;
+use super::{ATTRIBUTE_TYPES, EVENT_TYPES, XML_VERSION};
+use crate::gedcom::date::DateModifier;
+use crate::gedcom::place::degrees;
+use crate::gedcom::{gedcom7, ContactInfo, GedDate, GedWriter, Record, RecordRegistry, SimpleDate, Tag};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::collections::HashMap;
+use std::io::Write;
+use std::time::SystemTime;
+
+/// Handle of a record in the database
+fn handle(xref: &str) -> String {
+    format!("_{}", xref)
+}
+
+/// Reference to the record the tag points to
+fn link(name: &str, tag: &Tag) -> Option<Element> {
+    tag.pointer().map(|xref| Element::new(name).attr("hlink", &handle(xref)))
+}
+
+/// Gramps address of a residence, along with the web links
+/// standing for its emails and web pages. The free form
+/// address fills the street and locality when there's no
+/// structured one, the phones share the single phone field.
+fn contact(contact: &ContactInfo) -> (Option<Element>, Vec<Element>) {
+    let mut address = Element::new("address");
+    if let Some(postal) = &contact.address {
+        let free: Vec<&str> = postal.lines.as_deref().map_or(vec![], |l| l.lines().collect());
+        let (street, locality) = match &postal.line1 {
+            Some(line1) => (Some(line1.clone()), postal.line2.clone()),
+            None => (
+                free.first().map(|l| l.to_string()),
+                Some(free.iter().skip(1).copied().collect::<Vec<&str>>().join(", ")).filter(|l| !l.is_empty()),
+            ),
+        };
+        let parts = [
+            ("street", street),
+            ("locality", locality),
+            ("city", postal.city.clone()),
+            ("state", postal.state.clone()),
+            ("country", postal.country.clone()),
+            ("postal", postal.postal_code.clone()),
+        ];
+        for (name, value) in parts.iter() {
+            if let Some(value) = value {
+                address = address.nest(Element::with_text(name, value));
+            }
+        }
+    }
+    if !contact.phones.is_empty() {
+        address = address.nest(Element::with_text("phone", &contact.phones.join("; ")));
+    }
+    let urls = contact.emails.iter().map(|e| (e, "E-mail"))
+        .chain(contact.websites.iter().map(|w| (w, "Web Home")))
+        .map(|(href, kind)| Element::new("url").attr("href", href).attr("type", kind))
+        .collect();
+    (Some(address).filter(|a| !a.children.is_empty()), urls)
+}
+
+/// Gramps ISO date (`1850-03-12`)
+fn iso_date(date: &SimpleDate) -> String {
+    let mut iso = match date.year {
+        year if year < 0 => format!("-{:04}", -year),
+        year => format!("{:04}", year),
+    };
+    if let Some(month) = date.month {
+        iso.push_str(&format!("-{:02}", month));
+        if let Some(day) = date.day {
+            iso.push_str(&format!("-{:02}", day));
+        }
+    }
+    iso
+}
+
+/// Date element of a GED date value, the ones that can't be
+/// understood being kept as text
+fn date(value: &str) -> Element {
+    let calendar = [("JULIAN", "Julian"), ("HEBREW", "Hebrew"), ("FRENCH R", "French Republican")].iter()
+        .find(|(escape, _)| value.contains(escape))
+        .map(|(_, calendar)| *calendar);
+    let date = match GedDate::parse(value) {
+        Some(date) => date,
+        None => return Element::new("datestr").attr("val", value.trim()),
+    };
+    let element = match (date.modifier, date.start, date.end) {
+        (DateModifier::Between, Some(start), Some(end)) => Element::new("daterange")
+            .attr("start", &iso_date(&start))
+            .attr("stop", &iso_date(&end)),
+        (DateModifier::FromTo, Some(start), Some(end)) => Element::new("datespan")
+            .attr("start", &iso_date(&start))
+            .attr("stop", &iso_date(&end)),
+        (modifier, Some(single), _) | (modifier, None, Some(single)) => {
+            let value = Element::new("dateval").attr("val", &iso_date(&single));
+            match modifier {
+                DateModifier::About => value.attr("type", "about"),
+                DateModifier::Before => value.attr("type", "before"),
+                DateModifier::After => value.attr("type", "after"),
+                DateModifier::From => value.attr("type", "from"),
+                DateModifier::To => value.attr("type", "to"),
+                DateModifier::Estimated => value.attr("quality", "estimated"),
+                DateModifier::Calculated => value.attr("quality", "calculated"),
+                _ => value,
+            }
+        },
+        (_, None, None) => return Element::new("datestr").attr("val", date.phrase.as_deref().unwrap_or("")),
+    };
+    match calendar {
+        Some(calendar) => element.attr("cformat", calendar),
+        None => element,
+    }
+}
+
+/// Database under construction, with the events, places,
+/// citations and notes made out of the records
+#[derive(Default)]
+struct Database {
+    change: String,
+    researcher: Option<String>,
+    events: Vec<Element>,
+    people: Vec<Element>,
+    families: Vec<Element>,
+    citations: Vec<Element>,
+    sources: Vec<Element>,
+    places: Vec<Element>,
+    objects: Vec<Element>,
+    repositories: Vec<Element>,
+    notes: Vec<Element>,
+    /// Handle of every place, by full name
+    place_handles: HashMap<String, String>,
+    /// `PEDI` of the children, by child and family cross-references
+    pedigrees: HashMap<(String, String), String>,
+    /// Number of the notes made out of the inline ones
+    inline_notes: usize,
+}
+
+impl Database {
+    /// Element of a primary object, with its identifiers
+    fn object(&self, name: &str, handle: &str, id: &str) -> Element {
+        Element::new(name)
+            .attr("handle", handle)
+            .attr("change", &self.change)
+            .attr("id", id)
+    }
+
+    /// Note reference of a `NOTE` tag, the inline
+    /// notes getting a note object of their own
+    fn note(&mut self, tag: &Tag) -> Option<Element> {
+        if let Some(reference) = link("noteref", tag) {
+            return Some(reference);
+        }
+        let text = tag.text().filter(|t| !t.trim().is_empty())?;
+        self.inline_notes += 1;
+        let handle = format!("_note{}", self.inline_notes);
+        let note = self.object("note", &handle, &format!("NI{:04}", self.inline_notes))
+            .attr("type", "General")
+            .nest(Element::with_text("text", &text));
+        self.notes.push(note);
+        Some(Element::new("noteref").attr("hlink", &handle))
+    }
+
+    fn notes(&mut self, tag: &Tag) -> Vec<Element> {
+        tag.children("NOTE").filter_map(|n| self.note(n)).collect()
+    }
+
+    /// Citations of the sources the tag points to
+    fn citations(&mut self, tag: &Tag) -> Vec<Element> {
+        let mut references = vec![];
+        for source in tag.children("SOUR") {
+            let source_ref = match link("sourceref", source) {
+                Some(reference) => reference,
+                None => continue,
+            };
+            let number = self.citations.len() + 1;
+            let handle = format!("_citation{}", number);
+            let mut citation = self.object("citation", &handle, &format!("C{:04}", number));
+            if let Some(value) = source.child("DATA").and_then(|d| d.value("DATE")) {
+                citation = citation.nest(date(&value));
+            }
+            if let Some(page) = source.value("PAGE") {
+                citation = citation.nest(Element::with_text("page", &page));
+            }
+            let confidence = source.value("QUAY").and_then(|q| q.trim().parse::<u8>().ok()).unwrap_or(2);
+            citation = citation.nest(Element::with_text("confidence", &confidence.to_string()));
+            for note in self.notes(source) {
+                citation = citation.nest(note);
+            }
+            self.citations.push(citation.nest(source_ref));
+            references.push(Element::new("citationref").attr("hlink", &handle));
+        }
+        references
+    }
+
+    fn media(tag: &Tag) -> Vec<Element> {
+        tag.children("OBJE").filter_map(|o| link("objref", o)).collect()
+    }
+
+    /// Handle of the place, the places enclosing it
+    /// (the next parts of its name) being added first
+    fn place(&mut self, tag: &Tag) -> Option<String> {
+        let name = tag.content.as_deref()?.trim();
+        let parts: Vec<&str> = name.split(',').map(str::trim).filter(|p| !p.is_empty()).collect();
+        let mut enclosing: Option<String> = None;
+        for i in (0..parts.len()).rev() {
+            let full = parts[i..].join(", ");
+            if let Some(handle) = self.place_handles.get(&full) {
+                enclosing = Some(handle.clone());
+                continue;
+            }
+            let number = self.places.len() + 1;
+            let handle = format!("_place{}", number);
+            let mut place = self.object("placeobj", &handle, &format!("P{:04}", number))
+                .attr("type", "Unknown")
+                .nest(Element::new("pname").attr("value", parts[i]));
+            let map = tag.child("MAP").filter(|_| i == 0);
+            if let Some((latitude, longitude)) = map.and_then(|m| Some((m.value("LATI")?, m.value("LONG")?))) {
+                // Gramps takes signed degrees, the other values are kept as they are
+                let signed = |value: &str| degrees(value).map_or_else(|| value.trim().to_owned(), |d| d.to_string());
+                place = place.nest(Element::new("coord")
+                    .attr("long", &signed(&longitude))
+                    .attr("lat", &signed(&latitude)));
+            }
+            if let Some(enclosing) = &enclosing {
+                place = place.nest(Element::new("placeref").attr("hlink", enclosing));
+            }
+            self.places.push(place);
+            self.place_handles.insert(full, handle.clone());
+            enclosing = Some(handle);
+        }
+        enclosing
+    }
+
+    /// Handle of the event made out of a fact
+    fn event(&mut self, tag: &Tag) -> String {
+        let number = self.events.len() + 1;
+        let handle = format!("_event{}", number);
+        let kind = match EVENT_TYPES.iter().find(|(name, _)| *name == tag.name) {
+            Some((_, kind)) => kind.to_string(),
+            None => tag.value("TYPE").unwrap_or_else(|| String::from("Unknown")),
+        };
+        let mut event = self.object("event", &handle, &format!("E{:04}", number))
+            .nest(Element::with_text("type", &kind));
+        if let Some(value) = tag.value("DATE") {
+            event = event.nest(date(&value));
+        }
+        if let Some(place) = tag.child("PLAC").and_then(|p| self.place(p)) {
+            event = event.nest(Element::new("place").attr("hlink", &place));
+        }
+        if let Some(cause) = tag.value("CAUS") {
+            event = event.nest(Element::with_text("cause", &cause));
+        }
+        let description = tag.text().filter(|d| !d.is_empty() && d != "Y");
+        if let Some(description) = description {
+            event = event.nest(Element::with_text("description", &description));
+        }
+        let references = [self.notes(tag), self.citations(tag), Self::media(tag)].concat();
+        event.children.extend(references);
+        self.events.push(event);
+        handle
+    }
+
+    /// Attribute made out of a GED attribute or a `FACT`
+    fn attribute(&mut self, tag: &Tag) -> Element {
+        let kind = match ATTRIBUTE_TYPES.iter().find(|(name, _)| *name == tag.name) {
+            Some((_, kind)) => kind.to_string(),
+            None => tag.value("TYPE").unwrap_or_else(|| String::from("Unknown")),
+        };
+        let mut attribute = Element::new("attribute")
+            .attr("type", &kind)
+            .attr("value", &tag.text().unwrap_or_default());
+        attribute.children = [self.citations(tag), self.notes(tag)].concat();
+        attribute
+    }
+
+    fn name(&mut self, tag: &Tag, primary: bool) -> Element {
+        let content = tag.content.clone().unwrap_or_default();
+        let mut parts = content.splitn(3, '/');
+        let (before, between, after) = (parts.next(), parts.next(), parts.next());
+        let given = tag.value("GIVN").or_else(|| before.map(|b| b.trim().to_owned()));
+        let surname = tag.value("SURN").or_else(|| between.map(|s| s.trim().to_owned()));
+        let suffix = tag.value("NSFX").or_else(|| after.map(|a| a.trim().to_owned()));
+        let kind = match tag.value("TYPE").as_deref() {
+            Some("married") => "Married Name",
+            Some("aka") => "Also Known As",
+            _ if primary => "Birth Name",
+            _ => "Unknown",
+        };
+        let mut name = Element::new("name").attr("type", kind);
+        if !primary {
+            name = name.attr("alt", "1");
+        }
+        let nested = [
+            ("first", given),
+            ("surname", surname),
+            ("suffix", suffix),
+            ("title", tag.value("NPFX")),
+            ("nick", tag.value("NICK")),
+        ];
+        for (element, value) in nested.iter() {
+            let value = match value.as_deref().map(str::trim) {
+                Some(value) if !value.is_empty() => value,
+                _ => continue,
+            };
+            let mut child = Element::with_text(element, value);
+            if *element == "surname" {
+                if let Some(prefix) = tag.value("SPFX") {
+                    // The prefix is part of the surname of GED names
+                    child.text = value.trim_start_matches(prefix.trim()).trim().to_owned();
+                    child = child.attr("prefix", prefix.trim());
+                }
+            }
+            name = name.nest(child);
+        }
+        let references = [self.notes(tag), self.citations(tag)].concat();
+        name.children.extend(references);
+        name
+    }
+
+    fn is_event(tag: &Tag) -> bool {
+        tag.name == "EVEN" || EVENT_TYPES.iter().any(|(name, _)| *name == tag.name)
+    }
+
+    fn is_attribute(tag: &Tag) -> bool {
+        tag.name == "FACT" || ATTRIBUTE_TYPES.iter().any(|(name, _)| *name == tag.name)
+    }
+
+    fn person(&mut self, record: &Record) {
+        let tag = record.to_tag();
+        let gender = match record.sex() {
+            'M' => "M",
+            'F' => "F",
+            _ => "U",
+        };
+        let mut names = vec![];
+        let mut events = vec![];
+        let mut attributes = vec![];
+        let mut families = vec![];
+        let mut addresses = vec![];
+        let mut urls = vec![];
+        for nested in &tag.nested {
+            match nested.name.as_str() {
+                "NAME" => names.push(self.name(nested, names.is_empty())),
+                "FAMC" => families.extend(link("childof", nested)),
+                _ if Self::is_event(nested) => {
+                    if let Some(residence) = ContactInfo::from_parent(nested).filter(|_| nested.name == "RESI") {
+                        let (address, links) = contact(&residence);
+                        addresses.extend(address);
+                        urls.extend(links);
+                    }
+                    let event = self.event(nested);
+                    events.push(Element::new("eventref").attr("hlink", &event).attr("role", "Primary"));
+                },
+                _ if Self::is_attribute(nested) => attributes.push(self.attribute(nested)),
+                _ => (),
+            }
+        }
+        let partners = tag.children("FAMS").filter_map(|f| link("parentin", f));
+        let notes = self.notes(&tag);
+        let citations = self.citations(&tag);
+        let children = std::iter::once(Element::with_text("gender", gender))
+            .chain(names)
+            .chain(events)
+            .chain(Self::media(&tag))
+            .chain(addresses)
+            .chain(attributes)
+            .chain(urls)
+            .chain(families)
+            .chain(partners)
+            .chain(notes)
+            .chain(citations)
+            .collect();
+        let mut person = self.object("person", &handle(&record.xref), &record.xref);
+        person.children = children;
+        self.people.push(person);
+    }
+
+    fn family(&mut self, record: &Record) {
+        let tag = record.to_tag();
+        let married = tag.child("MARR").is_some();
+        let mut events = vec![];
+        let mut attributes = vec![];
+        for nested in &tag.nested {
+            if Self::is_event(nested) {
+                let event = self.event(nested);
+                events.push(Element::new("eventref").attr("hlink", &event).attr("role", "Family"));
+            } else if Self::is_attribute(nested) {
+                attributes.push(self.attribute(nested));
+            }
+        }
+        let children = tag.children("CHIL").filter_map(|child| {
+            let reference = link("childref", child)?;
+            let key = (child.pointer()?.to_owned(), record.xref.clone());
+            let relation = match self.pedigrees.get(&key).map(|p| p.to_lowercase()).as_deref() {
+                Some("adopted") => Some("Adopted"),
+                Some("foster") => Some("Foster"),
+                _ => None,
+            };
+            Some(match relation {
+                Some(relation) => reference.attr("mrel", relation).attr("frel", relation),
+                None => reference,
+            })
+        });
+        let children: Vec<Element> = children.collect();
+        let notes = self.notes(&tag);
+        let citations = self.citations(&tag);
+        let elements = std::iter::once(Element::new("rel").attr("type", if married { "Married" } else { "Unknown" }))
+            .chain(tag.children("HUSB").filter_map(|h| link("father", h)).take(1))
+            .chain(tag.children("WIFE").filter_map(|w| link("mother", w)).take(1))
+            .chain(events)
+            .chain(Self::media(&tag))
+            .chain(children)
+            .chain(attributes)
+            .chain(notes)
+            .chain(citations)
+            .collect();
+        let mut family = self.object("family", &handle(&record.xref), &record.xref);
+        family.children = elements;
+        self.families.push(family);
+    }
+
+    fn source(&mut self, record: &Record) {
+        let tag = record.to_tag();
+        let mut source = self.object("source", &handle(&record.xref), &record.xref);
+        let texts = [("stitle", "TITL"), ("sauthor", "AUTH"), ("spubinfo", "PUBL"), ("sabbrev", "ABBR")];
+        for (element, name) in texts.iter() {
+            if let Some(text) = tag.value(name) {
+                source = source.nest(Element::with_text(element, &text));
+            }
+        }
+        for note in self.notes(&tag) {
+            source = source.nest(note);
+        }
+        for media in Self::media(&tag) {
+            source = source.nest(media);
+        }
+        for repository in tag.children("REPO") {
+            let mut reference = match link("reporef", repository) {
+                Some(reference) => reference,
+                None => continue,
+            };
+            if let Some(call) = repository.child("CALN") {
+                reference = reference.attr("callno", call.content.as_deref().unwrap_or(""));
+                if let Some(medium) = call.value("MEDI") {
+                    reference = reference.attr("medium", &medium);
+                }
+            }
+            source = source.nest(reference);
+        }
+        self.sources.push(source);
+    }
+
+    fn repository(&mut self, record: &Record) {
+        let tag = record.to_tag();
+        let mut repository = self.object("repository", &handle(&record.xref), &record.xref)
+            .nest(Element::with_text("rname", &tag.value("NAME").unwrap_or_default()))
+            .nest(Element::with_text("type", "Unknown"));
+        for note in self.notes(&tag) {
+            repository = repository.nest(note);
+        }
+        self.repositories.push(repository);
+    }
+
+    fn media_object(&mut self, record: &Record) {
+        let tag = record.to_tag();
+        let file = tag.child("FILE");
+        let src = file.and_then(|f| f.content.clone()).unwrap_or_default();
+        let form = file.and_then(|f| f.value("FORM")).or_else(|| tag.value("FORM"));
+        let mime = form.as_deref().and_then(|f| gedcom7::media_type(f)).unwrap_or("unknown");
+        let title = file.and_then(|f| f.value("TITL")).or_else(|| tag.value("TITL")).unwrap_or_default();
+        let mut object = self.object("object", &handle(&record.xref), &record.xref)
+            .nest(Element::new("file").attr("src", &src).attr("mime", mime).attr("description", &title));
+        for reference in [self.notes(&tag), self.citations(&tag)].concat() {
+            object = object.nest(reference);
+        }
+        self.objects.push(object);
+    }
+
+    fn note_record(&mut self, record: &Record) {
+        let tag = record.to_tag();
+        let note = self.object("note", &handle(&record.xref), &record.xref)
+            .attr("type", "General")
+            .nest(Element::with_text("text", &tag.text().unwrap_or_default()));
+        self.notes.push(note);
+    }
+
+    fn add(&mut self, record: &Record) {
+        match record.rtype.as_str() {
+            "INDI" => self.person(record),
+            "FAM" => self.family(record),
+            "SOUR" => self.source(record),
+            "REPO" => self.repository(record),
+            "OBJE" => self.media_object(record),
+            "NOTE" => self.note_record(record),
+            "SUBM" if self.researcher.is_none() => self.researcher = Some(record.name.clone()),
+            _ => (),
+        }
+    }
+
+    /// Root element of the database
+    fn into_element(self) -> Element {
+        let mut header = Element::new("header")
+            .nest(Element::new("created")
+                .attr("date", &iso_date(&SimpleDate::today()))
+                .attr("version", env!("CARGO_PKG_VERSION")));
+        if let Some(researcher) = self.researcher.filter(|r| !r.is_empty()) {
+            header = header.nest(Element::new("researcher").nest(Element::with_text("resname", &researcher)));
+        }
+        let sections = vec![
+            ("events", self.events),
+            ("people", self.people),
+            ("families", self.families),
+            ("citations", self.citations),
+            ("sources", self.sources),
+            ("places", self.places),
+            ("objects", self.objects),
+            ("repositories", self.repositories),
+            ("notes", self.notes),
+        ];
+        let database = Element::new("database")
+            .attr("xmlns", &format!("http://gramps-project.org/xml/{}/", XML_VERSION))
+            .nest(header);
+        sections.into_iter()
+            .filter(|(_, elements)| !elements.is_empty())
+            .fold(database, |database, (name, elements)| {
+                let mut section = Element::new(name);
+                section.children = elements;
+                database.nest(section)
+            })
+    }
+}
+
+/// Writes the registry as a gzipped Gramps database, as set by
+/// the [export settings](GedWriter::exported) but the version
+pub fn write<W: Write>(writer: &GedWriter, registry: &RecordRegistry, out: W) -> std::io::Result<()> {
+    let records = writer.exported(registry);
+    let change = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let mut database = Database { change: change.to_string(), ..Default::default() };
+    for record in records.iter().filter(|r| r.rtype == "INDI") {
+        for family in record.to_tag().children("FAMC") {
+            if let (Some(xref), Some(pedigree)) = (family.pointer(), family.value("PEDI")) {
+                database.pedigrees.insert((record.xref.clone(), xref.to_owned()), pedigree.trim().to_owned());
+            }
+        }
+    }
+    for record in &records {
+        database.add(record);
+    }
+    let mut out = GzEncoder::new(out, Compression::default());
+    writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(out, r#"<!DOCTYPE database PUBLIC "-//Gramps//DTD Gramps XML {v}//EN" "http://gramps-project.org/xml/{v}/grampsxml.dtd">"#, v = XML_VERSION)?;
+    database.into_element().write(&mut out, 0)?;
+    out.finish()?;
+    Ok(())
+}
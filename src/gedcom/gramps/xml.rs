@@ -0,0 +1,254 @@
+//! Just enough XML for the Gramps files: elements with their
+//! attributes and text, read into a tree and written back.
+//! Comments, declarations and processing instructions are
+//! skipped, namespace prefixes dropped.
+
+use crate::gedcom::ParseError;
+use regex::Regex;
+use std::io::Write;
+
+/// Deepest nesting of elements read, deeper documents
+/// being rejected rather than overflowing the stack
+const MAX_DEPTH: usize = 256;
+
+#[derive(Debug,Clone,Default,PartialEq)]
+pub struct Element {
+    pub name: String,
+    pub attributes: Vec<(String, String)>,
+    pub children: Vec<Element>,
+    /// Text directly inside the element, around its children
+    pub text: String,
+}
+
+fn malformed(what: &str) -> ParseError {
+    ParseError::Runtime(format!("Malformed XML: {}", what))
+}
+
+/// Escapes the characters that can't appear as they are
+/// in text and attribute values
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Replaces the predefined entities and character references
+fn unescape(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_owned();
+    }
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let end = match rest.find(';') {
+            Some(end) => end,
+            None => break,
+        };
+        let entity = &rest[1..end];
+        let replaced = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => match entity.strip_prefix("#x").or_else(|| entity.strip_prefix("#X")) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok().and_then(std::char::from_u32),
+                None => entity.strip_prefix('#')
+                    .and_then(|d| d.parse().ok())
+                    .and_then(std::char::from_u32),
+            },
+        };
+        match replaced {
+            Some(c) => {
+                result.push(c);
+                rest = &rest[end + 1..];
+            },
+            None => {
+                result.push('&');
+                rest = &rest[1..];
+            },
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Name without its namespace prefix
+fn local_name(name: &str) -> &str {
+    name.rsplit(':').next().unwrap_or(name)
+}
+
+/// Position of the `>` closing a start tag, the
+/// quoted attribute values being skipped
+fn tag_end(text: &str) -> Option<usize> {
+    let mut quote = None;
+    for (i, c) in text.char_indices() {
+        match (quote, c) {
+            (None, '>') => return Some(i),
+            (None, '"') | (None, '\'') => quote = Some(c),
+            (Some(q), c) if c == q => quote = None,
+            _ => (),
+        }
+    }
+    None
+}
+
+/// The children are dropped one after the other rather than
+/// recursively, whatever the depth of the tree
+impl Drop for Element {
+    fn drop(&mut self) {
+        let mut pending = std::mem::take(&mut self.children);
+        while let Some(mut element) = pending.pop() {
+            pending.append(&mut element.children);
+        }
+    }
+}
+
+impl Element {
+    pub fn new(name: &str) -> Self {
+        Element {
+            name: String::from(name),
+            attributes: vec![],
+            children: vec![],
+            text: String::new(),
+        }
+    }
+
+    /// Adds an attribute
+    pub fn attr(mut self, name: &str, value: &str) -> Self {
+        self.attributes.push((name.to_owned(), value.to_owned()));
+        self
+    }
+
+    /// Adds a child element
+    pub fn nest(mut self, child: Element) -> Self {
+        self.children.push(child);
+        self
+    }
+
+    /// Element holding only the text
+    pub fn with_text(name: &str, text: &str) -> Self {
+        let mut element = Self::new(name);
+        element.text = text.to_owned();
+        element
+    }
+
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes.iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Returns the first child with the given name
+    pub fn first(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|e| e.name == name)
+    }
+
+    /// Returns all the children with the given name
+    pub fn elements<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Element> + 'a {
+        self.children.iter().filter(move |e| e.name == name)
+    }
+
+    /// Trimmed text of the first child with the given
+    /// name, when there's any
+    pub fn text_of(&self, name: &str) -> Option<String> {
+        self.first(name)
+            .map(|e| e.text.trim().to_owned())
+            .filter(|t| !t.is_empty())
+    }
+
+    /// Reads the root element of a document
+    pub fn parse(xml: &str) -> Result<Element, ParseError> {
+        let r_attribute = Regex::new(r#"([\w:.-]+)\s*=\s*(?:"([^"]*)"|'([^']*)')"#).unwrap();
+        let mut stack: Vec<Element> = vec![];
+        let mut rest = xml;
+        let skip = |rest: &str, end: &str| -> Result<usize, ParseError> {
+            rest.find(end).map(|p| p + end.len()).ok_or_else(|| malformed(&format!("missing '{}'", end)))
+        };
+        while !rest.is_empty() {
+            if rest.starts_with("<?") {
+                rest = &rest[skip(rest, "?>")?..];
+            } else if rest.starts_with("<!--") {
+                rest = &rest[skip(rest, "-->")?..];
+            } else if let Some(cdata) = rest.strip_prefix("<![CDATA[") {
+                let end = cdata.find("]]>").ok_or_else(|| malformed("unterminated CDATA section"))?;
+                if let Some(current) = stack.last_mut() {
+                    current.text.push_str(&cdata[..end]);
+                }
+                rest = &cdata[end + 3..];
+            } else if rest.starts_with("<!") {
+                // Declarations, with their internal subset if any
+                let close = rest.find('>').ok_or_else(|| malformed("unterminated declaration"))?;
+                rest = match rest.find('[') {
+                    Some(open) if open < close => &rest[skip(rest, "]>")?..],
+                    _ => &rest[close + 1..],
+                };
+            } else if let Some(end_tag) = rest.strip_prefix("</") {
+                let close = end_tag.find('>').ok_or_else(|| malformed("unterminated end tag"))?;
+                let name = local_name(end_tag[..close].trim());
+                let element = stack.pop().ok_or_else(|| malformed(&format!("unexpected </{}>", name)))?;
+                if element.name != name {
+                    return Err(malformed(&format!("<{}> closed by </{}>", element.name, name)));
+                }
+                rest = &end_tag[close + 1..];
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push(element),
+                    None => return Ok(element),
+                }
+            } else if let Some(start_tag) = rest.strip_prefix('<') {
+                let close = tag_end(start_tag).ok_or_else(|| malformed("unterminated start tag"))?;
+                let inside = &start_tag[..close];
+                let (inside, empty) = match inside.strip_suffix('/') {
+                    Some(inside) => (inside, true),
+                    None => (inside, false),
+                };
+                let name = inside.split_whitespace().next().ok_or_else(|| malformed("element without name"))?;
+                let mut element = Element::new(local_name(name));
+                for caps in r_attribute.captures_iter(&inside[name.len()..]) {
+                    let value = caps.get(2).or_else(|| caps.get(3)).map_or("", |v| v.as_str());
+                    element.attributes.push((local_name(&caps[1]).to_owned(), unescape(value)));
+                }
+                rest = &start_tag[close + 1..];
+                if !empty && stack.len() >= MAX_DEPTH {
+                    return Err(malformed(&format!("elements nested deeper than {} levels", MAX_DEPTH)));
+                }
+                match (empty, stack.last_mut()) {
+                    (false, _) => stack.push(element),
+                    (true, Some(parent)) => parent.children.push(element),
+                    (true, None) => return Ok(element),
+                }
+            } else {
+                let end = rest.find('<').unwrap_or_else(|| rest.len());
+                if let Some(current) = stack.last_mut() {
+                    current.text.push_str(&unescape(&rest[..end]));
+                }
+                rest = &rest[end..];
+            }
+        }
+        Err(malformed("no root element"))
+    }
+
+    /// Writes the element and its children indented
+    /// from the given level
+    pub fn write<W: Write>(&self, out: &mut W, level: usize) -> std::io::Result<()> {
+        let indent = "  ".repeat(level);
+        write!(out, "{}<{}", indent, self.name)?;
+        for (name, value) in &self.attributes {
+            write!(out, " {}=\"{}\"", name, escape(value))?;
+        }
+        match (self.children.is_empty(), self.text.is_empty()) {
+            (true, true) => writeln!(out, "/>"),
+            (true, false) => writeln!(out, ">{}</{}>", escape(&self.text), self.name),
+            (false, _) => {
+                writeln!(out, ">")?;
+                for child in &self.children {
+                    child.write(out, level + 1)?;
+                }
+                writeln!(out, "{}</{}>", indent, self.name)
+            },
+        }
+    }
+}
@@ -0,0 +1,343 @@
+//! Family graph of a record tree: individuals and families kept
+//! in two arenas and linked by their indices instead of shared
+//! pointers, so the parent/child cycles can't leak and the graph
+//! can be handed to other threads. It's built from the `HUSB`,
+//! `WIFE`, `CHIL`, `FAMC` and `FAMS` pointers of the records and
+//! has to be built again once they change.
+
+use crate::gedcom::{xref_key, Record, RecordRc, RecordRegistry};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// Index of an individual in the graph
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash,PartialOrd,Ord)]
+pub struct PersonId(u32);
+
+/// Index of a family in the graph
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash,PartialOrd,Ord)]
+pub struct FamilyId(u32);
+
+#[derive(Debug,Clone,Default)]
+struct PersonNode {
+    key: u64,
+    /// Families the person is a child of, the first
+    /// one giving the father and the mother
+    parents: Vec<FamilyId>,
+    /// Families the person is a partner in
+    spouses: Vec<FamilyId>,
+}
+
+#[derive(Debug,Clone,Default)]
+struct FamilyNode {
+    key: u64,
+    husband: Option<PersonId>,
+    wife: Option<PersonId>,
+    children: Vec<PersonId>,
+}
+
+/// Individuals and families of a registry with the links
+/// between them. Holds registry keys only, never records.
+#[derive(Debug,Clone,Default)]
+pub struct FamilyGraph {
+    persons: Vec<PersonNode>,
+    families: Vec<FamilyNode>,
+    person_index: HashMap<u64, PersonId>,
+    family_index: HashMap<u64, FamilyId>,
+}
+
+/// Pushes a value unless the list already has it
+fn push_unique<T: PartialEq>(list: &mut Vec<T>, value: T) {
+    if !list.contains(&value) {
+        list.push(value);
+    }
+}
+
+impl FamilyGraph {
+    pub fn new(registry: &RecordRegistry) -> Self {
+        let mut graph = FamilyGraph::default();
+        // Sorted so the indices don't depend on the hash order
+        let mut records: Vec<(&u64, &RecordRc)> = registry.iter().collect();
+        records.sort_by_key(|(key, _)| **key);
+        for (key, record) in &records {
+            match record.borrow().rtype.as_str() {
+                "INDI" => {
+                    graph.person_index.insert(**key, PersonId(graph.persons.len() as u32));
+                    graph.persons.push(PersonNode { key: **key, ..Default::default() });
+                },
+                "FAM" => {
+                    graph.family_index.insert(**key, FamilyId(graph.families.len() as u32));
+                    graph.families.push(FamilyNode { key: **key, ..Default::default() });
+                },
+                _ => (),
+            }
+        }
+        // The family pointers of the individuals come first, so
+        // that their order decides which family gives the parents
+        for (key, record) in &records {
+            let record = record.borrow();
+            let person = match graph.person_index.get(key) {
+                Some(person) => *person,
+                None => continue,
+            };
+            for (tag, is_child) in [("FAMC", true), ("FAMS", false)].iter() {
+                let families: Vec<FamilyId> = record.pointers(tag).iter()
+                    .filter_map(|x| graph.family(xref_key(x)))
+                    .collect();
+                for family in families {
+                    let node = &mut graph.persons[person.0 as usize];
+                    match is_child {
+                        true => push_unique(&mut node.parents, family),
+                        false => push_unique(&mut node.spouses, family),
+                    }
+                }
+            }
+        }
+        for (key, record) in &records {
+            let record = record.borrow();
+            let family = match graph.family_index.get(key) {
+                Some(family) => *family,
+                None => continue,
+            };
+            let person = |tag: &str| record.pointers(tag).first().and_then(|x| graph.person(xref_key(x)));
+            let (husband, wife) = (person("HUSB"), person("WIFE"));
+            let children: Vec<PersonId> = record.pointers("CHIL").iter()
+                .filter_map(|x| graph.person(xref_key(x)))
+                .collect();
+            for partner in husband.iter().chain(wife.iter()) {
+                push_unique(&mut graph.persons[partner.0 as usize].spouses, family);
+            }
+            for child in &children {
+                push_unique(&mut graph.persons[child.0 as usize].parents, family);
+            }
+            let node = &mut graph.families[family.0 as usize];
+            node.husband = husband;
+            node.wife = wife;
+            for child in children {
+                push_unique(&mut node.children, child);
+            }
+        }
+        // Children whose family doesn't list them
+        for (index, person) in graph.persons.iter().enumerate() {
+            for family in &person.parents {
+                push_unique(&mut graph.families[family.0 as usize].children, PersonId(index as u32));
+            }
+        }
+        graph
+    }
+
+    fn node(&self, person: PersonId) -> &PersonNode {
+        &self.persons[person.0 as usize]
+    }
+
+    fn family_node(&self, family: FamilyId) -> &FamilyNode {
+        &self.families[family.0 as usize]
+    }
+
+    /// Individual with the given registry key
+    pub fn person(&self, key: u64) -> Option<PersonId> {
+        self.person_index.get(&key).copied()
+    }
+
+    /// Family with the given registry key
+    pub fn family(&self, key: u64) -> Option<FamilyId> {
+        self.family_index.get(&key).copied()
+    }
+
+    pub fn person_of(&self, record: &Record) -> Option<PersonId> {
+        self.person(xref_key(&record.xref))
+    }
+
+    pub fn family_of(&self, record: &Record) -> Option<FamilyId> {
+        self.family(xref_key(&record.xref))
+    }
+
+    /// Registry key of an individual
+    pub fn key(&self, person: PersonId) -> u64 {
+        self.node(person).key
+    }
+
+    /// Registry key of a family
+    pub fn family_key(&self, family: FamilyId) -> u64 {
+        self.family_node(family).key
+    }
+
+    /// Record of an individual
+    pub fn record(&self, registry: &RecordRegistry, person: PersonId) -> Option<RecordRc> {
+        registry.get(&self.key(person)).cloned()
+    }
+
+    /// Record of a family
+    pub fn family_record(&self, registry: &RecordRegistry, family: FamilyId) -> Option<RecordRc> {
+        registry.get(&self.family_key(family)).cloned()
+    }
+
+    pub fn persons(&self) -> impl Iterator<Item = PersonId> {
+        (0..self.persons.len() as u32).map(PersonId)
+    }
+
+    pub fn families(&self) -> impl Iterator<Item = FamilyId> {
+        (0..self.families.len() as u32).map(FamilyId)
+    }
+
+    pub fn husband(&self, family: FamilyId) -> Option<PersonId> {
+        self.family_node(family).husband
+    }
+
+    pub fn wife(&self, family: FamilyId) -> Option<PersonId> {
+        self.family_node(family).wife
+    }
+
+    /// Husband then wife of a family, when known
+    pub fn partners(&self, family: FamilyId) -> Vec<PersonId> {
+        let node = self.family_node(family);
+        node.husband.iter().chain(node.wife.iter()).copied().collect()
+    }
+
+    /// Partner of the person in the family
+    pub fn partner(&self, family: FamilyId, person: PersonId) -> Option<PersonId> {
+        let node = self.family_node(family);
+        match node.husband {
+            Some(husband) if husband == person => node.wife,
+            _ => node.husband,
+        }
+    }
+
+    pub fn family_children(&self, family: FamilyId) -> &[PersonId] {
+        &self.family_node(family).children
+    }
+
+    /// Families the person is a child of
+    pub fn parent_families(&self, person: PersonId) -> &[FamilyId] {
+        &self.node(person).parents
+    }
+
+    /// Families the person is a partner in
+    pub fn spouse_families(&self, person: PersonId) -> &[FamilyId] {
+        &self.node(person).spouses
+    }
+
+    /// Father given by the first family of the person
+    pub fn father(&self, person: PersonId) -> Option<PersonId> {
+        self.parent_families(person).first().and_then(|f| self.husband(*f))
+    }
+
+    /// Mother given by the first family of the person
+    pub fn mother(&self, person: PersonId) -> Option<PersonId> {
+        self.parent_families(person).first().and_then(|f| self.wife(*f))
+    }
+
+    /// Parents from all the families of the person
+    pub fn parents(&self, person: PersonId) -> Vec<PersonId> {
+        let mut parents = vec![];
+        for family in self.parent_families(person) {
+            for parent in self.partners(*family) {
+                push_unique(&mut parents, parent);
+            }
+        }
+        parents
+    }
+
+    pub fn spouses(&self, person: PersonId) -> Vec<PersonId> {
+        let mut spouses = vec![];
+        for family in self.spouse_families(person) {
+            if let Some(spouse) = self.partner(*family, person) {
+                push_unique(&mut spouses, spouse);
+            }
+        }
+        spouses
+    }
+
+    /// Children from all the families of the person
+    pub fn children(&self, person: PersonId) -> Vec<PersonId> {
+        let mut children = vec![];
+        for family in self.spouse_families(person) {
+            for child in self.family_children(*family) {
+                push_unique(&mut children, *child);
+            }
+        }
+        children
+    }
+
+    /// Other children of the families the person is a child of
+    pub fn siblings(&self, person: PersonId) -> Vec<PersonId> {
+        let mut siblings = vec![];
+        for family in self.parent_families(person) {
+            for child in self.family_children(*family).iter().filter(|c| **c != person) {
+                push_unique(&mut siblings, *child);
+            }
+        }
+        siblings
+    }
+
+    /// Children the parents of the person had in other
+    /// families, who aren't siblings too
+    pub fn half_siblings(&self, person: PersonId) -> Vec<PersonId> {
+        let siblings = self.siblings(person);
+        let own = self.parent_families(person);
+        let mut half = vec![];
+        for parent in self.parents(person) {
+            for family in self.spouse_families(parent).iter().filter(|f| !own.contains(f)) {
+                for child in self.family_children(*family) {
+                    if *child != person && !siblings.contains(child) {
+                        push_unique(&mut half, *child);
+                    }
+                }
+            }
+        }
+        half
+    }
+
+    /// Ancestors of the person generation by generation, up to
+    /// `generations` generations above them. Each is reached once.
+    pub fn ancestors(&self, person: PersonId, generations: usize) -> Walk<'_> {
+        Walk::new(self, person, generations, FamilyGraph::parents)
+    }
+
+    /// Descendants of the person generation by generation, down
+    /// to `generations` generations below them. Each is reached once.
+    pub fn descendants(&self, person: PersonId, generations: usize) -> Walk<'_> {
+        Walk::new(self, person, generations, FamilyGraph::children)
+    }
+}
+
+/// Breadth first walk of the graph yielding the persons reached
+/// with their distance to the start, which isn't yielded. Every
+/// person is visited once, which makes it safe on cycles.
+pub struct Walk<'a> {
+    graph: &'a FamilyGraph,
+    next: fn(&FamilyGraph, PersonId) -> Vec<PersonId>,
+    queue: VecDeque<(PersonId, usize)>,
+    seen: HashSet<PersonId>,
+    generations: usize,
+}
+
+impl<'a> Walk<'a> {
+    fn new(graph: &'a FamilyGraph, start: PersonId, generations: usize, next: fn(&FamilyGraph, PersonId) -> Vec<PersonId>) -> Self {
+        Walk {
+            graph,
+            next,
+            queue: std::iter::once((start, 0)).collect(),
+            seen: std::iter::once(start).collect(),
+            generations,
+        }
+    }
+}
+
+impl<'a> Iterator for Walk<'a> {
+    type Item = (PersonId, usize);
+
+    fn next(&mut self) -> Option<(PersonId, usize)> {
+        loop {
+            let (person, generation) = self.queue.pop_front()?;
+            if generation < self.generations {
+                for relative in (self.next)(self.graph, person) {
+                    if self.seen.insert(relative) {
+                        self.queue.push_back((relative, generation + 1));
+                    }
+                }
+            }
+            if generation > 0 {
+                return Some((person, generation));
+            }
+        }
+    }
+}
@@ -0,0 +1,125 @@
+//! Stable record identifiers: Record Identification Numbers
+//! (`RIN`) and unique identifiers (`_UID`) as written by PAF.
+
+use crate::gedcom::writer::record_rank;
+use crate::gedcom::{xref_key, RecordRc, RecordRegistry};
+use std::collections::hash_map::RandomState;
+use std::collections::HashSet;
+use std::hash::{BuildHasher, Hash, Hasher};
+use std::time::SystemTime;
+
+/// Length of the hexadecimal part of an `_UID`, without
+/// its checksum
+const UID_LENGTH: usize = 32;
+
+/// Checksum of the 16 bytes of an `_UID`, as computed
+/// by PAF: two running sums of the bytes modulo 256
+fn uid_checksum(bytes: &[u8]) -> String {
+    let (a, b) = bytes.iter().fold((0u8, 0u8), |(a, b), byte| {
+        let a = a.wrapping_add(*byte);
+        (a, b.wrapping_add(a))
+    });
+    format!("{:02X}{:02X}", a, b)
+}
+
+fn hex_bytes(hex: &str) -> Option<Vec<u8>> {
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| hex.get(i..i + 2).and_then(|b| u8::from_str_radix(b, 16).ok()))
+        .collect()
+}
+
+/// Generates a new random `_UID`: 32 hexadecimal digits
+/// followed by the 4 digits of their checksum
+pub fn generate_uid() -> String {
+    let random = |salt: u64| {
+        let mut hasher = RandomState::new().build_hasher();
+        salt.hash(&mut hasher);
+        SystemTime::now().hash(&mut hasher);
+        hasher.finish()
+    };
+    let bytes: Vec<u8> = [random(0), random(1)].iter()
+        .flat_map(|n| n.to_be_bytes().to_vec())
+        .collect();
+    let hex: String = bytes.iter().map(|b| format!("{:02X}", b)).collect();
+    format!("{}{}", hex, uid_checksum(&bytes))
+}
+
+/// Checks the format of an `_UID`, the checksum is
+/// verified only if present
+pub fn is_valid_uid(uid: &str) -> bool {
+    let uid = uid.trim();
+    let digits = uid.chars().all(|c| c.is_ascii_hexdigit());
+    match uid.len() {
+        UID_LENGTH => digits,
+        len if len == UID_LENGTH + 4 && digits => {
+            hex_bytes(&uid[..UID_LENGTH])
+                .map_or(false, |b| uid_checksum(&b).eq_ignore_ascii_case(&uid[UID_LENGTH..]))
+        },
+        _ => false,
+    }
+}
+
+/// The `_UID` in the form of a 7.0 `UID`: a UUID made of its
+/// first 32 hexadecimal digits, the checksum being dropped
+pub fn uid_to_uuid(uid: &str) -> Option<String> {
+    let uid = uid.trim();
+    if !is_valid_uid(uid) {
+        return None;
+    }
+    let hex = uid[..UID_LENGTH].to_lowercase();
+    Some(format!("{}-{}-{}-{}-{}", &hex[..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..]))
+}
+
+/// The `_UID` standing for a 7.0 `UID`, when it is a UUID:
+/// its 32 hexadecimal digits followed by their checksum
+pub fn uuid_to_uid(uuid: &str) -> Option<String> {
+    let hex: String = uuid.trim().chars().filter(|c| *c != '-').collect::<String>().to_uppercase();
+    if hex.len() != UID_LENGTH {
+        return None;
+    }
+    let bytes = hex_bytes(&hex)?;
+    Some(format!("{}{}", hex, uid_checksum(&bytes)))
+}
+
+/// First RIN not used by any record of the registry
+pub fn next_rin(registry: &RecordRegistry) -> u64 {
+    registry.values().map(|r| r.borrow().rin).max().unwrap_or(0) + 1
+}
+
+/// First free cross-reference with the given prefix,
+/// following the highest one in use (`I13` after `I12`)
+pub fn next_xref(registry: &RecordRegistry, prefix: &str) -> String {
+    let last = registry.values()
+        .filter_map(|r| r.borrow().xref.strip_prefix(prefix).and_then(|n| n.parse::<u64>().ok()))
+        .max()
+        .unwrap_or(0);
+    (last + 1..)
+        .map(|number| format!("{}{}", prefix, number))
+        .find(|xref| !registry.contains_key(&xref_key(xref)))
+        .unwrap()
+}
+
+/// Gives a RIN and an `_UID` to every record lacking them.
+/// Imported RINs are kept unless two records share one, in
+/// which case the record met last gets a new one.
+pub fn assign_identifiers(registry: &RecordRegistry) {
+    let mut records: Vec<&RecordRc> = registry.values().collect();
+    records.sort_by_key(|r| {
+        let r = r.borrow();
+        (record_rank(&r.rtype), r.id, r.xref.clone())
+    });
+    let mut used: HashSet<u64> = HashSet::new();
+    let mut next = next_rin(registry);
+    for record in records {
+        let mut record = record.borrow_mut();
+        if record.rin == 0 || !used.insert(record.rin) {
+            record.rin = next;
+            used.insert(next);
+            next += 1;
+        }
+        if !record.uid.as_deref().map_or(false, is_valid_uid) {
+            record.uid = Some(generate_uid());
+        }
+    }
+}
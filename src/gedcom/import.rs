@@ -0,0 +1,321 @@
+//! Import of a GED file on a worker thread. The file is parsed
+//! into plain records, which can cross threads, the progress being
+//! reported along the way. The registry is built back on the
+//! thread owning it with [`Imported::into_registry`]. GEDCOM 7
+//! files are converted to 5.5.1 on the way, Gramps files and
+//! JSON documents read into the same structures.
+
+use crate::gedcom::gedex::GedEx;
+use crate::gedcom::{csv, gedcom7, gedzip, gramps, jsonld, GedParser, GedVersion, ParseError, Parser, Record, RecordRegistry, Tag};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+
+/// Lines or records handled between two progress reports
+const PROGRESS_STEP: usize = 500;
+
+/// Share of the line parsing in the overall progress,
+/// the rest going to the building of the records
+const PARSING_SHARE: f64 = 0.8;
+
+/// Level 0 structures expected without a cross-reference
+const UNIDENTIFIED_TAGS: [&str; 2] = ["HEAD", "TRLR"];
+
+#[derive(Debug,Clone,Copy,Default,PartialEq)]
+pub struct ImportProgress {
+    pub lines_read: usize,
+    pub total_lines: usize,
+    pub records_built: usize,
+    pub total_records: usize,
+}
+
+impl ImportProgress {
+    /// Overall progress, from 0 to 1
+    pub fn fraction(&self) -> f64 {
+        let ratio = |done: usize, total: usize| match total {
+            0 => 0.0,
+            total => done as f64 / total as f64,
+        };
+        ratio(self.lines_read, self.total_lines) * PARSING_SHARE
+            + ratio(self.records_built, self.total_records) * (1.0 - PARSING_SHARE)
+    }
+
+    pub fn what(&self) -> String {
+        format!("{} of {} lines read, {} records built.", self.lines_read, self.total_lines, self.records_built)
+    }
+}
+
+/// Problem met while importing, the import going on anyway
+#[derive(Debug,Clone,PartialEq)]
+pub enum Diagnostic {
+    /// Line that isn't valid GED, left out
+    UnparsedLine { number: usize, line: String },
+    /// Level 0 structure without cross-reference, left out
+    Unidentified { tag: String },
+    /// Encoding other than UTF-8 declared by a GEDCOM 7 file
+    Encoding { declared: String },
+    /// Line that isn't valid UTF-8, read with its invalid
+    /// bytes replaced
+    InvalidUtf8 { number: usize },
+    /// Pointer of an appended record to a record missing from
+    /// the file, dropped (see [`batch::append`](crate::gedcom::batch::append))
+    DanglingPointer { xref: String, tag: String, pointer: String },
+}
+
+impl Diagnostic {
+    /// Number of the line the problem is on, when known
+    pub fn line(&self) -> Option<usize> {
+        match self {
+            Diagnostic::UnparsedLine { number, .. } | Diagnostic::InvalidUtf8 { number } => Some(*number),
+            Diagnostic::Unidentified { .. } | Diagnostic::Encoding { .. } | Diagnostic::DanglingPointer { .. } => None,
+        }
+    }
+
+    pub fn what(&self) -> String {
+        match self {
+            Diagnostic::UnparsedLine { line, .. } => format!("Line not understood, left out: '{}'", line.trim()),
+            Diagnostic::Unidentified { tag } => format!("{} record without cross-reference, left out.", tag),
+            Diagnostic::Encoding { declared } => format!("GEDCOM 7 files are UTF-8 only, the {} encoding declared was ignored.", declared),
+            Diagnostic::InvalidUtf8 { .. } => String::from("Line not valid UTF-8, its invalid characters were replaced."),
+            Diagnostic::DanglingPointer { xref, tag, pointer } =>
+                format!("{} of @{}@ points to @{}@, which isn't in the file, left out.", tag, xref, pointer),
+        }
+    }
+}
+
+/// Format of an imported file
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum ImportFormat {
+    Ged(GedVersion),
+    Gramps,
+    Json,
+}
+
+impl Default for ImportFormat {
+    fn default() -> Self {
+        ImportFormat::Ged(GedVersion::default())
+    }
+}
+
+impl ImportFormat {
+    pub fn label(&self) -> String {
+        match self {
+            ImportFormat::Ged(version) => format!("GEDCOM {}", version.label()),
+            ImportFormat::Gramps => String::from("Gramps XML"),
+            ImportFormat::Json => String::from("JSON-LD"),
+        }
+    }
+}
+
+/// Counts and diagnostics of a finished import
+#[derive(Debug,Clone,Default,PartialEq)]
+pub struct ImportSummary {
+    /// Lines read, for the GED files
+    pub lines: usize,
+    pub format: ImportFormat,
+    /// Records imported by type (`INDI`, `FAM`...)
+    pub counts: BTreeMap<String, usize>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl ImportSummary {
+    pub fn records(&self) -> usize {
+        self.counts.values().sum()
+    }
+
+    /// Format of the file, with its length for the GED files
+    pub fn origin(&self) -> String {
+        match self.format {
+            ImportFormat::Ged(_) => format!("{}, {} lines", self.format.label(), self.lines),
+            ImportFormat::Gramps | ImportFormat::Json => self.format.label(),
+        }
+    }
+}
+
+/// Records read from a file along with the summary of the import
+#[derive(Debug,Clone,Default)]
+pub struct Imported {
+    pub records: Vec<Record>,
+    pub summary: ImportSummary,
+}
+
+impl Imported {
+    /// Builds the registry of the records, on the
+    /// thread that is going to own it
+    pub fn into_registry(self) -> (RecordRegistry, ImportSummary) {
+        (GedParser::registry_of(self.records), self.summary)
+    }
+}
+
+/// What the worker thread sends to the one waiting for the import
+#[derive(Debug)]
+pub enum ImportEvent {
+    Progress(ImportProgress),
+    Finished(Result<Imported, ParseError>),
+}
+
+/// Reads and parses a GED file, calling `progress` now and then.
+/// Stops with [`ParseError::Cancelled`] once `cancel` gets set.
+pub fn import<F: FnMut(ImportProgress)>(file: &std::fs::File, cancel: &AtomicBool, progress: F) -> Result<Imported, ParseError> {
+    let (_, content, invalid) = GedParser::read_lines_lossy(file);
+    let mut imported = import_lines(content, cancel, progress)?;
+    let invalid = invalid.into_iter().map(|number| Diagnostic::InvalidUtf8 { number });
+    imported.summary.diagnostics.splice(0..0, invalid);
+    Ok(imported)
+}
+
+/// Same as [`import`] for a [GEDZIP package](gedzip), its media
+/// files being extracted next to it
+pub fn import_package<F: FnMut(ImportProgress)>(path: &Path, cancel: &AtomicBool, progress: F) -> Result<Imported, ParseError> {
+    let content = gedzip::read(path)?;
+    let mut imported = import_lines(content, cancel, progress)?;
+    gedzip::relocate_media(&mut imported.records, &gedzip::media_dir(path));
+    Ok(imported)
+}
+
+/// Same as [`import`] for a [Gramps file](gramps)
+pub fn import_gramps<F: FnMut(ImportProgress)>(file: &std::fs::File, cancel: &AtomicBool, mut progress: F) -> Result<Imported, ParseError> {
+    let mut state = ImportProgress::default();
+    progress(state);
+    let tags = gramps::read_tags(file)?;
+    let summary = ImportSummary { format: ImportFormat::Gramps, ..Default::default() };
+    build_records(tags, summary, &mut state, cancel, progress)
+}
+
+/// Same as [`import`] for a [JSON document](jsonld)
+pub fn import_json<F: FnMut(ImportProgress)>(file: &std::fs::File, cancel: &AtomicBool, mut progress: F) -> Result<Imported, ParseError> {
+    let mut state = ImportProgress::default();
+    progress(state);
+    let tags = jsonld::read_tags(file)?;
+    let summary = ImportSummary { format: ImportFormat::Json, ..Default::default() };
+    build_records(tags, summary, &mut state, cancel, progress)
+}
+
+/// Imports the file with [`import`], [`import_package`],
+/// [`import_gramps`] or [`import_json`] depending on its extension.
+/// CSV files are refused, their columns having to be chosen
+/// first (see [`csv::read`]).
+pub fn import_path<F: FnMut(ImportProgress)>(path: &Path, cancel: &AtomicBool, progress: F) -> Result<Imported, ParseError> {
+    if csv::is_csv(path) {
+        return Err(ParseError::Runtime(String::from(
+            "CSV files can't be read as a tree, only GEDCOM (.ged), GEDZIP (.gdz, .zip), \
+            Gramps XML (.gramps) and JSON-LD (.json, .jsonld) files. \
+            Import CSV files with File > Import CSV...")));
+    }
+    if gedzip::is_package(path) {
+        return import_package(path, cancel, progress);
+    }
+    let file = std::fs::File::open(path)?;
+    if gramps::is_gramps(path) {
+        import_gramps(&file, cancel, progress)
+    } else if jsonld::is_json(path) {
+        import_json(&file, cancel, progress)
+    } else {
+        import(&file, cancel, progress)
+    }
+}
+
+/// Parses the lines of a GED file into records
+fn import_lines<F: FnMut(ImportProgress)>(content: Vec<String>, cancel: &AtomicBool, mut progress: F) -> Result<Imported, ParseError> {
+    let mut state = ImportProgress { total_lines: content.len(), ..Default::default() };
+    let mut summary = ImportSummary::default();
+    progress(state);
+
+    let tags = GedEx::new(content).parse_with(|number, line, parsed| {
+        if !parsed && !line.trim().is_empty() {
+            summary.diagnostics.push(Diagnostic::UnparsedLine { number, line: line.to_owned() });
+        }
+        state.lines_read = number;
+        if number % PROGRESS_STEP == 0 {
+            progress(state);
+        }
+        !cancel.load(Ordering::Relaxed)
+    })?;
+    summary.lines = state.lines_read;
+    let version = GedVersion::of(&tags);
+    summary.format = ImportFormat::Ged(version);
+    let tags = match version {
+        GedVersion::V7 => {
+            let declared = tags.iter()
+                .find(|t| t.name == "HEAD")
+                .and_then(|h| h.value("CHAR"))
+                .filter(|c| !c.trim().eq_ignore_ascii_case("UTF-8"));
+            if let Some(declared) = declared {
+                summary.diagnostics.push(Diagnostic::Encoding { declared });
+            }
+            gedcom7::to_v551(tags)
+        },
+        GedVersion::V551 => tags,
+    };
+    build_records(tags, summary, &mut state, cancel, progress)
+}
+
+/// Builds the records of the level 0 structures read
+fn build_records<F: FnMut(ImportProgress)>(
+    tags: Vec<Tag>,
+    mut summary: ImportSummary,
+    state: &mut ImportProgress,
+    cancel: &AtomicBool,
+    mut progress: F,
+) -> Result<Imported, ParseError> {
+    state.total_records = tags.len();
+    let mut records = Vec::with_capacity(tags.len());
+    for (i, tag) in tags.iter().enumerate() {
+        if cancel.load(Ordering::Relaxed) {
+            return Err(ParseError::Cancelled);
+        }
+        match tag.xref {
+            Some(_) => {
+                let record = Record::from_tag(tag);
+                *summary.counts.entry(record.rtype.clone()).or_default() += 1;
+                records.push(record);
+            },
+            None if UNIDENTIFIED_TAGS.contains(&tag.name.as_str()) => (),
+            None => summary.diagnostics.push(Diagnostic::Unidentified { tag: tag.name.clone() }),
+        }
+        state.records_built = i + 1;
+        if state.records_built % PROGRESS_STEP == 0 {
+            progress(*state);
+        }
+    }
+    progress(*state);
+    Ok(Imported { records, summary })
+}
+
+/// Import running on a worker thread
+pub struct ImportHandle {
+    cancel: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl ImportHandle {
+    /// Asks the worker to stop, which it does at the next line
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.load(Ordering::Relaxed)
+    }
+
+    /// Waits for the worker to be done
+    pub fn join(mut self) {
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Imports the file on a new thread, every progress report and
+/// finally the result being handed to `events` on that thread.
+pub fn spawn<F: Fn(ImportEvent) + Send + 'static>(path: PathBuf, events: F) -> ImportHandle {
+    let cancel = Arc::new(AtomicBool::new(false));
+    let flag = cancel.clone();
+    let thread = std::thread::spawn(move || {
+        let result = import_path(&path, &flag, |p| events(ImportEvent::Progress(p)));
+        events(ImportEvent::Finished(result));
+    });
+    ImportHandle { cancel, thread: Some(thread) }
+}
@@ -0,0 +1,103 @@
+//! Alphabetical index of the individuals, sorted by surname then
+//! given names, as shown by the name list of the main screen
+
+use crate::gedcom::RecordRegistry;
+use std::ops::Range;
+
+/// Individual of the index
+#[derive(Debug,Clone)]
+pub struct IndexEntry {
+    /// Key of the record in the registry
+    pub key: u64,
+    pub surname: String,
+    pub given: String,
+    pub lifespan: String,
+    /// Uppercase "SURNAME, GIVEN" the entries are sorted by
+    sort_key: String,
+}
+
+impl IndexEntry {
+    /// Name the way the list shows it, "Surname, Given"
+    pub fn list_name(&self) -> String {
+        match (self.surname.is_empty(), self.given.is_empty()) {
+            (true, true) => String::from("(no name)"),
+            (true, false) => format!(", {}", self.given),
+            (false, true) => self.surname.clone(),
+            (false, false) => format!("{}, {}", self.surname, self.given),
+        }
+    }
+}
+
+/// Text typed by the user, in the form of the sort keys
+fn sort_key(surname: &str, given: &str) -> String {
+    format!("{}, {}", surname.trim(), given.trim()).to_uppercase()
+}
+
+/// Every individual of a registry in alphabetical order,
+/// the ones without surname coming first
+#[derive(Debug,Clone,Default)]
+pub struct NameIndex {
+    pub entries: Vec<IndexEntry>,
+}
+
+impl NameIndex {
+    pub fn new(registry: &RecordRegistry) -> Self {
+        let mut entries: Vec<IndexEntry> = registry.iter()
+            .filter(|(_, r)| r.borrow().rtype == "INDI")
+            .map(|(key, record)| {
+                let record = record.borrow();
+                let (surname, given) = (record.surname().trim().to_owned(), record.given_names().trim().to_owned());
+                IndexEntry {
+                    key: *key,
+                    sort_key: sort_key(&surname, &given),
+                    surname,
+                    given,
+                    lifespan: record.lifespan(),
+                }
+            })
+            .collect();
+        entries.sort_by(|a, b| (&a.sort_key, &a.lifespan, a.key).cmp(&(&b.sort_key, &b.lifespan, b.key)));
+        Self { entries }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Surnames along with the range of their entries,
+    /// in the order of the index
+    pub fn surnames(&self) -> Vec<(&str, Range<usize>)> {
+        let mut groups: Vec<(&str, Range<usize>)> = vec![];
+        for (i, entry) in self.entries.iter().enumerate() {
+            match groups.last_mut() {
+                Some((surname, range)) if surname.to_uppercase() == entry.surname.to_uppercase() => range.end = i + 1,
+                _ => groups.push((&entry.surname, i..i + 1)),
+            }
+        }
+        groups
+    }
+
+    /// Entry the list jumps to when the user types a name: the first
+    /// one at or after the text, or the last one when the text comes
+    /// after every name. "smi" or "Smith, J" are both understood.
+    pub fn find(&self, typed: &str) -> Option<usize> {
+        if self.entries.is_empty() {
+            return None;
+        }
+        let typed = match typed.split_once(',') {
+            Some((surname, given)) => sort_key(surname, given),
+            None => typed.trim().to_uppercase(),
+        };
+        let position = self.entries.partition_point(|e| e.sort_key.as_str() < typed.as_str());
+        Some(position.min(self.entries.len() - 1))
+    }
+
+    /// Position of the individual in the index
+    pub fn position(&self, key: u64) -> Option<usize> {
+        self.entries.iter().position(|e| e.key == key)
+    }
+}
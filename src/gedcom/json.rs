@@ -0,0 +1,381 @@
+//! JSON values, built for the machine readable outputs and
+//! written either compact or indented, or read from a text.
+
+use crate::gedcom::ParseError;
+use std::fmt;
+
+/// Deepest nesting of arrays and objects read, deeper
+/// texts being rejected rather than overflowing the stack
+const MAX_DEPTH: usize = 256;
+
+#[derive(Debug,Clone,PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    /// Members in the order they were added
+    Object(Vec<(String, Json)>),
+}
+
+/// Escapes the quotes, backslashes and control characters
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len() + 2);
+    for c in text.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+impl Json {
+    /// Empty object, filled with [`with`](Json::with)
+    pub fn object() -> Self {
+        Json::Object(vec![])
+    }
+
+    /// Adds a member to an object, other values being left as they are
+    pub fn with<V: Into<Json>>(mut self, key: &str, value: V) -> Self {
+        if let Json::Object(members) = &mut self {
+            members.push((key.to_owned(), value.into()));
+        }
+        self
+    }
+
+    /// Value of a member of an object
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(members) => members.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Json::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Json::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    /// Items of an array, none for the other values
+    pub fn items(&self) -> &[Json] {
+        match self {
+            Json::Array(items) => items,
+            _ => &[],
+        }
+    }
+
+    /// Reads a JSON text holding a single value
+    pub fn parse(text: &str) -> Result<Json, ParseError> {
+        let mut reader = Reader { chars: text.chars().collect(), position: 0, depth: 0 };
+        let value = reader.value()?;
+        match reader.skip_spaces() {
+            None => Ok(value),
+            Some(c) => Err(reader.error(&format!("unexpected '{}' after the value", c))),
+        }
+    }
+
+    /// Text indented by two spaces, one member or item per line
+    pub fn pretty(&self) -> String {
+        let mut text = String::new();
+        self.write(&mut text, Some(0));
+        text
+    }
+
+    /// Appends the value to the text, indented from
+    /// the given level if any
+    fn write(&self, text: &mut String, level: Option<usize>) {
+        let (open, close, items): (char, char, Vec<(Option<&str>, &Json)>) = match self {
+            Json::Null => return text.push_str("null"),
+            Json::Bool(b) => return text.push_str(if *b { "true" } else { "false" }),
+            Json::Number(n) if !n.is_finite() => return text.push_str("null"),
+            // Whole numbers are written without decimals
+            Json::Number(n) if n.fract() == 0.0 && n.abs() < 1e15 => return text.push_str(&format!("{}", *n as i64)),
+            Json::Number(n) => return text.push_str(&n.to_string()),
+            Json::String(s) => return text.push_str(&format!("\"{}\"", escape(s))),
+            Json::Array(items) => ('[', ']', items.iter().map(|i| (None, i)).collect()),
+            Json::Object(members) => ('{', '}', members.iter().map(|(k, v)| (Some(k.as_str()), v)).collect()),
+        };
+        text.push(open);
+        let inner = level.map(|l| l + 1);
+        for (i, (key, value)) in items.iter().enumerate() {
+            if i > 0 {
+                text.push(',');
+            }
+            if let Some(inner) = inner {
+                text.push('\n');
+                text.push_str(&"  ".repeat(inner));
+            }
+            if let Some(key) = key {
+                text.push_str(&format!("\"{}\":", escape(key)));
+                if inner.is_some() {
+                    text.push(' ');
+                }
+            }
+            value.write(text, inner);
+        }
+        if let (Some(level), false) = (level, items.is_empty()) {
+            text.push('\n');
+            text.push_str(&"  ".repeat(level));
+        }
+        text.push(close);
+    }
+}
+
+/// Recursive descent through the characters of a text
+struct Reader {
+    chars: Vec<char>,
+    position: usize,
+    /// Arrays and objects the reader is in
+    depth: usize,
+}
+
+impl Reader {
+    fn error(&self, what: &str) -> ParseError {
+        ParseError::Runtime(format!("Malformed JSON at character {}: {}", self.position, what))
+    }
+
+    /// Skips the white space, returning the next character
+    fn skip_spaces(&mut self) -> Option<char> {
+        while let Some(c) = self.chars.get(self.position) {
+            if !c.is_whitespace() {
+                return Some(*c);
+            }
+            self.position += 1;
+        }
+        None
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.chars.get(self.position).copied();
+        self.position += 1;
+        c
+    }
+
+    fn expect(&mut self, word: &str) -> Result<(), ParseError> {
+        for expected in word.chars() {
+            if self.next() != Some(expected) {
+                return Err(self.error(&format!("expected '{}'", word)));
+            }
+        }
+        Ok(())
+    }
+
+    fn value(&mut self) -> Result<Json, ParseError> {
+        match self.skip_spaces() {
+            Some('{') => self.nested(Self::object),
+            Some('[') => self.nested(Self::array),
+            Some('"') => self.string().map(Json::String),
+            Some('t') => self.expect("true").map(|()| Json::Bool(true)),
+            Some('f') => self.expect("false").map(|()| Json::Bool(false)),
+            Some('n') => self.expect("null").map(|()| Json::Null),
+            Some(c) if c == '-' || c.is_ascii_digit() => self.number(),
+            Some(c) => Err(self.error(&format!("unexpected '{}'", c))),
+            None => Err(self.error("missing value")),
+        }
+    }
+
+    /// Reads an array or an object one level deeper
+    fn nested<F: FnOnce(&mut Self) -> Result<Json, ParseError>>(&mut self, read: F) -> Result<Json, ParseError> {
+        if self.depth >= MAX_DEPTH {
+            return Err(self.error(&format!("values nested deeper than {} levels", MAX_DEPTH)));
+        }
+        self.depth += 1;
+        let value = read(self);
+        self.depth -= 1;
+        value
+    }
+
+    fn number(&mut self) -> Result<Json, ParseError> {
+        let start = self.position;
+        while let Some(c) = self.chars.get(self.position) {
+            if !(c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E')) {
+                break;
+            }
+            self.position += 1;
+        }
+        let text: String = self.chars[start..self.position].iter().collect();
+        text.parse().map(Json::Number).map_err(|_| self.error(&format!("invalid number '{}'", text)))
+    }
+
+    /// Four hexadecimal digits of a `\u` escape
+    fn code_unit(&mut self) -> Result<u32, ParseError> {
+        let digits: String = (0..4).filter_map(|_| self.next()).collect();
+        u32::from_str_radix(&digits, 16).map_err(|_| self.error("invalid \\u escape"))
+    }
+
+    fn string(&mut self) -> Result<String, ParseError> {
+        self.next();
+        let mut text = String::new();
+        loop {
+            match self.next() {
+                Some('"') => return Ok(text),
+                Some('\\') => {
+                    let c = match self.next() {
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{c}',
+                        Some('u') => {
+                            let mut code = self.code_unit()?;
+                            // Characters out of the basic plane come as surrogate pairs
+                            if (0xd800..0xdc00).contains(&code) {
+                                self.expect("\\u")?;
+                                let low = self.code_unit()?;
+                                if !(0xdc00..0xe000).contains(&low) {
+                                    return Err(self.error("invalid surrogate pair"));
+                                }
+                                code = 0x10000 + ((code - 0xd800) << 10) + (low - 0xdc00);
+                            }
+                            std::char::from_u32(code).unwrap_or('\u{fffd}')
+                        },
+                        Some(c) => c,
+                        None => break,
+                    };
+                    text.push(c);
+                },
+                Some(c) => text.push(c),
+                None => break,
+            }
+        }
+        Err(self.error("unterminated string"))
+    }
+
+    /// Items separated by commas up to the closing character
+    fn sequence<F: FnMut(&mut Self) -> Result<(), ParseError>>(&mut self, close: char, mut item: F) -> Result<(), ParseError> {
+        self.next();
+        if self.skip_spaces() == Some(close) {
+            self.next();
+            return Ok(());
+        }
+        loop {
+            item(self)?;
+            match self.skip_spaces() {
+                Some(',') => self.position += 1,
+                Some(c) if c == close => {
+                    self.position += 1;
+                    return Ok(());
+                },
+                _ => return Err(self.error(&format!("expected ',' or '{}'", close))),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Json, ParseError> {
+        let mut items = vec![];
+        self.sequence(']', |reader| {
+            items.push(reader.value()?);
+            Ok(())
+        })?;
+        Ok(Json::Array(items))
+    }
+
+    fn object(&mut self) -> Result<Json, ParseError> {
+        let mut members = vec![];
+        self.sequence('}', |reader| {
+            if reader.skip_spaces() != Some('"') {
+                return Err(reader.error("expected a member name"));
+            }
+            let key = reader.string()?;
+            if reader.skip_spaces() != Some(':') {
+                return Err(reader.error("expected ':'"));
+            }
+            reader.position += 1;
+            members.push((key, reader.value()?));
+            Ok(())
+        })?;
+        Ok(Json::Object(members))
+    }
+}
+
+impl fmt::Display for Json {
+    /// Compact text, on a single line
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut text = String::new();
+        self.write(&mut text, None);
+        f.write_str(&text)
+    }
+}
+
+impl From<bool> for Json {
+    fn from(b: bool) -> Self {
+        Json::Bool(b)
+    }
+}
+
+impl From<&str> for Json {
+    fn from(s: &str) -> Self {
+        Json::String(s.to_owned())
+    }
+}
+
+impl From<String> for Json {
+    fn from(s: String) -> Self {
+        Json::String(s)
+    }
+}
+
+impl From<char> for Json {
+    fn from(c: char) -> Self {
+        Json::String(c.to_string())
+    }
+}
+
+impl From<f64> for Json {
+    fn from(n: f64) -> Self {
+        Json::Number(n)
+    }
+}
+
+impl From<i32> for Json {
+    fn from(n: i32) -> Self {
+        Json::Number(n as f64)
+    }
+}
+
+impl From<u64> for Json {
+    fn from(n: u64) -> Self {
+        Json::Number(n as f64)
+    }
+}
+
+impl From<usize> for Json {
+    fn from(n: usize) -> Self {
+        Json::Number(n as f64)
+    }
+}
+
+impl<T: Into<Json>> From<Option<T>> for Json {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Json::Null, Into::into)
+    }
+}
+
+impl<T: Into<Json>> From<Vec<T>> for Json {
+    fn from(items: Vec<T>) -> Self {
+        Json::Array(items.into_iter().map(Into::into).collect())
+    }
+}
@@ -0,0 +1,96 @@
+//! Family trees as JSON-LD documents, for the tools that would
+//! rather not read GED. The schema is described by
+//! `resources/npaf-tree.schema.json`; in short:
+//!
+//! ```text
+//! {
+//!   "@context": {...}, "@type": "FamilyTree", "version": 1,
+//!   "individuals": [{ "id": "I1", "names": [...], "sex": "M", "events": [...],
+//!                     "childOf": [...], "spouseOf": ["F1"], ... }],
+//!   "families": [{ "id": "F1", "husband": "I1", "wife": "I2", "children": [...], ... }],
+//!   "places": [{ "id": "P1A2B3C4D", "name": "Paris, France", "latitude": 48.85, ... }],
+//!   "sources": [...], "repositories": [...], "notes": [...], "media": [...],
+//!   "records": [...]
+//! }
+//! ```
+//!
+//! Records keep their cross-reference as identifier and places get
+//! one out of their name, so that the identifiers are the same from
+//! one export to the next. Events point to the places, citations to
+//! the sources; a place keeps the coordinates of its first mention.
+//! What the schema doesn't name is kept as raw GED structures in
+//! `extra`, so that a document reads back into the records it was
+//! written from.
+
+mod read;
+mod write;
+
+use crate::gedcom::json::Json;
+use crate::gedcom::{gedcom7, GedParser, ParseError, ParseResult, Parser, Tag};
+use std::io::Read;
+use std::path::Path;
+pub use write::write;
+
+/// Extension of the JSON files
+pub const EXTENSION: &str = "json";
+
+/// Version of the schema written, the documents
+/// of later versions being refused
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// `@type` of the document
+const TREE_TYPE: &str = "FamilyTree";
+
+/// Members of the objects holding identifiers of other objects
+const LINK_TERMS: [&str; 10] = [
+    "husband", "wife", "children", "family", "spouseOf",
+    "place", "source", "note", "repository", "media",
+];
+
+/// JSON-LD context of the documents: the terms are the ones of
+/// the nPAF vocabulary, the identifiers relative to the document
+fn context() -> Json {
+    LINK_TERMS.iter().fold(
+        Json::object()
+            .with("@vocab", gedcom7::NPAF_TERMS)
+            .with("id", "@id"),
+        |context, term| context.with(term, Json::object().with("@type", "@id")),
+    )
+}
+
+/// Whether the file is to be read as a JSON document
+pub fn is_json(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map_or(false, |e| e.eq_ignore_ascii_case(EXTENSION) || e.eq_ignore_ascii_case("jsonld"))
+}
+
+/// Reads a document into level 0 GED structures, as
+/// [`GedEx`](crate::gedcom::gedex::GedEx) does
+pub fn read_tags<R: Read>(mut input: R) -> Result<Vec<Tag>, ParseError> {
+    let mut text = String::new();
+    input.read_to_string(&mut text)?;
+    let document = Json::parse(text.trim_start_matches('\u{feff}'))?;
+    if document.get("@type").and_then(Json::as_str) != Some(TREE_TYPE) {
+        return Err(ParseError::Runtime(format!("Not a family tree: the @type isn't {}.", TREE_TYPE)));
+    }
+    match document.get("version").and_then(Json::as_f64) {
+        Some(version) if version > SCHEMA_VERSION as f64 => Err(ParseError::Runtime(
+            format!("Version {} of the schema isn't supported, {} at most.", version, SCHEMA_VERSION))),
+        _ => Ok(read::tags(&document)),
+    }
+}
+
+/// Parser of JSON documents, the counterpart of [`GedParser`]
+#[derive(Default)]
+pub struct JsonParser {
+
+}
+
+impl Parser for JsonParser {
+    type FileType = std::fs::File;
+
+    fn parse(&mut self, file: &Self::FileType) -> ParseResult {
+        Ok(GedParser::build_registry(&read_tags(file)?))
+    }
+}
@@ -0,0 +1,246 @@
+//! JSON-LD documents read back into GED structures, the
+//! members becoming the tags they were written from.
+
+use crate::gedcom::json::Json;
+use crate::gedcom::place::coordinate;
+use crate::gedcom::Tag;
+use std::collections::HashMap;
+
+fn text<'a>(json: &'a Json, key: &str) -> Option<&'a str> {
+    json.get(key).and_then(Json::as_str)
+}
+
+fn pointer(xref: &str) -> String {
+    format!("@{}@", xref.trim_matches('@'))
+}
+
+/// Tag of a text member, split into `CONT` lines
+fn text_tag(json: &Json, key: &str, name: &str) -> Option<Tag> {
+    text(json, key).map(|t| Tag::with_text(name, t))
+}
+
+/// Tag of a number member
+fn number_tag(json: &Json, key: &str, name: &str) -> Option<Tag> {
+    json.get(key)
+        .and_then(Json::as_f64)
+        .map(|n| Tag::new(name, Some(&Json::Number(n).to_string())))
+}
+
+/// Tag pointing to the identifier a member holds
+fn link_tag(json: &Json, key: &str, name: &str) -> Option<Tag> {
+    text(json, key).map(|xref| Tag::new(name, Some(&pointer(xref))))
+}
+
+/// Raw GED structure
+fn raw(json: &Json) -> Option<Tag> {
+    let name = text(json, "tag")?;
+    let tag = match text(json, "value") {
+        Some(value) => Tag::with_text(name, value),
+        None => Tag::new(name, None),
+    };
+    Some(json.get("children").map_or(&[][..], Json::items).iter()
+        .filter_map(raw)
+        .fold(tag, Tag::nest))
+}
+
+fn extra(json: &Json, key: &str) -> Vec<Tag> {
+    json.get(key).map_or(&[][..], Json::items).iter().filter_map(raw).collect()
+}
+
+/// Objects of an array member
+fn objects<'a>(json: &'a Json, key: &str) -> &'a [Json] {
+    json.get(key).map_or(&[], Json::items)
+}
+
+/// Builds the tag out of its nested tags, in order
+fn tag_of<I: IntoIterator<Item = Option<Tag>>>(tag: Tag, nested: I) -> Tag {
+    nested.into_iter().flatten().fold(tag, Tag::nest)
+}
+
+struct Reader {
+    /// Name and coordinates of every place, by identifier
+    places: HashMap<String, (String, Option<(f64, f64)>)>,
+}
+
+impl Reader {
+    fn citation(&self, json: &Json) -> Tag {
+        let tag = match text(json, "source") {
+            Some(xref) => Tag::new("SOUR", Some(&pointer(xref))),
+            None => text_tag(json, "text", "SOUR").unwrap_or_else(|| Tag::new("SOUR", None)),
+        };
+        let tag = tag_of(tag, vec![text_tag(json, "page", "PAGE"), number_tag(json, "quality", "QUAY")]);
+        tag_of(tag, self.annotations(json).into_iter().chain(extra(json, "extra")).map(Some))
+    }
+
+    fn note(&self, json: &Json) -> Tag {
+        let tag = match text(json, "note") {
+            Some(xref) => Tag::new("NOTE", Some(&pointer(xref))),
+            None => text_tag(json, "text", "NOTE").unwrap_or_else(|| Tag::new("NOTE", None)),
+        };
+        tag_of(tag, extra(json, "extra").into_iter().map(Some))
+    }
+
+    /// Citations, notes and media links
+    fn annotations(&self, json: &Json) -> Vec<Tag> {
+        let citations = objects(json, "citations").iter().map(|c| self.citation(c));
+        let notes = objects(json, "notes").iter().map(|n| self.note(n));
+        let media = objects(json, "media").iter()
+            .filter_map(Json::as_str)
+            .map(|xref| Tag::new("OBJE", Some(&pointer(xref))));
+        citations.chain(notes).chain(media).collect()
+    }
+
+    fn place(&self, json: &Json) -> Option<Tag> {
+        let (name, coordinates) = self.places.get(text(json, "place")?)?;
+        let left = extra(json, "placeExtra");
+        let map = match (coordinates, left.iter().any(|t| t.name == "MAP")) {
+            (Some((latitude, longitude)), false) => Some(Tag::new("MAP", None)
+                .nest(Tag::new("LATI", Some(&coordinate(*latitude, 'N', 'S'))))
+                .nest(Tag::new("LONG", Some(&coordinate(*longitude, 'E', 'W'))))),
+            _ => None,
+        };
+        Some(tag_of(Tag::with_text("PLAC", name), std::iter::once(map).chain(left.into_iter().map(Some))))
+    }
+
+    fn event(&self, json: &Json) -> Option<Tag> {
+        let name = text(json, "type")?;
+        let tag = text_tag(json, "value", name).unwrap_or_else(|| Tag::new(name, None));
+        let tag = tag_of(tag, vec![
+            text_tag(json, "kind", "TYPE"),
+            text_tag(json, "date", "DATE"),
+            self.place(json),
+            text_tag(json, "age", "AGE"),
+            text_tag(json, "cause", "CAUS"),
+            text_tag(json, "agency", "AGNC"),
+        ]);
+        Some(tag_of(tag, self.annotations(json).into_iter().chain(extra(json, "extra")).map(Some)))
+    }
+
+    fn events(&self, json: &Json) -> Vec<Tag> {
+        objects(json, "events").iter().filter_map(|e| self.event(e)).collect()
+    }
+
+    fn name(&self, json: &Json) -> Tag {
+        let tag = text_tag(json, "full", "NAME").unwrap_or_else(|| Tag::new("NAME", None));
+        let parts = [
+            ("given", "GIVN"), ("surname", "SURN"), ("prefix", "NPFX"),
+            ("surnamePrefix", "SPFX"), ("suffix", "NSFX"), ("nickname", "NICK"), ("kind", "TYPE"),
+        ];
+        let tag = tag_of(tag, parts.iter().map(|(key, name)| text_tag(json, key, name)));
+        tag_of(tag, self.annotations(json).into_iter().chain(extra(json, "extra")).map(Some))
+    }
+
+    /// Level 0 structure of an object, its identifiers
+    /// following the nested tags
+    fn record(&self, json: &Json, rtype: &str, nested: Vec<Tag>) -> Option<Tag> {
+        let xref = text(json, "id").map(|id| id.trim_matches('@').to_owned()).filter(|id| !id.is_empty())?;
+        let tag = Tag { xref: Some(xref), ..Tag::new(rtype, None) };
+        let living = json.get("living")
+            .and_then(Json::as_bool)
+            .map(|l| Tag::new("_LIVING", Some(if l { "Y" } else { "N" })));
+        let identifiers = vec![number_tag(json, "rin", "RIN"), text_tag(json, "uid", "_UID"), living];
+        let nested = nested.into_iter()
+            .chain(self.annotations(json))
+            .chain(extra(json, "extra"))
+            .map(Some)
+            .chain(identifiers);
+        Some(tag_of(tag, nested))
+    }
+
+    fn individual(&self, json: &Json) -> Option<Tag> {
+        let names = objects(json, "names").iter().map(|n| self.name(n));
+        let sex = text_tag(json, "sex", "SEX");
+        let parents = objects(json, "childOf").iter().filter_map(|link| {
+            let tag = link_tag(link, "family", "FAMC")?;
+            let tag = tag_of(tag, vec![text_tag(link, "pedigree", "PEDI")]);
+            Some(tag_of(tag, extra(link, "extra").into_iter().map(Some)))
+        });
+        let spouses = objects(json, "spouseOf").iter()
+            .filter_map(Json::as_str)
+            .map(|xref| Tag::new("FAMS", Some(&pointer(xref))));
+        let nested = names
+            .chain(sex)
+            .chain(self.events(json))
+            .chain(parents)
+            .chain(spouses)
+            .collect();
+        self.record(json, "INDI", nested)
+    }
+
+    fn family(&self, json: &Json) -> Option<Tag> {
+        let children = objects(json, "children").iter()
+            .filter_map(Json::as_str)
+            .map(|xref| Tag::new("CHIL", Some(&pointer(xref))));
+        let nested = link_tag(json, "husband", "HUSB").into_iter()
+            .chain(link_tag(json, "wife", "WIFE"))
+            .chain(children)
+            .chain(self.events(json))
+            .collect();
+        self.record(json, "FAM", nested)
+    }
+
+    fn source(&self, json: &Json) -> Option<Tag> {
+        let fields = [
+            ("title", "TITL"), ("author", "AUTH"), ("publication", "PUBL"),
+            ("abbreviation", "ABBR"), ("text", "TEXT"),
+        ];
+        let repositories = objects(json, "repositories").iter().filter_map(|link| {
+            let tag = link_tag(link, "repository", "REPO")?;
+            let tag = tag_of(tag, vec![text_tag(link, "callNumber", "CALN")]);
+            Some(tag_of(tag, extra(link, "extra").into_iter().map(Some)))
+        });
+        let nested = fields.iter()
+            .filter_map(|(key, name)| text_tag(json, key, name))
+            .chain(repositories)
+            .collect();
+        self.record(json, "SOUR", nested)
+    }
+
+    fn media(&self, json: &Json) -> Option<Tag> {
+        let files = objects(json, "files").iter().map(|file| {
+            let tag = text_tag(file, "path", "FILE").unwrap_or_else(|| Tag::new("FILE", None));
+            let tag = tag_of(tag, vec![text_tag(file, "format", "FORM"), text_tag(file, "title", "TITL")]);
+            tag_of(tag, extra(file, "extra").into_iter().map(Some))
+        });
+        let nested = files.chain(text_tag(json, "title", "TITL")).collect();
+        self.record(json, "OBJE", nested)
+    }
+
+    /// Record with its text as value (`NOTE`, other records)
+    fn valued(&self, json: &Json, rtype: &str, key: &str) -> Option<Tag> {
+        let mut tag = self.record(json, rtype, vec![])?;
+        if let Some(value) = text(json, key) {
+            let text = Tag::with_text(rtype, value);
+            tag.content = text.content;
+            tag.nested.splice(0..0, text.nested);
+        }
+        Some(tag)
+    }
+}
+
+/// Level 0 structures of the document
+pub(super) fn tags(document: &Json) -> Vec<Tag> {
+    let places = objects(document, "places").iter()
+        .filter_map(|place| {
+            let coordinates = match (place.get("latitude"), place.get("longitude")) {
+                (Some(latitude), Some(longitude)) => latitude.as_f64().zip(longitude.as_f64()),
+                _ => None,
+            };
+            Some((text(place, "id")?.to_owned(), (text(place, "name")?.to_owned(), coordinates)))
+        })
+        .collect();
+    let reader = Reader { places };
+    let mut tags = vec![];
+    tags.extend(objects(document, "individuals").iter().filter_map(|j| reader.individual(j)));
+    tags.extend(objects(document, "families").iter().filter_map(|j| reader.family(j)));
+    tags.extend(objects(document, "sources").iter().filter_map(|j| reader.source(j)));
+    tags.extend(objects(document, "repositories").iter().filter_map(|json| {
+        reader.record(json, "REPO", text_tag(json, "name", "NAME").into_iter().collect())
+    }));
+    tags.extend(objects(document, "notes").iter().filter_map(|j| reader.valued(j, "NOTE", "text")));
+    tags.extend(objects(document, "media").iter().filter_map(|j| reader.media(j)));
+    tags.extend(objects(document, "records").iter().filter_map(|json| {
+        reader.valued(json, text(json, "tag")?, "value")
+    }));
+    tags
+}
@@ -0,0 +1,407 @@
+//! Records of a registry written as a JSON-LD document. The
+//! structures the schema names become members, the others are
+//! kept in `extra`.
+
+use super::{context, SCHEMA_VERSION, TREE_TYPE};
+use crate::gedcom::json::Json;
+use crate::gedcom::place::{coordinate, degrees};
+use crate::gedcom::{xref_key, Event, GedDate, GedWriter, Record, RecordRegistry, Tag};
+use std::collections::HashMap;
+use std::io::Write;
+
+/// Whether the tag holds nothing but its text
+pub(super) fn is_plain(tag: &Tag) -> bool {
+    tag.nested.iter().all(|t| t.name == "CONT" || t.name == "CONC")
+}
+
+/// Adds the member unless it's null or an empty array
+fn put<V: Into<Json>>(json: Json, key: &str, value: V) -> Json {
+    match value.into() {
+        Json::Null => json,
+        Json::Array(items) if items.is_empty() => json,
+        value => json.with(key, value),
+    }
+}
+
+/// GED structure as it is, its continuation lines joined
+fn raw(tag: &Tag) -> Json {
+    let children: Vec<Json> = tag.nested.iter()
+        .filter(|t| t.name != "CONT" && t.name != "CONC")
+        .map(raw)
+        .collect();
+    let json = Json::object().with("tag", tag.name.as_str());
+    put(put(json, "value", tag.text()), "children", children)
+}
+
+/// Nested structures of a tag, taken out one
+/// kind after the other as they become members
+struct Fields {
+    tags: Vec<Tag>,
+}
+
+impl Fields {
+    fn of(tag: &Tag) -> Self {
+        Fields {
+            tags: tag.nested.iter()
+                .filter(|t| t.name != "CONT" && t.name != "CONC")
+                .cloned()
+                .collect(),
+        }
+    }
+
+    /// Takes the tags for which `f` holds
+    fn take_if<F: Fn(&Tag) -> bool>(&mut self, f: F) -> Vec<Tag> {
+        let (taken, left) = self.tags.drain(..).partition(|t| f(t));
+        self.tags = left;
+        taken
+    }
+
+    fn take(&mut self, name: &str) -> Vec<Tag> {
+        self.take_if(|t| t.name == name)
+    }
+
+    /// Takes the text of the first tag with the given name,
+    /// when that tag holds nothing else
+    fn text(&mut self, name: &str) -> Option<String> {
+        let index = self.tags.iter().position(|t| t.name == name)?;
+        match is_plain(&self.tags[index]) {
+            true => self.tags.remove(index).text(),
+            false => None,
+        }
+    }
+
+    /// Takes the plain pointers with the given name
+    fn pointers(&mut self, name: &str) -> Vec<String> {
+        self.take_if(|t| t.name == name && t.pointer().is_some() && is_plain(t))
+            .iter()
+            .filter_map(|t| t.pointer().map(str::to_owned))
+            .collect()
+    }
+
+    /// What is left, as raw structures
+    fn extra(self, json: Json) -> Json {
+        put(json, "extra", self.tags.iter().map(raw).collect::<Vec<Json>>())
+    }
+}
+
+/// Place of the events, with the coordinates of its first mention
+struct Place {
+    id: String,
+    name: String,
+    coordinates: Option<(String, String)>,
+}
+
+/// Document under construction
+#[derive(Default)]
+struct Document {
+    individuals: Vec<Json>,
+    families: Vec<Json>,
+    places: Vec<Place>,
+    sources: Vec<Json>,
+    repositories: Vec<Json>,
+    notes: Vec<Json>,
+    media: Vec<Json>,
+    records: Vec<Json>,
+    /// Position of every place, by name
+    place_index: HashMap<String, usize>,
+}
+
+impl Document {
+    /// Identifier of the place of a `PLAC` structure, with what
+    /// the place doesn't hold: coordinates other than its own,
+    /// forms, notes...
+    fn place(&mut self, tag: &Tag) -> (Option<String>, Vec<Tag>) {
+        let mut fields = Fields::of(tag);
+        let name = match tag.text().map(|n| n.trim().to_owned()).filter(|n| !n.is_empty()) {
+            Some(name) => name,
+            None => return (None, fields.tags),
+        };
+        let index = match self.place_index.get(&name) {
+            Some(index) => *index,
+            None => {
+                let mut id = format!("P{:08X}", xref_key(&name) as u32);
+                while self.places.iter().any(|p| p.id == id) {
+                    id.push('_');
+                }
+                self.places.push(Place { id, name: name.clone(), coordinates: None });
+                self.place_index.insert(name, self.places.len() - 1);
+                self.places.len() - 1
+            },
+        };
+        // Coordinates that read back the same go with the place
+        let map = fields.tags.iter().position(|t| t.name == "MAP").and_then(|i| {
+            let map = &fields.tags[i];
+            let (latitude, longitude) = (map.child("LATI")?, map.child("LONG")?);
+            let readable = map.nested.len() == 2 && is_plain(latitude) && is_plain(longitude);
+            let (latitude, longitude) = (latitude.content.clone()?, longitude.content.clone()?);
+            let same = |value: &str, positive, negative| degrees(value)
+                .map_or(false, |d| coordinate(d, positive, negative) == value);
+            match readable && same(&latitude, 'N', 'S') && same(&longitude, 'E', 'W') {
+                true => Some((i, (latitude, longitude))),
+                false => None,
+            }
+        });
+        if let Some((i, coordinates)) = map {
+            let place = &mut self.places[index];
+            if place.coordinates.is_none() {
+                place.coordinates = Some(coordinates.clone());
+            }
+            if place.coordinates.as_ref() == Some(&coordinates) {
+                fields.tags.remove(i);
+            }
+        }
+        (Some(self.places[index].id.clone()), fields.tags)
+    }
+
+    fn citation(&mut self, tag: &Tag) -> Json {
+        let mut fields = Fields::of(tag);
+        let json = match tag.pointer() {
+            Some(xref) => Json::object().with("source", xref),
+            None => put(Json::object(), "text", tag.text()),
+        };
+        let json = put(json, "page", fields.text("PAGE"));
+        let quality = fields.tags.iter()
+            .position(|t| t.name == "QUAY" && is_plain(t))
+            .and_then(|i| Some((i, fields.tags[i].content.as_deref()?.trim().parse::<i32>().ok()?)))
+            .filter(|(_, q)| (0..=3).contains(q));
+        let json = match quality {
+            Some((i, quality)) => {
+                fields.tags.remove(i);
+                json.with("quality", quality)
+            },
+            None => json,
+        };
+        let json = self.annotate(json, &mut fields);
+        fields.extra(json)
+    }
+
+    fn note(&mut self, tag: &Tag) -> Json {
+        let fields = Fields::of(tag);
+        let json = match tag.pointer() {
+            Some(xref) => Json::object().with("note", xref),
+            None => put(Json::object(), "text", tag.text()),
+        };
+        fields.extra(json)
+    }
+
+    /// Adds the citations, notes and media links
+    fn annotate(&mut self, json: Json, fields: &mut Fields) -> Json {
+        let citations: Vec<Json> = fields.take("SOUR").iter().map(|t| self.citation(t)).collect();
+        let notes: Vec<Json> = fields.take("NOTE").iter().map(|t| self.note(t)).collect();
+        let json = put(json, "citations", citations);
+        let json = put(json, "notes", notes);
+        put(json, "media", fields.pointers("OBJE"))
+    }
+
+    fn event(&mut self, tag: &Tag) -> Json {
+        let mut fields = Fields::of(tag);
+        let json = put(Json::object().with("type", tag.name.as_str()), "value", tag.text());
+        let json = put(json, "kind", fields.text("TYPE"));
+        let date = fields.text("DATE");
+        let year = date.as_deref().and_then(GedDate::parse).and_then(|d| d.year());
+        let json = put(put(json, "date", date), "year", year);
+        let json = match fields.take("PLAC").split_first() {
+            Some((place, others)) => {
+                fields.tags.extend(others.iter().cloned());
+                let (id, left) = self.place(place);
+                let left: Vec<Json> = left.iter().map(raw).collect();
+                put(put(json, "place", id), "placeExtra", left)
+            },
+            None => json,
+        };
+        let json = put(json, "age", fields.text("AGE"));
+        let json = put(json, "cause", fields.text("CAUS"));
+        let json = put(json, "agency", fields.text("AGNC"));
+        let json = self.annotate(json, &mut fields);
+        fields.extra(json)
+    }
+
+    fn events(&mut self, fields: &mut Fields) -> Vec<Json> {
+        fields.take_if(|t| Event::is_event_tag(&t.name)).iter().map(|t| self.event(t)).collect()
+    }
+
+    fn name(&mut self, tag: &Tag) -> Json {
+        let mut fields = Fields::of(tag);
+        let json = put(Json::object(), "full", tag.text());
+        let json = [
+            ("given", "GIVN"), ("surname", "SURN"), ("prefix", "NPFX"),
+            ("surnamePrefix", "SPFX"), ("suffix", "NSFX"), ("nickname", "NICK"), ("kind", "TYPE"),
+        ].iter().fold(json, |json, (key, name)| put(json, key, fields.text(name)));
+        let json = self.annotate(json, &mut fields);
+        fields.extra(json)
+    }
+
+    /// Object of a record with its identifiers
+    fn object(&self, kind: &str, tag: &Tag, fields: &mut Fields) -> Json {
+        let json = Json::object()
+            .with("@type", kind)
+            .with("id", tag.xref.clone().unwrap_or_default());
+        let json = put(json, "uid", fields.text("_UID"));
+        let json = put(json, "rin", fields.text("RIN").and_then(|r| r.trim().parse::<u64>().ok()));
+        let living = fields.tags.iter()
+            .position(|t| t.name == "_LIVING" && is_plain(t))
+            .and_then(|i| match fields.tags[i].content.as_deref().map(str::trim) {
+                Some("Y") => Some((i, true)),
+                Some("N") => Some((i, false)),
+                _ => None,
+            });
+        match living {
+            Some((i, living)) => {
+                fields.tags.remove(i);
+                json.with("living", living)
+            },
+            None => json,
+        }
+    }
+
+    fn individual(&mut self, record: &Record, tag: &Tag, mut fields: Fields) -> Json {
+        let json = self.object("Individual", tag, &mut fields);
+        let json = put(json, "name", Some(record.display_name()).filter(|n| !n.is_empty()));
+        let names: Vec<Json> = fields.take("NAME").iter().map(|t| self.name(t)).collect();
+        let json = put(json, "names", names);
+        let json = put(json, "sex", fields.text("SEX"));
+        let events = self.events(&mut fields);
+        let json = put(json, "events", events);
+        let parents: Vec<Json> = fields.take_if(|t| t.name == "FAMC" && t.pointer().is_some())
+            .iter()
+            .map(|t| {
+                let mut link = Fields::of(t);
+                let json = Json::object().with("family", t.pointer().unwrap_or_default());
+                let json = put(json, "pedigree", link.text("PEDI"));
+                link.extra(json)
+            })
+            .collect();
+        let json = put(json, "childOf", parents);
+        let json = put(json, "spouseOf", fields.pointers("FAMS"));
+        let json = self.annotate(json, &mut fields);
+        fields.extra(json)
+    }
+
+    fn family(&mut self, tag: &Tag, mut fields: Fields) -> Json {
+        let json = self.object("Family", tag, &mut fields);
+        let json = put(json, "husband", fields.pointers("HUSB").into_iter().next());
+        let json = put(json, "wife", fields.pointers("WIFE").into_iter().next());
+        let json = put(json, "children", fields.pointers("CHIL"));
+        let events = self.events(&mut fields);
+        let json = put(json, "events", events);
+        let json = self.annotate(json, &mut fields);
+        fields.extra(json)
+    }
+
+    fn source(&mut self, tag: &Tag, mut fields: Fields) -> Json {
+        let json = self.object("Source", tag, &mut fields);
+        let json = [
+            ("title", "TITL"), ("author", "AUTH"), ("publication", "PUBL"),
+            ("abbreviation", "ABBR"), ("text", "TEXT"),
+        ].iter().fold(json, |json, (key, name)| put(json, key, fields.text(name)));
+        let repositories: Vec<Json> = fields.take_if(|t| t.name == "REPO" && t.pointer().is_some())
+            .iter()
+            .map(|t| {
+                let mut link = Fields::of(t);
+                let json = Json::object().with("repository", t.pointer().unwrap_or_default());
+                let json = put(json, "callNumber", link.text("CALN"));
+                link.extra(json)
+            })
+            .collect();
+        let json = put(json, "repositories", repositories);
+        let json = self.annotate(json, &mut fields);
+        fields.extra(json)
+    }
+
+    fn media(&mut self, tag: &Tag, mut fields: Fields) -> Json {
+        let json = self.object("Media", tag, &mut fields);
+        let files: Vec<Json> = fields.take("FILE").iter()
+            .map(|t| {
+                let mut file = Fields::of(t);
+                let json = put(Json::object(), "path", t.text());
+                let json = put(json, "format", file.text("FORM"));
+                let json = put(json, "title", file.text("TITL"));
+                file.extra(json)
+            })
+            .collect();
+        let json = put(json, "files", files);
+        let json = put(json, "title", fields.text("TITL"));
+        let json = self.annotate(json, &mut fields);
+        fields.extra(json)
+    }
+
+    fn add(&mut self, record: &Record) {
+        let tag = record.to_tag();
+        let mut fields = Fields::of(&tag);
+        match record.rtype.as_str() {
+            "INDI" => {
+                let json = self.individual(record, &tag, fields);
+                self.individuals.push(json);
+            },
+            "FAM" => {
+                let json = self.family(&tag, fields);
+                self.families.push(json);
+            },
+            "SOUR" => {
+                let json = self.source(&tag, fields);
+                self.sources.push(json);
+            },
+            "OBJE" => {
+                let json = self.media(&tag, fields);
+                self.media.push(json);
+            },
+            "REPO" => {
+                let json = self.object("Repository", &tag, &mut fields);
+                let json = put(json, "name", fields.text("NAME"));
+                let json = self.annotate(json, &mut fields);
+                self.repositories.push(fields.extra(json));
+            },
+            "NOTE" => {
+                let json = self.object("Note", &tag, &mut fields);
+                let json = put(json, "text", tag.text());
+                let json = self.annotate(json, &mut fields);
+                self.notes.push(fields.extra(json));
+            },
+            rtype => {
+                let json = self.object("Record", &tag, &mut fields).with("tag", rtype);
+                let json = put(json, "value", tag.text());
+                self.records.push(fields.extra(json));
+            },
+        }
+    }
+
+    fn into_json(self) -> Json {
+        let places: Vec<Json> = self.places.into_iter()
+            .map(|place| {
+                let json = Json::object()
+                    .with("@type", "Place")
+                    .with("id", place.id)
+                    .with("name", place.name);
+                match place.coordinates {
+                    Some((latitude, longitude)) => json
+                        .with("latitude", degrees(&latitude))
+                        .with("longitude", degrees(&longitude)),
+                    None => json,
+                }
+            })
+            .collect();
+        Json::object()
+            .with("@context", context())
+            .with("@type", TREE_TYPE)
+            .with("version", SCHEMA_VERSION as usize)
+            .with("generator", format!("nPAF {}", env!("CARGO_PKG_VERSION")))
+            .with("individuals", self.individuals)
+            .with("families", self.families)
+            .with("places", places)
+            .with("sources", self.sources)
+            .with("repositories", self.repositories)
+            .with("notes", self.notes)
+            .with("media", self.media)
+            .with("records", self.records)
+    }
+}
+
+/// Writes the registry as a document, as set by the
+/// [export settings](GedWriter::exported) but the version
+pub fn write<W: Write>(writer: &GedWriter, registry: &RecordRegistry, mut out: W) -> std::io::Result<()> {
+    let mut document = Document::default();
+    for record in writer.exported(registry) {
+        document.add(&record);
+    }
+    writeln!(out, "{}", document.into_json().pretty())
+}
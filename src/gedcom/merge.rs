@@ -0,0 +1,375 @@
+//! Duplicate individuals detection and merging
+
+use crate::gedcom::phonetic::soundex;
+use crate::gedcom::{FamilyGraph, PersonId, Record, RecordRc, RecordRegistry, Tag, xref_key};
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+/// Pair of individuals that might be the same person,
+/// `score` going from 0 (different) to 100 (identical).
+#[derive(Debug,Clone)]
+pub struct DuplicateCandidate {
+    pub left: RecordRc,
+    pub right: RecordRc,
+    pub score: u32,
+}
+
+/// Pointer tags that must not be repeated with the same value
+const LINK_TAGS: [&str; 5] = ["FAMC", "FAMS", "HUSB", "WIFE", "CHIL"];
+
+/// Position of the primary `NAME` of a record, the
+/// one mirrored by its name
+fn primary_name(record: &Record) -> Option<usize> {
+    record.extra.iter().position(|t| t.name == "NAME")
+}
+
+/// Compares two names: equal, sounding alike, same initial
+fn name_score(left: &str, right: &str, equal: i32, alike: i32, initial: i32) -> i32 {
+    let (left, right) = (left.trim().to_lowercase(), right.trim().to_lowercase());
+    if left.is_empty() || right.is_empty() {
+        0
+    } else if left == right {
+        equal
+    } else if soundex(&left) == soundex(&right) {
+        alike
+    } else if left.chars().next() == right.chars().next() {
+        initial
+    } else {
+        0
+    }
+}
+
+/// Compares the years of an event of both persons: the same
+/// year gives all the points, distant years are penalized.
+fn year_score(left: &Record, right: &Record, tag: &str, points: i32) -> i32 {
+    let year = |r: &Record| r.event_date(tag).and_then(|d| d.year());
+    match (year(left), year(right)) {
+        (Some(l), Some(r)) => match (l - r).abs() {
+            0 => points,
+            1..=2 => points / 2,
+            3..=5 => 0,
+            _ => -points * 2,
+        },
+        _ => 0,
+    }
+}
+
+/// Compares the names of the parents of both persons
+fn parent_score(graph: &FamilyGraph, registry: &RecordRegistry, left: Option<PersonId>, right: Option<PersonId>) -> i32 {
+    match (left, right) {
+        (Some(l), Some(r)) if l == r => 15,
+        (Some(l), Some(r)) => match (graph.record(registry, l), graph.record(registry, r)) {
+            (Some(l), Some(r)) => name_score(&l.borrow().name, &r.borrow().name, 10, 5, 0),
+            _ => 0,
+        },
+        _ => 0,
+    }
+}
+
+/// Likelihood of two individuals being the same person
+pub fn score(graph: &FamilyGraph, registry: &RecordRegistry, left: &Record, right: &Record) -> u32 {
+    if left.rtype != "INDI" || right.rtype != "INDI" || left.xref == right.xref {
+        return 0;
+    }
+    if left.sex() != 'U' && right.sex() != 'U' && left.sex() != right.sex() {
+        return 0;
+    }
+    let first_given = |r: &Record| r.given_names().split_whitespace().next().unwrap_or("").to_owned();
+    let parent = |r: &Record, parent: fn(&FamilyGraph, PersonId) -> Option<PersonId>| {
+        graph.person_of(r).and_then(|p| parent(graph, p))
+    };
+    let total = name_score(&left.surname(), &right.surname(), 30, 20, 0)
+        + name_score(&first_given(left), &first_given(right), 30, 20, 5)
+        + year_score(left, right, "BIRT", 20)
+        + year_score(left, right, "DEAT", 10)
+        + parent_score(graph, registry, parent(left, FamilyGraph::father), parent(right, FamilyGraph::father))
+        + parent_score(graph, registry, parent(left, FamilyGraph::mother), parent(right, FamilyGraph::mother));
+    total.max(0).min(100) as u32
+}
+
+/// Finds all the pairs of individuals scoring at least
+/// `threshold`. Only persons whose surnames sound alike
+/// are compared, the best candidates come first.
+pub fn find_duplicates(registry: &RecordRegistry, threshold: u32) -> Vec<DuplicateCandidate> {
+    candidates(registry, threshold, |_, _| true)
+}
+
+/// Same as [`find_duplicates`], limited to the pairs having at
+/// least one of the given records, an imported batch usually.
+/// The records of the subset come first in the pairs.
+pub fn find_duplicates_in(registry: &RecordRegistry, subset: &HashSet<u64>, threshold: u32) -> Vec<DuplicateCandidate> {
+    let in_subset = |r: &RecordRc| subset.contains(&xref_key(&r.borrow().xref));
+    let mut found = candidates(registry, threshold, |l, r| in_subset(l) || in_subset(r));
+    for candidate in found.iter_mut() {
+        if !in_subset(&candidate.left) {
+            std::mem::swap(&mut candidate.left, &mut candidate.right);
+        }
+    }
+    found
+}
+
+fn candidates<F: Fn(&RecordRc, &RecordRc) -> bool>(registry: &RecordRegistry, threshold: u32, compared: F) -> Vec<DuplicateCandidate> {
+    let graph = FamilyGraph::new(registry);
+    let mut groups: HashMap<String, Vec<&RecordRc>> = HashMap::new();
+    for record in registry.values().filter(|r| r.borrow().rtype == "INDI") {
+        let code = soundex(&record.borrow().surname()).unwrap_or_default();
+        groups.entry(code).or_default().push(record);
+    }
+    let mut candidates: Vec<DuplicateCandidate> = vec![];
+    for group in groups.values_mut() {
+        group.sort_by_key(|r| r.borrow().rin);
+        for (i, left) in group.iter().enumerate() {
+            for right in group[i + 1..].iter().filter(|r| compared(left, r)) {
+                let score = score(&graph, registry, &left.borrow(), &right.borrow());
+                if score >= threshold {
+                    candidates.push(DuplicateCandidate {
+                        left: Rc::clone(left),
+                        right: Rc::clone(right),
+                        score,
+                    });
+                }
+            }
+        }
+    }
+    candidates.sort_by_key(|c| (std::cmp::Reverse(c.score), c.left.borrow().rin, c.right.borrow().rin));
+    candidates
+}
+
+/// Fields the user picks a value for while merging
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash)]
+pub enum MergeField {
+    Name,
+    Sex,
+    Event(&'static str),
+    CustomId,
+}
+
+/// Fields shown side by side in the merge window
+pub const MERGE_FIELDS: [MergeField; 7] = [
+    MergeField::Name,
+    MergeField::Sex,
+    MergeField::Event("BIRT"),
+    MergeField::Event("CHR"),
+    MergeField::Event("DEAT"),
+    MergeField::Event("BURI"),
+    MergeField::CustomId,
+];
+
+/// Record whose value wins for a field
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum Side {
+    Kept,
+    Dropped,
+}
+
+/// Per field choices, fields missing from the map keep
+/// the value of the kept record (or the dropped one if
+/// the kept record has none).
+pub type MergeChoices = HashMap<MergeField, Side>;
+
+#[derive(Debug)]
+pub enum MergeError {
+    NotFound,
+    SameRecord,
+    TypeMismatch,
+    SexConflict,
+}
+
+impl MergeError {
+    pub fn what(&self) -> String {
+        match &self {
+            MergeError::NotFound => "NotFound: one of the records does not exist.".to_string(),
+            MergeError::SameRecord => "SameRecord: a record can't be merged with itself.".to_string(),
+            MergeError::TypeMismatch => "TypeMismatch: only records of the same type can be merged.".to_string(),
+            MergeError::SexConflict => "SexConflict: a male and a female can't be the same person.".to_string(),
+        }
+    }
+}
+
+impl MergeField {
+    pub fn label(&self) -> &'static str {
+        match self {
+            MergeField::Name => "Name",
+            MergeField::Sex => "Sex",
+            MergeField::Event("BIRT") => "Birth",
+            MergeField::Event("CHR") => "Christening",
+            MergeField::Event("DEAT") => "Death",
+            MergeField::Event("BURI") => "Burial",
+            MergeField::Event(tag) => tag,
+            MergeField::CustomId => "Custom ID",
+        }
+    }
+
+    /// Human readable value of the field
+    pub fn value(&self, record: &Record) -> String {
+        match self {
+            MergeField::Name => record.display_name(),
+            MergeField::Sex => record.extra_value("SEX").unwrap_or_default(),
+            MergeField::Event(tag) => record.event_summary(tag).unwrap_or_default(),
+            MergeField::CustomId => record.custom_id().unwrap_or_default(),
+        }
+    }
+
+    /// Uninterpreted tag holding the field
+    fn extra_tag(&self) -> Option<&'static str> {
+        match self {
+            MergeField::Name => Some("NAME"),
+            MergeField::Sex => Some("SEX"),
+            MergeField::CustomId => Some("REFN"),
+            MergeField::Event(_) => None,
+        }
+    }
+
+    fn is_set(&self, record: &Record) -> bool {
+        match self {
+            MergeField::Event(tag) => record.events_of(tag).next().is_some(),
+            field => !field.value(record).is_empty(),
+        }
+    }
+
+    /// Copies the value of the field from one record to another
+    fn copy(&self, from: &Record, to: &mut Record) {
+        match self {
+            MergeField::Event(tag) => {
+                let at = to.events.iter().position(|e| e.tag == *tag).unwrap_or(to.events.len());
+                to.events.retain(|e| e.tag != *tag);
+                let at = at.min(to.events.len());
+                to.events.splice(at..at, from.events_of(tag).cloned());
+            },
+            // Only the primary name is chosen, the other
+            // names of both records are all kept
+            MergeField::Name => {
+                let at = match primary_name(to) {
+                    Some(at) => {
+                        to.extra.remove(at);
+                        at
+                    },
+                    None => 0,
+                };
+                if let Some(name) = primary_name(from).map(|i| from.extra[i].clone()) {
+                    to.extra.insert(at, name);
+                }
+                to.name = from.name.clone();
+            },
+            field => {
+                let name = field.extra_tag().unwrap();
+                to.extra.retain(|t| t.name != name);
+                to.extra.extend(from.extra.iter().filter(|t| t.name == name).cloned());
+            }
+        }
+    }
+}
+
+/// Builds the merged record: chosen fields first, then all
+/// the other names, events, notes, sources and links of both.
+/// The person is living if either record says so, and the
+/// `_UID` of the dropped record is kept as a second one.
+fn merged_record(kept: &Record, dropped: &Record, choices: &MergeChoices) -> Record {
+    let mut merged = kept.clone();
+    for field in MERGE_FIELDS.iter() {
+        let side = choices.get(field).copied().unwrap_or(Side::Kept);
+        let take_dropped = side == Side::Dropped || !field.is_set(kept);
+        if take_dropped && field.is_set(dropped) {
+            field.copy(dropped, &mut merged);
+        }
+    }
+    let chosen_events: Vec<&str> = MERGE_FIELDS.iter()
+        .filter_map(|f| match f { MergeField::Event(tag) => Some(*tag), _ => None })
+        .collect();
+    for event in dropped.events.iter().filter(|e| !chosen_events.contains(&e.tag.as_str())) {
+        if !merged.events.contains(event) {
+            merged.events.push(event.clone());
+        }
+    }
+    let chosen_tags: Vec<&str> = MERGE_FIELDS.iter().filter_map(|f| f.extra_tag()).collect();
+    let other_names = dropped.extra.iter()
+        .enumerate()
+        .filter(|(i, t)| t.name == "NAME" && Some(*i) != primary_name(dropped))
+        .map(|(_, t)| t);
+    for tag in dropped.extra.iter().filter(|t| !chosen_tags.contains(&t.name.as_str())).chain(other_names) {
+        if !merged.extra.contains(tag) {
+            merged.extra.push(tag.clone());
+        }
+    }
+    for ordinance in &dropped.ordinances {
+        if !merged.ordinances.contains(ordinance) {
+            merged.ordinances.push(ordinance.clone());
+        }
+    }
+    if merged.contact.is_none() {
+        merged.contact = dropped.contact.clone();
+    }
+    merged.living = match (kept.living, dropped.living) {
+        (Some(left), Some(right)) => Some(left || right),
+        (left, right) => left.or(right),
+    };
+    match (&merged.uid, &dropped.uid) {
+        (None, Some(uid)) => merged.uid = Some(uid.clone()),
+        (Some(kept), Some(uid)) if kept != uid => merged.extra.push(Tag::new("_UID", Some(uid))),
+        _ => (),
+    }
+    merged
+}
+
+/// Removes repeated family links left by the merge (a child
+/// listed twice in a family, a family listed twice...)
+fn dedup_links(tags: &mut Vec<Tag>) {
+    let mut seen: Vec<(String, Option<String>)> = vec![];
+    tags.retain(|t| {
+        if !LINK_TAGS.contains(&t.name.as_str()) || !t.nested.is_empty() {
+            return true;
+        }
+        let key = (t.name.clone(), t.content.clone());
+        if seen.contains(&key) {
+            false
+        } else {
+            seen.push(key);
+            true
+        }
+    });
+}
+
+/// Merges the `dropped` record into the `kept` one. All the
+/// pointers to the dropped record are redirected to the kept
+/// one, so no orphaned pointer is left in the registry. The
+/// [`FamilyGraph`] of the registry has to be built again.
+pub fn merge(registry: &mut RecordRegistry, kept: u64, dropped: u64, choices: &MergeChoices) -> Result<(), MergeError> {
+    if kept == dropped {
+        return Err(MergeError::SameRecord);
+    }
+    let (kept_rc, dropped_rc) = match (registry.get(&kept), registry.get(&dropped)) {
+        (Some(k), Some(d)) => (Rc::clone(k), Rc::clone(d)),
+        _ => return Err(MergeError::NotFound),
+    };
+    if kept_rc.borrow().rtype != dropped_rc.borrow().rtype {
+        return Err(MergeError::TypeMismatch);
+    }
+    let (kept_sex, dropped_sex) = (kept_rc.borrow().sex(), dropped_rc.borrow().sex());
+    if kept_sex != 'U' && dropped_sex != 'U' && kept_sex != dropped_sex {
+        return Err(MergeError::SexConflict);
+    }
+    let merged = merged_record(&kept_rc.borrow(), &dropped_rc.borrow(), choices);
+    let (from, to) = (dropped_rc.borrow().xref.clone(), merged.xref.clone());
+    *kept_rc.borrow_mut() = merged;
+    registry.remove(&dropped);
+
+    for record in registry.values() {
+        let mut record = record.borrow_mut();
+        for tag in record.extra.iter_mut() {
+            tag.replace_pointer(&from, &to);
+        }
+        for event in record.events.iter_mut() {
+            for tag in event.extra.iter_mut() {
+                tag.replace_pointer(&from, &to);
+            }
+        }
+        for ordinance in record.ordinances.iter_mut() {
+            if ordinance.family.as_deref().map(|f| f.trim_matches('@')) == Some(from.trim_matches('@')) {
+                ordinance.family = Some(format!("@{}@", to.trim_matches('@')));
+            }
+        }
+        dedup_links(&mut record.extra);
+    }
+    debug_assert!(registry.get(&xref_key(&from)).is_none());
+    Ok(())
+}
@@ -5,13 +5,54 @@ use std::rc::Rc;
 use std::io::{BufReader, BufRead};
 use std::fs::File;
 use std::cell::{RefMut, RefCell};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
 use unicode_bom::Bom;
 
 extern crate regex;
 pub mod gedex;
+pub mod graph;
+pub mod ahnentafel;
+pub mod address;
+pub mod event;
+pub mod export;
+pub mod family;
+pub mod date;
+pub mod gedcom7;
+pub mod gedzip;
+pub mod gramps;
+pub mod json;
+pub mod jsonld;
+pub mod phonetic;
+pub mod search;
+pub mod stats;
+pub mod ident;
+pub mod import;
+pub mod index;
+pub mod batch;
+pub mod csv;
+pub mod merge;
+pub mod ordinance;
+pub mod place;
+pub mod privacy;
+pub mod verify;
+pub mod writer;
 use regex::Regex;
 
+pub use gedex::Tag;
+pub use address::{Address, ContactInfo, ContactError};
+pub use event::Event;
+pub use export::{ExportContent, ExportFormat, ExportScope};
+pub use gedcom7::GedVersion;
+pub use gramps::GrampsParser;
+pub use jsonld::JsonParser;
+pub use graph::{FamilyGraph, FamilyId, PersonId};
+pub use ordinance::{Ordinance, OrdinanceKind, OrdinanceStatus};
+pub use date::{GedDate, SimpleDate};
+pub use privacy::{LivingRules, PrivacyFilter, PrivacyMode};
+pub use writer::GedWriter;
+
 /// Smart pointer to a record. Its implemented
 /// via the combination of `Rc<RefCell<...>>` so
 /// a record could have multiple owners and to be
@@ -35,14 +76,317 @@ pub type ParseResult = Result<RecordRegistry, ParseError>;
 /// about a person or a GED data chunk.
 /// > Don't know yet what it has to contain,
 /// > so it's under heavy developpment.
-#[derive(Default,Debug,Clone)]
+#[derive(Debug,Default,Clone)]
 pub struct Record {
     pub rtype: String,
     pub id: u64,
+    /// Record Identification Number, stable across
+    /// imports and exports (`RIN`)
+    pub rin: u64,
+    /// Unique identifier of the record (`_UID`)
+    pub uid: Option<String>,
+    /// Living status set by the user (`_LIVING`), taking
+    /// precedence over the [inferred one](privacy::LivingRules)
+    pub living: Option<bool>,
+    pub xref: String,
     pub name: String,
-    pub father: Option<RecordRc>,
-    pub mother: Option<RecordRc>,
-    pub children: Vec<RecordRc>
+    /// Line content of the record (text of a `NOTE` record)
+    pub value: Option<String>,
+    pub events: Vec<Event>,
+    /// LDS ordinances of the individual or the family
+    pub ordinances: Vec<Ordinance>,
+    /// Contact information of the record itself
+    /// (repositories and submitters)
+    pub contact: Option<ContactInfo>,
+    /// Nested structures not interpreted by the model,
+    /// kept as is so they could be exported back
+    pub extra: Vec<Tag>
+}
+
+/// Computes the registry key of a record from its
+/// cross-reference identifier (`I12` for `@I12@`).
+pub fn xref_key(xref: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    xref.trim_matches('@').hash(&mut hasher);
+    hasher.finish()
+}
+
+impl Record {
+    /// Builds a record from a level 0 GED structure, the
+    /// family links being resolved by the [`FamilyGraph`].
+    pub fn from_tag(tag: &Tag) -> Self {
+        let xref = tag.xref.clone().unwrap_or_default();
+        let digits: String = xref.chars().filter(|c| c.is_ascii_digit()).collect();
+        let uid = tag.value("_UID").map(|u| u.trim().to_owned());
+        Record {
+            rtype: tag.name.clone(),
+            id: digits.parse().unwrap_or(0),
+            rin: tag.value("RIN").and_then(|r| r.trim().parse().ok()).unwrap_or(0),
+            uid: uid.clone(),
+            living: tag.value("_LIVING").map(|l| l.trim().eq_ignore_ascii_case("Y")),
+            xref,
+            name: tag.value("NAME").unwrap_or_default(),
+            value: tag.content.clone(),
+            events: tag.nested.iter()
+                .filter(|t| Event::is_event_tag(&t.name))
+                .map(Event::from_tag)
+                .collect(),
+            ordinances: tag.nested.iter()
+                .filter_map(Ordinance::from_tag)
+                .collect(),
+            contact: ContactInfo::from_parent(tag),
+            extra: tag.nested.iter()
+                .filter(|t| !Event::is_event_tag(&t.name))
+                .filter(|t| !Ordinance::is_ordinance_tag(&t.name))
+                .filter(|t| !ContactInfo::is_contact_tag(&t.name))
+                .filter(|t| t.name != "RIN" && t.name != "_LIVING")
+                // Other `_UID`s are those of merged records
+                .filter(|t| t.name != "_UID" || t.text().map(|u| u.trim().to_owned()) != uid)
+                .cloned()
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Converts the record back into a GED structure
+    pub fn to_tag(&self) -> Tag {
+        let tag = Tag {
+            xref: Some(self.xref.clone()),
+            ..Tag::new(&self.rtype, self.value.as_deref())
+        };
+        let (uids, mut extra): (Vec<Tag>, Vec<Tag>) = self.extra.iter()
+            .cloned()
+            .partition(|t| t.name == "_UID");
+        let name = Some(self.name.clone()).filter(|n| !n.is_empty());
+        match extra.iter_mut().find(|t| t.name == "NAME") {
+            Some(original) => original.content = name,
+            None if name.is_some() => extra.insert(0, Tag::new("NAME", name.as_deref())),
+            None => (),
+        }
+        let events = self.events.iter().map(|e| e.to_tag());
+        let ordinances = self.ordinances.iter().map(|o| o.to_tag());
+        let contact = self.contact.iter().flat_map(|c| c.to_tags());
+        let rin = Some(self.rin)
+            .filter(|rin| *rin > 0)
+            .map(|rin| Tag::new("RIN", Some(&rin.to_string())));
+        let uid = self.uid.iter().map(|uid| Tag::new("_UID", Some(uid)));
+        let living = self.living.map(|l| Tag::new("_LIVING", Some(if l { "Y" } else { "N" })));
+        extra.into_iter()
+            .chain(events)
+            .chain(ordinances)
+            .chain(contact)
+            .chain(rin)
+            .chain(uid)
+            .chain(uids)
+            .chain(living)
+            .fold(tag, |tag, nested| tag.nest(nested))
+    }
+
+    /// Returns all the cross-references pointed by
+    /// the given tag (`FAMC`, `HUSB`, `CHIL`...)
+    pub fn pointers(&self, name: &str) -> Vec<String> {
+        self.extra.iter()
+            .filter(|t| t.name == name)
+            .filter_map(|t| t.content.as_deref())
+            .map(|c| c.trim_matches('@').to_owned())
+            .collect()
+    }
+
+    /// Events with the given tag
+    pub fn events_of<'a>(&'a self, tag: &'a str) -> impl Iterator<Item = &'a Event> + 'a {
+        self.events.iter().filter(move |e| e.tag == tag)
+    }
+
+    /// Families pointed by the given tag: `FAMS` for the families
+    /// where the individual is a spouse, `FAMC` for a child.
+    pub fn families(&self, tag: &str, registry: &RecordRegistry) -> Vec<RecordRc> {
+        self.pointers(tag).iter()
+            .filter_map(|xref| registry.get(&xref_key(xref)).cloned())
+            .collect()
+    }
+
+    /// One line description of the first event with the
+    /// given tag: its date and place
+    pub fn event_summary(&self, tag: &str) -> Option<String> {
+        let event = self.events_of(tag).next()?;
+        let parts: Vec<&str> = event.date.iter()
+            .chain(event.place.iter())
+            .map(|s| s.as_str())
+            .collect();
+        Some(parts.join(", "))
+    }
+
+    /// Value of the first uninterpreted tag with the given name
+    pub fn extra_value(&self, name: &str) -> Option<String> {
+        self.extra.iter().find(|t| t.name == name).and_then(|t| t.text())
+    }
+
+    /// Texts of the notes of the record, both inline and
+    /// pointing to a `NOTE` record
+    pub fn notes(&self, registry: &RecordRegistry) -> Vec<String> {
+        self.extra.iter()
+            .filter(|t| t.name == "NOTE")
+            .filter_map(|t| match t.content.as_deref().map(str::trim) {
+                Some(xref) if xref.starts_with('@') && xref.ends_with('@') && t.nested.is_empty() => {
+                    registry.get(&xref_key(xref)).and_then(|n| n.borrow().to_tag().text())
+                },
+                _ => t.text(),
+            })
+            .filter(|n| !n.trim().is_empty())
+            .collect()
+    }
+
+    /// Source records cited by the record or by its events
+    pub fn sources(&self, registry: &RecordRegistry) -> Vec<RecordRc> {
+        let mut sources: Vec<RecordRc> = vec![];
+        let citations = self.extra.iter()
+            .chain(self.events.iter().flat_map(|e| e.extra.iter()))
+            .filter(|t| t.name == "SOUR")
+            .filter_map(|t| t.content.as_deref());
+        for xref in citations {
+            if let Some(source) = registry.get(&xref_key(xref.trim())) {
+                if !sources.iter().any(|s| Rc::ptr_eq(s, source)) {
+                    sources.push(source.clone());
+                }
+            }
+        }
+        sources
+    }
+
+    /// Paths of the multimedia files linked to the record,
+    /// either inline or through `OBJE` records
+    pub fn media_files(&self, registry: &RecordRegistry) -> Vec<String> {
+        let mut files: Vec<String> = vec![];
+        for object in self.extra.iter().filter(|t| t.name == "OBJE") {
+            match object.content.as_deref() {
+                Some(xref) if object.nested.is_empty() => {
+                    if let Some(record) = registry.get(&xref_key(xref.trim())) {
+                        files.extend(record.borrow().extra.iter()
+                            .filter(|t| t.name == "FILE")
+                            .filter_map(|t| t.content.clone()));
+                    }
+                },
+                _ => files.extend(object.value("FILE")),
+            }
+        }
+        files
+    }
+
+    /// Surname, taken from `SURN` or from the slashes
+    /// of the name (`John /Smith/`)
+    pub fn surname(&self) -> String {
+        let name = self.extra.iter().find(|t| t.name == "NAME");
+        if let Some(surname) = name.and_then(|n| n.value("SURN")) {
+            return surname;
+        }
+        self.name.split('/').nth(1).unwrap_or("").trim().to_owned()
+    }
+
+    /// Given names, taken from `GIVN` or from the part
+    /// of the name before the surname
+    pub fn given_names(&self) -> String {
+        let name = self.extra.iter().find(|t| t.name == "NAME");
+        if let Some(given) = name.and_then(|n| n.value("GIVN")) {
+            return given;
+        }
+        self.name.split('/').next().unwrap_or("").trim().to_owned()
+    }
+
+    /// Sex of an individual: `M`, `F` or `U` if unknown
+    pub fn sex(&self) -> char {
+        self.extra_value("SEX")
+            .and_then(|s| s.trim().chars().next())
+            .map(|c| c.to_ascii_uppercase())
+            .filter(|c| *c == 'M' || *c == 'F')
+            .unwrap_or('U')
+    }
+
+    /// User reference number (`REFN`), shown as "Custom ID"
+    pub fn custom_id(&self) -> Option<String> {
+        self.extra_value("REFN")
+    }
+
+    /// Ancestral File Number (`AFN`)
+    pub fn afn(&self) -> Option<String> {
+        self.extra_value("AFN")
+    }
+
+    /// Parsed date of the first event with the given tag
+    pub fn event_date(&self, tag: &str) -> Option<GedDate> {
+        self.events_of(tag)
+            .filter_map(|e| e.date.as_deref())
+            .find_map(GedDate::parse)
+    }
+
+    /// Human readable lifespan (`1900-1970`)
+    pub fn lifespan(&self) -> String {
+        let year = |tags: &[&str]| tags.iter()
+            .find_map(|t| self.event_date(t).and_then(|d| d.year()))
+            .map(|y| y.to_string())
+            .unwrap_or_default();
+        let (birth, death) = (year(&["BIRT", "CHR"]), year(&["DEAT", "BURI"]));
+        if birth.is_empty() && death.is_empty() {
+            String::new()
+        } else {
+            format!("{}-{}", birth, death)
+        }
+    }
+
+    /// Name without the surname slashes
+    pub fn display_name(&self) -> String {
+        self.name.split_whitespace()
+            .map(|w| w.trim_matches('/'))
+            .filter(|w| !w.is_empty())
+            .collect::<Vec<&str>>()
+            .join(" ")
+    }
+
+    /// Contact information of the record: individuals keep
+    /// it in their latest residence, other records directly.
+    pub fn contact_info(&self) -> Option<&ContactInfo> {
+        match self.rtype.as_str() {
+            "INDI" => self.events_of("RESI").last().and_then(|e| e.contact.as_ref()),
+            _ => self.contact.as_ref(),
+        }
+    }
+
+    /// Keeps the uninterpreted tags of the record, its events
+    /// and its ordinances for which `keep` holds, at every level
+    pub fn retain_tags<F: Fn(&Tag) -> bool>(&mut self, keep: F) {
+        gedex::retain_tags(&mut self.extra, &keep);
+        for event in self.events.iter_mut() {
+            gedex::retain_tags(&mut event.extra, &keep);
+        }
+        for ordinance in self.ordinances.iter_mut() {
+            gedex::retain_tags(&mut ordinance.extra, &keep);
+        }
+    }
+
+    /// Drops everything pointing to one of the given records
+    pub fn prune_pointers(&mut self, removed: &HashSet<u64>) {
+        let points_to_removed = |xref: &str| removed.contains(&xref_key(xref));
+        self.retain_tags(|t| !t.pointer().map_or(false, points_to_removed));
+        for ordinance in self.ordinances.iter_mut() {
+            if ordinance.family.as_deref().and_then(gedex::pointer_xref).map_or(false, points_to_removed) {
+                ordinance.family = None;
+            }
+        }
+    }
+
+    /// Replaces the contact information of the record,
+    /// creating a residence for an individual if needed
+    pub fn set_contact_info(&mut self, contact: ContactInfo) {
+        let contact = Some(contact).filter(|c| !c.is_empty());
+        if self.rtype != "INDI" {
+            self.contact = contact;
+            return;
+        }
+        match self.events.iter_mut().rev().find(|e| e.tag == "RESI") {
+            Some(residence) => residence.contact = contact,
+            None if contact.is_some() => self.events.push(Event { contact, ..Event::new("RESI") }),
+            None => (),
+        }
+    }
 }
 
 /// Converter from `Record` to `RecordRef`.
@@ -61,7 +405,19 @@ type IOError = std::io::Error;
 #[derive(Debug)]
 pub enum ParseError {
     IO(IOError),
-    Runtime(String)
+    Runtime(String),
+    /// Parsing stopped at the user's request
+    Cancelled
+}
+
+impl ParseError {
+    pub fn what(&self) -> String {
+        match &self {
+            ParseError::IO(err) => format!("IO: {}", err),
+            ParseError::Runtime(msg) => format!("Runtime: {}", msg),
+            ParseError::Cancelled => "Cancelled: the import was stopped.".to_string(),
+        }
+    }
 }
 
 /// An enumeration that grants the interpretation
@@ -74,7 +430,7 @@ pub enum ParseError {
 #[derive(Debug,Clone)]
 enum GedLine {
     Data(u16, String, Option<String>),
-    Ref(u16, String, u64, Option<String>)
+    Ref(u16, String, Option<String>)
 }
 
 impl GedLine {
@@ -85,7 +441,7 @@ impl GedLine {
     /// > this function might disappear as well.
     fn level(&self) -> u16 {
         match &self {
-            Self::Data(lvl, _, _) | Self::Ref(lvl, _, _, _) => *lvl
+            Self::Data(lvl, _, _) | Self::Ref(lvl, _, _) => *lvl
         }
     }
 }
@@ -98,6 +454,12 @@ impl From<IOError> for ParseError {
     }
 }
 
+impl From<zip::result::ZipError> for ParseError {
+    fn from(err: zip::result::ZipError) -> ParseError {
+        ParseError::Runtime(format!("Invalid GEDZIP package: {}", err))
+    }
+}
+
 /// Generic trait made to represent any entity capable of
 /// parsing a file to transform it into a [record tree](RecordRegistry)
 pub trait Parser {
@@ -106,24 +468,41 @@ pub trait Parser {
     /// This method reads a file, extracts a BOM mark if
     /// it finds one and returns the file contents
     fn read_lines(file: &std::fs::File) -> (Bom, Vec<String>) {
-        let mut reader = BufReader::new(file);
-        let mut first_line = String::new();
-        if let Err(_) = reader.read_line(&mut first_line) {
-            return (Bom::Null, vec![])
-        };
-
-        let bom: Bom = first_line.as_bytes().into();
-        let mut content: Vec<String> = match bom {
-            Bom::Null => vec![first_line],
-            _ => vec![first_line[(bom.len() - 1)..].to_owned()],
-        };
-        let mut rest: Vec<String> = reader.lines()
-            .filter_map(|x| x.ok())
-            .collect();
-        content.append(&mut rest);
+        let (bom, content, _) = Self::read_lines_lossy(file);
         (bom, content)
     }
 
+    /// Same as [`read_lines`](Parser::read_lines), also returning
+    /// the numbers of the lines that weren't valid UTF-8. Their
+    /// invalid bytes are replaced, so that no line goes missing
+    /// and the lines after them keep their numbers.
+    fn read_lines_lossy(file: &std::fs::File) -> (Bom, Vec<String>, Vec<usize>) {
+        let reader = BufReader::new(file);
+        let (mut bom, mut content, mut invalid) = (Bom::Null, vec![], vec![]);
+        for (number, line) in reader.split(b'\n').enumerate() {
+            let mut line = match line {
+                Ok(line) => line,
+                Err(_) => break,
+            };
+            if number == 0 {
+                bom = line.as_slice().into();
+                line.drain(..bom.len());
+            }
+            if line.last() == Some(&b'\r') {
+                line.pop();
+            }
+            let line = match String::from_utf8(line) {
+                Ok(line) => line,
+                Err(err) => {
+                    invalid.push(number + 1);
+                    String::from_utf8_lossy(err.as_bytes()).into_owned()
+                },
+            };
+            content.push(line);
+        }
+        (bom, content, invalid)
+    }
+
     /// Main parsing method that all the descendants have to
     /// implement
     fn parse(&mut self, file: &Self::FileType) -> ParseResult;
@@ -138,15 +517,32 @@ pub struct GedParser {
 
 impl GedParser {
     fn classic_parse(&mut self, file: &std::fs::File) -> ParseResult {
-        let content: Vec<String> = BufReader::new(file).lines()
-            .filter_map(|x| x.ok())
+        let (_, content) = Self::read_lines(file);
+        let tags = gedex::GedEx::new(content).parse()?;
+        let tags = match GedVersion::of(&tags) {
+            GedVersion::V7 => gedcom7::to_v551(tags),
+            GedVersion::V551 => tags,
+        };
+        Ok(Self::build_registry(&tags))
+    }
+
+    /// Turns parsed level 0 structures into a registry of
+    /// records and gives every record its stable identifiers.
+    pub fn build_registry(tags: &[Tag]) -> RecordRegistry {
+        Self::registry_of(tags.iter()
+            .filter(|t| t.xref.is_some())
+            .map(Record::from_tag)
+            .collect())
+    }
+
+    /// Registry of already built records, used once they
+    /// come back from the [worker thread](import)
+    pub fn registry_of(records: Vec<Record>) -> RecordRegistry {
+        let registry: RecordRegistry = records.into_iter()
+            .map(|r| (xref_key(&r.xref), r.into()))
             .collect();
-        let res = gedex::GedEx::new(content).parse();
-        Err(
-            ParseError::Runtime(
-                String::from("Not implemented.")
-            )
-        )
+        ident::assign_identifiers(&registry);
+        registry
     }
 }
 
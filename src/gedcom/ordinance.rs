@@ -0,0 +1,312 @@
+//! LDS ordinances of individuals (baptism, confirmation,
+//! endowment, sealing to parents) and families (sealing to
+//! spouse), with the temple codes they are validated against.
+
+use crate::gedcom::{GedDate, Tag};
+
+/// Temple codes and names, one `CODE<TAB>Name` per line
+const TEMPLE_LIST: &str = include_str!("../../resources/temples.txt");
+
+/// Tags of the ordinances of an individual
+pub const INDIVIDUAL_ORDINANCES: [&str; 4] = ["BAPL", "CONL", "ENDL", "SLGC"];
+
+/// Tags of the ordinances of a family
+pub const FAMILY_ORDINANCES: [&str; 1] = ["SLGS"];
+
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash)]
+pub enum OrdinanceKind {
+    Baptism,
+    Confirmation,
+    Endowment,
+    SealingToParents,
+    SealingToSpouse,
+}
+
+impl OrdinanceKind {
+    pub fn from_tag(tag: &str) -> Option<Self> {
+        match tag {
+            "BAPL" => Some(OrdinanceKind::Baptism),
+            "CONL" => Some(OrdinanceKind::Confirmation),
+            "ENDL" => Some(OrdinanceKind::Endowment),
+            "SLGC" => Some(OrdinanceKind::SealingToParents),
+            "SLGS" => Some(OrdinanceKind::SealingToSpouse),
+            _ => None,
+        }
+    }
+
+    pub fn tag(&self) -> &'static str {
+        match self {
+            OrdinanceKind::Baptism => "BAPL",
+            OrdinanceKind::Confirmation => "CONL",
+            OrdinanceKind::Endowment => "ENDL",
+            OrdinanceKind::SealingToParents => "SLGC",
+            OrdinanceKind::SealingToSpouse => "SLGS",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            OrdinanceKind::Baptism => "LDS baptism",
+            OrdinanceKind::Confirmation => "LDS confirmation",
+            OrdinanceKind::Endowment => "Endowment",
+            OrdinanceKind::SealingToParents => "Sealing to parents",
+            OrdinanceKind::SealingToSpouse => "Sealing to spouse",
+        }
+    }
+
+    /// Whether the ordinance is recorded on a family
+    pub fn is_family(&self) -> bool {
+        *self == OrdinanceKind::SealingToSpouse
+    }
+
+    /// Statuses the GEDCOM standard allows for the ordinance
+    pub fn statuses(&self) -> &'static [OrdinanceStatus] {
+        use OrdinanceStatus::*;
+        match self {
+            OrdinanceKind::Baptism | OrdinanceKind::Confirmation | OrdinanceKind::Endowment =>
+                &[Child, Completed, Excluded, Infant, Pre1970, Stillborn, Submitted, Uncleared],
+            OrdinanceKind::SealingToParents =>
+                &[BornInCovenant, Completed, Excluded, DoNotSubmit, Pre1970, Stillborn, Submitted, Uncleared],
+            OrdinanceKind::SealingToSpouse =>
+                &[Canceled, Completed, DoNotSubmit, DoNotSubmitCanceled, Excluded, Pre1970, Submitted, Uncleared],
+        }
+    }
+}
+
+/// State of an ordinance (`STAT`)
+#[derive(Debug,Clone,Copy,PartialEq,Eq,Hash)]
+pub enum OrdinanceStatus {
+    BornInCovenant,
+    Canceled,
+    Child,
+    Completed,
+    Excluded,
+    DoNotSubmit,
+    DoNotSubmitCanceled,
+    Infant,
+    Pre1970,
+    Stillborn,
+    Submitted,
+    Uncleared,
+}
+
+impl OrdinanceStatus {
+    pub fn from_code(code: &str) -> Option<Self> {
+        use OrdinanceStatus::*;
+        match code.trim().to_uppercase().as_str() {
+            "BIC" => Some(BornInCovenant),
+            "CANCELED" | "CANCELLED" => Some(Canceled),
+            "CHILD" => Some(Child),
+            "COMPLETED" => Some(Completed),
+            "EXCLUDED" => Some(Excluded),
+            "DNS" => Some(DoNotSubmit),
+            "DNS/CAN" => Some(DoNotSubmitCanceled),
+            "INFANT" => Some(Infant),
+            "PRE-1970" => Some(Pre1970),
+            "STILLBORN" => Some(Stillborn),
+            "SUBMITTED" => Some(Submitted),
+            "UNCLEARED" => Some(Uncleared),
+            _ => None,
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        use OrdinanceStatus::*;
+        match self {
+            BornInCovenant => "BIC",
+            Canceled => "CANCELED",
+            Child => "CHILD",
+            Completed => "COMPLETED",
+            Excluded => "EXCLUDED",
+            DoNotSubmit => "DNS",
+            DoNotSubmitCanceled => "DNS/CAN",
+            Infant => "INFANT",
+            Pre1970 => "PRE-1970",
+            Stillborn => "STILLBORN",
+            Submitted => "SUBMITTED",
+            Uncleared => "UNCLEARED",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        use OrdinanceStatus::*;
+        match self {
+            BornInCovenant => "Born in the covenant",
+            Canceled => "Sealing canceled",
+            Child => "Died before eight",
+            Completed => "Completed",
+            Excluded => "Excluded",
+            DoNotSubmit => "Do not submit",
+            DoNotSubmitCanceled => "Do not submit, canceled",
+            Infant => "Died before one",
+            Pre1970 => "Completed before 1970",
+            Stillborn => "Stillborn",
+            Submitted => "Submitted",
+            Uncleared => "Uncleared",
+        }
+    }
+}
+
+/// Temple where ordinances are performed
+#[derive(Debug,Clone,PartialEq)]
+pub struct Temple {
+    pub code: String,
+    pub name: String,
+}
+
+/// All the known temples, in the order of the shipped list
+pub fn temples() -> Vec<Temple> {
+    TEMPLE_LIST.lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .filter_map(|l| {
+            let mut parts = l.splitn(2, '\t');
+            let code = parts.next()?.trim();
+            let name = parts.next()?.trim();
+            Some(Temple { code: code.to_owned(), name: name.to_owned() })
+        })
+        .collect()
+}
+
+/// Temple with the given code, case insensitive
+pub fn temple(code: &str) -> Option<Temple> {
+    let code = code.trim().to_uppercase();
+    temples().into_iter().find(|t| t.code == code)
+}
+
+/// Problems that can be found while validating an ordinance
+#[derive(Debug,Clone,PartialEq)]
+pub enum OrdinanceError {
+    InvalidDate(String),
+    UnknownTemple(String),
+    StatusNotAllowed(OrdinanceKind, OrdinanceStatus),
+    MissingFamily,
+}
+
+impl OrdinanceError {
+    pub fn what(&self) -> String {
+        match &self {
+            OrdinanceError::InvalidDate(date) => format!("'{}' is not a valid date.", date),
+            OrdinanceError::UnknownTemple(code) => format!("'{}' is not a known temple code.", code),
+            OrdinanceError::StatusNotAllowed(kind, status) =>
+                format!("The status '{}' can't be used for {}.", status.label(), kind.label().to_lowercase()),
+            OrdinanceError::MissingFamily => String::from("A sealing to parents needs the family of the parents."),
+        }
+    }
+}
+
+/// Single ordinance with its date, temple, place and status
+#[derive(Debug,Clone,PartialEq)]
+pub struct Ordinance {
+    pub kind: OrdinanceKind,
+    pub date: Option<String>,
+    /// Temple code (`TEMP`)
+    pub temple: Option<String>,
+    pub place: Option<String>,
+    pub status: Option<OrdinanceStatus>,
+    /// Date the status was given (`STAT.DATE`)
+    pub status_date: Option<String>,
+    /// Pointer to the family of the parents of a
+    /// sealing to parents (`FAMC`)
+    pub family: Option<String>,
+    /// Nested structures not interpreted by the model
+    pub extra: Vec<Tag>,
+}
+
+impl Ordinance {
+    pub fn new(kind: OrdinanceKind) -> Self {
+        Ordinance {
+            kind,
+            date: None,
+            temple: None,
+            place: None,
+            status: None,
+            status_date: None,
+            family: None,
+            extra: vec![],
+        }
+    }
+
+    pub fn is_ordinance_tag(name: &str) -> bool {
+        OrdinanceKind::from_tag(name).is_some()
+    }
+
+    /// Builds the ordinance from its GED structure, a status
+    /// that isn't understood is kept as is
+    pub fn from_tag(tag: &Tag) -> Option<Self> {
+        let kind = OrdinanceKind::from_tag(&tag.name)?;
+        let stat = tag.nested.iter().find(|t| t.name == "STAT");
+        let status = stat.and_then(|s| s.content.as_deref()).and_then(OrdinanceStatus::from_code);
+        Some(Ordinance {
+            kind,
+            date: tag.value("DATE"),
+            temple: tag.value("TEMP").map(|t| t.trim().to_owned()),
+            place: tag.value("PLAC"),
+            status,
+            status_date: stat.filter(|_| status.is_some()).and_then(|s| s.value("DATE")),
+            family: tag.value("FAMC").map(|f| f.trim().to_owned()),
+            extra: tag.nested.iter()
+                .filter(|t| !matches!(t.name.as_str(), "DATE" | "TEMP" | "PLAC" | "FAMC"))
+                .filter(|t| t.name != "STAT" || status.is_none())
+                .cloned()
+                .collect(),
+        })
+    }
+
+    pub fn to_tag(&self) -> Tag {
+        let tag = Tag::new(self.kind.tag(), None);
+        let status = self.status.map(|s| {
+            let stat = Tag::new("STAT", Some(s.code()));
+            match &self.status_date {
+                Some(date) => stat.nest(Tag::new("DATE", Some(date))),
+                None => stat,
+            }
+        });
+        self.date.iter().map(|d| Tag::new("DATE", Some(d)))
+            .chain(self.temple.iter().map(|t| Tag::new("TEMP", Some(t))))
+            .chain(self.place.iter().map(|p| Tag::new("PLAC", Some(p))))
+            .chain(status)
+            .chain(self.family.iter().map(|f| Tag::new("FAMC", Some(f))))
+            .chain(self.extra.iter().cloned())
+            .fold(tag, |tag, nested| tag.nest(nested))
+    }
+
+    /// Checks the date, the temple code against the shipped
+    /// list and the status against the kind of ordinance
+    pub fn validate(&self) -> Result<(), OrdinanceError> {
+        if let Some(date) = &self.date {
+            if GedDate::parse(date).is_none() {
+                return Err(OrdinanceError::InvalidDate(date.clone()));
+            }
+        }
+        if let Some(code) = &self.temple {
+            if temple(code).is_none() {
+                return Err(OrdinanceError::UnknownTemple(code.clone()));
+            }
+        }
+        if let Some(status) = self.status {
+            if !self.kind.statuses().contains(&status) {
+                return Err(OrdinanceError::StatusNotAllowed(self.kind, status));
+            }
+        }
+        if self.kind == OrdinanceKind::SealingToParents && self.family.is_none() {
+            return Err(OrdinanceError::MissingFamily);
+        }
+        Ok(())
+    }
+
+    /// One line description: status, date and temple
+    pub fn summary(&self) -> String {
+        let temple = self.temple.as_deref().map(|code| match temple(code) {
+            Some(temple) => format!("{} ({})", temple.name, temple.code),
+            None => code.to_owned(),
+        });
+        self.status.map(|s| s.label().to_owned()).into_iter()
+            .chain(self.date.clone())
+            .chain(temple)
+            .chain(self.place.clone())
+            .filter(|s| !s.trim().is_empty())
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+}
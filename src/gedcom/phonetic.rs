@@ -0,0 +1,265 @@
+//! Phonetic name encodings used to find persons whose
+//! names were spelled differently across the records.
+
+/// American Soundex code of a name (`Robert` -> `R163`)
+pub fn soundex(name: &str) -> Option<String> {
+    let letters: Vec<char> = name.chars()
+        .filter(|c| c.is_ascii_alphabetic())
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    let first = *letters.first()?;
+    let digit = |c: char| match c {
+        'B' | 'F' | 'P' | 'V' => Some('1'),
+        'C' | 'G' | 'J' | 'K' | 'Q' | 'S' | 'X' | 'Z' => Some('2'),
+        'D' | 'T' => Some('3'),
+        'L' => Some('4'),
+        'M' | 'N' => Some('5'),
+        'R' => Some('6'),
+        _ => None,
+    };
+    let mut code = first.to_string();
+    let mut last = digit(first);
+    for &c in &letters[1..] {
+        let current = digit(c);
+        // H and W do not separate letters with the same code
+        if c == 'H' || c == 'W' {
+            continue;
+        }
+        if let Some(d) = current {
+            if current != last {
+                code.push(d);
+            }
+        }
+        last = current;
+        if code.len() == 4 {
+            break;
+        }
+    }
+    Some(format!("{:0<4}", code))
+}
+
+/// Daitch–Mokotoff rule: the letters sequence with its codes
+/// at the start of a name, before a vowel and elsewhere. An
+/// empty code stands for a letter that is not coded.
+struct DmRule {
+    pattern: &'static str,
+    codes: [&'static str; 3],
+    alternate: Option<[&'static str; 3]>,
+}
+
+const fn rule(pattern: &'static str, codes: [&'static str; 3]) -> DmRule {
+    DmRule { pattern, codes, alternate: None }
+}
+
+const fn branching(pattern: &'static str, codes: [&'static str; 3], alternate: [&'static str; 3]) -> DmRule {
+    DmRule { pattern, codes, alternate: Some(alternate) }
+}
+
+/// Daitch–Mokotoff coding chart, longest patterns first
+/// so that the first match is always the right one
+const DM_RULES: &[DmRule] = &[
+    rule("SCHTSCH", ["2", "4", "4"]),
+    rule("SCHTSH", ["2", "4", "4"]),
+    rule("SCHTCH", ["2", "4", "4"]),
+    rule("SHTSCH", ["2", "4", "4"]),
+    rule("ZHDZH", ["2", "4", "4"]),
+    rule("SHTCH", ["2", "4", "4"]),
+    rule("SHTSH", ["2", "4", "4"]),
+    rule("STSCH", ["2", "4", "4"]),
+    rule("TTSCH", ["4", "4", "4"]),
+    rule("ZSCH", ["4", "4", "4"]),
+    rule("SCHT", ["2", "43", "43"]),
+    rule("SCHD", ["2", "43", "43"]),
+    rule("STCH", ["2", "4", "4"]),
+    rule("STRZ", ["2", "4", "4"]),
+    rule("STRS", ["2", "4", "4"]),
+    rule("STSH", ["2", "4", "4"]),
+    rule("SZCZ", ["2", "4", "4"]),
+    rule("SZCS", ["2", "4", "4"]),
+    rule("TTCH", ["4", "4", "4"]),
+    rule("TSCH", ["4", "4", "4"]),
+    rule("TTSZ", ["4", "4", "4"]),
+    rule("ZDZH", ["2", "4", "4"]),
+    rule("SHCH", ["2", "4", "4"]),
+    rule("CHS", ["5", "54", "54"]),
+    rule("CSZ", ["4", "4", "4"]),
+    rule("CZS", ["4", "4", "4"]),
+    rule("DRZ", ["4", "4", "4"]),
+    rule("DRS", ["4", "4", "4"]),
+    rule("DSH", ["4", "4", "4"]),
+    rule("DSZ", ["4", "4", "4"]),
+    rule("DZH", ["4", "4", "4"]),
+    rule("DZS", ["4", "4", "4"]),
+    rule("SCH", ["4", "4", "4"]),
+    rule("SHT", ["2", "43", "43"]),
+    rule("SZT", ["2", "43", "43"]),
+    rule("SHD", ["2", "43", "43"]),
+    rule("SZD", ["2", "43", "43"]),
+    rule("TCH", ["4", "4", "4"]),
+    rule("TRZ", ["4", "4", "4"]),
+    rule("TRS", ["4", "4", "4"]),
+    rule("TSH", ["4", "4", "4"]),
+    rule("TTS", ["4", "4", "4"]),
+    rule("TTZ", ["4", "4", "4"]),
+    rule("TZS", ["4", "4", "4"]),
+    rule("TSZ", ["4", "4", "4"]),
+    rule("ZDZ", ["2", "4", "4"]),
+    rule("ZHD", ["2", "43", "43"]),
+    rule("ZSH", ["4", "4", "4"]),
+    rule("AI", ["0", "1", ""]),
+    rule("AJ", ["0", "1", ""]),
+    rule("AY", ["0", "1", ""]),
+    rule("AU", ["0", "7", ""]),
+    branching("CH", ["5", "5", "5"], ["4", "4", "4"]),
+    branching("CK", ["5", "5", "5"], ["45", "45", "45"]),
+    rule("CS", ["4", "4", "4"]),
+    rule("CZ", ["4", "4", "4"]),
+    rule("DS", ["4", "4", "4"]),
+    rule("DZ", ["4", "4", "4"]),
+    rule("DT", ["3", "3", "3"]),
+    rule("EI", ["0", "1", ""]),
+    rule("EJ", ["0", "1", ""]),
+    rule("EY", ["0", "1", ""]),
+    rule("EU", ["1", "1", ""]),
+    rule("FB", ["7", "7", "7"]),
+    rule("IA", ["1", "", ""]),
+    rule("IE", ["1", "", ""]),
+    rule("IO", ["1", "", ""]),
+    rule("IU", ["1", "", ""]),
+    rule("KS", ["5", "54", "54"]),
+    rule("KH", ["5", "5", "5"]),
+    rule("MN", ["66", "66", "66"]),
+    rule("NM", ["66", "66", "66"]),
+    rule("OI", ["0", "1", ""]),
+    rule("OJ", ["0", "1", ""]),
+    rule("OY", ["0", "1", ""]),
+    rule("PF", ["7", "7", "7"]),
+    rule("PH", ["7", "7", "7"]),
+    branching("RZ", ["94", "94", "94"], ["4", "4", "4"]),
+    branching("RS", ["94", "94", "94"], ["4", "4", "4"]),
+    rule("SH", ["4", "4", "4"]),
+    rule("SC", ["2", "4", "4"]),
+    rule("ST", ["2", "43", "43"]),
+    rule("SZ", ["4", "4", "4"]),
+    rule("SD", ["2", "43", "43"]),
+    rule("TH", ["3", "3", "3"]),
+    rule("TS", ["4", "4", "4"]),
+    rule("TC", ["4", "4", "4"]),
+    rule("TZ", ["4", "4", "4"]),
+    rule("UI", ["0", "1", ""]),
+    rule("UJ", ["0", "1", ""]),
+    rule("UY", ["0", "1", ""]),
+    rule("UE", ["0", "", ""]),
+    rule("ZD", ["2", "43", "43"]),
+    rule("ZH", ["4", "4", "4"]),
+    rule("ZS", ["4", "4", "4"]),
+    rule("A", ["0", "", ""]),
+    rule("B", ["7", "7", "7"]),
+    branching("C", ["5", "5", "5"], ["4", "4", "4"]),
+    rule("D", ["3", "3", "3"]),
+    rule("E", ["0", "", ""]),
+    rule("F", ["7", "7", "7"]),
+    rule("G", ["5", "5", "5"]),
+    rule("H", ["5", "5", ""]),
+    rule("I", ["0", "", ""]),
+    branching("J", ["1", "1", "1"], ["4", "4", "4"]),
+    rule("K", ["5", "5", "5"]),
+    rule("L", ["8", "8", "8"]),
+    rule("M", ["6", "6", "6"]),
+    rule("N", ["6", "6", "6"]),
+    rule("O", ["0", "", ""]),
+    rule("P", ["7", "7", "7"]),
+    rule("Q", ["5", "5", "5"]),
+    rule("R", ["9", "9", "9"]),
+    rule("S", ["4", "4", "4"]),
+    rule("T", ["3", "3", "3"]),
+    rule("U", ["0", "", ""]),
+    rule("V", ["7", "7", "7"]),
+    rule("W", ["7", "7", "7"]),
+    rule("X", ["5", "54", "54"]),
+    rule("Y", ["1", "", ""]),
+    rule("Z", ["4", "4", "4"]),
+];
+
+/// Length of a Daitch–Mokotoff code
+const DM_LENGTH: usize = 6;
+
+/// One of the possible codings of a name, names with
+/// ambiguous letters produce several of them
+#[derive(Clone)]
+struct DmBranch {
+    code: String,
+    last: String,
+}
+
+impl DmBranch {
+    /// Whether the code has all its digits, so that
+    /// the rest of the name can no longer change it
+    fn is_full(&self) -> bool {
+        self.code.len() >= DM_LENGTH
+    }
+
+    fn push(&mut self, code: &str) {
+        if !code.is_empty() && code != self.last && self.code.len() < DM_LENGTH {
+            self.code.push_str(code);
+            self.code.truncate(DM_LENGTH);
+        }
+        self.last = code.to_owned();
+    }
+}
+
+/// All the Daitch–Mokotoff codes of a name, sorted and
+/// without duplicates (`Peters` -> `[734000, 739400]`)
+pub fn daitch_mokotoff(name: &str) -> Vec<String> {
+    let letters: String = name.chars()
+        .filter(|c| c.is_ascii_alphabetic())
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    if letters.is_empty() {
+        return vec![];
+    }
+    let is_vowel = |s: &str| s.starts_with(|c| "AEIOUY".contains(c));
+    let mut branches = vec![DmBranch { code: String::new(), last: String::new() }];
+    let mut pos = 0;
+    while pos < letters.len() {
+        let rest = &letters[pos..];
+        let rule = DM_RULES.iter().find(|r| rest.starts_with(r.pattern)).unwrap();
+        let next = &rest[rule.pattern.len()..];
+        let column = if pos == 0 { 0 } else if is_vowel(next) { 1 } else { 2 };
+        branches = match rule.alternate {
+            Some(alternate) => branches.into_iter()
+                .flat_map(|b| {
+                    if b.is_full() {
+                        return vec![b];
+                    }
+                    let (mut first, mut second) = (b.clone(), b);
+                    first.push(rule.codes[column]);
+                    second.push(alternate[column]);
+                    vec![first, second]
+                })
+                .collect(),
+            None => branches.into_iter()
+                .map(|mut b| { b.push(rule.codes[column]); b })
+                .collect(),
+        };
+        // Branches coded alike so far would go on alike
+        branches.sort_by(|a, b| (&a.code, &a.last).cmp(&(&b.code, &b.last)));
+        branches.dedup_by(|a, b| a.code == b.code && (a.is_full() || a.last == b.last));
+        if branches.iter().all(DmBranch::is_full) {
+            break;
+        }
+        pos += rule.pattern.len();
+    }
+    let mut codes: Vec<String> = branches.into_iter()
+        .map(|b| format!("{:0<width$}", b.code, width = DM_LENGTH))
+        .collect();
+    codes.sort();
+    codes.dedup();
+    codes
+}
+
+/// Checks whether two names share a Daitch–Mokotoff code
+pub fn daitch_mokotoff_match(left: &str, right: &str) -> bool {
+    let right = daitch_mokotoff(right);
+    daitch_mokotoff(left).iter().any(|c| right.contains(c))
+}
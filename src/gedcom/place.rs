@@ -0,0 +1,23 @@
+//! Coordinates of the places (`PLAC.MAP`): GED coordinates
+//! name their hemisphere (`N48.85`, `W2.3`) where the other
+//! formats give signed degrees.
+
+/// Degrees of a GED coordinate (`N48.85`, `W2.3`)
+pub fn degrees(value: &str) -> Option<f64> {
+    let value = value.trim();
+    let sign = match value.chars().next()? {
+        'N' | 'E' => 1.0,
+        'S' | 'W' => -1.0,
+        _ => return None,
+    };
+    value[1..].parse::<f64>().ok().map(|d| sign * d)
+}
+
+/// GED coordinate of the degrees, `positive` and `negative`
+/// being the hemispheres (`N` and `S`, `E` and `W`)
+pub fn coordinate(degrees: f64, positive: char, negative: char) -> String {
+    match degrees < 0.0 {
+        true => format!("{}{}", negative, -degrees),
+        false => format!("{}{}", positive, degrees),
+    }
+}
@@ -0,0 +1,281 @@
+//! Privacy of the living persons: inference of who is probably
+//! still alive and the filter the exports go through to omit or
+//! redact them.
+
+use crate::gedcom::export::pointed;
+use crate::gedcom::{xref_key, FamilyGraph, GedDate, Record, RecordRegistry, SimpleDate, Tag};
+use std::collections::HashSet;
+
+/// Events proving that a person is dead
+const DEATH_EVENTS: [&str; 3] = ["DEAT", "BURI", "CREM"];
+
+/// Tags kept for persons exported with their name only
+const NAME_ONLY_TAGS: [&str; 4] = ["NAME", "SEX", "FAMC", "FAMS"];
+
+/// Tags kept for redacted persons and private families
+const LINK_TAGS: [&str; 5] = ["FAMC", "FAMS", "HUSB", "WIFE", "CHIL"];
+
+/// Generations of descendants looked at to date a person
+const DESCENDANT_DEPTH: usize = 6;
+
+/// Rules of the living persons inference
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub struct LivingRules {
+    /// Persons born at least this many years ago are dead
+    pub max_age: i32,
+    /// Youngest age at which someone has a child or marries
+    pub min_parent_age: i32,
+    /// Year the ages are computed at
+    pub year: i32,
+}
+
+impl Default for LivingRules {
+    fn default() -> Self {
+        LivingRules {
+            max_age: 110,
+            min_parent_age: 12,
+            year: SimpleDate::today().year,
+        }
+    }
+}
+
+impl LivingRules {
+    /// Latest year the person can have been born in, given by
+    /// their own dated events, the marriages of their families
+    /// and the dates of their descendants
+    pub fn latest_birth(&self, person: &Record, graph: &FamilyGraph, registry: &RecordRegistry) -> Option<i32> {
+        self.latest_birth_at(person, graph, registry, DESCENDANT_DEPTH)
+    }
+
+    fn latest_birth_at(&self, person: &Record, graph: &FamilyGraph, registry: &RecordRegistry, depth: usize) -> Option<i32> {
+        let years = |record: &Record| -> Vec<i32> {
+            record.events.iter()
+                .filter_map(|e| e.date.as_deref())
+                .filter_map(GedDate::parse)
+                .filter_map(|d| d.year())
+                .collect()
+        };
+        let own = years(person).into_iter().min();
+        let families = person.families("FAMS", registry).iter()
+            .flat_map(|f| years(&f.borrow()))
+            .map(|y| y - self.min_parent_age)
+            .min();
+        let descendants = match (depth, graph.person_of(person)) {
+            (0, _) | (_, None) => None,
+            (_, Some(id)) => graph.children(id).into_iter()
+                .filter_map(|c| graph.record(registry, c))
+                .filter_map(|c| self.latest_birth_at(&c.borrow(), graph, registry, depth - 1))
+                .map(|y| y - self.min_parent_age)
+                .min(),
+        };
+        own.into_iter().chain(families).chain(descendants).min()
+    }
+
+    /// Whether the person is probably alive: no death event
+    /// and no evidence of a birth more than [`max_age`] years
+    /// ago. Persons without any date are considered alive,
+    /// the user's own flag always prevails.
+    ///
+    /// [`max_age`]: LivingRules::max_age
+    pub fn is_living(&self, person: &Record, graph: &FamilyGraph, registry: &RecordRegistry) -> bool {
+        if let Some(living) = person.living {
+            return living;
+        }
+        if DEATH_EVENTS.iter().any(|t| person.events_of(t).next().is_some()) {
+            return false;
+        }
+        self.latest_birth(person, graph, registry)
+            .map_or(true, |born| self.year - born < self.max_age)
+    }
+}
+
+/// What the exports do with the living persons
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum PrivacyMode {
+    /// Exported like everybody else
+    Include,
+    /// Name and family links only, without any event
+    NamesOnly,
+    /// Shown as "Living", keeping the family links only
+    Redact,
+    /// Left out along with the links to them
+    Omit,
+}
+
+impl Default for PrivacyMode {
+    fn default() -> Self {
+        PrivacyMode::Include
+    }
+}
+
+impl PrivacyMode {
+    /// Mode from the identifier used by the option lists
+    /// (`include`, `names`, `redact`, `omit`)
+    pub fn from_id(id: &str) -> Option<Self> {
+        match id {
+            "include" => Some(PrivacyMode::Include),
+            "names" => Some(PrivacyMode::NamesOnly),
+            "redact" => Some(PrivacyMode::Redact),
+            "omit" => Some(PrivacyMode::Omit),
+            _ => None,
+        }
+    }
+}
+
+/// How a record appears in an export
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum Visibility {
+    Public,
+    NameOnly,
+    Redacted,
+    Hidden,
+}
+
+/// Living persons of a registry and the way the
+/// chosen mode exports them
+pub struct PrivacyFilter {
+    mode: PrivacyMode,
+    living: HashSet<u64>,
+}
+
+impl PrivacyFilter {
+    pub fn new(registry: &RecordRegistry, mode: PrivacyMode, rules: &LivingRules) -> Self {
+        let graph = FamilyGraph::new(registry);
+        let living = registry.iter()
+            .filter(|(_, r)| r.borrow().rtype == "INDI")
+            .filter(|(_, r)| rules.is_living(&r.borrow(), &graph, registry))
+            .map(|(key, _)| *key)
+            .collect();
+        PrivacyFilter { mode, living }
+    }
+
+    pub fn mode(&self) -> PrivacyMode {
+        self.mode
+    }
+
+    pub fn is_living(&self, record: &Record) -> bool {
+        self.living.contains(&xref_key(&record.xref))
+    }
+
+    /// Number of living persons found
+    pub fn living_count(&self) -> usize {
+        self.living.len()
+    }
+
+    pub fn visibility(&self, record: &Record) -> Visibility {
+        self.visibility_of(xref_key(&record.xref))
+    }
+
+    fn visibility_of(&self, key: u64) -> Visibility {
+        if !self.living.contains(&key) {
+            return Visibility::Public;
+        }
+        match self.mode {
+            PrivacyMode::Include => Visibility::Public,
+            PrivacyMode::NamesOnly => Visibility::NameOnly,
+            PrivacyMode::Redact => Visibility::Redacted,
+            PrivacyMode::Omit => Visibility::Hidden,
+        }
+    }
+
+    /// Name under which a person is exported
+    pub fn shown_name(&self, person: &Record) -> String {
+        match self.visibility(person) {
+            Visibility::Public | Visibility::NameOnly => person.display_name(),
+            _ => String::from("Living"),
+        }
+    }
+
+    /// Whether the events of a family can be exported,
+    /// which is not the case when a partner is private
+    pub fn is_family_public(&self, family: &Record) -> bool {
+        ["HUSB", "WIFE"].iter()
+            .flat_map(|tag| family.pointers(tag))
+            .all(|xref| self.visibility_of(xref_key(&xref)) == Visibility::Public)
+    }
+
+    /// Copy of the registry as it should be exported: hidden
+    /// persons and the pointers to them are removed, private
+    /// persons and families are stripped of their details.
+    pub fn apply(&self, registry: &RecordRegistry) -> RecordRegistry {
+        let hidden: HashSet<u64> = match self.mode {
+            PrivacyMode::Omit => self.living.clone(),
+            _ => HashSet::new(),
+        };
+        let points_to_hidden = |tag: &Tag| tag.content.as_deref()
+            .map(str::trim)
+            .filter(|c| c.len() > 2 && c.starts_with('@') && c.ends_with('@'))
+            .map_or(false, |xref| hidden.contains(&xref_key(xref)));
+        let mut filtered = RecordRegistry::new();
+        for (key, record) in registry.iter().filter(|(key, _)| !hidden.contains(key)) {
+            let original = record.borrow();
+            let mut copy = original.clone();
+            copy.extra.retain(|t| !points_to_hidden(t));
+            let keep_only = |copy: &mut Record, tags: &[&str]| {
+                copy.extra.retain(|t| tags.contains(&t.name.as_str()));
+                copy.events.clear();
+                copy.ordinances.clear();
+                copy.contact = None;
+            };
+            match copy.rtype.as_str() {
+                "INDI" => match self.visibility(&original) {
+                    Visibility::NameOnly => keep_only(&mut copy, &NAME_ONLY_TAGS),
+                    Visibility::Redacted => {
+                        keep_only(&mut copy, &LINK_TAGS);
+                        copy.name = String::from("Living //");
+                        // Identifiers would tell who the person is
+                        copy.rin = 0;
+                        copy.uid = None;
+                    },
+                    _ => (),
+                },
+                "FAM" if !self.is_family_public(&original) => keep_only(&mut copy, &LINK_TAGS),
+                _ => (),
+            }
+            // A family none of whose members is left is dropped
+            if copy.rtype == "FAM" && !copy.extra.iter().any(|t| LINK_TAGS[2..].contains(&t.name.as_str())) {
+                continue;
+            }
+            filtered.insert(*key, copy.into());
+        }
+        let families: HashSet<u64> = filtered.iter()
+            .filter(|(_, r)| r.borrow().rtype == "FAM")
+            .map(|(key, _)| *key)
+            .collect();
+        for record in filtered.values() {
+            let mut record = record.borrow_mut();
+            if record.rtype == "INDI" {
+                record.extra.retain(|t| match t.name.as_str() {
+                    "FAMC" | "FAMS" => t.content.as_deref().map_or(false, |x| families.contains(&xref_key(x.trim()))),
+                    _ => true,
+                });
+            }
+        }
+        Self::drop_unreferenced(registry, &mut filtered);
+        filtered
+    }
+
+    /// Drops the records (notes, sources, media...) that were only
+    /// pointed to by the persons left out or stripped, and then
+    /// those only pointed to by the dropped ones
+    fn drop_unreferenced(registry: &RecordRegistry, filtered: &mut RecordRegistry) {
+        let referenced = |registry: &RecordRegistry| -> HashSet<u64> {
+            registry.values().flat_map(|r| pointed(&r.borrow())).collect()
+        };
+        let was_referenced = referenced(registry);
+        loop {
+            let is_referenced = referenced(filtered);
+            let unreferenced: Vec<u64> = filtered.iter()
+                .filter(|(_, r)| !matches!(r.borrow().rtype.as_str(), "INDI" | "FAM" | "SUBM"))
+                .map(|(key, _)| *key)
+                .filter(|key| was_referenced.contains(key) && !is_referenced.contains(key))
+                .collect();
+            if unreferenced.is_empty() {
+                break;
+            }
+            for key in unreferenced {
+                filtered.remove(&key);
+            }
+        }
+    }
+}
@@ -0,0 +1,148 @@
+//! Multi-criteria search of individuals in a record tree
+
+use crate::gedcom::phonetic::{daitch_mokotoff_match, soundex};
+use crate::gedcom::{Record, RecordRc, RecordRegistry, SimpleDate, GedDate};
+
+/// The way names are compared
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum NameMatch {
+    /// Case insensitive equality
+    Exact,
+    /// Case insensitive substring
+    Contains,
+    Soundex,
+    DaitchMokotoff,
+}
+
+impl Default for NameMatch {
+    fn default() -> Self {
+        NameMatch::Contains
+    }
+}
+
+/// Date range an event of the individual has to fall into,
+/// `event` being `None` means "any event".
+#[derive(Default,Debug,Clone)]
+pub struct DateCriterion {
+    pub event: Option<String>,
+    pub from: Option<SimpleDate>,
+    pub to: Option<SimpleDate>,
+}
+
+/// Search criteria, empty ones are ignored. An individual
+/// has to match all of the given criteria.
+#[derive(Default,Debug,Clone)]
+pub struct SearchCriteria {
+    pub given: Option<String>,
+    pub surname: Option<String>,
+    pub name_match: NameMatch,
+    pub sex: Option<char>,
+    pub dates: Vec<DateCriterion>,
+    pub place: Option<String>,
+    pub custom_id: Option<String>,
+}
+
+impl NameMatch {
+    /// Compares a searched name to a record name word by
+    /// word: any of the words has to match.
+    pub fn matches(&self, searched: &str, name: &str) -> bool {
+        let (searched, name) = (searched.trim().to_lowercase(), name.to_lowercase());
+        if searched.is_empty() {
+            return true;
+        }
+        match self {
+            NameMatch::Exact => name.split_whitespace().any(|w| w == searched) || name == searched,
+            NameMatch::Contains => name.contains(&searched),
+            NameMatch::Soundex => {
+                let code = soundex(&searched);
+                code.is_some() && name.split_whitespace().any(|w| soundex(w) == code)
+            },
+            NameMatch::DaitchMokotoff => name.split_whitespace()
+                .any(|w| daitch_mokotoff_match(&searched, w)),
+        }
+    }
+}
+
+impl DateCriterion {
+    pub fn matches(&self, record: &Record) -> bool {
+        record.events.iter()
+            .filter(|e| self.event.as_ref().map_or(true, |t| *t == e.tag))
+            .filter_map(|e| e.date.as_deref().and_then(GedDate::parse))
+            .any(|d| d.overlaps(self.from, self.to))
+    }
+}
+
+impl SearchCriteria {
+    pub fn is_empty(&self) -> bool {
+        self.given.is_none()
+            && self.surname.is_none()
+            && self.sex.is_none()
+            && self.dates.is_empty()
+            && self.place.is_none()
+            && self.custom_id.is_none()
+    }
+
+    pub fn matches(&self, record: &Record) -> bool {
+        if record.rtype != "INDI" {
+            return false;
+        }
+        let given = self.given.as_ref()
+            .map_or(true, |g| self.name_match.matches(g, &record.given_names()));
+        let surname = self.surname.as_ref()
+            .map_or(true, |s| self.name_match.matches(s, &record.surname()));
+        let sex = self.sex.map_or(true, |s| s.to_ascii_uppercase() == record.sex());
+        let dates = self.dates.iter().all(|d| d.matches(record));
+        let place = self.place.as_ref().map_or(true, |p| {
+            let p = p.to_lowercase();
+            record.events.iter()
+                .filter_map(|e| e.place.as_ref())
+                .any(|place| place.to_lowercase().contains(&p))
+        });
+        let custom_id = self.custom_id.as_ref().map_or(true, |id| {
+            record.custom_id().map_or(false, |c| c.trim().eq_ignore_ascii_case(id.trim()))
+        });
+        given && surname && sex && dates && place && custom_id
+    }
+}
+
+/// Returns all the individuals matching the criteria,
+/// sorted by surname and given names.
+pub fn search(registry: &RecordRegistry, criteria: &SearchCriteria) -> Vec<RecordRc> {
+    let mut found: Vec<RecordRc> = registry.values()
+        .filter(|r| criteria.matches(&r.borrow()))
+        .cloned()
+        .collect();
+    found.sort_by_key(|r| {
+        let r = r.borrow();
+        (r.surname().to_lowercase(), r.given_names().to_lowercase(), r.id)
+    });
+    found
+}
+
+/// Identifiers a record can be looked up by
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum IdKind {
+    /// Record Identification Number
+    Rin,
+    /// User reference number (`REFN`)
+    CustomId,
+    /// Ancestral File Number
+    Afn,
+}
+
+/// Returns the records having the given identifier. RINs are
+/// unique, custom IDs and AFNs may be shared by duplicates.
+pub fn find_by_id(registry: &RecordRegistry, kind: IdKind, id: &str) -> Vec<RecordRc> {
+    let id = id.trim();
+    let matches = |record: &Record| match kind {
+        IdKind::Rin => id.parse::<u64>().map_or(false, |rin| record.rin == rin),
+        IdKind::CustomId => record.custom_id().map_or(false, |c| c.trim().eq_ignore_ascii_case(id)),
+        IdKind::Afn => record.afn().map_or(false, |a| a.trim().eq_ignore_ascii_case(id)),
+    };
+    let mut found: Vec<RecordRc> = registry.values()
+        .filter(|r| matches(&r.borrow()))
+        .cloned()
+        .collect();
+    found.sort_by_key(|r| r.borrow().rin);
+    found
+}
@@ -0,0 +1,340 @@
+//! Statistics of a tree: counts, distributions and completeness
+//! of the data, shown by the statistics window, printed by the
+//! command line and exportable as CSV.
+
+use crate::gedcom::json::Json;
+use crate::gedcom::privacy::{LivingRules, PrivacyFilter, PrivacyMode};
+use crate::gedcom::{csv, xref_key, FamilyGraph, GedDate, PersonId, Record, RecordRegistry};
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+
+/// Ages beyond which a lifespan or an age at
+/// marriage is taken for an error of the data
+const MAX_AGE: i32 = 120;
+
+/// Places listed by the summaries
+pub const TOP_PLACES: usize = 20;
+
+/// Count, mean and range of a series of ages
+#[derive(Debug,Clone,Default,PartialEq)]
+pub struct Measure {
+    pub count: usize,
+    pub mean: Option<f64>,
+    pub min: Option<i32>,
+    pub max: Option<i32>,
+}
+
+impl Measure {
+    fn of(values: &[i32]) -> Self {
+        Measure {
+            count: values.len(),
+            mean: match values.len() {
+                0 => None,
+                n => Some(values.iter().map(|v| *v as f64).sum::<f64>() / n as f64),
+            },
+            min: values.iter().min().copied(),
+            max: values.iter().max().copied(),
+        }
+    }
+
+    /// Mean with one decimal, empty without values
+    pub fn mean_text(&self) -> String {
+        self.mean.map(|m| format!("{:.1}", m)).unwrap_or_default()
+    }
+
+    fn to_json(&self) -> Json {
+        Json::object()
+            .with("count", self.count)
+            .with("mean", self.mean.map(|m| (m * 10.0).round() / 10.0))
+            .with("min", self.min)
+            .with("max", self.max)
+    }
+}
+
+/// Persons having a piece of data, out of all the persons
+#[derive(Debug,Clone,PartialEq)]
+pub struct Completeness {
+    pub label: &'static str,
+    pub count: usize,
+    pub total: usize,
+}
+
+impl Completeness {
+    pub fn percent(&self) -> f64 {
+        match self.total {
+            0 => 0.0,
+            total => self.count as f64 * 100.0 / total as f64,
+        }
+    }
+}
+
+/// Statistics of the records of a registry
+#[derive(Debug,Clone,Default,PartialEq)]
+pub struct Statistics {
+    /// Records by type (`INDI`, `FAM`...)
+    pub records: BTreeMap<String, usize>,
+    pub males: usize,
+    pub females: usize,
+    pub unknown_sex: usize,
+    pub living: usize,
+    /// Surnames with the number of persons bearing them,
+    /// the most frequent first
+    pub surnames: Vec<(String, usize)>,
+    /// Births and deaths by decade (`1850` for 1850-1859)
+    pub births: BTreeMap<i32, usize>,
+    pub deaths: BTreeMap<i32, usize>,
+    /// Earliest and latest years of the events
+    pub years: Option<(i32, i32)>,
+    /// Age at death of the persons with both years known
+    pub lifespan: Measure,
+    /// Age of the husbands and of the wives at their marriage
+    pub marriage_men: Measure,
+    pub marriage_women: Measure,
+    /// Families by number of children
+    pub children: BTreeMap<usize, usize>,
+    pub children_per_family: Measure,
+    /// Places with their number of events, the most frequent first
+    pub places: Vec<(String, usize)>,
+    /// Generations of the longest line of ancestors
+    pub generations: usize,
+    pub completeness: Vec<Completeness>,
+}
+
+/// Year of the first of the events found with a year
+fn year_of(record: &Record, tags: &[&str]) -> Option<i32> {
+    tags.iter().find_map(|t| record.event_date(t).and_then(|d| d.year()))
+}
+
+fn decade(year: i32) -> i32 {
+    year.div_euclid(10) * 10
+}
+
+/// Entries by decreasing count, then by name
+fn by_frequency(counts: HashMap<String, usize>) -> Vec<(String, usize)> {
+    let mut sorted: Vec<(String, usize)> = counts.into_iter().collect();
+    sorted.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    sorted
+}
+
+/// Generations of ancestors of every person, the person included,
+/// a line looping back on itself being cut where it does
+fn generations(graph: &FamilyGraph) -> usize {
+    let mut depth: HashMap<PersonId, usize> = HashMap::new();
+    for start in graph.persons() {
+        // Depth first walk, a person being done once their parents are
+        let mut stack = vec![(start, false)];
+        let mut visiting = vec![];
+        while let Some((person, expanded)) = stack.pop() {
+            if depth.contains_key(&person) {
+                continue;
+            }
+            if expanded {
+                visiting.retain(|p| *p != person);
+                let parents = graph.parents(person).iter().filter_map(|p| depth.get(p)).max().copied();
+                depth.insert(person, parents.unwrap_or(0) + 1);
+                continue;
+            }
+            visiting.push(person);
+            stack.push((person, true));
+            for parent in graph.parents(person) {
+                if !depth.contains_key(&parent) && !visiting.contains(&parent) {
+                    stack.push((parent, false));
+                }
+            }
+        }
+    }
+    depth.values().max().copied().unwrap_or(0)
+}
+
+impl Statistics {
+    pub fn new(registry: &RecordRegistry) -> Self {
+        let graph = FamilyGraph::new(registry);
+        let mut stats = Statistics::default();
+        let mut surnames = HashMap::new();
+        let mut places = HashMap::new();
+        let (mut lifespans, mut men, mut women) = (vec![], vec![], vec![]);
+        let mut children = vec![];
+        let mut years: Vec<i32> = vec![];
+        let (mut birth_dates, mut birth_places, mut death_dates, mut sources, mut parents) = (0, 0, 0, 0, 0);
+        let mut persons = 0;
+
+        for record in registry.values() {
+            let record = record.borrow();
+            *stats.records.entry(record.rtype.clone()).or_default() += 1;
+            for event in &record.events {
+                years.extend(event.date.as_deref().and_then(|d| GedDate::parse(d)?.year()));
+                if let Some(place) = event.place.as_deref().map(str::trim).filter(|p| !p.is_empty()) {
+                    *places.entry(place.to_owned()).or_default() += 1;
+                }
+            }
+            match record.rtype.as_str() {
+                "INDI" => (),
+                "FAM" => {
+                    let count = graph.family_of(&record).map_or(0, |f| graph.family_children(f).len());
+                    *stats.children.entry(count).or_default() += 1;
+                    children.push(count as i32);
+                    let married = year_of(&record, &["MARR"]);
+                    for (tag, ages) in [("HUSB", &mut men), ("WIFE", &mut women)].iter_mut() {
+                        let partner = record.pointers(tag).first()
+                            .and_then(|xref| registry.get(&xref_key(xref)).cloned());
+                        let born = partner.and_then(|p| year_of(&p.borrow(), &["BIRT", "CHR"]));
+                        if let (Some(married), Some(born)) = (married, born) {
+                            ages.extend(Some(married - born).filter(|age| (0..=MAX_AGE).contains(age)));
+                        }
+                    }
+                    continue;
+                },
+                _ => continue,
+            }
+            persons += 1;
+            match record.sex() {
+                'M' => stats.males += 1,
+                'F' => stats.females += 1,
+                _ => stats.unknown_sex += 1,
+            }
+            let surname = record.surname();
+            if !surname.trim().is_empty() {
+                *surnames.entry(surname.trim().to_owned()).or_default() += 1;
+            }
+            let (born, died) = (year_of(&record, &["BIRT", "CHR"]), year_of(&record, &["DEAT", "BURI"]));
+            if let Some(born) = born {
+                *stats.births.entry(decade(born)).or_default() += 1;
+            }
+            if let Some(died) = died {
+                *stats.deaths.entry(decade(died)).or_default() += 1;
+            }
+            if let (Some(born), Some(died)) = (born, died) {
+                lifespans.extend(Some(died - born).filter(|age| (0..=MAX_AGE).contains(age)));
+            }
+            let birth = record.events_of("BIRT").next();
+            birth_dates += birth.map_or(false, |b| b.date.is_some()) as usize;
+            birth_places += birth.map_or(false, |b| b.place.is_some()) as usize;
+            death_dates += record.events_of("DEAT").any(|d| d.date.is_some()) as usize;
+            sources += record.extra.iter()
+                .chain(record.events.iter().flat_map(|e| e.extra.iter()))
+                .any(|t| t.name == "SOUR") as usize;
+            parents += graph.person_of(&record).map_or(false, |p| !graph.parents(p).is_empty()) as usize;
+        }
+
+        stats.living = PrivacyFilter::new(registry, PrivacyMode::Omit, &LivingRules::default()).living_count();
+        stats.surnames = by_frequency(surnames);
+        stats.places = by_frequency(places);
+        stats.years = years.iter().min().copied().zip(years.iter().max().copied());
+        stats.lifespan = Measure::of(&lifespans);
+        stats.marriage_men = Measure::of(&men);
+        stats.marriage_women = Measure::of(&women);
+        stats.children_per_family = Measure::of(&children);
+        stats.generations = generations(&graph);
+        let completeness = |label, count| Completeness { label, count, total: persons };
+        stats.completeness = vec![
+            completeness("Birth date", birth_dates),
+            completeness("Birth place", birth_places),
+            completeness("Death date", death_dates),
+            completeness("Sources", sources),
+            completeness("Parents", parents),
+            completeness("Sex", stats.males + stats.females),
+        ];
+        stats
+    }
+
+    pub fn individuals(&self) -> usize {
+        self.males + self.females + self.unknown_sex
+    }
+
+    /// Every figure as a section, an item and a value, in the order
+    /// they are shown. Surnames and places are limited to the
+    /// [most frequent](TOP_PLACES) unless `all` is set.
+    pub fn rows(&self, all: bool) -> Vec<(&'static str, String, String)> {
+        let limit = if all { usize::MAX } else { TOP_PLACES };
+        let mut rows = vec![];
+        let mut row = |section, item: &str, value: String| rows.push((section, item.to_owned(), value));
+        for (rtype, count) in &self.records {
+            row("Records", rtype, count.to_string());
+        }
+        row("Individuals", "Total", self.individuals().to_string());
+        row("Individuals", "Male", self.males.to_string());
+        row("Individuals", "Female", self.females.to_string());
+        row("Individuals", "Unknown sex", self.unknown_sex.to_string());
+        row("Individuals", "Living", self.living.to_string());
+        row("Individuals", "Surnames", self.surnames.len().to_string());
+        if let Some((earliest, latest)) = self.years {
+            row("Individuals", "Years", format!("{} to {}", earliest, latest));
+        }
+        row("Individuals", "Generations", self.generations.to_string());
+        for (surname, count) in self.surnames.iter().take(limit) {
+            row("Surnames", surname, count.to_string());
+        }
+        for (decade, count) in &self.births {
+            row("Births by decade", &format!("{}s", decade), count.to_string());
+        }
+        for (decade, count) in &self.deaths {
+            row("Deaths by decade", &format!("{}s", decade), count.to_string());
+        }
+        for (label, measure) in [("Lifespan", &self.lifespan), ("Age at marriage, men", &self.marriage_men),
+                                 ("Age at marriage, women", &self.marriage_women)].iter() {
+            row("Ages", &format!("{}: persons", label), measure.count.to_string());
+            if let (Some(min), Some(max)) = (measure.min, measure.max) {
+                row("Ages", &format!("{}: average", label), measure.mean_text());
+                row("Ages", &format!("{}: range", label), format!("{} to {}", min, max));
+            }
+        }
+        for (count, families) in &self.children {
+            row("Children per family", &count.to_string(), families.to_string());
+        }
+        row("Children per family", "Average", self.children_per_family.mean_text());
+        for (place, count) in self.places.iter().take(limit) {
+            row("Places", place, count.to_string());
+        }
+        for item in &self.completeness {
+            row("Completeness", item.label, format!("{:.0}% ({}/{})", item.percent(), item.count, item.total));
+        }
+        rows
+    }
+
+    /// Every row of the statistics, as CSV
+    pub fn write_csv<W: Write>(&self, mut out: W) -> std::io::Result<()> {
+        let header = ["Section", "Item", "Value"].iter().map(|s| s.to_string()).collect::<Vec<String>>();
+        csv::write_row(&mut out, &header, ',')?;
+        for (section, item, value) in self.rows(true) {
+            csv::write_row(&mut out, &[section.to_owned(), item, value], ',')?;
+        }
+        Ok(())
+    }
+
+    pub fn to_json(&self) -> Json {
+        let counts = |map: &BTreeMap<i32, usize>| map.iter()
+            .fold(Json::object(), |json, (decade, n)| json.with(&decade.to_string(), *n));
+        let ranked = |list: &[(String, usize)], key: &str| Json::from(list.iter()
+            .map(|(name, n)| Json::object().with(key, name.as_str()).with("count", *n))
+            .collect::<Vec<Json>>());
+        Json::object()
+            .with("records", self.records.iter().fold(Json::object(), |json, (rtype, n)| json.with(rtype, *n)))
+            .with("individuals", Json::object()
+                .with("total", self.individuals())
+                .with("male", self.males)
+                .with("female", self.females)
+                .with("unknown", self.unknown_sex)
+                .with("living", self.living))
+            .with("years", Json::object()
+                .with("earliest", self.years.map(|y| y.0))
+                .with("latest", self.years.map(|y| y.1)))
+            .with("generations", self.generations)
+            .with("surnames", ranked(&self.surnames, "surname"))
+            .with("birthsByDecade", counts(&self.births))
+            .with("deathsByDecade", counts(&self.deaths))
+            .with("lifespan", self.lifespan.to_json())
+            .with("ageAtMarriage", Json::object()
+                .with("men", self.marriage_men.to_json())
+                .with("women", self.marriage_women.to_json()))
+            .with("childrenPerFamily", Json::object()
+                .with("average", self.children_per_family.to_json())
+                .with("families", self.children.iter()
+                    .fold(Json::object(), |json, (count, n)| json.with(&count.to_string(), *n))))
+            .with("places", ranked(&self.places, "place"))
+            .with("completeness", self.completeness.iter()
+                .fold(Json::object(), |json, c| json.with(c.label, Json::object()
+                    .with("count", c.count)
+                    .with("total", c.total)
+                    .with("percent", (c.percent() * 10.0).round() / 10.0))))
+    }
+}
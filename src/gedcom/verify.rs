@@ -0,0 +1,289 @@
+//! Consistency checks of a record tree: impossible dates,
+//! suspicious ages, broken family links and pointers to
+//! records that don't exist.
+
+use crate::gedcom::{xref_key, FamilyGraph, GedDate, PersonId, Record, RecordRc, RecordRegistry, Tag};
+use std::collections::{HashMap, HashSet};
+
+/// Thresholds of the age checks
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub struct VerifyRules {
+    /// Youngest age at which someone has a child
+    pub min_parent_age: i32,
+    /// Oldest age at which a woman gives birth
+    pub max_mother_age: i32,
+}
+
+impl Default for VerifyRules {
+    fn default() -> Self {
+        VerifyRules {
+            min_parent_age: 12,
+            max_mother_age: 55,
+        }
+    }
+}
+
+#[derive(Debug,Clone,Copy,PartialEq,Eq,PartialOrd,Ord)]
+pub enum Severity {
+    /// Data that can't be right
+    Error,
+    /// Data that is unlikely but possible
+    Warning,
+}
+
+#[derive(Debug,Clone,PartialEq)]
+pub enum Problem {
+    DeathBeforeBirth,
+    /// Marriage of a family before the person was born
+    MarriageBeforeBirth,
+    ChildBeforeParent { child: String },
+    ParentTooYoung { child: String, age: i32 },
+    MotherTooOld { child: String, age: i32 },
+    OwnAncestor,
+    DuplicateSpouse { spouse: String },
+    MissingSex,
+    DanglingPointer { tag: String, pointer: String },
+}
+
+/// Problem found on a record
+#[derive(Debug,Clone,PartialEq)]
+pub struct Issue {
+    /// Registry key of the record to fix
+    pub record: u64,
+    /// Name of the record, or its cross-reference
+    pub name: String,
+    pub problem: Problem,
+}
+
+impl Issue {
+    pub fn severity(&self) -> Severity {
+        match self.problem {
+            Problem::ParentTooYoung { .. } | Problem::MotherTooOld { .. } |
+            Problem::DuplicateSpouse { .. } | Problem::MissingSex => Severity::Warning,
+            _ => Severity::Error,
+        }
+    }
+
+    pub fn what(&self) -> String {
+        match &self.problem {
+            Problem::DeathBeforeBirth => String::from("Died before being born."),
+            Problem::MarriageBeforeBirth => String::from("Married before being born."),
+            Problem::ChildBeforeParent { child } => format!("Child {} was born before this parent.", child),
+            Problem::ParentTooYoung { child, age } => format!("Was {} at the birth of {}.", age, child),
+            Problem::MotherTooOld { child, age } => format!("Was {} at the birth of {}.", age, child),
+            Problem::OwnAncestor => String::from("Is their own ancestor."),
+            Problem::DuplicateSpouse { spouse } => format!("Married to {} in several families.", spouse),
+            Problem::MissingSex => String::from("Is a parent without a known sex."),
+            Problem::DanglingPointer { tag, pointer } => format!("{} points to {}, which doesn't exist.", tag, pointer),
+        }
+    }
+}
+
+/// Date of the first event of the list the person has
+fn first_date(person: &Record, tags: &[&str]) -> Option<GedDate> {
+    tags.iter().find_map(|t| person.event_date(t))
+}
+
+fn birth(person: &Record) -> Option<GedDate> {
+    first_date(person, &["BIRT", "CHR", "BAPM"])
+}
+
+/// Whether the first date surely comes before the second one
+fn surely_before(first: &GedDate, second: &GedDate) -> bool {
+    match (first.bounds().1, second.bounds().0) {
+        (Some(upper), Some(lower)) => upper < lower,
+        _ => false,
+    }
+}
+
+/// Full years between two dates
+fn age(from: &GedDate, to: &GedDate) -> Option<i32> {
+    Some(from.sort_date()?.years_until(&to.sort_date()?))
+}
+
+struct Verifier<'a> {
+    registry: &'a RecordRegistry,
+    graph: FamilyGraph,
+    rules: &'a VerifyRules,
+    issues: Vec<Issue>,
+}
+
+impl<'a> Verifier<'a> {
+    fn report(&mut self, record: &Record, problem: Problem) {
+        let name = match record.display_name() {
+            name if name.is_empty() => record.xref.clone(),
+            name => name,
+        };
+        self.issues.push(Issue { record: xref_key(&record.xref), name, problem });
+    }
+
+    fn sex(&mut self, person: &Record) {
+        if person.sex() == 'U' && !person.pointers("FAMS").is_empty() {
+            self.report(person, Problem::MissingSex);
+        }
+    }
+
+    fn dates(&mut self, person: &Record) {
+        let death = first_date(person, &["DEAT", "BURI", "CREM"]);
+        if let (Some(birth), Some(death)) = (birth(person), death) {
+            if surely_before(&death, &birth) {
+                self.report(person, Problem::DeathBeforeBirth);
+            }
+        }
+    }
+
+    fn records(&self, persons: &[PersonId]) -> Vec<RecordRc> {
+        persons.iter().filter_map(|p| self.graph.record(self.registry, *p)).collect()
+    }
+
+    fn family(&mut self, family: &Record) {
+        let id = match self.graph.family_of(family) {
+            Some(id) => id,
+            None => return,
+        };
+        let partners = self.records(&self.graph.partners(id));
+        let children = self.records(self.graph.family_children(id));
+        let marriage = family.event_date("MARR");
+        for partner in &partners {
+            let partner = partner.borrow();
+            if let (Some(marriage), Some(birth)) = (&marriage, birth(&partner)) {
+                if surely_before(marriage, &birth) {
+                    self.report(&partner, Problem::MarriageBeforeBirth);
+                }
+            }
+        }
+        for child in &children {
+            let child = child.borrow();
+            let child_birth = match birth(&child) {
+                Some(birth) => birth,
+                None => continue,
+            };
+            for parent in &partners {
+                let parent = parent.borrow();
+                let parent_birth = match birth(&parent) {
+                    Some(birth) => birth,
+                    None => continue,
+                };
+                let name = child.display_name();
+                if surely_before(&child_birth, &parent_birth) {
+                    self.report(&parent, Problem::ChildBeforeParent { child: name });
+                    continue;
+                }
+                match age(&parent_birth, &child_birth) {
+                    Some(age) if age >= 0 && age < self.rules.min_parent_age =>
+                        self.report(&parent, Problem::ParentTooYoung { child: name, age }),
+                    Some(age) if age > self.rules.max_mother_age && parent.sex() == 'F' =>
+                        self.report(&parent, Problem::MotherTooOld { child: name, age }),
+                    _ => (),
+                }
+            }
+        }
+    }
+
+    /// Partners found in more than one family of the person
+    fn spouses(&mut self, person: &Record) {
+        let id = match self.graph.person_of(person) {
+            Some(id) => id,
+            None => return,
+        };
+        let mut partners: HashMap<PersonId, usize> = HashMap::new();
+        for family in self.graph.spouse_families(id) {
+            if let Some(partner) = self.graph.partner(*family, id).filter(|p| *p != id) {
+                *partners.entry(partner).or_default() += 1;
+            }
+        }
+        let mut repeated: Vec<PersonId> = partners.into_iter().filter(|(_, n)| *n > 1).map(|(p, _)| p).collect();
+        repeated.sort();
+        for spouse in self.records(&repeated) {
+            let spouse = spouse.borrow().display_name();
+            self.report(person, Problem::DuplicateSpouse { spouse });
+        }
+    }
+
+    /// Pointers of the record to missing records, looked
+    /// for in all its structures
+    fn pointers(&mut self, record: &Record) {
+        fn walk(tag: &Tag, registry: &RecordRegistry, found: &mut Vec<(String, String)>) {
+            if let Some(content) = tag.content.as_deref().map(str::trim) {
+                let is_pointer = content.len() > 2 && content.starts_with('@')
+                    && content.ends_with('@') && !content.starts_with("@#");
+                if is_pointer && !registry.contains_key(&xref_key(content)) {
+                    found.push((tag.name.clone(), content.to_owned()));
+                }
+            }
+            for nested in &tag.nested {
+                walk(nested, registry, found);
+            }
+        }
+        let mut found = vec![];
+        for nested in &record.to_tag().nested {
+            walk(nested, self.registry, &mut found);
+        }
+        for (tag, pointer) in found {
+            self.report(record, Problem::DanglingPointer { tag, pointer });
+        }
+    }
+
+    /// Persons who are their own ancestor, found as the cycles
+    /// of the parent links by a depth first walk. All the
+    /// families of the parents are followed.
+    fn cycles(&mut self) {
+        let graph = &self.graph;
+        let mut done: HashSet<PersonId> = HashSet::new();
+        let mut in_cycle: HashSet<PersonId> = HashSet::new();
+        for start in graph.persons() {
+            if done.contains(&start) {
+                continue;
+            }
+            // Path from the start person with the parents
+            // left to visit at each step
+            let mut path: Vec<(PersonId, Vec<PersonId>)> = vec![(start, graph.parents(start))];
+            while let Some((_, pending)) = path.last_mut() {
+                match pending.pop() {
+                    Some(parent) => {
+                        if let Some(pos) = path.iter().position(|(k, _)| *k == parent) {
+                            in_cycle.extend(path[pos..].iter().map(|(k, _)| *k));
+                        } else if !done.contains(&parent) {
+                            path.push((parent, graph.parents(parent)));
+                        }
+                    },
+                    None => {
+                        let (key, _) = path.pop().unwrap();
+                        done.insert(key);
+                    },
+                }
+            }
+        }
+        let mut in_cycle: Vec<PersonId> = in_cycle.into_iter().collect();
+        in_cycle.sort();
+        for person in self.records(&in_cycle) {
+            self.report(&person.borrow(), Problem::OwnAncestor);
+        }
+    }
+}
+
+/// Checks the whole registry, the issues being sorted by
+/// severity and then by record name
+pub fn verify(registry: &RecordRegistry, rules: &VerifyRules) -> Vec<Issue> {
+    let graph = FamilyGraph::new(registry);
+    let mut verifier = Verifier { registry, graph, rules, issues: vec![] };
+    let mut records: Vec<&RecordRc> = registry.values().collect();
+    records.sort_by_key(|r| r.borrow().xref.clone());
+    for record in records {
+        let record = record.borrow();
+        match record.rtype.as_str() {
+            "INDI" => {
+                verifier.dates(&record);
+                verifier.sex(&record);
+                verifier.spouses(&record);
+            },
+            "FAM" => verifier.family(&record),
+            _ => (),
+        }
+        verifier.pointers(&record);
+    }
+    verifier.cycles();
+    let mut issues = verifier.issues;
+    issues.sort_by(|a, b| (a.severity(), &a.name).cmp(&(b.severity(), &b.name)));
+    issues
+}
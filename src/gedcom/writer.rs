@@ -0,0 +1,111 @@
+//! GED file generation from a [record tree](RecordRegistry)
+
+use crate::gedcom::export::{self, ExportContent, ExportScope};
+use crate::gedcom::gedcom7::{self, GedVersion};
+use crate::gedcom::{LivingRules, PrivacyFilter, PrivacyMode, Record, RecordRegistry, Tag};
+use std::io::Write;
+
+/// Order in which the records are written, unknown
+/// record types go at the end
+const RECORD_ORDER: [&str; 7] = ["SUBM", "INDI", "FAM", "SOUR", "REPO", "NOTE", "OBJE"];
+
+/// Position of a record type in the export order
+pub(crate) fn record_rank(rtype: &str) -> usize {
+    RECORD_ORDER.iter()
+        .position(|t| *t == rtype)
+        .unwrap_or(RECORD_ORDER.len())
+}
+
+/// Specialized structure for GED writer containing
+/// all the export settings
+#[derive(Default,Clone)]
+pub struct GedWriter {
+    /// What is exported of the living persons
+    pub privacy: PrivacyMode,
+    pub living_rules: LivingRules,
+    /// Part of the tree exported
+    pub scope: ExportScope,
+    /// Kinds of data exported
+    pub content: ExportContent,
+    /// Version of the standard the file follows
+    pub version: GedVersion,
+}
+
+impl GedWriter {
+    /// Header of an exported file, the file always gets
+    /// written in UTF-8. The 7.0 header documents the
+    /// extension tags used by the records.
+    fn header(&self, records: &[Tag]) -> Tag {
+        let source = Tag::new("SOUR", Some("NPAF"))
+            .nest(Tag::new("NAME", Some("nPAF")))
+            .nest(Tag::new("VERS", Some(env!("CARGO_PKG_VERSION"))));
+        match self.version {
+            GedVersion::V551 => {
+                let gedc = Tag::new("GEDC", None)
+                    .nest(Tag::new("VERS", Some("5.5.1")))
+                    .nest(Tag::new("FORM", Some("LINEAGE-LINKED")));
+                Tag::new("HEAD", None)
+                    .nest(source)
+                    .nest(gedc)
+                    .nest(Tag::new("CHAR", Some("UTF-8")))
+            },
+            GedVersion::V7 => {
+                let head = Tag::new("HEAD", None)
+                    .nest(Tag::new("GEDC", None).nest(Tag::new("VERS", Some("7.0"))));
+                let head = match gedcom7::schema(records) {
+                    Some(schema) => head.nest(schema),
+                    None => head,
+                };
+                head.nest(source)
+            },
+        }
+    }
+
+    /// Returns the records in the export order
+    pub fn sorted(registry: &RecordRegistry) -> Vec<Record> {
+        let rank = |r: &Record| record_rank(&r.rtype);
+        let mut records: Vec<Record> = registry.values()
+            .map(|r| r.borrow().clone())
+            .collect();
+        records.sort_by(|a, b| (rank(a), a.id, &a.xref).cmp(&(rank(b), b.id, &b.xref)));
+        records
+    }
+
+    /// Records of the registry as they are exported: the scope
+    /// and the content settings are applied, then the living
+    /// persons go through the privacy filter. Who is living is
+    /// told from the whole registry.
+    pub fn exported(&self, registry: &RecordRegistry) -> Vec<Record> {
+        let scoped = match (&self.scope, self.content == ExportContent::default()) {
+            (ExportScope::All, true) => None,
+            (scope, _) => Some(export::apply(registry, scope, &self.content)),
+        };
+        let scoped = scoped.as_ref().unwrap_or(registry);
+        match self.privacy {
+            PrivacyMode::Include => Self::sorted(scoped),
+            mode => Self::sorted(&PrivacyFilter::new(registry, mode, &self.living_rules).apply(scoped)),
+        }
+    }
+
+    /// Level 0 structures of the exported file in the version
+    /// it follows, from the header on, the trailer left out
+    pub fn tags(&self, registry: &RecordRegistry) -> Vec<Tag> {
+        let records: Vec<Tag> = self.exported(registry).iter().map(|r| r.to_tag()).collect();
+        let records = match self.version {
+            GedVersion::V551 => records,
+            GedVersion::V7 => gedcom7::to_v7(records),
+        };
+        std::iter::once(self.header(&records)).chain(records).collect()
+    }
+
+    /// Writes the registry as a GED file, as set
+    /// by the [export settings](GedWriter::exported)
+    pub fn write<W: Write>(&self, registry: &RecordRegistry, out: &mut W) -> std::io::Result<()> {
+        for tag in self.tags(registry) {
+            for line in tag.to_lines(0) {
+                writeln!(out, "{}", line)?;
+            }
+        }
+        writeln!(out, "0 TRLR")
+    }
+}
@@ -0,0 +1,159 @@
+use gtk::prelude::*;
+
+use crate::app::models::AppModel;
+use crate::batch_view::BatchView;
+use crate::gedcom::batch;
+use crate::gedcom::import::{self, ImportEvent, ImportHandle, ImportSummary};
+use crate::gedcom::ParseError;
+use crate::prelude::*;
+use std::cell::RefCell;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+/// What becomes of the opened tree once the file is read
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum ImportMode {
+    /// The imported records replace the opened tree
+    Replace,
+    /// The imported records are added to the opened tree
+    /// as a new [batch](crate::gedcom::batch)
+    Append,
+}
+
+/// Progress dialog of a GEDCOM import running in the background,
+/// turning into a summary of the import once it's done
+pub struct ImportView {
+    gbuilder: gtk::Builder,
+    model: AppModel,
+    path: PathBuf,
+    mode: ImportMode,
+    handle: Rc<RefCell<Option<ImportHandle>>>,
+}
+
+impl ImportView {
+    pub fn new(model: AppModel, path: PathBuf, mode: ImportMode) -> Self {
+        Self {
+            gbuilder: gtk::Builder::from_resource("/org/altereigo/npaf/Import.glade"),
+            model,
+            path,
+            mode,
+            handle: Default::default(),
+        }
+    }
+
+    fn label(gbuilder: &gtk::Builder, name: &str) -> gtk::Label {
+        gbuilder.object(name).unwrap()
+    }
+
+    fn button(gbuilder: &gtk::Builder, name: &str) -> gtk::Button {
+        gbuilder.object(name).unwrap()
+    }
+
+    fn show_summary(gbuilder: &gtk::Builder, summary: &ImportSummary) {
+        let counts: gtk::ListStore = gbuilder.object("counts_store").unwrap();
+        for (rtype, count) in &summary.counts {
+            counts.insert_with_values(None, &[(0, rtype), (1, &(*count as u32))]);
+        }
+        let diagnostics: gtk::ListStore = gbuilder.object("diagnostics_store").unwrap();
+        for diagnostic in &summary.diagnostics {
+            let line = diagnostic.line().map(|l| l.to_string()).unwrap_or_default();
+            diagnostics.insert_with_values(None, &[(0, &line), (1, &diagnostic.what())]);
+        }
+        Self::label(gbuilder, "l_result").set_text(&format!(
+            "{} records imported ({}), {} problem(s) found.",
+            summary.records(), summary.origin(), summary.diagnostics.len()));
+    }
+
+    /// Handles the events of the worker, returns whether
+    /// more of them are expected
+    fn on_event(gbuilder: &gtk::Builder, model: &AppModel, mode: ImportMode, event: ImportEvent) -> bool {
+        let result = match event {
+            ImportEvent::Progress(progress) => {
+                let bar: gtk::ProgressBar = gbuilder.object("pb_progress").unwrap();
+                bar.set_fraction(progress.fraction());
+                Self::label(gbuilder, "l_progress").set_text(&progress.what());
+                return true;
+            },
+            ImportEvent::Finished(result) => result,
+        };
+        let pages: gtk::Stack = gbuilder.object("st_pages").unwrap();
+        pages.set_visible_child_name("summary");
+        Self::button(gbuilder, "b_cancel").set_sensitive(false);
+        Self::button(gbuilder, "b_close").set_sensitive(true);
+        match result {
+            Ok(imported) if mode == ImportMode::Append => {
+                let appended = batch::append(&mut model.registry_mut(), imported.records);
+                let mut summary = imported.summary;
+                summary.diagnostics.extend(appended.diagnostics.iter().cloned());
+                model.relink();
+                Self::show_summary(gbuilder, &summary);
+                Self::label(gbuilder, "l_result").set_text(&format!(
+                    "{} records added to the tree as batch {} ({}), {} problem(s) found.",
+                    summary.records(), appended.batch, summary.origin(), summary.diagnostics.len()));
+                let b_review = Self::button(gbuilder, "b_review");
+                let model = model.clone();
+                b_review.connect_clicked(move |_| {
+                    BatchView::with_batch(model.clone(), &appended.batch).assemble_window().present();
+                });
+                b_review.show();
+            },
+            Ok(imported) => {
+                let (registry, summary) = imported.into_registry();
+                model.load(registry);
+                Self::show_summary(gbuilder, &summary);
+            },
+            Err(ParseError::Cancelled) => Self::label(gbuilder, "l_result").set_text("Import cancelled, nothing was changed."),
+            Err(err) => Self::label(gbuilder, "l_result").set_text(&format!("Could not import the file: {}", err.what())),
+        }
+        false
+    }
+
+    fn cancel(handle: &Rc<RefCell<Option<ImportHandle>>>) {
+        if let Some(handle) = handle.borrow().as_ref() {
+            handle.cancel();
+        }
+    }
+}
+
+impl View for ImportView {
+    fn assemble(&self) -> gtk::Widget {
+        let root: gtk::Grid = self.gbuilder.object("root").unwrap();
+        Self::label(&self.gbuilder, "l_file").set_text(&format!("Importing '{}'", self.path.display()));
+        Self::button(&self.gbuilder, "b_close").set_sensitive(false);
+        Self::button(&self.gbuilder, "b_review").hide();
+
+        let (sender, receiver) = glib::MainContext::channel(glib::PRIORITY_DEFAULT);
+        let (gbuilder, model, mode) = (self.gbuilder.clone(), self.model.clone(), self.mode);
+        receiver.attach(None, move |event| glib::Continue(Self::on_event(&gbuilder, &model, mode, event)));
+        let handle = import::spawn(self.path.clone(), move |event| {
+            let _ = sender.send(event);
+        });
+        self.handle.replace(Some(handle));
+
+        let b_cancel = Self::button(&self.gbuilder, "b_cancel");
+        let (gbuilder, handle) = (self.gbuilder.clone(), self.handle.clone());
+        b_cancel.connect_clicked(move |btn| {
+            Self::cancel(&handle);
+            btn.set_sensitive(false);
+            Self::label(&gbuilder, "l_progress").set_text("Cancelling...");
+        });
+
+        root.show();
+        root.dynamic_cast::<gtk::Widget>().unwrap()
+    }
+}
+
+impl Windowed for ImportView {
+    fn assemble_window(&self) -> gtk::Window {
+        let wdg = self.assemble();
+        let wdw: gtk::Window = self.gbuilder.object("top").unwrap();
+        wdw.set_child(Some(&wdg));
+        let b_close = Self::button(&self.gbuilder, "b_close");
+        let wdw_cpy = wdw.clone();
+        b_close.connect_clicked(move |_| wdw_cpy.close());
+        // Closing the window while importing stops the worker
+        let handle = self.handle.clone();
+        wdw.connect_destroy(move |_| Self::cancel(&handle));
+        wdw
+    }
+}
@@ -1,34 +1,53 @@
+mod address_editor;
 mod app;
+mod batch_view;
+mod cli;
+mod csv_import_view;
+mod diagram;
+mod export_view;
+mod family_editor;
+mod find;
+mod import_view;
+mod merge;
+mod ordinance_editor;
 mod person_editor;
+mod person_table;
+mod preferences_view;
 mod prelude;
+mod print_report;
+mod report;
+mod rin_search;
 mod root;
+mod stats_view;
+mod verify_view;
+mod website;
+mod website_view;
 mod gedcom;
 
 use crate::prelude::*;
 use gtk::prelude::*;
 use std::rc::Rc;
-use gedcom::*;
 
 fn main() {
-    let mut parser: GedParser = Default::default();
-    let data = std::fs::File::open("data_sample.ged").unwrap();
-    let unparsed = gedcom::gedex::GedEx::count_unparsed(&data);
-    let data = std::fs::File::open("data_sample.ged").unwrap();
-    if unparsed > 0 {
-        println!("Did not parse exactly {} lines.", unparsed);
-    }
-    let res = parser.parse(&data);
-    if let Ok(parsed) = res {
-        println!("{:#?}", parsed);
-    } else {
-        println!("An error occured: '{:#?}'", res);
-    }
-}
+    let invocation = match cli::Invocation::parse(std::env::args().skip(1)) {
+        Ok(invocation) => invocation,
+        Err(msg) => {
+            eprintln!("npaf: {}\n\n{}", msg, cli::USAGE);
+            std::process::exit(cli::EXIT_USAGE);
+        },
+    };
+    let import = match &invocation.command {
+        cli::Command::Gui { import } => import.clone(),
+        _ => std::process::exit(invocation.execute()),
+    };
 
-fn main2() {
     gtk::init().expect("Could not initialize GTK");
 
-    let tm = app::Application::builder().build().unwrap();
+    let mut builder = app::Application::builder();
+    if let Some(path) = import {
+        builder = builder.import(path);
+    }
+    let tm = builder.build().unwrap();
 
     tm.run();
 }
@@ -0,0 +1,152 @@
+use gtk::prelude::*;
+
+use crate::app::models::AppModel;
+use crate::app::preferences::Preferences;
+use crate::gedcom::merge::{self, MergeChoices, Side, MERGE_FIELDS};
+use crate::gedcom::{xref_key, Record};
+use crate::prelude::*;
+use std::cell::Cell;
+use std::rc::Rc;
+
+/// Pair of records being merged: the kept one and the
+/// one merged into it
+type Pair = Rc<Cell<Option<(u64, u64)>>>;
+
+pub struct MergeView {
+    gbuilder: gtk::Builder,
+    model: AppModel,
+    pair: Pair,
+}
+
+impl MergeView {
+    pub fn new(model: AppModel) -> Self {
+        Self {
+            gbuilder: gtk::Builder::from_resource("/org/altereigo/npaf/Merge.glade"),
+            model,
+            pair: Default::default(),
+        }
+    }
+
+    fn radio(gbuilder: &gtk::Builder, side: &str, i: usize) -> gtk::RadioButton {
+        gbuilder.object(&format!("rb_{}_{}", side, i)).unwrap()
+    }
+
+    fn find_duplicates(gbuilder: &gtk::Builder, model: &AppModel) {
+        let threshold: gtk::SpinButton = gbuilder.object("sb_threshold").unwrap();
+        let store: gtk::ListStore = gbuilder.object("candidates_store").unwrap();
+        let status: gtk::Label = gbuilder.object("l_status").unwrap();
+        store.clear();
+        let describe = |r: &Record| match r.lifespan() {
+            lifespan if lifespan.is_empty() => r.display_name(),
+            lifespan => format!("{} ({})", r.display_name(), lifespan),
+        };
+        let candidates = merge::find_duplicates(&model.registry(), threshold.value_as_int().max(0) as u32);
+        for candidate in &candidates {
+            let (left, right) = (candidate.left.borrow(), candidate.right.borrow());
+            store.insert_with_values(None, &[
+                (0, &xref_key(&left.xref)),
+                (1, &xref_key(&right.xref)),
+                (2, &candidate.score),
+                (3, &describe(&left)),
+                (4, &describe(&right)),
+            ]);
+        }
+        status.set_text(&format!("{} possible duplicate(s) found.", candidates.len()));
+    }
+
+    /// Fills the side by side comparison of the pair, the
+    /// kept record values being selected by default.
+    fn show_pair(gbuilder: &gtk::Builder, model: &AppModel, pair: &Pair) {
+        let records = pair.get().and_then(|(kept, dropped)| Some((model.record(kept)?, model.record(dropped)?)));
+        for (i, field) in MERGE_FIELDS.iter().enumerate() {
+            let (left, right) = (Self::radio(gbuilder, "left", i), Self::radio(gbuilder, "right", i));
+            let (kept, dropped) = match &records {
+                Some((kept, dropped)) => (field.value(&kept.borrow()), field.value(&dropped.borrow())),
+                None => (String::new(), String::new()),
+            };
+            left.set_label(&kept);
+            right.set_label(&dropped);
+            left.set_active(true);
+            left.set_sensitive(records.is_some());
+            right.set_sensitive(records.is_some());
+        }
+    }
+
+    fn choices(gbuilder: &gtk::Builder) -> MergeChoices {
+        MERGE_FIELDS.iter()
+            .enumerate()
+            .filter(|(i, _)| Self::radio(gbuilder, "right", *i).is_active())
+            .map(|(_, field)| (*field, Side::Dropped))
+            .collect()
+    }
+
+    fn merge(gbuilder: &gtk::Builder, model: &AppModel, pair: &Pair) {
+        let status: gtk::Label = gbuilder.object("l_status").unwrap();
+        let (kept, dropped) = match pair.get() {
+            Some(pair) => pair,
+            None => return status.set_text("Select a pair of records first."),
+        };
+        let result = merge::merge(&mut model.registry_mut(), kept, dropped, &Self::choices(gbuilder));
+        match result {
+            Ok(()) => {
+                model.relink();
+                pair.set(None);
+                Self::find_duplicates(gbuilder, model);
+                Self::show_pair(gbuilder, model, pair);
+                status.set_text("Records merged.");
+                let _ = model.navigate(kept);
+            },
+            Err(err) => status.set_text(&err.what()),
+        }
+    }
+}
+
+impl View for MergeView {
+    fn assemble(&self) -> gtk::Widget {
+        let root: gtk::Grid = self.gbuilder.object("root").unwrap();
+        let threshold: gtk::SpinButton = self.gbuilder.object("sb_threshold").unwrap();
+        threshold.set_value(Preferences::load().duplicate_score as f64);
+
+        let b_find: gtk::Button = self.gbuilder.object("b_find").unwrap();
+        let (gbuilder, model) = (self.gbuilder.clone(), self.model.clone());
+        b_find.connect_clicked(move |_| Self::find_duplicates(&gbuilder, &model));
+
+        let candidates: gtk::TreeView = self.gbuilder.object("tv_candidates").unwrap();
+        let (gbuilder, model, pair) = (self.gbuilder.clone(), self.model.clone(), self.pair.clone());
+        candidates.selection().connect_changed(move |selection| {
+            if let Some((store, iter)) = selection.selected() {
+                let left = store.value(&iter, 0).get::<u64>().unwrap();
+                let right = store.value(&iter, 1).get::<u64>().unwrap();
+                pair.set(Some((left, right)));
+                Self::show_pair(&gbuilder, &model, &pair);
+            }
+        });
+
+        let b_swap: gtk::Button = self.gbuilder.object("b_swap").unwrap();
+        let (gbuilder, model, pair) = (self.gbuilder.clone(), self.model.clone(), self.pair.clone());
+        b_swap.connect_clicked(move |_| {
+            pair.set(pair.get().map(|(kept, dropped)| (dropped, kept)));
+            Self::show_pair(&gbuilder, &model, &pair);
+        });
+
+        let b_merge: gtk::Button = self.gbuilder.object("b_merge").unwrap();
+        let (gbuilder, model, pair) = (self.gbuilder.clone(), self.model.clone(), self.pair.clone());
+        b_merge.connect_clicked(move |_| Self::merge(&gbuilder, &model, &pair));
+
+        Self::show_pair(&self.gbuilder, &self.model, &self.pair);
+        root.show();
+        root.dynamic_cast::<gtk::Widget>().unwrap()
+    }
+}
+
+impl Windowed for MergeView {
+    fn assemble_window(&self) -> gtk::Window {
+        let wdg = self.assemble();
+        let wdw: gtk::Window = self.gbuilder.object("top").unwrap();
+        wdw.set_child(Some(&wdg));
+        let b_close: gtk::Button = self.gbuilder.object("b_close").unwrap();
+        let wdw_cpy = wdw.clone();
+        b_close.connect_clicked(move |_| wdw_cpy.close());
+        wdw
+    }
+}
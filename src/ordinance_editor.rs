@@ -0,0 +1,298 @@
+use gtk::prelude::*;
+
+use crate::app::models::AppModel;
+use crate::gedcom::ordinance::{self, Ordinance, OrdinanceKind, OrdinanceStatus};
+use crate::gedcom::{xref_key, RecordRc, RecordRef};
+use crate::prelude::*;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// Editor of the LDS ordinances of a person, including the
+/// sealings to spouse kept by the families of the person.
+/// Clones share the edited list.
+#[derive(Clone)]
+pub struct OrdinanceEditorView {
+    gbuilder: gtk::Builder,
+    model: AppModel,
+    person: RecordRef,
+    /// Ordinances being edited, the sealings to spouse
+    /// carrying the pointer to their family
+    ordinances: Rc<RefCell<Vec<Ordinance>>>,
+}
+
+impl OrdinanceEditorView {
+    pub fn new(model: AppModel, person: RecordRef) -> Self {
+        let mut ordinances = person.borrow().ordinances.clone();
+        for family in person.borrow().families("FAMS", &model.registry()) {
+            let family = family.borrow();
+            ordinances.extend(family.ordinances.iter()
+                .filter(|o| o.kind.is_family())
+                .map(|o| Ordinance { family: Some(format!("@{}@", family.xref)), ..o.clone() }));
+        }
+        Self {
+            gbuilder: gtk::Builder::from_resource("/org/altereigo/npaf/Ordinance.glade"),
+            model,
+            person,
+            ordinances: Rc::new(RefCell::new(ordinances)),
+        }
+    }
+
+    fn combo(&self, name: &str) -> gtk::ComboBoxText {
+        self.gbuilder.object(name).unwrap()
+    }
+
+    fn entry(&self, name: &str) -> gtk::Entry {
+        self.gbuilder.object(name).unwrap()
+    }
+
+    fn status(&self, text: &str) {
+        let status: gtk::Label = self.gbuilder.object("l_status").unwrap();
+        status.set_text(text);
+    }
+
+    /// Families an ordinance can be attached to: the families
+    /// of the parents for a sealing to parents, the person's
+    /// own families for a sealing to spouse
+    fn families(&self, kind: OrdinanceKind) -> Vec<RecordRc> {
+        let tag = match kind {
+            OrdinanceKind::SealingToParents => "FAMC",
+            OrdinanceKind::SealingToSpouse => "FAMS",
+            _ => return vec![],
+        };
+        self.person.borrow().families(tag, &self.model.registry())
+    }
+
+    fn family_label(&self, pointer: &str) -> String {
+        let family = match self.model.record(xref_key(pointer.trim())) {
+            Some(family) => family,
+            None => return pointer.to_owned(),
+        };
+        let family = family.borrow();
+        let (graph, registry) = (self.model.graph(), self.model.registry());
+        let partners: Vec<String> = graph.family_of(&family)
+            .map(|f| graph.partners(f))
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|p| graph.record(&registry, p))
+            .map(|p| p.borrow().display_name())
+            .collect();
+        match partners.is_empty() {
+            true => family.xref.clone(),
+            false => partners.join(" and "),
+        }
+    }
+
+    fn fill_store(&self) {
+        let store: gtk::ListStore = self.gbuilder.object("ordinances_store").unwrap();
+        store.clear();
+        for ordinance in self.ordinances.borrow().iter() {
+            let family = ordinance.family.clone().unwrap_or_default();
+            store.insert_with_values(None, &[
+                (0, &ordinance.kind.tag()),
+                (1, &family),
+                (2, &ordinance.date.clone().unwrap_or_default()),
+                (3, &ordinance.temple.clone().unwrap_or_default()),
+                (4, &ordinance.status.map_or("", |s| s.code())),
+                (5, &ordinance.place.clone().unwrap_or_default()),
+                (6, &ordinance.kind.label()),
+                (7, &ordinance.status.map_or("", |s| s.label())),
+                (8, &if family.is_empty() { family.clone() } else { self.family_label(&family) }),
+            ]);
+        }
+    }
+
+    fn kind(&self) -> OrdinanceKind {
+        self.combo("cb_kind").active_id()
+            .and_then(|id| OrdinanceKind::from_tag(&id))
+            .unwrap_or(OrdinanceKind::Baptism)
+    }
+
+    /// Offers the statuses and families fitting the kind
+    /// of ordinance selected
+    fn kind_changed(&self) {
+        let kind = self.kind();
+        let statuses = self.combo("cb_status");
+        statuses.remove_all();
+        statuses.append(Some(""), "");
+        for status in kind.statuses() {
+            statuses.append(Some(status.code()), status.label());
+        }
+        statuses.set_active_id(Some(""));
+        let families = self.combo("cb_family");
+        families.remove_all();
+        for family in self.families(kind) {
+            let pointer = format!("@{}@", family.borrow().xref);
+            families.append(Some(&pointer), &self.family_label(&pointer));
+        }
+        families.set_active(Some(0));
+        families.set_sensitive(kind == OrdinanceKind::SealingToParents || kind.is_family());
+    }
+
+    fn selected(&self) -> Option<usize> {
+        let tv: gtk::TreeView = self.gbuilder.object("tv_ordinances").unwrap();
+        let (model, iter) = tv.selection().selected()?;
+        let index = *model.path(&iter)?.indices().first()?;
+        Some(index as usize)
+    }
+
+    fn show(&self, ordinance: &Ordinance) {
+        self.combo("cb_kind").set_active_id(Some(ordinance.kind.tag()));
+        self.kind_changed();
+        if let Some(family) = &ordinance.family {
+            self.combo("cb_family").set_active_id(Some(family));
+        }
+        self.entry("e_date").set_text(ordinance.date.as_deref().unwrap_or(""));
+        // Unknown codes are offered as is, so they are
+        // reported when the ordinance gets applied
+        let (temples, code) = (self.combo("cb_temple"), ordinance.temple.as_deref().unwrap_or(""));
+        if !temples.set_active_id(Some(code)) {
+            temples.append(Some(code), code);
+            temples.set_active_id(Some(code));
+        }
+        self.combo("cb_status").set_active_id(Some(ordinance.status.map_or("", |s| s.code())));
+        self.entry("e_place").set_text(ordinance.place.as_deref().unwrap_or(""));
+    }
+
+    /// Reads the form into an ordinance based on `base`, the
+    /// structures the form doesn't show being kept
+    fn read_form(&self, base: Option<&Ordinance>) -> std::result::Result<Ordinance, String> {
+        let text = |name: &str| Some(self.entry(name).text().trim().to_owned()).filter(|t| !t.is_empty());
+        let kind = self.kind();
+        let status = self.combo("cb_status").active_id().and_then(|id| OrdinanceStatus::from_code(&id));
+        let mut ordinance = match base {
+            Some(base) if base.kind == kind => base.clone(),
+            _ => Ordinance::new(kind),
+        };
+        if ordinance.status != status {
+            ordinance.status_date = None;
+        }
+        ordinance.status = status;
+        ordinance.date = text("e_date");
+        ordinance.place = text("e_place");
+        ordinance.temple = self.combo("cb_temple").active_id()
+            .map(|id| id.to_string())
+            .filter(|id| !id.is_empty());
+        ordinance.family = match kind {
+            OrdinanceKind::SealingToParents | OrdinanceKind::SealingToSpouse =>
+                self.combo("cb_family").active_id().map(|id| id.to_string()),
+            _ => None,
+        };
+        if kind.is_family() && ordinance.family.is_none() {
+            return Err(String::from("A sealing to spouse needs one of the families of the person."));
+        }
+        ordinance.validate().map_err(|e| e.what())?;
+        Ok(ordinance)
+    }
+
+    fn add(&self) {
+        match self.read_form(None) {
+            Ok(ordinance) => {
+                self.ordinances.borrow_mut().push(ordinance);
+                self.fill_store();
+                self.status("");
+            },
+            Err(msg) => self.status(&msg),
+        }
+    }
+
+    fn apply(&self) {
+        let index = match self.selected() {
+            Some(index) => index,
+            None => return self.status("Select the ordinance to change first."),
+        };
+        let base = self.ordinances.borrow().get(index).cloned();
+        match self.read_form(base.as_ref()) {
+            Ok(ordinance) => {
+                self.ordinances.borrow_mut()[index] = ordinance;
+                self.fill_store();
+                self.status("");
+            },
+            Err(msg) => self.status(&msg),
+        }
+    }
+
+    fn remove(&self) {
+        if let Some(index) = self.selected() {
+            self.ordinances.borrow_mut().remove(index);
+            self.fill_store();
+        }
+    }
+
+    /// Writes the ordinances back: those of the person to the
+    /// person, the sealings to spouse to their families
+    fn save(&self) {
+        let ordinances = self.ordinances.borrow();
+        self.person.borrow_mut().ordinances = ordinances.iter()
+            .filter(|o| !o.kind.is_family())
+            .cloned()
+            .collect();
+        for family in self.families(OrdinanceKind::SealingToSpouse) {
+            let pointer = format!("@{}@", family.borrow().xref);
+            let sealings = ordinances.iter()
+                .filter(|o| o.kind.is_family() && o.family.as_deref() == Some(pointer.as_str()))
+                .map(|o| Ordinance { family: None, ..o.clone() });
+            let mut family = family.borrow_mut();
+            family.ordinances.retain(|o| !o.kind.is_family());
+            family.ordinances.extend(sealings);
+        }
+    }
+}
+
+impl View for OrdinanceEditorView {
+    fn assemble(&self) -> gtk::Widget {
+        let root: gtk::Grid = self.gbuilder.object("root").unwrap();
+
+        let temples = self.combo("cb_temple");
+        temples.append(Some(""), "");
+        for temple in ordinance::temples() {
+            temples.append(Some(&temple.code), &format!("{} - {}", temple.code, temple.name));
+        }
+        temples.set_active_id(Some(""));
+        self.kind_changed();
+        self.fill_store();
+
+        let view = self.clone();
+        self.combo("cb_kind").connect_changed(move |_| view.kind_changed());
+        let tv: gtk::TreeView = self.gbuilder.object("tv_ordinances").unwrap();
+        let view = self.clone();
+        tv.selection().connect_changed(move |_| {
+            let ordinance = view.selected().and_then(|i| view.ordinances.borrow().get(i).cloned());
+            if let Some(ordinance) = ordinance {
+                view.show(&ordinance);
+            }
+        });
+        let b_add: gtk::Button = self.gbuilder.object("b_add").unwrap();
+        let view = self.clone();
+        b_add.connect_clicked(move |_| view.add());
+        let b_apply: gtk::Button = self.gbuilder.object("b_apply").unwrap();
+        let view = self.clone();
+        b_apply.connect_clicked(move |_| view.apply());
+        let b_remove: gtk::Button = self.gbuilder.object("b_remove").unwrap();
+        let view = self.clone();
+        b_remove.connect_clicked(move |_| view.remove());
+
+        root.show();
+        root.dynamic_cast::<gtk::Widget>().unwrap()
+    }
+}
+
+impl Windowed for OrdinanceEditorView {
+    fn assemble_window(&self) -> gtk::Window {
+        let wdg = self.assemble();
+        let wdw: gtk::Window = self.gbuilder.object("top").unwrap();
+        wdw.set_child(Some(&wdg));
+        wdw.set_title(&format!("LDS ordinances: {}", self.person.borrow().display_name()));
+
+        let b_cancel: gtk::Button = self.gbuilder.object("b_cancel").unwrap();
+        let wdw_cpy = wdw.clone();
+        b_cancel.connect_clicked(move |_| wdw_cpy.close());
+
+        let b_save: gtk::Button = self.gbuilder.object("b_save").unwrap();
+        let (view, wdw_cpy) = (self.clone(), wdw.clone());
+        b_save.connect_clicked(move |_| {
+            view.save();
+            wdw_cpy.close();
+        });
+        wdw
+    }
+}
@@ -1,22 +1,270 @@
 use gtk::prelude::*;
 
+use crate::address_editor::AddressEditorView;
+use crate::app::models::AppModel;
+use crate::gedcom::{ident, xref_key, Event, Record, RecordRef, Tag};
+use crate::ordinance_editor::OrdinanceEditorView;
 use crate::prelude::*;
 
+/// Events whose date and place fill the rows 2 to 9
+/// of the editor, in that order
+const EVENTS: [&str; 4] = ["BIRT", "DEAT", "CHR", "BURI"];
+
+/// Sets, replaces or removes the first tag with the given name
+fn set_tag(tags: &mut Vec<Tag>, name: &str, value: Option<&str>) {
+    match (tags.iter().position(|t| t.name == name), value) {
+        (Some(index), Some(value)) => {
+            tags[index].content = Some(value.to_owned());
+            tags[index].nested.retain(|t| t.name != "CONT" && t.name != "CONC");
+        },
+        (Some(index), None) => {
+            tags.remove(index);
+        },
+        (None, Some(value)) => tags.push(Tag::new(name, Some(value))),
+        (None, None) => (),
+    }
+}
+
+/// First event with the given tag, added when missing
+fn event_mut<'a>(record: &'a mut Record, tag: &str) -> &'a mut Event {
+    let index = match record.events.iter().position(|e| e.tag == tag) {
+        Some(index) => index,
+        None => {
+            record.events.push(Event::new(tag));
+            record.events.len() - 1
+        },
+    };
+    &mut record.events[index]
+}
+
+/// Primary `NAME` structure, the one mirroring the full
+/// name of the record, added when missing
+fn primary_name_mut(record: &mut Record) -> &mut Tag {
+    let index = match record.extra.iter().position(|t| t.name == "NAME") {
+        Some(index) => index,
+        None => {
+            record.extra.insert(0, Tag::new("NAME", Some(&record.name)));
+            0
+        },
+    };
+    &mut record.extra[index]
+}
+
+/// Position of the other name of the given `TYPE`
+/// (`married`, `aka`), the primary one never matching
+fn typed_name(record: &Record, kind: &str) -> Option<usize> {
+    record.extra.iter()
+        .enumerate()
+        .filter(|(_, t)| t.name == "NAME")
+        .skip(1)
+        .find(|(_, t)| t.value("TYPE").map_or(false, |k| k.trim().eq_ignore_ascii_case(kind)))
+        .map(|(index, _)| index)
+}
+
+/// Value shown by the row with the given id
+fn field(record: &Record, id: i64) -> Option<String> {
+    match id {
+        0 => Some(record.name.clone()).filter(|n| !n.is_empty()),
+        1 => record.extra_value("SEX"),
+        2..=9 => {
+            let event = record.events_of(EVENTS[(id as usize - 2) / 2]).next()?;
+            if id % 2 == 0 { event.date.clone() } else { event.place.clone() }
+        },
+        10 => record.extra.iter().find(|t| t.name == "NAME")?.value("NPFX"),
+        11 => record.extra[typed_name(record, "married")?].text(),
+        12 => record.extra[typed_name(record, "aka")?].text(),
+        13 => record.extra.iter().find(|t| t.name == "NAME")?.value("NICK"),
+        14 => record.events_of("DEAT").next()?.extra.iter().find(|t| t.name == "CAUS")?.text(),
+        15 => record.events_of("DSCR").next()?.value.clone(),
+        16 => record.afn(),
+        17 => record.custom_id(),
+        _ => None,
+    }
+}
+
+/// Writes the value of the row with the given id to the
+/// record, the structures the editor doesn't show being kept
+fn set_field(record: &mut Record, id: i64, value: Option<&str>) {
+    match id {
+        0 => {
+            record.name = value.unwrap_or("").to_owned();
+            // The name pieces would no longer match the name
+            if let Some(name) = record.extra.iter_mut().find(|t| t.name == "NAME") {
+                name.nested.retain(|t| t.name != "GIVN" && t.name != "SURN");
+            }
+        },
+        1 => set_tag(&mut record.extra, "SEX", value.map(|v| v.to_uppercase()).as_deref()),
+        2..=9 => {
+            let event = event_mut(record, EVENTS[(id as usize - 2) / 2]);
+            let value = value.map(String::from);
+            if id % 2 == 0 { event.date = value } else { event.place = value }
+        },
+        10 => set_tag(&mut primary_name_mut(record).nested, "NPFX", value),
+        13 => set_tag(&mut primary_name_mut(record).nested, "NICK", value),
+        11 | 12 => {
+            let kind = if id == 11 { "married" } else { "aka" };
+            primary_name_mut(record);
+            match (typed_name(record, kind), value) {
+                (Some(index), Some(value)) => record.extra[index].content = Some(value.to_owned()),
+                (Some(index), None) => {
+                    record.extra.remove(index);
+                },
+                (None, Some(value)) => record.extra.push(Tag::new("NAME", Some(value)).nest(Tag::new("TYPE", Some(kind)))),
+                (None, None) => (),
+            }
+        },
+        14 => set_tag(&mut event_mut(record, "DEAT").extra, "CAUS", value),
+        15 => event_mut(record, "DSCR").value = value.map(String::from),
+        16 => set_tag(&mut record.extra, "AFN", value),
+        17 => set_tag(&mut record.extra, "REFN", value),
+        _ => (),
+    }
+}
+
+/// Editor of a person, either one of the tree or a new one,
+/// inserted into the tree when saved. Clones share the person.
+#[derive(Clone)]
 pub struct PersonEditorView {
     gbuilder: gtk::Builder,
+    model: AppModel,
+    record: RecordRef,
 }
 
 impl PersonEditorView {
-    pub fn new() -> Self {
+    pub fn new(model: AppModel) -> Self {
+        let record = Record {
+            rtype: String::from("INDI"),
+            ..Default::default()
+        };
+        Self::with_record(model, record.into())
+    }
+
+    pub fn with_record(model: AppModel, record: RecordRef) -> Self {
         Self {
             gbuilder: gtk::Builder::from_resource("/org/altereigo/npaf/PersonEditor.glade"),
+            model,
+            record,
+        }
+    }
+
+    fn store(&self) -> gtk::ListStore {
+        self.gbuilder.object("list_store").unwrap()
+    }
+
+    fn fill_store(&self) {
+        let (store, record) = (self.store(), self.record.borrow());
+        if let Some(iter) = store.iter_first() {
+            loop {
+                let id = store.value(&iter, 0).get::<i64>().unwrap();
+                store.set_value(&iter, 2, &field(&record, id).unwrap_or_default().to_value());
+                if !store.iter_next(&iter) {
+                    break;
+                }
+            }
         }
     }
+
+    fn connect_fields(&self) {
+        let renderer: gtk::CellRendererText = self.gbuilder.object("r_value").unwrap();
+        let store = self.store();
+        renderer.connect_edited(move |_, path, text| {
+            if let Some(iter) = store.iter(&path) {
+                store.set_value(&iter, 2, &text.to_value());
+            }
+        });
+    }
+
+    /// Writes the changed fields to the person. A new person
+    /// gets its cross-reference and identifiers, and joins the
+    /// tree, the workspace moving to it.
+    fn save(&self) {
+        let store = self.store();
+        if let Some(iter) = store.iter_first() {
+            let mut record = self.record.borrow_mut();
+            loop {
+                let id = store.value(&iter, 0).get::<i64>().unwrap();
+                let text = store.value(&iter, 2).get::<String>().unwrap_or_default();
+                let value = Some(text.trim()).filter(|t| !t.is_empty());
+                if value.map(String::from) != field(&record, id) {
+                    set_field(&mut record, id, value);
+                }
+                if !store.iter_next(&iter) {
+                    break;
+                }
+            }
+        }
+        let inserted = self.record.borrow().xref.is_empty();
+        if inserted {
+            let xref = ident::next_xref(&self.model.registry(), "I");
+            {
+                let mut record = self.record.borrow_mut();
+                record.id = xref[1..].parse().unwrap_or(0);
+                record.xref = xref.clone();
+            }
+            let mut registry = self.model.registry_mut();
+            registry.insert(xref_key(&xref), self.record.clone());
+            ident::assign_identifiers(&registry);
+        }
+        self.model.relink();
+        if inserted {
+            let key = xref_key(&self.record.borrow().xref);
+            let _ = self.model.navigate(key);
+        }
+    }
+
+    fn connect_address(&self) {
+        let btn: gtk::Button = self.gbuilder.object("b_address").unwrap();
+        let (model, record) = (self.model.clone(), self.record.clone());
+        btn.connect_clicked(move |_| {
+            let contact = record.borrow().contact_info().cloned();
+            let mut editor = AddressEditorView::new(contact);
+            let (model, record) = (model.clone(), record.clone());
+            editor.subscribe(move |contact| {
+                record.borrow_mut().set_contact_info(contact);
+                model.relink();
+            });
+            editor.assemble_window().present();
+        });
+    }
+
+    fn connect_ordinances(&self) {
+        let btn: gtk::Button = self.gbuilder.object("b_lds").unwrap();
+        let (model, record) = (self.model.clone(), self.record.clone());
+        btn.connect_clicked(move |_| {
+            let editor = OrdinanceEditorView::new(model.clone(), record.clone());
+            editor.assemble_window().present();
+        });
+    }
+
+    /// Living status override, applied to the record as
+    /// soon as it is picked, the views following
+    fn connect_living(&self) {
+        let combo: gtk::ComboBoxText = self.gbuilder.object("cb_living").unwrap();
+        combo.set_active_id(Some(match self.record.borrow().living {
+            Some(true) => "living",
+            Some(false) => "deceased",
+            None => "auto",
+        }));
+        let (model, record) = (self.model.clone(), self.record.clone());
+        combo.connect_changed(move |combo| {
+            record.borrow_mut().living = match combo.active_id().as_deref() {
+                Some("living") => Some(true),
+                Some("deceased") => Some(false),
+                _ => None,
+            };
+            model.relink();
+        });
+    }
 }
 
 impl View for PersonEditorView {
     fn assemble(&self) -> gtk::Widget {
         let root: gtk::Grid = self.gbuilder.object("root").unwrap();
+        self.fill_store();
+        self.connect_fields();
+        self.connect_address();
+        self.connect_living();
+        self.connect_ordinances();
         root.show();
         root.dynamic_cast::<gtk::Widget>().unwrap()
     }
@@ -27,6 +275,17 @@ impl Windowed for PersonEditorView {
         let wdg = self.assemble();
         let wdw: gtk::Window = self.gbuilder.object("top").unwrap();
         wdw.set_child(Some(&wdg));
+
+        let b_cancel: gtk::Button = self.gbuilder.object("b_cancel").unwrap();
+        let wdw_cpy = wdw.clone();
+        b_cancel.connect_clicked(move |_| wdw_cpy.close());
+
+        let b_save: gtk::Button = self.gbuilder.object("b_save").unwrap();
+        let (view, wdw_cpy) = (self.clone(), wdw.clone());
+        b_save.connect_clicked(move |_| {
+            view.save();
+            wdw_cpy.close();
+        });
         wdw
     }
 }
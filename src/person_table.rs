@@ -0,0 +1,160 @@
+use gtk::prelude::*;
+
+use crate::app::models::AppModel;
+use crate::gedcom::index::NameIndex;
+use crate::gedcom::xref_key;
+use crate::prelude::*;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+const NORMAL: i32 = 400;
+const BOLD: i32 = 700;
+
+/// Index shown by the list, along with the row of every entry,
+/// which moves when the surnames are grouped
+#[derive(Default)]
+struct Listed {
+    index: NameIndex,
+    rows: Vec<usize>,
+}
+
+/// Name list of the main screen: every individual sorted by surname
+/// and given names. Typing a name jumps to it, activating a row
+/// focuses the workspace on the person.
+pub struct PersonTableView {
+    gbuilder: gtk::Builder,
+    model: AppModel,
+    listed: Rc<RefCell<Listed>>,
+}
+
+impl PersonTableView {
+    pub fn new(model: AppModel) -> Self {
+        Self {
+            gbuilder: gtk::Builder::from_resource("/org/altereigo/npaf/PersonTable.glade"),
+            model,
+            listed: Default::default(),
+        }
+    }
+
+    /// Lists the individuals again, surname headers being
+    /// inserted when they are grouped. The headers are told
+    /// apart by their last column rather than by their key.
+    fn fill(gbuilder: &gtk::Builder, model: &AppModel, listed: &Rc<RefCell<Listed>>) {
+        let store: gtk::ListStore = gbuilder.object("person_store").unwrap();
+        let persons: gtk::TreeView = gbuilder.object("tv_persons").unwrap();
+        let grouped = gbuilder.object::<gtk::CheckButton>("c_group").unwrap().is_active();
+        let status: gtk::Label = gbuilder.object("l_status").unwrap();
+        let index = NameIndex::new(&model.registry());
+        let mut rows = Vec::with_capacity(index.len());
+
+        // Detached while filled, so that the view
+        // does not follow every single insertion
+        persons.set_model(None::<&gtk::TreeModel>);
+        store.clear();
+        let surnames = index.surnames();
+        let mut row = 0;
+        for (surname, range) in &surnames {
+            if grouped {
+                let header = match surname.is_empty() {
+                    true => format!("(no surname) ({})", range.len()),
+                    false => format!("{} ({})", surname, range.len()),
+                };
+                store.insert_with_values(None, &[(0, &0u64), (1, &header), (2, &String::new()), (3, &BOLD), (4, &true)]);
+                row += 1;
+            }
+            for entry in &index.entries[range.clone()] {
+                let name = match (grouped, entry.given.is_empty()) {
+                    (true, false) => format!("    {}", entry.given),
+                    (true, true) => String::from("    (no given names)"),
+                    (false, _) => entry.list_name(),
+                };
+                store.insert_with_values(None, &[(0, &entry.key), (1, &name), (2, &entry.lifespan), (3, &NORMAL), (4, &false)]);
+                rows.push(row);
+                row += 1;
+            }
+        }
+        persons.set_model(Some(&store));
+        status.set_text(&format!("{} individual(s), {} surname(s).", index.len(), surnames.len()));
+        listed.replace(Listed { index, rows });
+        if let Some(key) = model.current_key() {
+            Self::select(gbuilder, listed, key);
+        }
+    }
+
+    fn select_row(gbuilder: &gtk::Builder, row: usize, at_top: bool) {
+        let persons: gtk::TreeView = gbuilder.object("tv_persons").unwrap();
+        let path = gtk::TreePath::from_indicesv(&[row as i32]);
+        persons.selection().select_path(&path);
+        persons.scroll_to_cell(Some(&path), None::<&gtk::TreeViewColumn>, at_top, 0.0, 0.0);
+    }
+
+    /// Selects the person the workspace is focused on
+    fn select(gbuilder: &gtk::Builder, listed: &Rc<RefCell<Listed>>, key: u64) {
+        let listed = listed.borrow();
+        if let Some(position) = listed.index.position(key) {
+            Self::select_row(gbuilder, listed.rows[position], false);
+        }
+    }
+
+    /// Moves the list to the name typed, at the top of the view
+    fn jump(gbuilder: &gtk::Builder, listed: &Rc<RefCell<Listed>>, typed: &str) {
+        let listed = listed.borrow();
+        if let Some(position) = listed.index.find(typed) {
+            Self::select_row(gbuilder, listed.rows[position], true);
+        }
+    }
+
+    /// Focuses the workspace on the selected person, if any
+    fn open_selected(gbuilder: &gtk::Builder, model: &AppModel) {
+        let persons: gtk::TreeView = gbuilder.object("tv_persons").unwrap();
+        if let Some((store, iter)) = persons.selection().selected() {
+            if !store.value(&iter, 4).get::<bool>().unwrap() {
+                let key = store.value(&iter, 0).get::<u64>().unwrap();
+                let _ = model.navigate(key);
+            }
+        }
+    }
+}
+
+impl View for PersonTableView {
+    fn assemble(&self) -> gtk::Widget {
+        let root: gtk::Grid = self.gbuilder.object("root").unwrap();
+        Self::fill(&self.gbuilder, &self.model, &self.listed);
+
+        let (gbuilder, model, listed) = (self.gbuilder.clone(), self.model.clone(), self.listed.clone());
+        self.model.on_change(move || Self::fill(&gbuilder, &model, &listed));
+        let mut model = self.model.clone();
+        let (gbuilder, listed) = (self.gbuilder.clone(), self.listed.clone());
+        model.subscribe(move |person| Self::select(&gbuilder, &listed, xref_key(&person.borrow().xref)));
+
+        let group: gtk::CheckButton = self.gbuilder.object("c_group").unwrap();
+        let (gbuilder, model, listed) = (self.gbuilder.clone(), self.model.clone(), self.listed.clone());
+        group.connect_toggled(move |_| Self::fill(&gbuilder, &model, &listed));
+
+        let jump: gtk::SearchEntry = self.gbuilder.object("e_jump").unwrap();
+        let (gbuilder, listed) = (self.gbuilder.clone(), self.listed.clone());
+        jump.connect_search_changed(move |entry| Self::jump(&gbuilder, &listed, &entry.text()));
+        let (gbuilder, model) = (self.gbuilder.clone(), self.model.clone());
+        jump.connect_activate(move |_| Self::open_selected(&gbuilder, &model));
+
+        let persons: gtk::TreeView = self.gbuilder.object("tv_persons").unwrap();
+        let (gbuilder, model) = (self.gbuilder.clone(), self.model.clone());
+        persons.connect_row_activated(move |_, _, _| Self::open_selected(&gbuilder, &model));
+        // Type-ahead: printable keys typed in the list go to the jump entry
+        persons.connect_key_press_event(move |_, event| {
+            let modified = event.state().intersects(gtk::gdk::ModifierType::CONTROL_MASK | gtk::gdk::ModifierType::MOD1_MASK);
+            match event.keyval().to_unicode() {
+                Some(c) if !c.is_control() && !modified => {
+                    jump.grab_focus_without_selecting();
+                    jump.set_text(&format!("{}{}", jump.text(), c));
+                    jump.set_position(-1);
+                    gtk::Inhibit(true)
+                },
+                _ => gtk::Inhibit(false),
+            }
+        });
+
+        root.show();
+        root.dynamic_cast::<gtk::Widget>().unwrap()
+    }
+}
@@ -0,0 +1,72 @@
+use gtk::prelude::*;
+
+use crate::app::preferences::Preferences;
+use crate::prelude::*;
+
+/// Dialog editing the [preferences](Preferences), saved
+/// for the windows opened afterwards
+pub struct PreferencesView {
+    gbuilder: gtk::Builder,
+}
+
+impl PreferencesView {
+    pub fn new() -> Self {
+        Self {
+            gbuilder: gtk::Builder::from_resource("/org/altereigo/npaf/Preferences.glade"),
+        }
+    }
+
+    fn spin(gbuilder: &gtk::Builder, name: &str) -> gtk::SpinButton {
+        gbuilder.object(name).unwrap()
+    }
+
+    fn fill(&self) {
+        let preferences = Preferences::load();
+        Self::spin(&self.gbuilder, "sb_max_age").set_value(preferences.max_age as f64);
+        Self::spin(&self.gbuilder, "sb_min_parent_age").set_value(preferences.min_parent_age as f64);
+        Self::spin(&self.gbuilder, "sb_duplicate_score").set_value(preferences.duplicate_score as f64);
+    }
+
+    fn collect(gbuilder: &gtk::Builder) -> Preferences {
+        Preferences {
+            max_age: Self::spin(gbuilder, "sb_max_age").value_as_int(),
+            min_parent_age: Self::spin(gbuilder, "sb_min_parent_age").value_as_int(),
+            duplicate_score: Self::spin(gbuilder, "sb_duplicate_score").value_as_int(),
+        }
+    }
+}
+
+impl View for PreferencesView {
+    fn assemble(&self) -> gtk::Widget {
+        let root: gtk::Grid = self.gbuilder.object("root").unwrap();
+        self.fill();
+        root.show();
+        root.dynamic_cast::<gtk::Widget>().unwrap()
+    }
+}
+
+impl Windowed for PreferencesView {
+    fn assemble_window(&self) -> gtk::Window {
+        let wdg = self.assemble();
+        let wdw: gtk::Window = self.gbuilder.object("top").unwrap();
+        wdw.set_child(Some(&wdg));
+
+        let b_cancel: gtk::Button = self.gbuilder.object("b_cancel").unwrap();
+        let wdw_cpy = wdw.clone();
+        b_cancel.connect_clicked(move |_| wdw_cpy.close());
+
+        let b_save: gtk::Button = self.gbuilder.object("b_save").unwrap();
+        let (gbuilder, wdw_cpy) = (self.gbuilder.clone(), wdw.clone());
+        b_save.connect_clicked(move |_| {
+            match Self::collect(&gbuilder).save() {
+                Ok(()) => wdw_cpy.close(),
+                Err(err) => {
+                    let status: gtk::Label = gbuilder.object("l_status").unwrap();
+                    status.set_text(&format!("Could not save the preferences: {}", err));
+                    status.style_context().add_class("color-urgent");
+                },
+            }
+        });
+        wdw
+    }
+}
@@ -0,0 +1,237 @@
+use gtk::prelude::*;
+
+use crate::app::models::AppModel;
+use crate::app::preferences::Preferences;
+use crate::gedcom::PrivacyMode;
+use crate::prelude::*;
+use crate::report::{self, Element, FontFamily, Margins, PageSize, Report, ReportKind, ReportSettings};
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+/// Points in a millimetre
+const MM: f64 = 72.0 / 25.4;
+
+/// Clones share the laid out report and the previewed page
+#[derive(Clone)]
+pub struct PrintReportView {
+    gbuilder: gtk::Builder,
+    model: AppModel,
+    report: Rc<RefCell<Option<Report>>>,
+    page: Rc<Cell<usize>>,
+}
+
+impl PrintReportView {
+    pub fn new(model: AppModel) -> Self {
+        Self {
+            gbuilder: gtk::Builder::from_resource("/org/altereigo/npaf/Report.glade"),
+            model,
+            report: Default::default(),
+            page: Default::default(),
+        }
+    }
+
+    /// View opened on the report of the given kind (the
+    /// ids of `cb_kind`, such as `descendancy`)
+    pub fn with_kind(model: AppModel, kind: &str) -> Self {
+        let view = Self::new(model);
+        let combo: gtk::ComboBoxText = view.gbuilder.object("cb_kind").unwrap();
+        combo.set_active_id(Some(kind));
+        view
+    }
+
+    fn combo(gbuilder: &gtk::Builder, name: &str) -> Option<String> {
+        let combo: gtk::ComboBoxText = gbuilder.object(name).unwrap();
+        combo.active_id().map(|id| id.to_string())
+    }
+
+    fn spin(gbuilder: &gtk::Builder, name: &str) -> f64 {
+        let spin: gtk::SpinButton = gbuilder.object(name).unwrap();
+        spin.value()
+    }
+
+    fn kind(gbuilder: &gtk::Builder) -> ReportKind {
+        match Self::combo(gbuilder, "cb_kind").as_deref() {
+            Some("family") => ReportKind::FamilyGroup,
+            Some("individual") => ReportKind::Individual,
+            Some("descendancy") => ReportKind::Descendancy,
+            Some("ahnentafel") => ReportKind::Ahnentafel,
+            _ => ReportKind::Pedigree,
+        }
+    }
+
+    fn settings(gbuilder: &gtk::Builder) -> ReportSettings {
+        let landscape: gtk::CheckButton = gbuilder.object("c_landscape").unwrap();
+        ReportSettings {
+            page_size: match Self::combo(gbuilder, "cb_page_size").as_deref() {
+                Some("letter") => PageSize::Letter,
+                Some("legal") => PageSize::Legal,
+                _ => PageSize::A4,
+            },
+            landscape: landscape.is_active(),
+            margins: Margins::uniform(Self::spin(gbuilder, "sb_margin") * MM),
+            font: match Self::combo(gbuilder, "cb_font").as_deref() {
+                Some("times") => FontFamily::Times,
+                Some("courier") => FontFamily::Courier,
+                _ => FontFamily::Helvetica,
+            },
+            font_size: Self::spin(gbuilder, "sb_font_size"),
+            generations: Self::spin(gbuilder, "sb_generations") as usize,
+            privacy: Self::combo(gbuilder, "cb_living")
+                .and_then(|id| PrivacyMode::from_id(&id))
+                .unwrap_or(PrivacyMode::Include),
+            living_rules: Preferences::load().living_rules(),
+            ..Default::default()
+        }
+    }
+
+    /// Lays out the report about the current person
+    fn refresh(&self) {
+        let status: gtk::Label = self.gbuilder.object("l_status").unwrap();
+        let report = match self.model.current() {
+            Some(root) => {
+                let report = report::build(Self::kind(&self.gbuilder), &self.model.registry(), &root, &Self::settings(&self.gbuilder));
+                status.set_text(&report.title);
+                Some(report)
+            },
+            None => {
+                status.set_text("Select a person in the workspace first.");
+                None
+            }
+        };
+        self.report.replace(report);
+        self.page.set(0);
+        self.update_page();
+    }
+
+    fn update_page(&self) {
+        let count = self.report.borrow().as_ref().map_or(0, |r| r.pages.len());
+        let label: gtk::Label = self.gbuilder.object("l_page").unwrap();
+        label.set_text(&match count {
+            0 => String::new(),
+            count => format!("Page {} of {}", self.page.get() + 1, count),
+        });
+        let area: gtk::DrawingArea = self.gbuilder.object("da_preview").unwrap();
+        area.queue_draw();
+    }
+
+    /// Paints a page scaled to the preview area
+    fn paint(report: &Report, page: usize, area: &gtk::DrawingArea, cr: &gtk::cairo::Context) {
+        let page = match report.pages.get(page) {
+            Some(page) => page,
+            None => return,
+        };
+        let (width, height) = report.settings.page_dimensions();
+        let (area_width, area_height) = (area.allocated_width() as f64, area.allocated_height() as f64);
+        let scale = ((area_width - 20.0) / width).min((area_height - 20.0) / height).max(0.1);
+        cr.translate((area_width - width * scale) / 2.0, 10.0);
+        cr.scale(scale, scale);
+        cr.set_source_rgb(1.0, 1.0, 1.0);
+        cr.rectangle(0.0, 0.0, width, height);
+        let _ = cr.fill();
+        cr.set_source_rgb(0.0, 0.0, 0.0);
+        let face = report.settings.font.face();
+        for element in &page.elements {
+            match element {
+                Element::Text { x, y, size, bold, text } => {
+                    let weight = if *bold { gtk::cairo::FontWeight::Bold } else { gtk::cairo::FontWeight::Normal };
+                    cr.select_font_face(face, gtk::cairo::FontSlant::Normal, weight);
+                    cr.set_font_size(*size);
+                    cr.move_to(*x, *y);
+                    let _ = cr.show_text(text);
+                },
+                Element::Line { x1, y1, x2, y2, width } => {
+                    cr.set_line_width(*width);
+                    cr.move_to(*x1, *y1);
+                    cr.line_to(*x2, *y2);
+                    let _ = cr.stroke();
+                },
+                Element::Rect { x, y, width, height } => {
+                    cr.set_line_width(0.5);
+                    cr.rectangle(*x, *y, *width, *height);
+                    let _ = cr.stroke();
+                },
+            }
+        }
+    }
+
+    fn save(report: &Report, status: &gtk::Label) {
+        let dialog = gtk::FileChooserDialog::with_buttons::<gtk::Window>(
+            Some("Save report"),
+            None,
+            gtk::FileChooserAction::Save,
+            &[("_Cancel", gtk::ResponseType::Cancel), ("_Save", gtk::ResponseType::Accept)],
+        );
+        dialog.set_do_overwrite_confirmation(true);
+        dialog.set_current_name(&format!("{}.pdf", report.title.replace(':', "")));
+        if dialog.run() == gtk::ResponseType::Accept {
+            if let Some(path) = dialog.filename() {
+                let written = std::fs::File::create(&path)
+                    .and_then(|mut file| report::pdf::write(report, &mut file));
+                status.set_text(&match written {
+                    Ok(()) => format!("Report saved to '{}'.", path.display()),
+                    Err(err) => format!("Could not save the report: {}", err),
+                });
+            }
+        }
+        dialog.close();
+    }
+}
+
+impl View for PrintReportView {
+    fn assemble(&self) -> gtk::Widget {
+        let root: gtk::Grid = self.gbuilder.object("root").unwrap();
+
+        let area: gtk::DrawingArea = self.gbuilder.object("da_preview").unwrap();
+        let (report, page) = (self.report.clone(), self.page.clone());
+        area.connect_draw(move |area, cr| {
+            if let Some(report) = report.borrow().as_ref() {
+                Self::paint(report, page.get(), area, cr);
+            }
+            gtk::Inhibit(false)
+        });
+
+        let view = self.clone();
+        let b_preview: gtk::Button = self.gbuilder.object("b_preview").unwrap();
+        let view_cpy = self.clone();
+        b_preview.connect_clicked(move |_| view_cpy.refresh());
+
+        for (name, step) in &[("b_prev_page", -1), ("b_next_page", 1)] {
+            let button: gtk::Button = self.gbuilder.object(name).unwrap();
+            let (view, step) = (view.clone(), *step);
+            button.connect_clicked(move |_| {
+                let count = view.report.borrow().as_ref().map_or(0, |r| r.pages.len()) as i64;
+                let page = (view.page.get() as i64 + step).max(0).min((count - 1).max(0));
+                view.page.set(page as usize);
+                view.update_page();
+            });
+        }
+
+        let b_save: gtk::Button = self.gbuilder.object("b_save").unwrap();
+        let view_cpy = self.clone();
+        b_save.connect_clicked(move |_| {
+            if view_cpy.report.borrow().is_none() {
+                view_cpy.refresh();
+            }
+            let status: gtk::Label = view_cpy.gbuilder.object("l_status").unwrap();
+            if let Some(report) = view_cpy.report.borrow().as_ref() {
+                Self::save(report, &status);
+            }
+        });
+
+        self.refresh();
+        root.show();
+        root.dynamic_cast::<gtk::Widget>().unwrap()
+    }
+}
+
+impl Windowed for PrintReportView {
+    fn assemble_window(&self) -> gtk::Window {
+        let wdg = self.assemble();
+        let wdw: gtk::Window = self.gbuilder.object("top").unwrap();
+        wdw.set_child(Some(&wdg));
+        let b_close: gtk::Button = self.gbuilder.object("b_close").unwrap();
+        let wdw_cpy = wdw.clone();
+        b_close.connect_clicked(move |_| wdw_cpy.close());
+        wdw
+    }
+}
@@ -0,0 +1,69 @@
+//! Graphical reports
+
+use crate::gedcom::{ahnentafel, FamilyGraph, RecordRc, RecordRegistry};
+use crate::report::{add_page_numbers, fit, Page, ReportSettings};
+
+/// Generations a pedigree chart can hold on one page
+const MAX_GENERATIONS: usize = 6;
+
+/// Pedigree chart: the person on the left, each generation
+/// of ancestors in its own column towards the right.
+pub(crate) fn pedigree(graph: &FamilyGraph, registry: &RecordRegistry, root: &RecordRc, settings: &ReportSettings) -> Vec<Page> {
+    let generations = settings.generations.max(2).min(MAX_GENERATIONS);
+    let slots = ahnentafel::slots(graph, registry, &root.borrow(), generations);
+    let (left, top, width, height) = settings.area();
+    let title_size = settings.font_size * 1.4;
+    let mut page = Page::default();
+    page.text(left, top + title_size, title_size, true,
+        &format!("Pedigree Chart of {}", root.borrow().display_name()));
+
+    let chart_top = top + title_size * 2.5;
+    let chart_height = height - title_size * 2.5 - settings.font_size * 2.0;
+    let column = width / generations as f64;
+    let box_width = column - 12.0;
+    let rows = (1 << (generations - 1)) as f64;
+    // The last column is the most crowded one, the font
+    // shrinks so that its boxes still fit two lines.
+    let size = settings.font_size.min(chart_height / rows / 2.6).max(4.0);
+    let box_height = size * 2.6;
+
+    let center = |generation: usize, index: usize| -> (f64, f64) {
+        let count = (1 << generation) as f64;
+        let x = left + column * generation as f64;
+        let y = chart_top + chart_height * (index as f64 + 0.5) / count;
+        (x, y)
+    };
+    for generation in 0..generations {
+        for index in 0..(1 << generation) {
+            let n = (1 << generation) + index;
+            let person = match &slots[n] {
+                Some(person) => person.borrow(),
+                None => continue,
+            };
+            let (x, y) = center(generation, index);
+            page.rect(x, y - box_height / 2.0, box_width, box_height);
+            let name = fit(settings, &format!("{}. {}", n, person.display_name()), size, true, box_width - 4.0);
+            page.text(x + 2.0, y - size * 0.2, size, true, &name);
+            let dates = fit(settings, &person.lifespan(), size * 0.9, false, box_width - 4.0);
+            page.text(x + 2.0, y + size * 1.0, size * 0.9, false, &dates);
+            if generation + 1 < generations {
+                // Elbow connector to both parents boxes
+                let (px, father_y) = center(generation + 1, index * 2);
+                let (_, mother_y) = center(generation + 1, index * 2 + 1);
+                let elbow = (x + box_width + px) / 2.0;
+                if slots[2 * n].is_some() || slots[2 * n + 1].is_some() {
+                    page.line(x + box_width, y, elbow, y);
+                }
+                for (parent, parent_y) in [(2 * n, father_y), (2 * n + 1, mother_y)].iter() {
+                    if slots[*parent].is_some() {
+                        page.line(elbow, y, elbow, *parent_y);
+                        page.line(elbow, *parent_y, px, *parent_y);
+                    }
+                }
+            }
+        }
+    }
+    let mut pages = vec![page];
+    add_page_numbers(&mut pages, settings);
+    pages
+}
@@ -0,0 +1,74 @@
+//! Generation lists: descendancy and ahnentafel reports
+
+use crate::gedcom::ahnentafel::Ancestors;
+use crate::gedcom::{FamilyGraph, PersonId, Record, RecordRc, RecordRegistry};
+use crate::report::{Flow, Page, ReportSettings};
+
+/// Vital events of a person on one line
+/// (`b. 12 MAR 1900, London; d. 1970`)
+fn vitals_line(person: &Record) -> String {
+    [("BIRT", "b."), ("CHR", "chr."), ("DEAT", "d."), ("BURI", "bur.")].iter()
+        .filter_map(|(tag, abbr)| person.event_summary(tag)
+            .filter(|s| !s.is_empty())
+            .map(|s| format!("{} {}", abbr, s)))
+        .collect::<Vec<String>>()
+        .join("; ")
+}
+
+fn describe(person: &Record) -> String {
+    match vitals_line(person) {
+        vitals if vitals.is_empty() => person.display_name(),
+        vitals => format!("{}, {}", person.display_name(), vitals),
+    }
+}
+
+fn descendants(flow: &mut Flow, graph: &FamilyGraph, registry: &RecordRegistry, person: PersonId, generation: usize, max: usize) {
+    let indent = flow.line_height() * 1.2 * (generation - 1) as f64;
+    if let Some(record) = graph.record(registry, person) {
+        flow.paragraph(indent, &format!("{} {}", generation, describe(&record.borrow())), false);
+    }
+    for family in graph.spouse_families(person) {
+        if let Some(spouse) = graph.partner(*family, person).and_then(|s| graph.record(registry, s)) {
+            flow.paragraph(indent, &format!("+ {}", describe(&spouse.borrow())), false);
+        }
+        if generation < max {
+            for child in graph.family_children(*family) {
+                descendants(flow, graph, registry, *child, generation + 1, max);
+            }
+        }
+    }
+}
+
+/// Descendancy report: the person, their spouses and their
+/// descendants, one indentation level per generation.
+pub(crate) fn descendancy(graph: &FamilyGraph, registry: &RecordRegistry, root: &RecordRc, settings: &ReportSettings) -> Vec<Page> {
+    let title = format!("Descendants of {}", root.borrow().display_name());
+    let mut flow = Flow::new(settings, &title);
+    match graph.person_of(&root.borrow()) {
+        Some(person) => descendants(&mut flow, graph, registry, person, 1, settings.generations.max(1)),
+        None => flow.paragraph(0.0, &format!("1 {}", describe(&root.borrow())), false),
+    }
+    flow.finish()
+}
+
+/// Ahnentafel report: the ancestors numbered by their position
+/// in the pedigree, grouped by generation. An ancestor reached
+/// through several lines refers to their first number.
+pub(crate) fn ahnentafel(graph: &FamilyGraph, registry: &RecordRegistry, root: &RecordRc, settings: &ReportSettings) -> Vec<Page> {
+    let title = format!("Ancestors of {}", root.borrow().display_name());
+    let mut flow = Flow::new(settings, &title);
+    let mut generation = None;
+    for ancestor in Ancestors::new(graph, registry, &root.borrow(), settings.generations.max(1)) {
+        if generation != Some(ancestor.generation) {
+            generation = Some(ancestor.generation);
+            flow.heading(&format!("Generation {}", ancestor.generation + 1));
+        }
+        let person = ancestor.person.borrow();
+        let line = match ancestor.duplicate_of {
+            Some(first) => format!("{}. {}, see {}", ancestor.number, person.display_name(), first),
+            None => format!("{}. {}", ancestor.number, describe(&person)),
+        };
+        flow.paragraph(0.0, &line, false);
+    }
+    flow.finish()
+}
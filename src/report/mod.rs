@@ -0,0 +1,412 @@
+//! Printable reports. A report is laid out once as pages of
+//! simple drawing elements, which are then either written to
+//! a PDF file or painted by the print preview.
+
+mod charts;
+mod lists;
+pub mod pdf;
+mod sheets;
+
+use crate::gedcom::{xref_key, FamilyGraph, LivingRules, PrivacyFilter, PrivacyMode, RecordRc, RecordRegistry};
+
+/// Paper formats, dimensions are in points (1/72 inch)
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum PageSize {
+    A4,
+    Letter,
+    Legal,
+}
+
+impl PageSize {
+    pub fn dimensions(&self) -> (f64, f64) {
+        match self {
+            PageSize::A4 => (595.28, 841.89),
+            PageSize::Letter => (612.0, 792.0),
+            PageSize::Legal => (612.0, 1008.0),
+        }
+    }
+}
+
+/// Blank space around the printed area, in points
+#[derive(Debug,Clone,Copy)]
+pub struct Margins {
+    pub top: f64,
+    pub right: f64,
+    pub bottom: f64,
+    pub left: f64,
+}
+
+impl Margins {
+    pub fn uniform(margin: f64) -> Self {
+        Margins { top: margin, right: margin, bottom: margin, left: margin }
+    }
+}
+
+impl Default for Margins {
+    fn default() -> Self {
+        Margins::uniform(36.0)
+    }
+}
+
+/// Standard PDF fonts, available on every reader without
+/// being embedded in the file
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum FontFamily {
+    Helvetica,
+    Times,
+    Courier,
+}
+
+/// Advance widths of the printable ASCII characters,
+/// in 1/1000 of the font size
+const HELVETICA_WIDTHS: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278,
+    556, 556, 556, 556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556,
+    1015, 667, 667, 722, 722, 667, 611, 778, 722, 278, 500, 667, 556, 833, 722, 778,
+    667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 278, 278, 278, 469, 556,
+    333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500, 222, 833, 556, 556,
+    556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584,
+];
+
+const TIMES_WIDTHS: [u16; 95] = [
+    250, 333, 408, 500, 500, 833, 778, 180, 333, 333, 500, 564, 250, 333, 250, 278,
+    500, 500, 500, 500, 500, 500, 500, 500, 500, 500, 278, 278, 564, 564, 564, 444,
+    921, 722, 667, 667, 722, 611, 556, 722, 722, 333, 389, 722, 611, 889, 722, 722,
+    556, 722, 667, 556, 611, 722, 722, 944, 722, 722, 611, 333, 278, 333, 469, 500,
+    333, 444, 500, 444, 500, 444, 333, 500, 500, 278, 278, 500, 278, 778, 500, 500,
+    500, 500, 333, 389, 278, 500, 500, 722, 500, 500, 444, 480, 200, 480, 541,
+];
+
+impl FontFamily {
+    /// PostScript name of the font in a PDF file
+    pub fn pdf_name(&self, bold: bool) -> &'static str {
+        match (self, bold) {
+            (FontFamily::Helvetica, false) => "Helvetica",
+            (FontFamily::Helvetica, true) => "Helvetica-Bold",
+            (FontFamily::Times, false) => "Times-Roman",
+            (FontFamily::Times, true) => "Times-Bold",
+            (FontFamily::Courier, false) => "Courier",
+            (FontFamily::Courier, true) => "Courier-Bold",
+        }
+    }
+
+    /// Font family name understood by the preview toolkit
+    pub fn face(&self) -> &'static str {
+        match self {
+            FontFamily::Helvetica => "Sans",
+            FontFamily::Times => "Serif",
+            FontFamily::Courier => "Monospace",
+        }
+    }
+
+    fn char_width(&self, c: char) -> u16 {
+        let widths = match self {
+            FontFamily::Helvetica => &HELVETICA_WIDTHS,
+            FontFamily::Times => &TIMES_WIDTHS,
+            FontFamily::Courier => return 600,
+        };
+        match c {
+            ' '..='~' => widths[c as usize - 32],
+            // Accented letters are about as wide as their base letter
+            _ => widths['o' as usize - 32],
+        }
+    }
+
+    /// Width of a text printed at the given size, bold
+    /// glyphs being slightly wider than regular ones
+    pub fn text_width(&self, text: &str, size: f64, bold: bool) -> f64 {
+        let units: u32 = text.chars().map(|c| self.char_width(c) as u32).sum();
+        let factor = if bold && *self != FontFamily::Courier { 1.06 } else { 1.0 };
+        units as f64 * size / 1000.0 * factor
+    }
+}
+
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum ReportKind {
+    Pedigree,
+    FamilyGroup,
+    Individual,
+    Descendancy,
+    Ahnentafel,
+}
+
+impl ReportKind {
+    pub fn title(&self) -> &'static str {
+        match self {
+            ReportKind::Pedigree => "Pedigree Chart",
+            ReportKind::FamilyGroup => "Family Group Record",
+            ReportKind::Individual => "Individual Summary",
+            ReportKind::Descendancy => "Descendancy Report",
+            ReportKind::Ahnentafel => "Ahnentafel Report",
+        }
+    }
+}
+
+/// Layout settings shared by all the reports
+#[derive(Debug,Clone)]
+pub struct ReportSettings {
+    pub page_size: PageSize,
+    pub landscape: bool,
+    pub margins: Margins,
+    pub font: FontFamily,
+    pub font_size: f64,
+    /// Generations shown by the pedigree, descendancy
+    /// and ahnentafel reports
+    pub generations: usize,
+    /// What is printed of the living persons
+    pub privacy: PrivacyMode,
+    pub living_rules: LivingRules,
+}
+
+impl Default for ReportSettings {
+    fn default() -> Self {
+        ReportSettings {
+            page_size: PageSize::A4,
+            landscape: false,
+            margins: Margins::default(),
+            font: FontFamily::Helvetica,
+            font_size: 10.0,
+            generations: 4,
+            privacy: PrivacyMode::Include,
+            living_rules: LivingRules::default(),
+        }
+    }
+}
+
+impl ReportSettings {
+    /// Page dimensions taking the orientation into account
+    pub fn page_dimensions(&self) -> (f64, f64) {
+        let (width, height) = self.page_size.dimensions();
+        if self.landscape { (height, width) } else { (width, height) }
+    }
+
+    /// Printable area: left, top, width and height
+    pub fn area(&self) -> (f64, f64, f64, f64) {
+        let (width, height) = self.page_dimensions();
+        let m = &self.margins;
+        (m.left, m.top, width - m.left - m.right, height - m.top - m.bottom)
+    }
+}
+
+/// Drawing element of a page, coordinates are in points from
+/// the top left corner, texts are placed by their baseline.
+#[derive(Debug,Clone,PartialEq)]
+pub enum Element {
+    Text { x: f64, y: f64, size: f64, bold: bool, text: String },
+    Line { x1: f64, y1: f64, x2: f64, y2: f64, width: f64 },
+    Rect { x: f64, y: f64, width: f64, height: f64 },
+}
+
+#[derive(Debug,Clone,Default)]
+pub struct Page {
+    pub elements: Vec<Element>,
+}
+
+impl Page {
+    pub fn text(&mut self, x: f64, y: f64, size: f64, bold: bool, text: &str) {
+        if !text.is_empty() {
+            self.elements.push(Element::Text { x, y, size, bold, text: text.to_owned() });
+        }
+    }
+
+    pub fn line(&mut self, x1: f64, y1: f64, x2: f64, y2: f64) {
+        self.elements.push(Element::Line { x1, y1, x2, y2, width: 0.5 });
+    }
+
+    pub fn rect(&mut self, x: f64, y: f64, width: f64, height: f64) {
+        self.elements.push(Element::Rect { x, y, width, height });
+    }
+}
+
+/// Laid out report, ready to be rendered
+#[derive(Debug,Clone)]
+pub struct Report {
+    pub title: String,
+    pub settings: ReportSettings,
+    pub pages: Vec<Page>,
+}
+
+/// Lays out a report about the given record: an individual,
+/// or a family for the family group record.
+pub fn build(kind: ReportKind, registry: &RecordRegistry, root: &RecordRc, settings: &ReportSettings) -> Report {
+    if settings.privacy != PrivacyMode::Include {
+        let filtered = PrivacyFilter::new(registry, settings.privacy, &settings.living_rules).apply(registry);
+        let settings = ReportSettings { privacy: PrivacyMode::Include, ..settings.clone() };
+        return match filtered.get(&xref_key(&root.borrow().xref)) {
+            Some(root) => build(kind, &filtered, &root.clone(), &settings),
+            None => {
+                let mut flow = Flow::new(&settings, kind.title());
+                flow.paragraph(0.0, "The selected person is living and left out of the report.", false);
+                let pages = flow.finish();
+                Report { title: kind.title().to_owned(), settings, pages }
+            },
+        };
+    }
+    let graph = FamilyGraph::new(registry);
+    let pages = match kind {
+        ReportKind::Pedigree => charts::pedigree(&graph, registry, root, settings),
+        ReportKind::FamilyGroup => sheets::family_group(&graph, registry, root, settings),
+        ReportKind::Individual => sheets::individual(&graph, registry, root, settings),
+        ReportKind::Descendancy => lists::descendancy(&graph, registry, root, settings),
+        ReportKind::Ahnentafel => lists::ahnentafel(&graph, registry, root, settings),
+    };
+    Report {
+        title: format!("{}: {}", kind.title(), root.borrow().display_name()),
+        settings: settings.clone(),
+        pages,
+    }
+}
+
+/// Cuts a text so that it fits the given width, marking
+/// the cut with an ellipsis
+pub(crate) fn fit(settings: &ReportSettings, text: &str, size: f64, bold: bool, width: f64) -> String {
+    let font = settings.font;
+    if font.text_width(text, size, bold) <= width {
+        return text.to_owned();
+    }
+    let mut fitted: String = text.to_owned();
+    while !fitted.is_empty() && font.text_width(&format!("{}...", fitted), size, bold) > width {
+        fitted.pop();
+    }
+    format!("{}...", fitted.trim_end())
+}
+
+/// Splits a text into lines fitting the given width
+pub(crate) fn wrap(settings: &ReportSettings, text: &str, size: f64, bold: bool, width: f64) -> Vec<String> {
+    let mut lines: Vec<String> = vec![];
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            let candidate = if line.is_empty() { word.to_owned() } else { format!("{} {}", line, word) };
+            if line.is_empty() || settings.font.text_width(&candidate, size, bold) <= width {
+                line = candidate;
+            } else {
+                lines.push(std::mem::replace(&mut line, word.to_owned()));
+            }
+        }
+        lines.push(line);
+    }
+    lines.into_iter()
+        .map(|l| fit(settings, &l, size, bold, width))
+        .collect()
+}
+
+/// Text flowing from page to page, used by the list-like
+/// reports. Every page gets the report title and a footer
+/// with its number.
+pub(crate) struct Flow<'a> {
+    settings: &'a ReportSettings,
+    title: String,
+    pages: Vec<Page>,
+    y: f64,
+}
+
+impl<'a> Flow<'a> {
+    pub fn new(settings: &'a ReportSettings, title: &str) -> Self {
+        let mut flow = Flow { settings, title: title.to_owned(), pages: vec![], y: 0.0 };
+        flow.new_page();
+        flow
+    }
+
+    fn new_page(&mut self) {
+        let (left, top, width, _) = self.settings.area();
+        let size = self.settings.font_size * 1.4;
+        let mut page = Page::default();
+        page.text(left, top + size, size, true, &self.title);
+        page.line(left, top + size * 1.5, left + width, top + size * 1.5);
+        self.pages.push(page);
+        self.y = top + size * 2.5;
+    }
+
+    fn page(&mut self) -> &mut Page {
+        self.pages.last_mut().unwrap()
+    }
+
+    pub fn line_height(&self) -> f64 {
+        self.settings.font_size * 1.35
+    }
+
+    /// Starts a new page if the given height does not fit
+    /// in the current one
+    pub fn ensure(&mut self, height: f64) {
+        let (_, top, _, area_height) = self.settings.area();
+        if self.y + height > top + area_height - self.line_height() {
+            self.new_page();
+        }
+    }
+
+    pub fn gap(&mut self, lines: f64) {
+        self.y += self.line_height() * lines;
+    }
+
+    pub fn heading(&mut self, text: &str) {
+        let size = self.settings.font_size * 1.2;
+        self.ensure(size * 3.0);
+        self.gap(0.5);
+        let (left, _, width, _) = self.settings.area();
+        let y = self.y + size;
+        let text = fit(self.settings, text, size, true, width);
+        self.page().text(left, y, size, true, &text);
+        self.y += size * 1.5;
+    }
+
+    /// Wrapped text shifted by `indent` points
+    pub fn paragraph(&mut self, indent: f64, text: &str, bold: bool) {
+        let (left, _, width, _) = self.settings.area();
+        let size = self.settings.font_size;
+        for line in wrap(self.settings, text, size, bold, width - indent) {
+            self.ensure(self.line_height());
+            let y = self.y + size;
+            self.page().text(left + indent, y, size, bold, &line);
+            self.y += self.line_height();
+        }
+    }
+
+    /// Labelled value, the values being aligned on a column
+    pub fn field(&mut self, indent: f64, label: &str, value: &str) {
+        if value.trim().is_empty() {
+            return;
+        }
+        let (left, _, width, _) = self.settings.area();
+        let size = self.settings.font_size;
+        let column = indent + size * 9.0;
+        let lines = wrap(self.settings, value, size, false, width - column);
+        self.ensure(self.line_height());
+        let y = self.y + size;
+        self.page().text(left + indent, y, size, true, label);
+        for line in lines {
+            self.ensure(self.line_height());
+            let y = self.y + size;
+            self.page().text(left + column, y, size, false, &line);
+            self.y += self.line_height();
+        }
+    }
+
+    pub fn rule(&mut self) {
+        let (left, _, width, _) = self.settings.area();
+        self.ensure(self.line_height());
+        let y = self.y + self.line_height() / 2.0;
+        self.page().line(left, y, left + width, y);
+        self.y += self.line_height();
+    }
+
+    /// Adds the page numbers and returns the pages
+    pub fn finish(self) -> Vec<Page> {
+        let mut pages = self.pages;
+        add_page_numbers(&mut pages, self.settings);
+        pages
+    }
+}
+
+/// Writes "Page n of m" at the bottom of every page
+pub(crate) fn add_page_numbers(pages: &mut [Page], settings: &ReportSettings) {
+    let (left, top, width, height) = settings.area();
+    let size = settings.font_size * 0.8;
+    let count = pages.len();
+    for (i, page) in pages.iter_mut().enumerate() {
+        let text = format!("Page {} of {}", i + 1, count);
+        let x = left + width - settings.font.text_width(&text, size, false);
+        page.text(x, top + height, size, false, &text);
+    }
+}
+
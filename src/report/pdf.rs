@@ -0,0 +1,121 @@
+//! Minimal PDF 1.4 writer for the laid out reports. Only the
+//! standard fonts are used, so nothing has to be embedded.
+
+use crate::report::{Element, Report};
+use std::io::Write;
+
+/// Converts a character to the `WinAnsiEncoding` used by
+/// the standard fonts, unknown characters become `?`
+fn win_ansi(c: char) -> u8 {
+    match c {
+        ' '..='~' => c as u8,
+        '\u{A0}'..='\u{FF}' => c as u32 as u8,
+        '€' => 0x80,
+        '‚' => 0x82,
+        '„' => 0x84,
+        '…' => 0x85,
+        '‘' => 0x91,
+        '’' => 0x92,
+        '“' => 0x93,
+        '”' => 0x94,
+        '•' => 0x95,
+        '–' => 0x96,
+        '—' => 0x97,
+        'Š' => 0x8A,
+        'Œ' => 0x8C,
+        'Ž' => 0x8E,
+        'š' => 0x9A,
+        'œ' => 0x9C,
+        'ž' => 0x9E,
+        'Ÿ' => 0x9F,
+        _ => b'?',
+    }
+}
+
+/// PDF string literal of a text
+fn string_literal(text: &str) -> Vec<u8> {
+    let mut literal = vec![b'('];
+    for byte in text.chars().map(win_ansi) {
+        if byte == b'(' || byte == b')' || byte == b'\\' {
+            literal.push(b'\\');
+        }
+        literal.push(byte);
+    }
+    literal.push(b')');
+    literal
+}
+
+/// Drawing operators of a page, the PDF origin being the
+/// bottom left corner of the page
+fn content_stream(elements: &[Element], height: f64) -> Vec<u8> {
+    let mut stream: Vec<u8> = vec![];
+    for element in elements {
+        match element {
+            Element::Text { x, y, size, bold, text } => {
+                let font = if *bold { "F2" } else { "F1" };
+                stream.extend(format!("BT /{} {:.2} Tf {:.2} {:.2} Td ", font, size, x, height - y).bytes());
+                stream.extend(string_literal(text));
+                stream.extend(b" Tj ET\n");
+            },
+            Element::Line { x1, y1, x2, y2, width } => {
+                stream.extend(format!("{:.2} w {:.2} {:.2} m {:.2} {:.2} l S\n",
+                    width, x1, height - y1, x2, height - y2).bytes());
+            },
+            Element::Rect { x, y, width, height: h } => {
+                stream.extend(format!("0.5 w {:.2} {:.2} {:.2} {:.2} re S\n", x, height - y - h, width, h).bytes());
+            },
+        }
+    }
+    stream
+}
+
+/// Writes the report as a PDF document
+pub fn write<W: Write>(report: &Report, out: &mut W) -> std::io::Result<()> {
+    let (width, height) = report.settings.page_dimensions();
+    let font = report.settings.font;
+    // Objects 1 to 5 are the catalog, the page tree, both
+    // fonts and the document information, each page then
+    // takes two objects: the page and its content stream.
+    let page_ids: Vec<usize> = (0..report.pages.len()).map(|i| 6 + i * 2).collect();
+    let mut objects: Vec<Vec<u8>> = vec![
+        b"<< /Type /Catalog /Pages 2 0 R >>".to_vec(),
+        format!("<< /Type /Pages /Kids [{}] /Count {} >>",
+            page_ids.iter().map(|id| format!("{} 0 R", id)).collect::<Vec<String>>().join(" "),
+            page_ids.len()).into_bytes(),
+    ];
+    for bold in &[false, true] {
+        objects.push(format!("<< /Type /Font /Subtype /Type1 /BaseFont /{} /Encoding /WinAnsiEncoding >>",
+            font.pdf_name(*bold)).into_bytes());
+    }
+    let mut info = b"<< /Title ".to_vec();
+    info.extend(string_literal(&report.title));
+    info.extend(b" /Producer (nPAF) >>");
+    objects.push(info);
+    for (page, id) in report.pages.iter().zip(&page_ids) {
+        objects.push(format!("<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {:.2} {:.2}] \
+            /Resources << /Font << /F1 3 0 R /F2 4 0 R >> >> /Contents {} 0 R >>",
+            width, height, id + 1).into_bytes());
+        let stream = content_stream(&page.elements, height);
+        let mut object = format!("<< /Length {} >>\nstream\n", stream.len()).into_bytes();
+        object.extend(stream);
+        object.extend(b"endstream");
+        objects.push(object);
+    }
+
+    let mut file: Vec<u8> = b"%PDF-1.4\n%\xE2\xE3\xCF\xD3\n".to_vec();
+    let mut offsets: Vec<usize> = vec![];
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(file.len());
+        file.extend(format!("{} 0 obj\n", i + 1).bytes());
+        file.extend(object);
+        file.extend(b"\nendobj\n");
+    }
+    let xref = file.len();
+    file.extend(format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).bytes());
+    for offset in offsets {
+        file.extend(format!("{:010} 00000 n \n", offset).bytes());
+    }
+    file.extend(format!("trailer\n<< /Size {} /Root 1 0 R /Info 5 0 R >>\nstartxref\n{}\n%%EOF\n",
+        objects.len() + 1, xref).bytes());
+    out.write_all(&file)
+}
@@ -0,0 +1,186 @@
+//! Record sheets: family group record and individual summary
+
+use crate::gedcom::{FamilyGraph, PersonId, Record, RecordRc, RecordRegistry};
+use crate::report::{Flow, Page, ReportSettings};
+
+/// Vital events printed for every person of a sheet
+const VITAL_EVENTS: [(&str, &str); 4] = [
+    ("BIRT", "Born:"),
+    ("CHR", "Christened:"),
+    ("DEAT", "Died:"),
+    ("BURI", "Buried:"),
+];
+
+fn name_of(record: &Option<RecordRc>) -> String {
+    record.as_ref().map(|r| r.borrow().display_name()).unwrap_or_default()
+}
+
+fn relatives(graph: &FamilyGraph, registry: &RecordRegistry, persons: &[PersonId]) -> Vec<RecordRc> {
+    persons.iter().filter_map(|p| graph.record(registry, *p)).collect()
+}
+
+fn relative(graph: &FamilyGraph, registry: &RecordRegistry, person: Option<PersonId>) -> Option<RecordRc> {
+    person.and_then(|p| graph.record(registry, p))
+}
+
+fn vitals(flow: &mut Flow, indent: f64, person: &Record) {
+    for (tag, label) in VITAL_EVENTS.iter() {
+        if let Some(summary) = person.event_summary(tag) {
+            flow.field(indent, label, &summary);
+        }
+    }
+}
+
+/// Family of the sheet: the family itself, or the first family
+/// where the person is a spouse. A person without family is
+/// printed alone along with the children linked to them.
+fn family_members(graph: &FamilyGraph, registry: &RecordRegistry, root: &RecordRc) -> (Option<RecordRc>, Option<RecordRc>, Option<RecordRc>, Vec<RecordRc>) {
+    let record = root.borrow();
+    let family = match record.rtype.as_str() {
+        "FAM" => graph.family_of(&record),
+        _ => graph.person_of(&record).and_then(|p| graph.spouse_families(p).first().copied()),
+    };
+    if let Some(family) = family {
+        return (
+            graph.family_record(registry, family),
+            relative(graph, registry, graph.husband(family)),
+            relative(graph, registry, graph.wife(family)),
+            relatives(graph, registry, graph.family_children(family)),
+        );
+    }
+    let person = Some(root.clone());
+    match record.sex() {
+        'F' => (None, None, person, vec![]),
+        _ => (None, person, None, vec![]),
+    }
+}
+
+fn parent_section(flow: &mut Flow, graph: &FamilyGraph, registry: &RecordRegistry, role: &str, person: &Option<RecordRc>) {
+    let person = match person {
+        Some(person) => person.borrow(),
+        None => return,
+    };
+    flow.heading(&format!("{}: {}", role, person.display_name()));
+    vitals(flow, 0.0, &person);
+    let id = graph.person_of(&person);
+    flow.field(0.0, "Father:", &name_of(&relative(graph, registry, id.and_then(|p| graph.father(p)))));
+    flow.field(0.0, "Mother:", &name_of(&relative(graph, registry, id.and_then(|p| graph.mother(p)))));
+}
+
+/// Family group record: both partners with their parents,
+/// the marriage and all the children of the family.
+pub(crate) fn family_group(graph: &FamilyGraph, registry: &RecordRegistry, root: &RecordRc, settings: &ReportSettings) -> Vec<Page> {
+    let (family, husband, wife, children) = family_members(graph, registry, root);
+    let title = match (&husband, &wife) {
+        (Some(_), Some(_)) => format!("Family Group Record: {} and {}", name_of(&husband), name_of(&wife)),
+        _ => format!("Family Group Record: {}{}", name_of(&husband), name_of(&wife)),
+    };
+    let mut flow = Flow::new(settings, &title);
+    parent_section(&mut flow, graph, registry, "Husband", &husband);
+    parent_section(&mut flow, graph, registry, "Wife", &wife);
+    if let Some(family) = &family {
+        let family = family.borrow();
+        let events: Vec<(String, String)> = family.events.iter()
+            .map(|e| (format!("{}:", e.label()), e.summary()))
+            .chain(family.ordinances.iter().map(|o| (format!("{}:", o.kind.label()), o.summary())))
+            .collect();
+        if !events.is_empty() {
+            flow.heading("Family events");
+            for (label, summary) in &events {
+                flow.field(0.0, label, summary);
+            }
+        }
+    }
+    if !children.is_empty() {
+        flow.heading("Children");
+    }
+    let indent = settings.font_size * 2.0;
+    for (i, child) in children.iter().enumerate() {
+        let child = child.borrow();
+        flow.ensure(flow.line_height() * 3.0);
+        flow.paragraph(0.0, &format!("{}. {} ({})", i + 1, child.display_name(), child.sex()), true);
+        vitals(&mut flow, indent, &child);
+        let id = match graph.person_of(&child) {
+            Some(id) => id,
+            None => continue,
+        };
+        for spouse_family in graph.spouse_families(id) {
+            let married = graph.family_record(registry, *spouse_family)
+                .and_then(|f| f.borrow().event_summary("MARR"))
+                .unwrap_or_default();
+            flow.field(indent, "Spouse:", &name_of(&relative(graph, registry, graph.partner(*spouse_family, id))));
+            flow.field(indent, "Married:", &married);
+        }
+        flow.gap(0.3);
+    }
+    flow.finish()
+}
+
+/// Individual summary: identifiers, all the events, parents,
+/// families and notes of a person.
+pub(crate) fn individual(graph: &FamilyGraph, registry: &RecordRegistry, root: &RecordRc, settings: &ReportSettings) -> Vec<Page> {
+    let person = root.borrow();
+    let id = graph.person_of(&person);
+    let mut flow = Flow::new(settings, &format!("Individual Summary: {}", person.display_name()));
+    let sex = match person.sex() {
+        'M' => "Male",
+        'F' => "Female",
+        _ => "Unknown",
+    };
+    flow.field(0.0, "Sex:", sex);
+    flow.field(0.0, "RIN:", &person.rin.to_string());
+    flow.field(0.0, "Custom ID:", &person.custom_id().unwrap_or_default());
+    flow.field(0.0, "AFN:", &person.afn().unwrap_or_default());
+
+    if !person.events.is_empty() {
+        flow.heading("Events");
+        for event in &person.events {
+            flow.field(0.0, &format!("{}:", event.label()), &event.summary());
+        }
+    }
+    if !person.ordinances.is_empty() {
+        flow.heading("LDS ordinances");
+        for ordinance in &person.ordinances {
+            flow.field(0.0, &format!("{}:", ordinance.kind.label()), &ordinance.summary());
+        }
+    }
+    if let Some(contact) = person.contact_info() {
+        flow.heading("Contact");
+        for line in contact.lines() {
+            flow.paragraph(0.0, &line, false);
+        }
+    }
+
+    flow.heading("Parents");
+    flow.field(0.0, "Father:", &name_of(&relative(graph, registry, id.and_then(|p| graph.father(p)))));
+    flow.field(0.0, "Mother:", &name_of(&relative(graph, registry, id.and_then(|p| graph.mother(p)))));
+
+    let indent = settings.font_size * 2.0;
+    let families = id.map(|p| graph.spouse_families(p)).unwrap_or_default();
+    for family in families {
+        let spouse = relative(graph, registry, id.and_then(|p| graph.partner(*family, p)));
+        flow.heading(&format!("Family with {}", match &spouse {
+            Some(_) => name_of(&spouse),
+            None => String::from("unknown spouse"),
+        }));
+        let married = graph.family_record(registry, *family)
+            .and_then(|f| f.borrow().event_summary("MARR"))
+            .unwrap_or_default();
+        flow.field(0.0, "Married:", &married);
+        let children = graph.family_children(*family).iter().filter(|c| Some(**c) != id).copied();
+        for child in relatives(graph, registry, &children.collect::<Vec<PersonId>>()) {
+            let child = child.borrow();
+            flow.field(indent, "Child:", format!("{} {}", child.display_name(), child.lifespan()).trim());
+        }
+    }
+
+    let notes = person.notes(registry);
+    if !notes.is_empty() {
+        flow.heading("Notes");
+        for note in &notes {
+            flow.paragraph(0.0, note, false);
+            flow.gap(0.3);
+        }
+    }
+    flow.finish()
+}
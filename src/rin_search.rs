@@ -0,0 +1,80 @@
+use gtk::prelude::*;
+
+use crate::app::models::AppModel;
+use crate::gedcom::search::{find_by_id, IdKind};
+use crate::gedcom::xref_key;
+use crate::prelude::*;
+
+pub struct RinSearchView {
+    gbuilder: gtk::Builder,
+    model: AppModel,
+}
+
+impl RinSearchView {
+    pub fn new(model: AppModel) -> Self {
+        Self {
+            gbuilder: gtk::Builder::from_resource("/org/altereigo/npaf/RinSearch.glade"),
+            model,
+        }
+    }
+
+    fn kind(gbuilder: &gtk::Builder) -> IdKind {
+        let combo: gtk::ComboBoxText = gbuilder.object("cb_kind").unwrap();
+        match combo.active_id().as_deref() {
+            Some("refn") => IdKind::CustomId,
+            Some("afn") => IdKind::Afn,
+            _ => IdKind::Rin,
+        }
+    }
+
+    /// Jumps to the first record with the entered identifier,
+    /// returns `false` if there is none.
+    fn jump(gbuilder: &gtk::Builder, model: &AppModel) -> bool {
+        let entry: gtk::Entry = gbuilder.object("e_id").unwrap();
+        let status: gtk::Label = gbuilder.object("l_status").unwrap();
+        let found = find_by_id(&model.registry(), Self::kind(gbuilder), &entry.text());
+        let first = match found.first() {
+            Some(first) => xref_key(&first.borrow().xref),
+            None => {
+                status.set_text(&format!("No record with identifier '{}'.", entry.text()));
+                return false;
+            }
+        };
+        model.navigate(first).is_ok()
+    }
+}
+
+impl View for RinSearchView {
+    fn assemble(&self) -> gtk::Widget {
+        let root: gtk::Grid = self.gbuilder.object("root").unwrap();
+        root.show();
+        root.dynamic_cast::<gtk::Widget>().unwrap()
+    }
+}
+
+impl Windowed for RinSearchView {
+    fn assemble_window(&self) -> gtk::Window {
+        let wdg = self.assemble();
+        let wdw: gtk::Window = self.gbuilder.object("top").unwrap();
+        wdw.set_child(Some(&wdg));
+
+        let b_cancel: gtk::Button = self.gbuilder.object("b_cancel").unwrap();
+        let wdw_cpy = wdw.clone();
+        b_cancel.connect_clicked(move |_| wdw_cpy.close());
+
+        let go = {
+            let (gbuilder, model, wdw) = (self.gbuilder.clone(), self.model.clone(), wdw.clone());
+            move || {
+                if Self::jump(&gbuilder, &model) {
+                    wdw.close();
+                }
+            }
+        };
+        let go_cpy = go.clone();
+        let b_go: gtk::Button = self.gbuilder.object("b_go").unwrap();
+        b_go.connect_clicked(move |_| go());
+        let e_id: gtk::Entry = self.gbuilder.object("e_id").unwrap();
+        e_id.connect_activate(move |_| go_cpy());
+        wdw
+    }
+}
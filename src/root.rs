@@ -1,18 +1,90 @@
 use gtk::prelude::*;
 
 use crate::{person_editor::PersonEditorView, prelude::*};
+use crate::app::models::AppModel;
+use crate::app::{actions, window};
+use crate::export_view::ExportView;
+use crate::family_editor::FamilyEditorView;
+use crate::find::FindView;
+use crate::batch_view::BatchView;
+use crate::csv_import_view::CsvImportView;
+use crate::import_view::{ImportMode, ImportView};
+use crate::merge::MergeView;
+use crate::ordinance_editor::OrdinanceEditorView;
+use crate::person_table::PersonTableView;
+use crate::preferences_view::PreferencesView;
+use crate::print_report::PrintReportView;
+use crate::rin_search::RinSearchView;
+use crate::stats_view::StatsView;
+use crate::verify_view::VerifyView;
+use crate::website_view::WebsiteView;
+use crate::gedcom::{xref_key, PersonId, RecordRc};
 use std::rc::Rc;
 
-#[derive(Default)]
 pub struct WorkspaceView {
-    gbuilder: gtk::Builder
+    gbuilder: gtk::Builder,
+    model: AppModel,
 }
 
 impl WorkspaceView {
-    pub fn new() -> Self {
+    pub fn new(model: AppModel) -> Self {
         Self {
             gbuilder: gtk::Builder::from_resource("/org/altereigo/npaf/Workspace.glade"),
-            ..Default::default()
+            model,
+        }
+    }
+
+    fn set_text(gbuilder: &gtk::Builder, name: &str, text: &str) {
+        let view: gtk::TextView = gbuilder.object(name).unwrap();
+        view.buffer().unwrap().set_text(text);
+    }
+
+    /// Short description of a person shown in the family frames
+    fn summary(record: Option<&RecordRc>) -> String {
+        let record = match record {
+            Some(record) => record.borrow(),
+            None => return String::new(),
+        };
+        let events = [("BIRT", "Born"), ("CHR", "Christened"), ("DEAT", "Died"), ("BURI", "Buried")];
+        let lines = events.iter()
+            .filter_map(|(tag, label)| record.event_summary(tag).map(|s| format!("{}: {}", label, s)));
+        std::iter::once(record.display_name())
+            .chain(lines)
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    fn show_person(gbuilder: &gtk::Builder, model: &AppModel, person: &RecordRc) {
+        let (registry, graph) = (model.registry(), model.graph());
+        let record = person.borrow();
+        let id = graph.person_of(&record);
+        let lookup = |person: Option<PersonId>| person.and_then(|p| graph.record(&registry, p));
+        let family = id.and_then(|p| graph.spouse_families(p).first().copied());
+        let spouse = lookup(family.zip(id).and_then(|(f, p)| graph.partner(f, p)));
+        let marriage = family.and_then(|f| graph.family_record(&registry, f))
+            .and_then(|f| f.borrow().event_summary("MARR"))
+            .unwrap_or_default();
+        let children: Vec<RecordRc> = family.map(|f| graph.family_children(f))
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|c| lookup(Some(*c)))
+            .collect();
+        Self::set_text(gbuilder, "tv_selected", &Self::summary(Some(person)));
+        Self::set_text(gbuilder, "tv_parent1", &Self::summary(lookup(id.and_then(|p| graph.father(p))).as_ref()));
+        Self::set_text(gbuilder, "tv_parent2", &Self::summary(lookup(id.and_then(|p| graph.mother(p))).as_ref()));
+        Self::set_text(gbuilder, "tv_spouse", &Self::summary(spouse.as_ref()));
+        Self::set_text(gbuilder, "tv_marriage", &marriage);
+
+        let store: gtk::ListStore = gbuilder.object("children_store").unwrap();
+        store.clear();
+        for child in &children {
+            let child = child.borrow();
+            store.insert_with_values(None, &[
+                (0, &xref_key(&child.xref)),
+                (1, &child.display_name()),
+                (2, &child.event_summary("BIRT").unwrap_or_default()),
+                (3, &String::from("Natural")),
+            ]);
         }
     }
 }
@@ -20,14 +92,38 @@ impl WorkspaceView {
 impl View for WorkspaceView {
     fn assemble(&self) -> gtk::Widget {
         let root: gtk::Grid = self.gbuilder.object("root").unwrap();
+
+        let mut model = self.model.clone();
+        let (gbuilder, model_cpy) = (self.gbuilder.clone(), self.model.clone());
+        model.subscribe(move |person| Self::show_person(&gbuilder, &model_cpy, &person));
+        if let Some(person) = self.model.current() {
+            Self::show_person(&self.gbuilder, &self.model, &person);
+        }
+
+        let children: gtk::TreeView = self.gbuilder.object("tv_children").unwrap();
+        let model = self.model.clone();
+        children.connect_row_activated(move |tv, path, _| {
+            let store = tv.model().unwrap();
+            if let Some(iter) = store.iter(path) {
+                let key = store.value(&iter, 0).get::<u64>().unwrap();
+                let _ = model.navigate(key);
+            }
+        });
+
         root.dynamic_cast::<gtk::Widget>().unwrap()
     }
 }
 
 pub enum MenuBarButton {
-    Minimize,
+    File,
+    Edit,
+    Add,
+    View,
+    Search,
+    Tools,
+    Window,
+    Help,
     Maximize,
-    Close,
 }
 
 #[derive(Default)]
@@ -46,22 +142,49 @@ impl MenuBarView {
     pub fn button(&self, name: MenuBarButton) -> gtk::Button {
         let getter = |name| -> gtk::Button { self.gbuilder.object(name).unwrap() };
         match name {
-            MenuBarButton::Close => getter("b_close"),
-            MenuBarButton::Minimize => getter("b_minimize"),
+            MenuBarButton::File => getter("b_file"),
+            MenuBarButton::Edit => getter("b_edit"),
+            MenuBarButton::Add => getter("b_add"),
+            MenuBarButton::View => getter("b_view"),
+            MenuBarButton::Search => getter("b_search"),
+            MenuBarButton::Tools => getter("b_tools"),
+            MenuBarButton::Window => getter("b_window"),
+            MenuBarButton::Help => getter("b_help"),
             MenuBarButton::Maximize => getter("b_maximize"),
         }
     }
+
+    /// Drops the menu down under its button. The menu is attached
+    /// to the button to reach the actions of the application.
+    fn connect_menu(&self, button: MenuBarButton, menu: &str) {
+        let menu: gtk::Menu = self.gbuilder.object(menu).unwrap();
+        let button = self.button(button);
+        menu.set_attach_widget(Some(&button));
+        button.connect_clicked(move |btn| {
+            menu.popup_at_widget(btn, gtk::gdk::Gravity::SouthWest, gtk::gdk::Gravity::NorthWest, None);
+        });
+    }
 }
 
 impl View for MenuBarView {
     fn assemble(&self) -> gtk::Widget {
         let grid: gtk::Grid = self.gbuilder.object("root").unwrap();
+        self.connect_menu(MenuBarButton::File, "m_file");
+        self.connect_menu(MenuBarButton::Edit, "m_edit");
+        self.connect_menu(MenuBarButton::Add, "m_add");
+        self.connect_menu(MenuBarButton::View, "m_view");
+        self.connect_menu(MenuBarButton::Search, "m_search");
+        self.connect_menu(MenuBarButton::Tools, "m_tools");
+        self.connect_menu(MenuBarButton::Window, "m_window");
+        self.connect_menu(MenuBarButton::Help, "m_help");
         grid.show();
         grid.dynamic_cast::<gtk::Widget>().unwrap()
     }
 }
 
-#[derive(Default)]
+/// Toolbar of the main screen, its buttons being bound
+/// to the actions of the application
+#[derive(Default, Clone)]
 pub struct ToolBarView {
     gbuilder: gtk::Builder,
 }
@@ -74,9 +197,17 @@ impl ToolBarView {
         }
     }
 
-    pub fn on_person_edit<CallbackT: Fn(&gtk::Button) + 'static>(&self, cb: CallbackT) {
-        let btn: gtk::Button = self.gbuilder.object("b_add_person").unwrap();
-        btn.connect_clicked(cb);
+    pub fn button(&self, name: &str) -> gtk::Button {
+        self.gbuilder.object(name).unwrap()
+    }
+
+    /// Highlights the bookmark button for a bookmarked person
+    pub fn show_bookmark(&self, bookmarked: bool) {
+        let bookmark = self.button("b_bookmark").style_context();
+        match bookmarked {
+            true => bookmark.add_class("color-primary"),
+            false => bookmark.remove_class("color-primary"),
+        }
     }
 }
 
@@ -91,19 +222,227 @@ impl View for ToolBarView {
 pub struct RootView {
     gbuilder: gtk::Builder,
     menubar: MenuBarView,
+    toolbar: ToolBarView,
+    model: AppModel,
 }
 
 impl RootView {
-    pub fn new() -> Self {
+    pub fn new(model: AppModel) -> Self {
         RootView {
             gbuilder: gtk::Builder::from_resource("/org/altereigo/npaf/Root.glade"),
             menubar: MenuBarView::new(),
+            toolbar: ToolBarView::new(),
+            model,
+        }
+    }
+
+    /// Drops down the recently viewed persons and the
+    /// bookmarks under the history button
+    fn open_history(model: &AppModel, button: &gtk::Button) {
+        let menu = gtk::Menu::new();
+        let history = model.history();
+        let sections = [("Recently viewed", history.recent()), ("Bookmarks", history.bookmarks().to_vec())];
+        drop(history);
+        for (n, (title, keys)) in sections.iter().enumerate() {
+            if n > 0 {
+                menu.append(&gtk::SeparatorMenuItem::new());
+            }
+            let header = gtk::MenuItem::with_label(title);
+            header.set_sensitive(false);
+            menu.append(&header);
+            if keys.is_empty() {
+                let none = gtk::MenuItem::with_label("None");
+                none.set_sensitive(false);
+                menu.append(&none);
+            }
+            for key in keys {
+                let record = match model.record(*key) {
+                    Some(record) => record,
+                    None => continue,
+                };
+                let record = record.borrow();
+                let label = match record.lifespan().as_str() {
+                    "" => record.display_name(),
+                    lifespan => format!("{} ({})", record.display_name(), lifespan),
+                };
+                let item = gtk::MenuItem::with_label(&label);
+                let (model, key) = (model.clone(), *key);
+                item.connect_activate(move |_| {
+                    let _ = model.navigate(key);
+                });
+                menu.append(&item);
+            }
         }
+        menu.set_attach_widget(Some(button));
+        menu.show_all();
+        menu.popup_at_widget(button, gtk::gdk::Gravity::SouthWest, gtk::gdk::Gravity::NorthWest, None);
     }
 
-    pub fn on_window_close<CallbackT: Fn(&gtk::Button) + 'static>(&self, f: CallbackT) {
-        let btn: gtk::Button = self.menubar.button(MenuBarButton::Close);
-        btn.connect_clicked(f);
+    /// Asks for a GED file and imports it in the background
+    fn open_import(model: &AppModel, mode: ImportMode) {
+        let dialog = gtk::FileChooserDialog::with_buttons::<gtk::Window>(
+            Some("Import GEDCOM"),
+            None,
+            gtk::FileChooserAction::Open,
+            &[("_Cancel", gtk::ResponseType::Cancel), ("_Import", gtk::ResponseType::Accept)],
+        );
+        let filter = gtk::FileFilter::new();
+        filter.set_name(Some("GEDCOM files"));
+        filter.add_pattern("*.ged");
+        filter.add_pattern("*.GED");
+        dialog.add_filter(&filter);
+        let packages = gtk::FileFilter::new();
+        packages.set_name(Some("GEDZIP packages"));
+        packages.add_pattern("*.gdz");
+        packages.add_pattern("*.zip");
+        dialog.add_filter(&packages);
+        let gramps = gtk::FileFilter::new();
+        gramps.set_name(Some("Gramps files"));
+        gramps.add_pattern("*.gramps");
+        dialog.add_filter(&gramps);
+        let json = gtk::FileFilter::new();
+        json.set_name(Some("JSON-LD documents"));
+        json.add_pattern("*.json");
+        json.add_pattern("*.jsonld");
+        dialog.add_filter(&json);
+        let path = match dialog.run() {
+            gtk::ResponseType::Accept => dialog.filename(),
+            _ => None,
+        };
+        dialog.close();
+        if let Some(path) = path {
+            let import = ImportView::new(model.clone(), path, mode);
+            import.assemble_window().present();
+        }
+    }
+
+    /// Asks for a CSV file and opens the import wizard on it
+    fn open_csv_import(model: &AppModel) {
+        let dialog = gtk::FileChooserDialog::with_buttons::<gtk::Window>(
+            Some("Import CSV"),
+            None,
+            gtk::FileChooserAction::Open,
+            &[("_Cancel", gtk::ResponseType::Cancel), ("_Open", gtk::ResponseType::Accept)],
+        );
+        let filter = gtk::FileFilter::new();
+        filter.set_name(Some("CSV files"));
+        filter.add_pattern("*.csv");
+        filter.add_pattern("*.CSV");
+        filter.add_pattern("*.txt");
+        dialog.add_filter(&filter);
+        let path = match dialog.run() {
+            gtk::ResponseType::Accept => dialog.filename(),
+            _ => None,
+        };
+        dialog.close();
+        if let Some(path) = path {
+            let import = CsvImportView::new(model.clone(), path);
+            import.assemble_window().present();
+        }
+    }
+
+    /// Opens the view built from the model in its own window
+    fn present<V: Windowed, F: Fn(AppModel) -> V>(model: &AppModel, view: F) {
+        view(model.clone()).assemble_window().present();
+    }
+
+    /// Registers the commands of the main screen as actions of the
+    /// application, which the menus and the toolbar are bound to
+    pub fn register_actions(&self, app: &gtk::Application) {
+        let model = self.model.clone();
+        actions::add(app, "open", &["<Primary>o"], move || Self::open_import(&model, ImportMode::Replace));
+        let model = self.model.clone();
+        actions::add(app, "import", &["<Primary>i"], move || Self::open_import(&model, ImportMode::Append));
+        let model = self.model.clone();
+        actions::add(app, "import-csv", &[], move || Self::open_csv_import(&model));
+        let model = self.model.clone();
+        actions::add(app, "export", &["<Primary>e"], move || Self::present(&model, ExportView::new));
+        let model = self.model.clone();
+        actions::add(app, "print", &["<Primary>p"], move || Self::present(&model, PrintReportView::new));
+        let model = self.model.clone();
+        actions::add(app, "descendancy", &[], move || {
+            PrintReportView::with_kind(model.clone(), "descendancy").assemble_window().present();
+        });
+
+        let model = self.model.clone();
+        actions::add(app, "add-person", &["<Primary>n"], move || Self::present(&model, PersonEditorView::new));
+        let model = self.model.clone();
+        actions::add(app, "add-family", &["<Primary><Shift>n"], move || Self::present(&model, FamilyEditorView::new));
+        let model = self.model.clone();
+        actions::add(app, "edit-person", &["F2"], move || {
+            if let Some(person) = model.current() {
+                PersonEditorView::with_record(model.clone(), person).assemble_window().present();
+            }
+        });
+        let model = self.model.clone();
+        actions::add(app, "lds", &[], move || {
+            if let Some(person) = model.current() {
+                OrdinanceEditorView::new(model.clone(), person).assemble_window().present();
+            }
+        });
+        actions::add(app, "preferences", &["<Primary>comma"], || PreferencesView::new().assemble_window().present());
+
+        let model = self.model.clone();
+        actions::add(app, "find", &["<Primary>f"], move || Self::present(&model, FindView::new));
+        let model = self.model.clone();
+        actions::add(app, "rin-search", &["<Primary>r"], move || Self::present(&model, RinSearchView::new));
+        let model = self.model.clone();
+        actions::add(app, "merge", &["<Primary>m"], move || Self::present(&model, MergeView::new));
+
+        let model = self.model.clone();
+        actions::add(app, "verify", &[], move || Self::present(&model, VerifyView::new));
+        let model = self.model.clone();
+        actions::add(app, "batches", &[], move || Self::present(&model, BatchView::new));
+        let model = self.model.clone();
+        actions::add(app, "statistics", &[], move || Self::present(&model, StatsView::new));
+        let model = self.model.clone();
+        actions::add(app, "website", &[], move || Self::present(&model, WebsiteView::new));
+
+        let model = self.model.clone();
+        let back = actions::add(app, "back", &["<Alt>Left"], move || {
+            let _ = model.go_back();
+        });
+        let model = self.model.clone();
+        let forward = actions::add(app, "forward", &["<Alt>Right"], move || {
+            let _ = model.go_forward();
+        });
+        let model = self.model.clone();
+        actions::add(app, "home", &["<Alt>Home"], move || {
+            let _ = model.go_home();
+        });
+        let (model, history) = (self.model.clone(), self.toolbar.button("b_history"));
+        actions::add(app, "history", &["<Primary>h"], move || Self::open_history(&model, &history));
+        let (model, toolbar) = (self.model.clone(), self.toolbar.clone());
+        actions::add(app, "bookmark", &["<Primary>d"], move || {
+            toolbar.show_bookmark(model.toggle_bookmark().unwrap_or(false));
+        });
+
+        // Back and forward follow the history, which changes
+        // with every move and when persons are removed
+        let (model, toolbar) = (self.model.clone(), self.toolbar.clone());
+        let show_history = Rc::new(move || {
+            let history = model.history();
+            back.set_enabled(history.can_go_back());
+            forward.set_enabled(history.can_go_forward());
+            toolbar.show_bookmark(model.current_key().map_or(false, |key| history.is_bookmarked(key)));
+        });
+        show_history();
+        let (mut model, show) = (self.model.clone(), show_history.clone());
+        model.subscribe(move |_| show());
+        self.model.on_change(move || show_history());
+    }
+
+    /// Presses on the menu bar outside of its buttons,
+    /// which stands for the title bar of the window
+    pub fn on_title_bar_pressed<CallbackT: Fn(&gtk::EventBox, &gtk::gdk::EventButton) -> gtk::Inhibit + 'static>(&self, f: CallbackT) {
+        let title_bar: gtk::EventBox = self.gbuilder.object("eb_title_bar").unwrap();
+        title_bar.connect_button_press_event(f);
+    }
+
+    /// Shows whether the window is maximized on the maximize button
+    pub fn show_maximized(&self, maximized: bool) {
+        let btn: gtk::Button = self.menubar.button(MenuBarButton::Maximize);
+        btn.set_label(if maximized { window::RESTORE_GLYPH } else { window::MAXIMIZE_GLYPH });
     }
 }
 
@@ -115,18 +454,12 @@ impl View for RootView {
             self.gbuilder.object::<gtk::Grid>("p_tool_bar").unwrap(),
             self.gbuilder.object::<gtk::Grid>("p_workspace").unwrap(),
         );
-        let menubar = self.menubar.assemble();
-        p_menubar.attach(&menubar, 0, 0, 1, 1);
-        let toolbar = ToolBarView::new();
-        toolbar.on_person_edit(|_| {
-            let editor = PersonEditorView::new();
-            let window = editor.assemble_window();
-            window.present();
-        });
-        let toolbar = toolbar.assemble();
-        p_toolbar.attach(&toolbar, 0, 0, 1, 1);
-        let workspace = WorkspaceView::new();
-        p_workspace.attach(&workspace.assemble(), 0, 0, 1, 1);
+        p_menubar.attach(&self.menubar.assemble(), 0, 0, 1, 1);
+        p_toolbar.attach(&self.toolbar.assemble(), 0, 0, 1, 1);
+        let names = PersonTableView::new(self.model.clone());
+        p_workspace.attach(&names.assemble(), 0, 0, 1, 1);
+        let workspace = WorkspaceView::new(self.model.clone());
+        p_workspace.attach(&workspace.assemble(), 1, 0, 1, 1);
         root.set_row_homogeneous(false);
         root.show();
         root.dynamic_cast::<gtk::Widget>().unwrap()
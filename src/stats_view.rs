@@ -0,0 +1,94 @@
+use gtk::prelude::*;
+
+use crate::app::models::AppModel;
+use crate::gedcom::stats::Statistics;
+use crate::prelude::*;
+
+pub struct StatsView {
+    gbuilder: gtk::Builder,
+    model: AppModel,
+}
+
+impl StatsView {
+    pub fn new(model: AppModel) -> Self {
+        Self {
+            gbuilder: gtk::Builder::from_resource("/org/altereigo/npaf/Stats.glade"),
+            model,
+        }
+    }
+
+    /// Fills the list with the figures of the section chosen
+    fn refresh(gbuilder: &gtk::Builder, model: &AppModel) {
+        let store: gtk::ListStore = gbuilder.object("stats_store").unwrap();
+        let status: gtk::Label = gbuilder.object("l_status").unwrap();
+        let section: gtk::ComboBoxText = gbuilder.object("cb_section").unwrap();
+        let section = section.active_id().map(|s| s.to_string()).unwrap_or_else(|| String::from("all"));
+        store.clear();
+        let stats = Statistics::new(&model.registry());
+        for (name, item, value) in stats.rows(false) {
+            if section == "all" || section == name {
+                store.insert_with_values(None, &[(0, &name), (1, &item), (2, &value)]);
+            }
+        }
+        status.set_text(&format!("{} individual(s), {} surname(s), {} generation(s).",
+                                 stats.individuals(), stats.surnames.len(), stats.generations));
+    }
+
+    /// Asks for a file and writes every figure to it as CSV
+    fn export(gbuilder: &gtk::Builder, model: &AppModel) {
+        let status: gtk::Label = gbuilder.object("l_status").unwrap();
+        let dialog = gtk::FileChooserDialog::with_buttons::<gtk::Window>(
+            Some("Export statistics"),
+            None,
+            gtk::FileChooserAction::Save,
+            &[("_Cancel", gtk::ResponseType::Cancel), ("_Export", gtk::ResponseType::Accept)],
+        );
+        dialog.set_do_overwrite_confirmation(true);
+        dialog.set_current_name("statistics.csv");
+        if dialog.run() == gtk::ResponseType::Accept {
+            if let Some(path) = dialog.filename() {
+                let written = std::fs::File::create(&path)
+                    .and_then(|file| Statistics::new(&model.registry()).write_csv(file));
+                status.set_text(&match written {
+                    Ok(()) => format!("Exported to '{}'.", path.display()),
+                    Err(err) => format!("Could not export the file: {}", err),
+                });
+            }
+        }
+        dialog.close();
+    }
+}
+
+impl View for StatsView {
+    fn assemble(&self) -> gtk::Widget {
+        let root: gtk::Grid = self.gbuilder.object("root").unwrap();
+        Self::refresh(&self.gbuilder, &self.model);
+
+        let section: gtk::ComboBoxText = self.gbuilder.object("cb_section").unwrap();
+        let (gbuilder, model) = (self.gbuilder.clone(), self.model.clone());
+        section.connect_changed(move |_| Self::refresh(&gbuilder, &model));
+
+        let b_refresh: gtk::Button = self.gbuilder.object("b_refresh").unwrap();
+        let (gbuilder, model) = (self.gbuilder.clone(), self.model.clone());
+        b_refresh.connect_clicked(move |_| Self::refresh(&gbuilder, &model));
+
+        let b_export: gtk::Button = self.gbuilder.object("b_export").unwrap();
+        let (gbuilder, model) = (self.gbuilder.clone(), self.model.clone());
+        b_export.connect_clicked(move |_| Self::export(&gbuilder, &model));
+
+        root.show();
+        root.dynamic_cast::<gtk::Widget>().unwrap()
+    }
+}
+
+impl Windowed for StatsView {
+    fn assemble_window(&self) -> gtk::Window {
+        let wdg = self.assemble();
+        let wdw: gtk::Window = self.gbuilder.object("top").unwrap();
+        wdw.set_child(Some(&wdg));
+        let b_close: gtk::Button = self.gbuilder.object("b_close").unwrap();
+        let wdw_cpy = wdw.clone();
+        b_close.connect_clicked(move |_| wdw_cpy.close());
+        wdw
+    }
+}
@@ -0,0 +1,103 @@
+use gtk::prelude::*;
+
+use crate::app::models::AppModel;
+use crate::gedcom::verify::{self, Severity, VerifyRules};
+use crate::gedcom::xref_key;
+use crate::person_editor::PersonEditorView;
+use crate::prelude::*;
+
+pub struct VerifyView {
+    gbuilder: gtk::Builder,
+    model: AppModel,
+}
+
+impl VerifyView {
+    pub fn new(model: AppModel) -> Self {
+        Self {
+            gbuilder: gtk::Builder::from_resource("/org/altereigo/npaf/Verify.glade"),
+            model,
+        }
+    }
+
+    fn run(gbuilder: &gtk::Builder, model: &AppModel) {
+        let store: gtk::ListStore = gbuilder.object("issues_store").unwrap();
+        let status: gtk::Label = gbuilder.object("l_status").unwrap();
+        store.clear();
+        let issues = verify::verify(&model.registry(), &VerifyRules::default());
+        for issue in &issues {
+            let severity = match issue.severity() {
+                Severity::Error => "Error",
+                Severity::Warning => "Warning",
+            };
+            store.insert_with_values(None, &[
+                (0, &issue.record),
+                (1, &severity),
+                (2, &issue.name),
+                (3, &issue.what()),
+            ]);
+        }
+        let errors = issues.iter().filter(|i| i.severity() == Severity::Error).count();
+        status.set_text(&match issues.len() {
+            0 => String::from("No problem found."),
+            n => format!("{} problem(s) found, {} error(s) and {} warning(s).", n, errors, n - errors),
+        });
+    }
+
+    /// Focuses the workspace on the record and opens it in the
+    /// editor, families being opened through their first partner
+    fn open(model: &AppModel, key: u64) {
+        let record = match model.record(key) {
+            Some(record) => record,
+            None => return,
+        };
+        let graph = model.graph();
+        let person = match record.borrow().rtype.as_str() {
+            "INDI" => Some(record.clone()),
+            "FAM" => graph.family_of(&record.borrow())
+                .and_then(|f| graph.partners(f).first().copied())
+                .and_then(|p| graph.record(&model.registry(), p)),
+            _ => None,
+        };
+        if let Some(person) = person {
+            let _ = model.navigate(xref_key(&person.borrow().xref));
+            let editor = PersonEditorView::with_record(model.clone(), person);
+            editor.assemble_window().present();
+        }
+    }
+}
+
+impl View for VerifyView {
+    fn assemble(&self) -> gtk::Widget {
+        let root: gtk::Grid = self.gbuilder.object("root").unwrap();
+        Self::run(&self.gbuilder, &self.model);
+
+        let b_verify: gtk::Button = self.gbuilder.object("b_verify").unwrap();
+        let (gbuilder, model) = (self.gbuilder.clone(), self.model.clone());
+        b_verify.connect_clicked(move |_| Self::run(&gbuilder, &model));
+
+        let issues: gtk::TreeView = self.gbuilder.object("tv_issues").unwrap();
+        let model = self.model.clone();
+        issues.connect_row_activated(move |tv, path, _| {
+            let store = tv.model().unwrap();
+            if let Some(iter) = store.iter(path) {
+                let key = store.value(&iter, 0).get::<u64>().unwrap();
+                Self::open(&model, key);
+            }
+        });
+
+        root.show();
+        root.dynamic_cast::<gtk::Widget>().unwrap()
+    }
+}
+
+impl Windowed for VerifyView {
+    fn assemble_window(&self) -> gtk::Window {
+        let wdg = self.assemble();
+        let wdw: gtk::Window = self.gbuilder.object("top").unwrap();
+        wdw.set_child(Some(&wdg));
+        let b_close: gtk::Button = self.gbuilder.object("b_close").unwrap();
+        let wdw_cpy = wdw.clone();
+        b_close.connect_clicked(move |_| wdw_cpy.close());
+        wdw
+    }
+}
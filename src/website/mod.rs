@@ -0,0 +1,335 @@
+//! Static family history website: a surname index and a page
+//! per person, written to a directory and browsable offline.
+
+mod svg;
+pub mod templates;
+
+use crate::gedcom::privacy::{LivingRules, PrivacyFilter, PrivacyMode, Visibility};
+use crate::gedcom::{ahnentafel, FamilyGraph, PersonId, Record, RecordRc, RecordRegistry, SimpleDate};
+use std::collections::BTreeMap;
+use std::io::Result;
+use std::path::Path;
+pub use templates::TemplateSet;
+
+#[derive(Debug,Clone)]
+pub struct SiteSettings {
+    pub title: String,
+    pub templates: TemplateSet,
+    pub living: PrivacyMode,
+    pub living_rules: LivingRules,
+    /// Generations of the pedigree chart of each person,
+    /// no chart is drawn below 2
+    pub pedigree_generations: usize,
+    /// Copies the multimedia files into the site
+    pub copy_media: bool,
+}
+
+impl Default for SiteSettings {
+    fn default() -> Self {
+        SiteSettings {
+            title: String::from("Family History"),
+            templates: TemplateSet::default(),
+            living: PrivacyMode::Redact,
+            living_rules: LivingRules::default(),
+            pedigree_generations: 4,
+            copy_media: true,
+        }
+    }
+}
+
+/// Counts reported once the website is written
+#[derive(Debug,Default,Clone)]
+pub struct SiteSummary {
+    pub pages: usize,
+    pub redacted: usize,
+    pub excluded: usize,
+    pub media: usize,
+}
+
+/// Escapes a text for HTML and SVG
+pub(crate) fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Name of the page of a record, from its cross-reference.
+/// Letters and digits are kept, every other byte is escaped
+/// as `_XX` so that two cross-references never share a page.
+fn page_name(xref: &str) -> String {
+    let mut name = String::with_capacity(xref.len());
+    for byte in xref.trim_matches('@').bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' => name.push(byte as char),
+            _ => name.push_str(&format!("_{:02X}", byte)),
+        }
+    }
+    format!("{}.html", name)
+}
+
+struct Site<'a> {
+    registry: &'a RecordRegistry,
+    graph: FamilyGraph,
+    settings: &'a SiteSettings,
+    privacy: PrivacyFilter,
+    generated: String,
+}
+
+impl<'a> Site<'a> {
+    fn new(registry: &'a RecordRegistry, settings: &'a SiteSettings) -> Self {
+        let today = SimpleDate::today();
+        let generated = format!("{}-{:02}-{:02}", today.year, today.month.unwrap_or(1), today.day.unwrap_or(1));
+        let privacy = PrivacyFilter::new(registry, settings.living, &settings.living_rules);
+        let graph = FamilyGraph::new(registry);
+        Site { registry, graph, settings, privacy, generated }
+    }
+
+    fn visibility(&self, person: &Record) -> Visibility {
+        self.privacy.visibility(person)
+    }
+
+    fn shown_name(&self, person: &Record) -> String {
+        self.privacy.shown_name(person)
+    }
+
+    /// Link to the page of a person, `dir` being the path of
+    /// the `people` directory from the linking page
+    fn link(&self, person: &RecordRc, dir: &str) -> String {
+        let person = person.borrow();
+        let href = format!("{}{}", dir, page_name(&person.xref));
+        match self.visibility(&person) {
+            Visibility::Hidden => String::new(),
+            Visibility::Redacted => format!("<a class=\"living\" href=\"{}\">Living</a>", href),
+            Visibility::NameOnly => format!("<a class=\"living\" href=\"{}\">{}</a>", href, escape(&person.display_name())),
+            Visibility::Public => {
+                let lifespan = match person.lifespan() {
+                    lifespan if lifespan.is_empty() => lifespan,
+                    lifespan => format!(" ({})", lifespan),
+                };
+                format!("<a href=\"{}\">{}</a>{}", href, escape(&person.display_name()), lifespan)
+            }
+        }
+    }
+
+    fn page(&self, title: &str, root: &str, content: &str) -> String {
+        self.settings.templates.render(&[
+            ("site_title", &escape(&self.settings.title)),
+            ("title", &escape(title)),
+            ("root", root),
+            ("generated", &self.generated),
+            ("content", content),
+        ])
+    }
+
+    fn index(&self) -> String {
+        let mut surnames: BTreeMap<String, Vec<&RecordRc>> = BTreeMap::new();
+        for record in self.registry.values() {
+            let person = record.borrow();
+            let listed = match self.visibility(&person) {
+                Visibility::Public | Visibility::NameOnly => true,
+                _ => false,
+            };
+            if person.rtype == "INDI" && listed {
+                let surname = match person.surname() {
+                    surname if surname.is_empty() => String::from("(unknown)"),
+                    surname => surname,
+                };
+                surnames.entry(surname).or_default().push(record);
+            }
+        }
+        let mut content = format!("<h1>{}</h1>\n<h2>Surnames</h2>\n<ul class=\"surnames\">\n", escape(&self.settings.title));
+        for (i, (surname, persons)) in surnames.iter().enumerate() {
+            content.push_str(&format!("<li><a href=\"#s{}\">{}</a> ({})</li>\n", i, escape(surname), persons.len()));
+        }
+        content.push_str("</ul>\n");
+        for (i, (surname, persons)) in surnames.iter_mut().enumerate() {
+            persons.sort_by_key(|p| {
+                let p = p.borrow();
+                (p.given_names().to_lowercase(), p.lifespan())
+            });
+            content.push_str(&format!("<h2 id=\"s{}\">{}</h2>\n<ul>\n", i, escape(surname)));
+            for person in persons.iter() {
+                content.push_str(&format!("<li>{}</li>\n", self.link(person, "people/")));
+            }
+            content.push_str("</ul>\n");
+        }
+        self.page("Surname index", "", &content)
+    }
+
+    fn events(&self, person: &Record) -> String {
+        if person.events.is_empty() {
+            return String::new();
+        }
+        let mut content = String::from("<h2>Events</h2>\n<table>\n");
+        for event in &person.events {
+            content.push_str(&format!("<tr><th>{}</th><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                escape(event.label()),
+                escape(event.date.as_deref().unwrap_or("")),
+                escape(event.place.as_deref().unwrap_or("")),
+                escape(event.value.as_deref().unwrap_or(""))));
+        }
+        content.push_str("</table>\n");
+        content
+    }
+
+    /// Link to the page of a person of the graph
+    fn link_to(&self, person: Option<PersonId>) -> Option<String> {
+        person.and_then(|p| self.graph.record(self.registry, p))
+            .map(|p| self.link(&p, ""))
+            .filter(|link| !link.is_empty())
+    }
+
+    fn families(&self, person: &Record) -> String {
+        let mut content = String::from("<h2>Family</h2>\n");
+        let id = match self.graph.person_of(person) {
+            Some(id) => id,
+            None => return content,
+        };
+        let parents: Vec<String> = [("Father", self.graph.father(id)), ("Mother", self.graph.mother(id))].iter()
+            .filter_map(|(label, parent)| self.link_to(*parent).map(|link| (label, link)))
+            .map(|(label, link)| format!("<tr><th>{}</th><td>{}</td></tr>\n", label, link))
+            .collect();
+        if !parents.is_empty() {
+            content.push_str(&format!("<table>\n{}</table>\n", parents.join("")));
+        }
+        for family_id in self.graph.spouse_families(id) {
+            let family = match self.graph.family_record(self.registry, *family_id) {
+                Some(family) => family,
+                None => continue,
+            };
+            let family = family.borrow();
+            let spouse = self.link_to(self.graph.partner(*family_id, id));
+            content.push_str(&format!("<h3>Family with {}</h3>\n", spouse.unwrap_or_else(|| String::from("unknown spouse"))));
+            if self.privacy.is_family_public(&family) {
+                if let Some(marriage) = family.event_summary("MARR").filter(|m| !m.is_empty()) {
+                    content.push_str(&format!("<p>Married: {}</p>\n", escape(&marriage)));
+                }
+            }
+            let children: Vec<String> = self.graph.family_children(*family_id).iter()
+                .filter_map(|c| self.link_to(Some(*c)))
+                .collect();
+            if !children.is_empty() {
+                content.push_str("<ol>\n");
+                for child in children {
+                    content.push_str(&format!("<li>{}</li>\n", child));
+                }
+                content.push_str("</ol>\n");
+            }
+        }
+        content
+    }
+
+    fn sources(&self, person: &Record) -> String {
+        let sources = person.sources(self.registry);
+        if sources.is_empty() {
+            return String::new();
+        }
+        let mut content = String::from("<h2>Sources</h2>\n<ul>\n");
+        for source in &sources {
+            let source = source.borrow();
+            let title = source.extra_value("TITL").unwrap_or_else(|| source.xref.clone());
+            let author = source.extra_value("AUTH").map(|a| format!(", {}", escape(&a))).unwrap_or_default();
+            content.push_str(&format!("<li>{}{}</li>\n", escape(&title), author));
+        }
+        content.push_str("</ul>\n");
+        content
+    }
+
+    fn notes(&self, person: &Record) -> String {
+        person.notes(self.registry).iter()
+            .map(|n| format!("<p>{}</p>\n", escape(n).replace('\n', "<br>")))
+            .collect::<Vec<String>>()
+            .join("")
+    }
+
+    /// Copies the media of a person into the site, files that
+    /// can't be found are skipped
+    fn media(&self, person: &Record, dir: &Path, summary: &mut SiteSummary) -> Result<String> {
+        let mut content = String::new();
+        for file in person.media_files(self.registry) {
+            let source = Path::new(&file);
+            let name = match source.file_name() {
+                Some(name) if self.settings.copy_media && source.is_file() => name.to_string_lossy().into_owned(),
+                _ => continue,
+            };
+            let target = format!("{}-{}", person.xref, name);
+            std::fs::copy(source, dir.join("media").join(&target))?;
+            summary.media += 1;
+            let lower = name.to_lowercase();
+            let href = format!("../media/{}", escape(&target));
+            if [".jpg", ".jpeg", ".png", ".gif", ".svg", ".webp"].iter().any(|e| lower.ends_with(e)) {
+                content.push_str(&format!("<a href=\"{0}\"><img class=\"media\" src=\"{0}\" alt=\"{1}\"></a>\n", href, escape(&name)));
+            } else {
+                content.push_str(&format!("<p><a href=\"{}\">{}</a></p>\n", href, escape(&name)));
+            }
+        }
+        Ok(if content.is_empty() { content } else { format!("<h2>Media</h2>\n{}", content) })
+    }
+
+    fn person(&self, record: &RecordRc, dir: &Path, summary: &mut SiteSummary) -> Result<String> {
+        let person = record.borrow();
+        let name = self.shown_name(&person);
+        let mut content = format!("<h1>{}</h1>\n", escape(&name));
+        let visibility = self.visibility(&person);
+        if visibility != Visibility::Public {
+            content.push_str("<p class=\"living\">Details of living persons are not published.</p>\n");
+        }
+        if visibility == Visibility::Redacted {
+            return Ok(self.page(&name, "../", &content));
+        }
+        if visibility == Visibility::Public {
+            content.push_str(&self.events(&person));
+        }
+        content.push_str(&self.families(&person));
+        let generations = self.settings.pedigree_generations;
+        let has_parents = self.graph.person_of(&person)
+            .map_or(false, |p| self.graph.father(p).is_some() || self.graph.mother(p).is_some());
+        if generations >= 2 && has_parents {
+            let slots = ahnentafel::slots(&self.graph, self.registry, &person, generations);
+            content.push_str("<h2>Pedigree</h2>\n");
+            content.push_str(&svg::pedigree(&slots, generations, |p| {
+                let p = p.borrow();
+                (self.visibility(&p), self.shown_name(&p), page_name(&p.xref))
+            }));
+        }
+        if visibility == Visibility::NameOnly {
+            return Ok(self.page(&name, "../", &content));
+        }
+        let notes = self.notes(&person);
+        if !notes.is_empty() {
+            content.push_str("<h2>Notes</h2>\n");
+            content.push_str(&notes);
+        }
+        content.push_str(&self.sources(&person));
+        content.push_str(&self.media(&person, dir, summary)?);
+        Ok(self.page(&name, "../", &content))
+    }
+}
+
+/// Writes the whole website into `dir`: `index.html`, the
+/// style sheet, a page per person in `people` and the copied
+/// multimedia files in `media`.
+pub fn generate(registry: &RecordRegistry, dir: &Path, settings: &SiteSettings) -> Result<SiteSummary> {
+    std::fs::create_dir_all(dir.join("people"))?;
+    std::fs::create_dir_all(dir.join("media"))?;
+    let site = Site::new(registry, settings);
+    let mut summary = SiteSummary::default();
+    std::fs::write(dir.join("style.css"), &settings.templates.style)?;
+    std::fs::write(dir.join("index.html"), site.index())?;
+    for record in registry.values().filter(|r| r.borrow().rtype == "INDI") {
+        let visibility = site.visibility(&record.borrow());
+        match visibility {
+            Visibility::Hidden => {
+                summary.excluded += 1;
+                continue;
+            },
+            Visibility::Redacted | Visibility::NameOnly => summary.redacted += 1,
+            Visibility::Public => (),
+        }
+        let page = site.person(record, dir, &mut summary)?;
+        std::fs::write(dir.join("people").join(page_name(&record.borrow().xref)), page)?;
+        summary.pages += 1;
+    }
+    Ok(summary)
+}
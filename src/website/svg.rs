@@ -0,0 +1,71 @@
+//! Pedigree charts drawn as inline SVG
+
+use crate::gedcom::RecordRc;
+use crate::gedcom::privacy::Visibility;
+use crate::website::escape;
+
+const BOX_WIDTH: usize = 180;
+const BOX_HEIGHT: usize = 36;
+const COLUMN: usize = 210;
+const ROW: usize = 44;
+/// Characters of a name fitting in a box
+const NAME_LENGTH: usize = 26;
+
+/// Pedigree of the person at slot 1, the ancestors being
+/// given by ahnentafel position. `person` tells how each of
+/// them is shown and where their page is.
+pub(crate) fn pedigree<F>(slots: &[Option<RecordRc>], generations: usize, person: F) -> String
+    where F: Fn(&RecordRc) -> (Visibility, String, String)
+{
+    let rows = 1 << (generations - 1);
+    let (width, height) = (COLUMN * (generations - 1) + BOX_WIDTH + 2, ROW * rows);
+    let center = |generation: usize, index: usize| -> (usize, usize) {
+        let span = height >> generation;
+        (COLUMN * generation + 1, span * index + span / 2)
+    };
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" font-family=\"sans-serif\" font-size=\"12\">\n",
+        width, height);
+    for generation in 0..generations {
+        for index in 0..(1 << generation) {
+            let n = (1 << generation) + index;
+            let record = match slots.get(n).and_then(|s| s.as_ref()) {
+                Some(record) => record,
+                None => continue,
+            };
+            let (visibility, mut name, href) = person(record);
+            if visibility == Visibility::Hidden {
+                continue;
+            }
+            if name.chars().count() > NAME_LENGTH {
+                name = name.chars().take(NAME_LENGTH - 1).chain(std::iter::once('…')).collect();
+            }
+            let (x, y) = center(generation, index);
+            if generation + 1 < generations {
+                for parent in [2 * n, 2 * n + 1].iter() {
+                    let shown = slots.get(*parent)
+                        .and_then(|s| s.as_ref())
+                        .map_or(false, |p| person(p).0 != Visibility::Hidden);
+                    if shown {
+                        let (px, py) = center(generation + 1, parent - 2 * n + index * 2);
+                        let elbow = x + BOX_WIDTH + (px - x - BOX_WIDTH) / 2;
+                        svg.push_str(&format!(
+                            "<polyline points=\"{},{} {},{} {},{} {},{}\" fill=\"none\" stroke=\"#888\"/>\n",
+                            x + BOX_WIDTH, y, elbow, y, elbow, py, px, py));
+                    }
+                }
+            }
+            let lifespan = match visibility {
+                Visibility::Public => record.borrow().lifespan(),
+                _ => String::new(),
+            };
+            svg.push_str(&format!(
+                "<a href=\"{}\"><rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" rx=\"4\" fill=\"#fff\" stroke=\"#5b4636\"/>\
+                 <text x=\"{}\" y=\"{}\" font-weight=\"bold\">{}</text><text x=\"{}\" y=\"{}\">{}</text></a>\n",
+                escape(&href), x, y - BOX_HEIGHT / 2, BOX_WIDTH, BOX_HEIGHT,
+                x + 6, y - 3, escape(&name), x + 6, y + 12, escape(&lifespan)));
+        }
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
@@ -0,0 +1,105 @@
+//! Page templates of the generated websites. A template set is
+//! a page layout and a style sheet, the layout containing
+//! `{{placeholders}}` replaced while writing each page.
+
+use std::path::Path;
+
+const CLASSIC_PAGE: &str = r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>{{title}} - {{site_title}}</title>
+<link rel="stylesheet" href="{{root}}style.css">
+</head>
+<body>
+<header><a href="{{root}}index.html">{{site_title}}</a></header>
+<main>
+{{content}}
+</main>
+<footer>Generated by nPAF on {{generated}}</footer>
+</body>
+</html>
+"#;
+
+const CLASSIC_STYLE: &str = "body { font-family: Georgia, serif; margin: 0; background: #f6f3ea; color: #2b2b2b; }
+header { background: #5b4636; padding: 12px 24px; }
+header a { color: #fff; font-size: 1.4em; text-decoration: none; }
+main { max-width: 960px; margin: 0 auto; padding: 12px 24px; background: #fff; }
+footer { text-align: center; font-size: 0.8em; color: #777; padding: 12px; }
+h1 { border-bottom: 1px solid #c9b99a; }
+table { border-collapse: collapse; }
+td, th { text-align: left; padding: 2px 12px 2px 0; vertical-align: top; }
+a { color: #7a3d16; }
+.living { color: #777; font-style: italic; }
+.surnames { columns: 3; }
+svg a:hover rect { fill: #efe6d2; }
+img.media { max-width: 240px; margin: 4px; }
+";
+
+const PLAIN_PAGE: &str = r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>{{title}}</title>
+<link rel="stylesheet" href="{{root}}style.css">
+</head>
+<body>
+<p><a href="{{root}}index.html">{{site_title}}</a></p>
+{{content}}
+<hr>
+<p><small>{{generated}}</small></p>
+</body>
+</html>
+"#;
+
+const PLAIN_STYLE: &str = "body { font-family: sans-serif; max-width: 900px; margin: 0 auto; padding: 8px; }
+.living { color: gray; }
+img.media { max-width: 200px; }
+";
+
+/// Names of the template sets shipped with the application
+pub const BUILTIN_TEMPLATES: [&str; 2] = ["classic", "plain"];
+
+#[derive(Debug,Clone)]
+pub struct TemplateSet {
+    pub page: String,
+    pub style: String,
+}
+
+impl Default for TemplateSet {
+    fn default() -> Self {
+        TemplateSet::builtin("classic")
+    }
+}
+
+impl TemplateSet {
+    /// One of the [shipped template sets](BUILTIN_TEMPLATES),
+    /// unknown names fall back to the classic one
+    pub fn builtin(name: &str) -> Self {
+        let (page, style) = match name {
+            "plain" => (PLAIN_PAGE, PLAIN_STYLE),
+            _ => (CLASSIC_PAGE, CLASSIC_STYLE),
+        };
+        TemplateSet { page: page.to_owned(), style: style.to_owned() }
+    }
+
+    /// Reads `page.html` and `style.css` from a directory, a
+    /// missing file is replaced by the classic one
+    pub fn from_dir(dir: &Path) -> std::io::Result<Self> {
+        let read = |name: &str, default: &str| -> std::io::Result<String> {
+            let path = dir.join(name);
+            if path.exists() { std::fs::read_to_string(path) } else { Ok(default.to_owned()) }
+        };
+        Ok(TemplateSet {
+            page: read("page.html", CLASSIC_PAGE)?,
+            style: read("style.css", CLASSIC_STYLE)?,
+        })
+    }
+
+    /// Fills the page layout, unknown placeholders are left as is
+    pub fn render(&self, values: &[(&str, &str)]) -> String {
+        values.iter().fold(self.page.clone(), |page, (key, value)| {
+            page.replace(&format!("{{{{{}}}}}", key), value)
+        })
+    }
+}
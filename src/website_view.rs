@@ -0,0 +1,108 @@
+use gtk::prelude::*;
+
+use crate::app::models::AppModel;
+use crate::app::preferences::Preferences;
+use crate::prelude::*;
+use crate::gedcom::PrivacyMode;
+use crate::website::{self, SiteSettings, TemplateSet};
+
+pub struct WebsiteView {
+    gbuilder: gtk::Builder,
+    model: AppModel,
+}
+
+impl WebsiteView {
+    pub fn new(model: AppModel) -> Self {
+        Self {
+            gbuilder: gtk::Builder::from_resource("/org/altereigo/npaf/Website.glade"),
+            model,
+        }
+    }
+
+    fn combo(gbuilder: &gtk::Builder, name: &str) -> Option<String> {
+        let combo: gtk::ComboBoxText = gbuilder.object(name).unwrap();
+        combo.active_id().map(|id| id.to_string())
+    }
+
+    fn folder(gbuilder: &gtk::Builder, name: &str) -> Option<std::path::PathBuf> {
+        let chooser: gtk::FileChooserButton = gbuilder.object(name).unwrap();
+        chooser.filename()
+    }
+
+    /// Reads the settings from the form, fails when a
+    /// required folder is missing
+    fn settings(gbuilder: &gtk::Builder) -> std::result::Result<SiteSettings, String> {
+        let templates = match Self::combo(gbuilder, "cb_template").as_deref() {
+            Some("custom") => {
+                let dir = Self::folder(gbuilder, "fc_templates").ok_or("Select the folder of the custom templates.")?;
+                TemplateSet::from_dir(&dir).map_err(|e| format!("Could not read the templates: {}", e))?
+            },
+            Some(name) => TemplateSet::builtin(name),
+            None => TemplateSet::default(),
+        };
+        let title: gtk::Entry = gbuilder.object("e_title").unwrap();
+        let generations: gtk::SpinButton = gbuilder.object("sb_generations").unwrap();
+        let copy_media: gtk::CheckButton = gbuilder.object("c_copy_media").unwrap();
+        Ok(SiteSettings {
+            title: title.text().trim().to_owned(),
+            templates,
+            living: Self::combo(gbuilder, "cb_living")
+                .and_then(|id| PrivacyMode::from_id(&id))
+                .unwrap_or(PrivacyMode::Redact),
+            pedigree_generations: generations.value_as_int().max(0) as usize,
+            copy_media: copy_media.is_active(),
+            living_rules: Preferences::load().living_rules(),
+            ..Default::default()
+        })
+    }
+
+    fn generate(gbuilder: &gtk::Builder, model: &AppModel) {
+        let status: gtk::Label = gbuilder.object("l_status").unwrap();
+        let dir = match Self::folder(gbuilder, "fc_output") {
+            Some(dir) => dir,
+            None => return status.set_text("Select the output folder first."),
+        };
+        let settings = match Self::settings(gbuilder) {
+            Ok(settings) => settings,
+            Err(msg) => return status.set_text(&msg),
+        };
+        status.set_text(&match website::generate(&model.registry(), &dir, &settings) {
+            Ok(summary) => format!(
+                "{} page(s) written to '{}', {} living person(s) kept private, {} left out, {} media file(s) copied.",
+                summary.pages, dir.display(), summary.redacted, summary.excluded, summary.media),
+            Err(err) => format!("Could not write the website: {}", err),
+        });
+    }
+}
+
+impl View for WebsiteView {
+    fn assemble(&self) -> gtk::Widget {
+        let root: gtk::Grid = self.gbuilder.object("root").unwrap();
+
+        let cb_template: gtk::ComboBoxText = self.gbuilder.object("cb_template").unwrap();
+        let fc_templates: gtk::FileChooserButton = self.gbuilder.object("fc_templates").unwrap();
+        fc_templates.set_sensitive(false);
+        cb_template.connect_changed(move |combo| {
+            fc_templates.set_sensitive(combo.active_id().as_deref() == Some("custom"));
+        });
+
+        let b_generate: gtk::Button = self.gbuilder.object("b_generate").unwrap();
+        let (gbuilder, model) = (self.gbuilder.clone(), self.model.clone());
+        b_generate.connect_clicked(move |_| Self::generate(&gbuilder, &model));
+
+        root.show();
+        root.dynamic_cast::<gtk::Widget>().unwrap()
+    }
+}
+
+impl Windowed for WebsiteView {
+    fn assemble_window(&self) -> gtk::Window {
+        let wdg = self.assemble();
+        let wdw: gtk::Window = self.gbuilder.object("top").unwrap();
+        wdw.set_child(Some(&wdg));
+        let b_close: gtk::Button = self.gbuilder.object("b_close").unwrap();
+        let wdw_cpy = wdw.clone();
+        b_close.connect_clicked(move |_| wdw_cpy.close());
+        wdw
+    }
+}